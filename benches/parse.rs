@@ -0,0 +1,33 @@
+// Times tokenizing and parsing a program with thousands of functions.
+// Run with `cargo bench --bench parse`.
+use std::time::Instant;
+use zenith::parser::parse;
+
+const FUNCTIONS: usize = 5_000;
+const RUNS: u32 = 20;
+
+fn main() {
+    let mut source = String::new();
+    for i in 0..FUNCTIONS {
+        source.push_str(&format!(
+            "fn func{i}(a: i32, b: i32) -> i32 {{\n    \
+                 var mut total = 0;\n    \
+                 for i in 0..b {{ if i % 2 == 0 {{ total += a * i; }} else {{ total -= 1; }} }}\n    \
+                 match total {{ 0 => 1, n => n + func{i}(a, b - 1) }}\n\
+             }}\n",
+        ));
+    }
+
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let program = parse(&source).unwrap();
+        assert_eq!(program.items.len(), FUNCTIONS);
+    }
+    let elapsed = start.elapsed() / RUNS;
+    println!(
+        "parsed {} functions ({} bytes) in {:?} per run",
+        FUNCTIONS,
+        source.len(),
+        elapsed
+    );
+}
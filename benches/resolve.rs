@@ -0,0 +1,36 @@
+// Times name resolution on a function with thousands of bindings.
+// Run with `cargo bench --bench resolve`.
+use std::time::Instant;
+use zenith::checker::resolver::resolve_program;
+use zenith::parser::parse;
+
+const BINDINGS: usize = 20_000;
+const RUNS: u32 = 20;
+
+fn main() {
+    let mut source = String::from("fn f() {\n");
+    for i in 0..BINDINGS {
+        source.push_str(&format!("    var v{} = {};\n", i, i));
+    }
+    for i in 0..BINDINGS {
+        source.push_str(&format!(
+            "    {{ var w = v{} + v{}; }}\n",
+            i,
+            BINDINGS - 1 - i
+        ));
+    }
+    source.push('}');
+    let program = parse(&source).unwrap();
+
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let resolution = resolve_program(&program);
+        assert_eq!(resolution.bindings.len(), 2 * BINDINGS);
+    }
+    let elapsed = start.elapsed() / RUNS;
+    println!(
+        "resolved {} bindings in {:?} per run",
+        2 * BINDINGS,
+        elapsed
+    );
+}
@@ -0,0 +1,100 @@
+use crate::ast::Program;
+use crate::checker::check_program;
+use crate::checker::lints::{
+    check_assignment_in_conditions, check_chained_comparisons, check_union_field_reads,
+    check_unused_variables,
+};
+use crate::checker::resolver::resolve_program;
+use crate::diagnostics::{Diagnostic, DiagnosticBag};
+use crate::lexer::{SpannedToken, tokenize};
+use crate::parser::parse_recovering;
+
+#[derive(Debug, Clone)]
+pub struct AnalysisResult {
+    pub tokens: Vec<SpannedToken>,
+    // `None` only when the source could not be tokenized at all.
+    pub program: Option<Program>,
+    // In source order.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl AnalysisResult {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+}
+
+// Lexes, parses and checks `src`, reporting every problem as a diagnostic
+// instead of failing. Parsing recovers from errors, and the checks run over
+// whatever part of the program could be parsed.
+pub fn analyze(src: &str) -> AnalysisResult {
+    let tokens = match tokenize(src) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            return AnalysisResult {
+                tokens: Vec::new(),
+                program: None,
+                diagnostics: vec![Diagnostic::error(error.message, error.span)],
+            };
+        }
+    };
+
+    let (program, errors) = parse_recovering(src);
+    let mut diagnostics: DiagnosticBag = errors
+        .iter()
+        .map(|error| Diagnostic::error(error.message(), error.span().clone()))
+        .collect();
+    diagnostics.extend(resolve_program(&program).unresolved_diagnostics());
+    diagnostics.extend(check_program(&program));
+    diagnostics.extend(check_unused_variables(&program));
+    diagnostics.extend(check_chained_comparisons(&program));
+    diagnostics.extend(check_assignment_in_conditions(&program));
+    diagnostics.extend(check_union_field_reads(&program));
+
+    AnalysisResult {
+        tokens,
+        program: Some(program),
+        diagnostics: diagnostics.sorted(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lex_error_has_no_program() {
+        let result = analyze("fn f() { let s = \"open; }");
+        assert!(result.program.is_none());
+        assert!(result.tokens.is_empty());
+        assert_eq!(result.diagnostics.len(), 1);
+        assert!(result.has_errors());
+    }
+
+    #[test]
+    fn test_parse_errors_are_recovered() {
+        let result = analyze("fn f() { let = 1; }\nfn g() -> i32 { 1 }");
+        let program = result.program.unwrap();
+        assert_eq!(program.items.len(), 2);
+        assert_eq!(result.diagnostics.len(), 1, "{:?}", result.diagnostics);
+        assert_eq!(result.diagnostics[0].message, "expected pattern, found `=`");
+    }
+
+    #[test]
+    fn test_unresolved_names_are_reported() {
+        let result = analyze("fn f() -> i32 { var length = 1; lenght }");
+        let messages: Vec<&str> = result
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert!(messages.contains(&"cannot find `lenght` in this scope; did you mean `length`?"));
+
+        let result = analyze("fn f() { loop { break 'b; } }");
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(
+            result.diagnostics[0].message,
+            "use of undeclared label `'b`"
+        );
+    }
+}
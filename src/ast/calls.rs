@@ -0,0 +1,92 @@
+use super::expressions::Expression;
+use super::visit::{Visitor, walk_expression};
+use super::{Program, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSite {
+    // The function called by name, as in `f(x)` or `m::f(x)`; `None` for a
+    // computed callee such as `obj.method(x)` or `make()(x)`.
+    pub callee_name: Option<String>,
+    pub span: Span,
+    pub arg_count: usize,
+}
+
+// Every call in `program`, outer calls before the calls in their arguments,
+// for building a call graph.
+pub fn collect_calls(program: &Program) -> Vec<CallSite> {
+    let mut collector = CallCollector::default();
+    collector.visit_program(program);
+    collector.calls
+}
+
+#[derive(Default)]
+struct CallCollector {
+    calls: Vec<CallSite>,
+}
+
+impl Visitor for CallCollector {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Call(call) = expression {
+            self.calls.push(CallSite {
+                callee_name: callee_name(&call.callee),
+                span: call.span.clone(),
+                arg_count: call.arguments.len(),
+            });
+        }
+        walk_expression(self, expression)
+    }
+}
+
+fn callee_name(callee: &Expression) -> Option<String> {
+    match callee {
+        Expression::Identifier(ident) => Some(ident.name.clone()),
+        Expression::Path(path) => Some(
+            path.segments
+                .iter()
+                .map(|segment| segment.ident.name.as_str())
+                .collect::<Vec<_>>()
+                .join("::"),
+        ),
+        Expression::Paren(inner, _) => callee_name(inner),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_collect_calls() {
+        let program = parse(
+            "fn helper(a: i32, b: i32) -> i32 { a + b }\n\
+             fn main() {\n\
+                 let x = helper(1, helper(2, 3));\n\
+                 math::abs(x);\n\
+                 p.show();\n\
+             }",
+        )
+        .unwrap();
+        let calls = collect_calls(&program);
+        let sites: Vec<(Option<&str>, usize, usize)> = calls
+            .iter()
+            .map(|call| (call.callee_name.as_deref(), call.arg_count, call.span.line))
+            .collect();
+        assert_eq!(
+            sites,
+            vec![
+                (Some("helper"), 2, 3),
+                (Some("helper"), 2, 3),
+                (Some("math::abs"), 1, 4),
+                (None, 0, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_calls_without_calls() {
+        let program = parse("fn main() { let x = 1 + 2; }").unwrap();
+        assert!(collect_calls(&program).is_empty());
+    }
+}
@@ -1,4 +1,5 @@
-use super::{Identifier, Span, Type};
+use super::types::TypePath;
+use super::{Block, Identifier, Span, Type};
 use std::fmt::{self, Display, Formatter};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -11,7 +12,7 @@ pub enum Expression {
     Member(Box<MemberExpr>),
     Index(Box<IndexExpr>),
     Cast(Box<CastExpr>),
-    Block(Box<BlockExpr>),
+    Block(Box<Block>),
     If(Box<IfExpr>),
     Match(Box<MatchExpr>),
     Loop(Box<LoopExpr>),
@@ -19,38 +20,134 @@ pub enum Expression {
     For(Box<ForExpr>),
     Range(Box<RangeExpr>),
     MacroInvocation(Box<MacroInvocation>),
+    // `panic(message)` in expression position; it has type `!`.
+    Panic(Box<PanicExpr>),
+    Struct(Box<StructExpr>),
+    // `a::b` or a name with explicit generic arguments, `f::<T>`. A lone name
+    // stays an `Identifier`.
+    Path(TypePath),
+    // Parentheses written in the source, kept so they can be printed back.
+    Paren(Box<Expression>, Span),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
-    Integer(i128, Option<Type>),
+    // The base records how the literal was written, so it can be printed back
+    // the same way.
+    Integer(i128, Option<Type>, IntBase),
     Float(f64, Option<Type>),
     String(String),
     Character(char),
     Boolean(bool),
+    Unit,
     Array(Vec<Expression>),
+    Tuple(Vec<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntBase {
+    #[default]
+    Dec,
+    Hex,
+    Oct,
+    Bin,
+}
+
+impl IntBase {
+    pub fn radix(&self) -> u32 {
+        match self {
+            IntBase::Dec => 10,
+            IntBase::Hex => 16,
+            IntBase::Oct => 8,
+            IntBase::Bin => 2,
+        }
+    }
+}
+
+impl Literal {
+    pub fn char_to_u32(&self) -> Option<u32> {
+        match self {
+            Literal::Character(c) => Some(u32::from(*c)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Literal::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_char(&self) -> Option<char> {
+        match self {
+            Literal::Character(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            Literal::Integer(n, _, _) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Literal::Float(n, _) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Literal::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
 }
 
 impl Display for Literal {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Literal::Integer(n, ty) => {
+            Literal::Integer(n, ty, base) => {
+                if *n < 0 {
+                    write!(f, "-")?;
+                }
+                let magnitude = n.unsigned_abs();
+                match base {
+                    IntBase::Dec => write!(f, "{}", magnitude)?,
+                    IntBase::Hex => write!(f, "0x{:x}", magnitude)?,
+                    IntBase::Oct => write!(f, "0o{:o}", magnitude)?,
+                    IntBase::Bin => write!(f, "0b{:b}", magnitude)?,
+                }
                 if let Some(t) = ty {
-                    write!(f, "{}{:?}", n, t)
-                } else {
-                    write!(f, "{}", n)
+                    write!(f, "{}", t)?;
                 }
+                Ok(())
             }
             Literal::Float(n, ty) => {
+                write_float(f, *n)?;
                 if let Some(t) = ty {
-                    write!(f, "{}{:?}", n, t)
-                } else {
-                    write!(f, "{}", n)
+                    write!(f, "{}", t)?;
                 }
+                Ok(())
+            }
+            Literal::String(s) => {
+                f.write_str("\"")?;
+                for c in s.chars() {
+                    write_escaped(f, c, '"')?;
+                }
+                f.write_str("\"")
+            }
+            Literal::Character(c) => {
+                f.write_str("'")?;
+                write_escaped(f, *c, '\'')?;
+                f.write_str("'")
             }
-            Literal::String(s) => write!(f, "\"{}\"", s),
-            Literal::Character(c) => write!(f, "'{}'", c),
             Literal::Boolean(b) => write!(f, "{}", b),
+            Literal::Unit => write!(f, "()"),
             Literal::Array(elements) => {
                 write!(f, "[")?;
                 for (i, elem) in elements.iter().enumerate() {
@@ -61,6 +158,20 @@ impl Display for Literal {
                 }
                 write!(f, "]")
             }
+            Literal::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, elem) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", elem)?;
+                }
+                // A one-element tuple needs its comma to differ from a parenthesized value.
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
@@ -73,7 +184,7 @@ pub struct BinaryExpr {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum BinaryOperator {
     Add,
     Sub,
@@ -106,6 +217,100 @@ pub enum BinaryOperator {
     ShrAssign,
 }
 
+impl BinaryOperator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Rem => "%",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+            BinaryOperator::BitAnd => "&",
+            BinaryOperator::BitOr => "|",
+            BinaryOperator::BitXor => "^",
+            BinaryOperator::Shl => "<<",
+            BinaryOperator::Shr => ">>",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::NotEq => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::LtEq => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::GtEq => ">=",
+            BinaryOperator::Assign => "=",
+            BinaryOperator::AddAssign => "+=",
+            BinaryOperator::SubAssign => "-=",
+            BinaryOperator::MulAssign => "*=",
+            BinaryOperator::DivAssign => "/=",
+            BinaryOperator::RemAssign => "%=",
+            BinaryOperator::BitAndAssign => "&=",
+            BinaryOperator::BitOrAssign => "|=",
+            BinaryOperator::BitXorAssign => "^=",
+            BinaryOperator::ShlAssign => "<<=",
+            BinaryOperator::ShrAssign => ">>=",
+        }
+    }
+
+    // Higher binds tighter. Assignments are the only right-associative level.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Assign
+            | BinaryOperator::AddAssign
+            | BinaryOperator::SubAssign
+            | BinaryOperator::MulAssign
+            | BinaryOperator::DivAssign
+            | BinaryOperator::RemAssign
+            | BinaryOperator::BitAndAssign
+            | BinaryOperator::BitOrAssign
+            | BinaryOperator::BitXorAssign
+            | BinaryOperator::ShlAssign
+            | BinaryOperator::ShrAssign => 1,
+            BinaryOperator::Or => 2,
+            BinaryOperator::And => 3,
+            BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Lt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::GtEq => 4,
+            BinaryOperator::BitOr => 5,
+            BinaryOperator::BitXor => 6,
+            BinaryOperator::BitAnd => 7,
+            BinaryOperator::Shl | BinaryOperator::Shr => 8,
+            BinaryOperator::Add | BinaryOperator::Sub => 9,
+            BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Rem => 10,
+        }
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        self.precedence() == 1
+    }
+
+    // The operator a compound assignment applies: `Add` for `+=`.
+    pub fn compound_base(&self) -> Option<BinaryOperator> {
+        match self {
+            BinaryOperator::AddAssign => Some(BinaryOperator::Add),
+            BinaryOperator::SubAssign => Some(BinaryOperator::Sub),
+            BinaryOperator::MulAssign => Some(BinaryOperator::Mul),
+            BinaryOperator::DivAssign => Some(BinaryOperator::Div),
+            BinaryOperator::RemAssign => Some(BinaryOperator::Rem),
+            BinaryOperator::BitAndAssign => Some(BinaryOperator::BitAnd),
+            BinaryOperator::BitOrAssign => Some(BinaryOperator::BitOr),
+            BinaryOperator::BitXorAssign => Some(BinaryOperator::BitXor),
+            BinaryOperator::ShlAssign => Some(BinaryOperator::Shl),
+            BinaryOperator::ShrAssign => Some(BinaryOperator::Shr),
+            _ => None,
+        }
+    }
+}
+
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct UnaryExpr {
     pub operator: UnaryOperator,
@@ -123,6 +328,19 @@ pub enum UnaryOperator {
     RefMut,
 }
 
+impl Display for UnaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOperator::Neg => f.write_str("-"),
+            UnaryOperator::Not => f.write_str("!"),
+            UnaryOperator::BitNot => f.write_str("~"),
+            UnaryOperator::Deref => f.write_str("*"),
+            UnaryOperator::Ref => f.write_str("&"),
+            UnaryOperator::RefMut => f.write_str("&mut "),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CallExpr {
     pub callee: Expression,
@@ -151,12 +369,6 @@ pub struct CastExpr {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct BlockExpr {
-    pub statements: Vec<Expression>,
-    pub span: Span,
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct IfExpr {
     pub condition: Expression,
@@ -172,6 +384,8 @@ pub struct MatchExpr {
     pub span: Span,
 }
 
+// Shared by `MatchExpr` and `MatchStatement`. Statement-position arms wrap
+// their block in `Expression::Block`.
 #[derive(Debug, Clone, PartialEq)]
 pub struct MatchArm {
     pub pattern: Pattern,
@@ -185,10 +399,85 @@ pub enum Pattern {
     Literal(Literal),
     Identifier(Identifier),
     Tuple(Vec<Pattern>),
-    Struct(Identifier, Vec<(Identifier, Pattern)>),
+    // The flag is set when the field list ends in `..`, ignoring the remaining fields.
+    Struct(Identifier, Vec<(Identifier, Pattern)>, bool),
     Or(Vec<Pattern>),
     Range(Box<Pattern>, Box<Pattern>),
     Wildcard,
+    // `..` inside a tuple pattern, standing for any number of elements.
+    Rest,
+}
+
+impl Pattern {
+    // The names this pattern binds, in source order. Every alternative of an
+    // or-pattern must bind the same names; the first alternative's are returned.
+    pub fn bindings(&self) -> Result<Vec<&Identifier>, InconsistentBindings> {
+        let mut bindings = Vec::new();
+        self.collect_bindings(&mut bindings)?;
+        Ok(bindings)
+    }
+
+    fn collect_bindings<'a>(
+        &'a self,
+        bindings: &mut Vec<&'a Identifier>,
+    ) -> Result<(), InconsistentBindings> {
+        match self {
+            Pattern::Identifier(ident) => bindings.push(ident),
+            Pattern::Tuple(patterns) => {
+                for pattern in patterns {
+                    pattern.collect_bindings(bindings)?;
+                }
+            }
+            Pattern::Struct(_, fields, _) => {
+                for (_, pattern) in fields {
+                    pattern.collect_bindings(bindings)?;
+                }
+            }
+            Pattern::Or(alternatives) => {
+                let Some((first, rest)) = alternatives.split_first() else {
+                    return Ok(());
+                };
+                let expected = first.bindings()?;
+                for alternative in rest {
+                    let found = alternative.bindings()?;
+                    let missing = |from: &[&'a Identifier], other: &[&Identifier]| {
+                        from.iter()
+                            .find(|ident| !other.iter().any(|o| o.name == ident.name))
+                            .map(|ident| InconsistentBindings {
+                                name: ident.name.clone(),
+                                span: ident.span.clone(),
+                            })
+                    };
+                    if let Some(error) =
+                        missing(&expected, &found).or_else(|| missing(&found, &expected))
+                    {
+                        return Err(error);
+                    }
+                }
+                bindings.extend(expected);
+            }
+            Pattern::Literal(_) | Pattern::Range(_, _) | Pattern::Wildcard | Pattern::Rest => {}
+        }
+        Ok(())
+    }
+}
+
+// A name bound by some alternatives of an or-pattern but not all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InconsistentBindings {
+    pub name: String,
+    // Where the name is bound.
+    pub span: Span,
+}
+
+impl Display for InconsistentBindings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "variable `{}` is not bound in all alternatives of the pattern",
+            self.name
+        )
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -223,6 +512,16 @@ pub struct RangeExpr {
     pub span: Span,
 }
 
+// `Name { field: value, ..base }`. Omitted fields are taken from `base` when
+// present, otherwise from the field's declared default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructExpr {
+    pub name: Identifier,
+    pub fields: Vec<(Identifier, Expression)>,
+    pub rest: Option<Box<Expression>>,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MacroInvocation {
     pub name: Identifier,
@@ -230,6 +529,43 @@ pub struct MacroInvocation {
     pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanicExpr {
+    pub message: Expression,
+    pub span: Span,
+}
+
+// The shortest text that reads back as exactly `n`. `Debug` gives that, and
+// unlike `Display` keeps a `.0` or an exponent, so `1.0` does not come back as
+// an integer and `1e300` stays short. NaN and the infinities cannot be written
+// as literals; they are printed as divisions that evaluate to them.
+// Writes `c` as it would appear between `quote`s in source, escaping the
+// backslash, the quote itself and control characters so the literal lexes
+// back to the same value.
+fn write_escaped(f: &mut Formatter<'_>, c: char, quote: char) -> fmt::Result {
+    match c {
+        '\\' => f.write_str("\\\\"),
+        '\n' => f.write_str("\\n"),
+        '\r' => f.write_str("\\r"),
+        '\t' => f.write_str("\\t"),
+        '\0' => f.write_str("\\0"),
+        c if c == quote => write!(f, "\\{}", c),
+        c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32),
+        c => write!(f, "{}", c),
+    }
+}
+
+fn write_float(f: &mut Formatter<'_>, n: f64) -> fmt::Result {
+    if n.is_nan() {
+        f.write_str("(0.0 / 0.0)")
+    } else if n.is_infinite() {
+        let sign = if n < 0.0 { "-" } else { "" };
+        write!(f, "({}1.0 / 0.0)", sign)
+    } else {
+        write!(f, "{:?}", n)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::f64::consts;
@@ -239,7 +575,7 @@ mod tests {
 
     #[test]
     fn test_literal_expressions() {
-        let integer = Expression::Literal(Literal::Integer(42, None));
+        let integer = Expression::Literal(Literal::Integer(42, None, IntBase::Dec));
         let float = Expression::Literal(Literal::Float(consts::PI, None));
         let string = Expression::Literal(Literal::String("hello".to_string()));
         let character = Expression::Literal(Literal::Character('a'));
@@ -247,7 +583,7 @@ mod tests {
 
         assert!(matches!(
             integer,
-            Expression::Literal(Literal::Integer(42, None))
+            Expression::Literal(Literal::Integer(42, None, IntBase::Dec))
         ));
         assert!(matches!(
             float,
@@ -267,8 +603,8 @@ mod tests {
     #[test]
     fn test_binary_expression() {
         let span = Span::dummy();
-        let left = Expression::Literal(Literal::Integer(1, None));
-        let right = Expression::Literal(Literal::Integer(2, None));
+        let left = Expression::Literal(Literal::Integer(1, None, IntBase::Dec));
+        let right = Expression::Literal(Literal::Integer(2, None, IntBase::Dec));
 
         let binary = Expression::Binary(Box::new(BinaryExpr {
             left,
@@ -281,11 +617,11 @@ mod tests {
             assert!(matches!(expr.operator, BinaryOperator::Add));
             assert!(matches!(
                 expr.left,
-                Expression::Literal(Literal::Integer(1, None))
+                Expression::Literal(Literal::Integer(1, None, IntBase::Dec))
             ));
             assert!(matches!(
                 expr.right,
-                Expression::Literal(Literal::Integer(2, None))
+                Expression::Literal(Literal::Integer(2, None, IntBase::Dec))
             ));
         } else {
             panic!("Expected binary expression");
@@ -296,7 +632,7 @@ mod tests {
     fn test_call_expression() {
         let span = Span::dummy();
         let callee = Expression::Identifier(Identifier::new("foo".to_string(), span.clone()));
-        let arg = Expression::Literal(Literal::Integer(42, None));
+        let arg = Expression::Literal(Literal::Integer(42, None, IntBase::Dec));
 
         let call = Expression::Call(Box::new(CallExpr {
             callee,
@@ -316,8 +652,8 @@ mod tests {
     fn test_if_expression() {
         let span = Span::dummy();
         let condition = Expression::Literal(Literal::Boolean(true));
-        let then_branch = Expression::Literal(Literal::Integer(1, None));
-        let else_branch = Some(Expression::Literal(Literal::Integer(2, None)));
+        let then_branch = Expression::Literal(Literal::Integer(1, None, IntBase::Dec));
+        let else_branch = Some(Expression::Literal(Literal::Integer(2, None, IntBase::Dec)));
 
         let if_expr = Expression::If(Box::new(IfExpr {
             condition,
@@ -333,11 +669,11 @@ mod tests {
             ));
             assert!(matches!(
                 expr.then_branch,
-                Expression::Literal(Literal::Integer(1, None))
+                Expression::Literal(Literal::Integer(1, None, IntBase::Dec))
             ));
             assert!(matches!(
                 expr.else_branch,
-                Some(Expression::Literal(Literal::Integer(2, None)))
+                Some(Expression::Literal(Literal::Integer(2, None, IntBase::Dec)))
             ));
         } else {
             panic!("Expected if expression");
@@ -348,7 +684,7 @@ mod tests {
     fn test_match_expression() {
         let span = Span::dummy();
         let value = Expression::Identifier(Identifier::new("x".to_string(), span.clone()));
-        let pattern = Pattern::Literal(Literal::Integer(1, None));
+        let pattern = Pattern::Literal(Literal::Integer(1, None, IntBase::Dec));
         let body = Expression::Literal(Literal::String("one".to_string()));
 
         let match_expr = Expression::Match(Box::new(MatchExpr {
@@ -367,10 +703,118 @@ mod tests {
             assert_eq!(expr.arms.len(), 1);
             assert!(matches!(
                 expr.arms[0].pattern,
-                Pattern::Literal(Literal::Integer(1, None))
+                Pattern::Literal(Literal::Integer(1, None, IntBase::Dec))
             ));
         } else {
             panic!("Expected match expression");
         }
     }
+
+    #[test]
+    fn test_char_to_u32() {
+        assert_eq!(Literal::Character('a').char_to_u32(), Some(97));
+        assert_eq!(Literal::Character('\u{1F600}').char_to_u32(), Some(0x1F600));
+        assert_eq!(Literal::Boolean(true).char_to_u32(), None);
+    }
+
+    #[test]
+    fn test_literal_accessors() {
+        let string = Literal::String("hi".to_string());
+        let character = Literal::Character('x');
+        let integer = Literal::Integer(7, Some(Type::U8), IntBase::Dec);
+        let float = Literal::Float(1.5, None);
+        let boolean = Literal::Boolean(true);
+
+        assert_eq!(string.as_str(), Some("hi"));
+        assert_eq!(character.as_char(), Some('x'));
+        assert_eq!(integer.as_integer(), Some(7));
+        assert_eq!(float.as_float(), Some(1.5));
+        assert_eq!(boolean.as_bool(), Some(true));
+
+        assert_eq!(character.as_str(), None);
+        assert_eq!(string.as_char(), None);
+        assert_eq!(float.as_integer(), None);
+        assert_eq!(integer.as_float(), None);
+        assert_eq!(Literal::Unit.as_bool(), None);
+    }
+
+    #[test]
+    fn test_integer_display_keeps_base() {
+        assert_eq!(
+            Literal::Integer(255, None, IntBase::Hex).to_string(),
+            "0xff"
+        );
+        assert_eq!(Literal::Integer(15, None, IntBase::Oct).to_string(), "0o17");
+        assert_eq!(
+            Literal::Integer(10, None, IntBase::Bin).to_string(),
+            "0b1010"
+        );
+        assert_eq!(
+            Literal::Integer(-16, Some(Type::I8), IntBase::Hex).to_string(),
+            "-0x10i8"
+        );
+        assert_eq!(
+            Literal::Integer(1000, None, IntBase::Dec).to_string(),
+            "1000"
+        );
+    }
+
+    fn binding(name: &str, column: usize) -> Pattern {
+        Pattern::Identifier(Identifier::new(
+            name.to_string(),
+            Span::new(column - 1, column, 1, column),
+        ))
+    }
+
+    fn names(pattern: &Pattern) -> Vec<&str> {
+        let bindings = pattern.bindings().unwrap();
+        bindings.iter().map(|ident| ident.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tuple_pattern_bindings() {
+        let pattern = Pattern::Tuple(vec![binding("a", 2), Pattern::Wildcard, binding("b", 8)]);
+        assert_eq!(names(&pattern), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_struct_pattern_bindings() {
+        let field = |name: &str, column| {
+            let ident = Identifier::new(name.to_string(), Span::new(column - 1, column, 1, column));
+            (ident, binding(name, column))
+        };
+        let pattern = Pattern::Struct(
+            Identifier::new("Point".to_string(), Span::new(0, 5, 1, 1)),
+            vec![field("x", 9), field("y", 12)],
+            false,
+        );
+        assert_eq!(names(&pattern), vec!["x", "y"]);
+    }
+
+    #[test]
+    fn test_or_pattern_bindings_must_agree() {
+        let consistent = Pattern::Or(vec![
+            Pattern::Tuple(vec![
+                binding("x", 2),
+                Pattern::Literal(Literal::Boolean(true)),
+            ]),
+            Pattern::Tuple(vec![
+                Pattern::Literal(Literal::Boolean(false)),
+                binding("x", 18),
+            ]),
+        ]);
+        assert_eq!(names(&consistent), vec!["x"]);
+
+        let inconsistent = Pattern::Or(vec![
+            Pattern::Tuple(vec![binding("x", 2), binding("y", 5)]),
+            Pattern::Tuple(vec![binding("x", 11), Pattern::Wildcard]),
+        ]);
+        let error = inconsistent.bindings().unwrap_err();
+        assert_eq!(error.name, "y");
+        assert_eq!(error.span.column, 5);
+        assert_eq!(
+            error.to_string(),
+            "variable `y` is not bound in all alternatives of the pattern"
+        );
+    }
 }
@@ -0,0 +1,413 @@
+use super::expressions::{Expression, Literal, MatchArm, Pattern};
+use super::statements::{ElseBranch, IfStatement, Statement};
+use super::types::TypePath;
+use super::{Block, Declaration, FunctionDecl, Parameter, Program, Type};
+
+// Rewrites an AST by value: each method takes ownership of a node and returns
+// its replacement, which may be a different kind of node. The defaults fold
+// every child through the matching `walk_*` function and rebuild the node, so
+// an implementation only overrides the methods for the nodes it rewrites.
+pub trait Folder {
+    fn fold_program(&mut self, program: Program) -> Program {
+        walk_program(self, program)
+    }
+
+    fn fold_declaration(&mut self, declaration: Declaration) -> Declaration {
+        walk_declaration(self, declaration)
+    }
+
+    fn fold_function(&mut self, function: FunctionDecl) -> FunctionDecl {
+        walk_function(self, function)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        walk_block(self, block)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        walk_statement(self, statement)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        walk_expression(self, expression)
+    }
+
+    fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+        walk_pattern(self, pattern)
+    }
+
+    fn fold_type(&mut self, ty: Type) -> Type {
+        walk_type(self, ty)
+    }
+}
+
+pub fn walk_program<F: Folder + ?Sized>(folder: &mut F, mut program: Program) -> Program {
+    program.items = fold_all(program.items, |item| folder.fold_declaration(item));
+    program
+}
+
+pub fn walk_declaration<F: Folder + ?Sized>(
+    folder: &mut F,
+    declaration: Declaration,
+) -> Declaration {
+    match declaration {
+        Declaration::Function(function) => Declaration::Function(folder.fold_function(function)),
+        Declaration::Struct(mut decl) => {
+            for field in &mut decl.fields {
+                field.ty = folder.fold_type(field.ty.clone());
+                field.default = field.default.take().map(|e| folder.fold_expression(e));
+            }
+            Declaration::Struct(decl)
+        }
+        Declaration::Enum(mut decl) => {
+            for variant in &mut decl.variants {
+                variant.data = variant.data.take().map(|ty| folder.fold_type(ty));
+                variant.discriminant = variant
+                    .discriminant
+                    .take()
+                    .map(|e| folder.fold_expression(e));
+            }
+            Declaration::Enum(decl)
+        }
+        Declaration::Union(mut decl) => {
+            for field in &mut decl.fields {
+                field.ty = folder.fold_type(field.ty.clone());
+            }
+            Declaration::Union(decl)
+        }
+        Declaration::Variable(mut decl) => {
+            decl.ty = decl.ty.map(|ty| folder.fold_type(ty));
+            decl.initializer = decl.initializer.map(|e| folder.fold_expression(e));
+            Declaration::Variable(decl)
+        }
+        Declaration::Constant(mut decl) => {
+            decl.ty = folder.fold_type(decl.ty);
+            decl.value = folder.fold_expression(decl.value);
+            Declaration::Constant(decl)
+        }
+        Declaration::Static(mut decl) => {
+            decl.ty = folder.fold_type(decl.ty);
+            decl.value = folder.fold_expression(decl.value);
+            Declaration::Static(decl)
+        }
+        Declaration::Module(mut module) => {
+            module.items = module
+                .items
+                .map(|items| fold_all(items, |item| folder.fold_declaration(item)));
+            Declaration::Module(module)
+        }
+        // Macro bodies are unparsed token streams.
+        Declaration::Macro(decl) => Declaration::Macro(decl),
+    }
+}
+
+pub fn walk_function<F: Folder + ?Sized>(
+    folder: &mut F,
+    mut function: FunctionDecl,
+) -> FunctionDecl {
+    function.params = fold_all(function.params, |param| Parameter {
+        ty: folder.fold_type(param.ty),
+        default: param.default.map(|e| folder.fold_expression(e)),
+        ..param
+    });
+    function.return_type = function
+        .return_type
+        .map(|ty| Box::new(folder.fold_type(*ty)));
+    function.body = folder.fold_block(function.body);
+    function
+}
+
+pub fn walk_block<F: Folder + ?Sized>(folder: &mut F, mut block: Block) -> Block {
+    block.statements = fold_all(block.statements, |s| folder.fold_statement(s));
+    block.tail = block.tail.map(|e| Box::new(folder.fold_expression(*e)));
+    block
+}
+
+pub fn walk_statement<F: Folder + ?Sized>(folder: &mut F, statement: Statement) -> Statement {
+    match statement {
+        Statement::Empty | Statement::Continue(_) => statement,
+        Statement::Expression(expr) => Statement::Expression(folder.fold_expression(expr)),
+        Statement::Let(mut stmt) => {
+            stmt.pattern = folder.fold_pattern(stmt.pattern);
+            stmt.type_annotation = stmt.type_annotation.map(|ty| folder.fold_type(ty));
+            stmt.initializer = stmt.initializer.map(|e| folder.fold_expression(e));
+            Statement::Let(stmt)
+        }
+        Statement::Return(mut stmt) => {
+            stmt.expression = stmt.expression.map(|e| folder.fold_expression(e));
+            Statement::Return(stmt)
+        }
+        Statement::Break(mut stmt) => {
+            stmt.expression = stmt.expression.map(|e| folder.fold_expression(e));
+            Statement::Break(stmt)
+        }
+        Statement::While(mut stmt) => {
+            stmt.condition = folder.fold_expression(stmt.condition);
+            stmt.body = folder.fold_block(stmt.body);
+            Statement::While(stmt)
+        }
+        Statement::For(mut stmt) => {
+            stmt.pattern = folder.fold_pattern(stmt.pattern);
+            stmt.iterator = folder.fold_expression(stmt.iterator);
+            stmt.body = folder.fold_block(stmt.body);
+            Statement::For(stmt)
+        }
+        Statement::Loop(mut stmt) => {
+            stmt.body = folder.fold_block(stmt.body);
+            Statement::Loop(stmt)
+        }
+        Statement::Block(block) => Statement::Block(folder.fold_block(block)),
+        Statement::If(stmt) => Statement::If(walk_if_statement(folder, stmt)),
+        Statement::Match(mut stmt) => {
+            stmt.expression = folder.fold_expression(stmt.expression);
+            stmt.arms = walk_match_arms(folder, stmt.arms);
+            Statement::Match(stmt)
+        }
+        Statement::Panic(mut stmt) => {
+            stmt.message = folder.fold_expression(stmt.message);
+            Statement::Panic(stmt)
+        }
+    }
+}
+
+fn walk_if_statement<F: Folder + ?Sized>(folder: &mut F, mut stmt: IfStatement) -> IfStatement {
+    stmt.condition = folder.fold_expression(stmt.condition);
+    stmt.then_branch = folder.fold_block(stmt.then_branch);
+    stmt.else_branch = stmt.else_branch.map(|branch| match branch {
+        ElseBranch::Block(block) => ElseBranch::Block(folder.fold_block(block)),
+        ElseBranch::If(nested) => ElseBranch::If(Box::new(walk_if_statement(folder, *nested))),
+    });
+    stmt
+}
+
+fn walk_match_arms<F: Folder + ?Sized>(folder: &mut F, arms: Vec<MatchArm>) -> Vec<MatchArm> {
+    fold_all(arms, |arm| MatchArm {
+        pattern: folder.fold_pattern(arm.pattern),
+        guard: arm.guard.map(|e| folder.fold_expression(e)),
+        body: folder.fold_expression(arm.body),
+        span: arm.span,
+    })
+}
+
+pub fn walk_expression<F: Folder + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    match expression {
+        Expression::Literal(Literal::Array(elements)) => {
+            Expression::Literal(Literal::Array(fold_all(elements, |e| {
+                folder.fold_expression(e)
+            })))
+        }
+        Expression::Literal(Literal::Tuple(elements)) => {
+            Expression::Literal(Literal::Tuple(fold_all(elements, |e| {
+                folder.fold_expression(e)
+            })))
+        }
+        Expression::Literal(Literal::Integer(value, suffix, base)) => Expression::Literal(
+            Literal::Integer(value, suffix.map(|ty| folder.fold_type(ty)), base),
+        ),
+        Expression::Literal(Literal::Float(value, suffix)) => {
+            Expression::Literal(Literal::Float(value, suffix.map(|ty| folder.fold_type(ty))))
+        }
+        Expression::Literal(_) | Expression::Identifier(_) => expression,
+        Expression::Binary(mut binary) => {
+            binary.left = folder.fold_expression(binary.left);
+            binary.right = folder.fold_expression(binary.right);
+            Expression::Binary(binary)
+        }
+        Expression::Unary(mut unary) => {
+            unary.operand = folder.fold_expression(unary.operand);
+            Expression::Unary(unary)
+        }
+        Expression::Call(mut call) => {
+            call.callee = folder.fold_expression(call.callee);
+            call.arguments = fold_all(call.arguments, |e| folder.fold_expression(e));
+            Expression::Call(call)
+        }
+        Expression::Member(mut member) => {
+            member.object = folder.fold_expression(member.object);
+            Expression::Member(member)
+        }
+        Expression::Index(mut index) => {
+            index.array = folder.fold_expression(index.array);
+            index.index = folder.fold_expression(index.index);
+            Expression::Index(index)
+        }
+        Expression::Cast(mut cast) => {
+            cast.expr = folder.fold_expression(cast.expr);
+            cast.target_type = folder.fold_type(cast.target_type);
+            Expression::Cast(cast)
+        }
+        Expression::Block(block) => Expression::Block(Box::new(folder.fold_block(*block))),
+        Expression::If(mut if_expr) => {
+            if_expr.condition = folder.fold_expression(if_expr.condition);
+            if_expr.then_branch = folder.fold_expression(if_expr.then_branch);
+            if_expr.else_branch = if_expr.else_branch.map(|e| folder.fold_expression(e));
+            Expression::If(if_expr)
+        }
+        Expression::Match(mut match_expr) => {
+            match_expr.value = folder.fold_expression(match_expr.value);
+            match_expr.arms = walk_match_arms(folder, match_expr.arms);
+            Expression::Match(match_expr)
+        }
+        Expression::Loop(mut loop_expr) => {
+            loop_expr.body = folder.fold_expression(loop_expr.body);
+            Expression::Loop(loop_expr)
+        }
+        Expression::While(mut while_expr) => {
+            while_expr.condition = folder.fold_expression(while_expr.condition);
+            while_expr.body = folder.fold_expression(while_expr.body);
+            Expression::While(while_expr)
+        }
+        Expression::For(mut for_expr) => {
+            for_expr.pattern = folder.fold_pattern(for_expr.pattern);
+            for_expr.iterator = folder.fold_expression(for_expr.iterator);
+            for_expr.body = folder.fold_expression(for_expr.body);
+            Expression::For(for_expr)
+        }
+        Expression::Range(mut range) => {
+            range.start = range.start.map(|e| folder.fold_expression(e));
+            range.end = range.end.map(|e| folder.fold_expression(e));
+            Expression::Range(range)
+        }
+        Expression::MacroInvocation(mut invocation) => {
+            invocation.arguments = fold_all(invocation.arguments, |e| folder.fold_expression(e));
+            Expression::MacroInvocation(invocation)
+        }
+        Expression::Panic(mut panic) => {
+            panic.message = folder.fold_expression(panic.message);
+            Expression::Panic(panic)
+        }
+        Expression::Struct(mut literal) => {
+            literal.fields = fold_all(literal.fields, |(field, e)| {
+                (field, folder.fold_expression(e))
+            });
+            literal.rest = literal.rest.map(|e| Box::new(folder.fold_expression(*e)));
+            Expression::Struct(literal)
+        }
+        Expression::Path(path) => Expression::Path(walk_path(folder, path)),
+        Expression::Paren(inner, span) => {
+            Expression::Paren(Box::new(folder.fold_expression(*inner)), span)
+        }
+    }
+}
+
+pub fn walk_pattern<F: Folder + ?Sized>(folder: &mut F, pattern: Pattern) -> Pattern {
+    match pattern {
+        Pattern::Tuple(patterns) => Pattern::Tuple(fold_all(patterns, |p| folder.fold_pattern(p))),
+        Pattern::Struct(name, fields, rest) => Pattern::Struct(
+            name,
+            fold_all(fields, |(field, p)| (field, folder.fold_pattern(p))),
+            rest,
+        ),
+        Pattern::Or(patterns) => Pattern::Or(fold_all(patterns, |p| folder.fold_pattern(p))),
+        Pattern::Range(start, end) => Pattern::Range(
+            Box::new(folder.fold_pattern(*start)),
+            Box::new(folder.fold_pattern(*end)),
+        ),
+        Pattern::Literal(_) | Pattern::Identifier(_) | Pattern::Wildcard | Pattern::Rest => pattern,
+    }
+}
+
+pub fn walk_type<F: Folder + ?Sized>(folder: &mut F, ty: Type) -> Type {
+    match ty {
+        Type::Array(element, size) => Type::Array(
+            Box::new(folder.fold_type(*element)),
+            size.map(|e| Box::new(folder.fold_expression(*e))),
+        ),
+        Type::Slice(element) => Type::Slice(Box::new(folder.fold_type(*element))),
+        Type::Pointer(target, mutability) => {
+            Type::Pointer(Box::new(folder.fold_type(*target)), mutability)
+        }
+        Type::Reference(target, mutability, lifetime) => {
+            Type::Reference(Box::new(folder.fold_type(*target)), mutability, lifetime)
+        }
+        Type::Tuple(types) => Type::Tuple(fold_all(types, |t| folder.fold_type(t))),
+        Type::Function(params, ret, abi) => Type::Function(
+            fold_all(params, |t| folder.fold_type(t)),
+            Box::new(folder.fold_type(*ret)),
+            abi,
+        ),
+        Type::Named(path) => Type::Named(walk_path(folder, path)),
+        Type::Generic(base, args) => Type::Generic(
+            Box::new(folder.fold_type(*base)),
+            fold_all(args, |t| folder.fold_type(t)),
+        ),
+        _ => ty,
+    }
+}
+
+fn walk_path<F: Folder + ?Sized>(folder: &mut F, mut path: TypePath) -> TypePath {
+    path.segments = fold_all(path.segments, |mut segment| {
+        segment.generic_args = segment
+            .generic_args
+            .map(|args| fold_all(args, |t| folder.fold_type(t)));
+        segment
+    });
+    path
+}
+
+fn fold_all<T>(items: Vec<T>, fold: impl FnMut(T) -> T) -> Vec<T> {
+    items.into_iter().map(fold).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::expressions::{BinaryExpr, BinaryOperator};
+    use crate::parser::parse;
+    use crate::printer::print_program;
+
+    // Rewrites `a op= b` into `a = a op b`.
+    struct DesugarCompound;
+
+    impl Folder for DesugarCompound {
+        fn fold_expression(&mut self, expression: Expression) -> Expression {
+            match walk_expression(self, expression) {
+                Expression::Binary(binary) => match binary.operator.compound_base() {
+                    Some(operator) => {
+                        let BinaryExpr {
+                            left, right, span, ..
+                        } = *binary;
+                        let value = Expression::Binary(Box::new(BinaryExpr {
+                            left: left.clone(),
+                            operator,
+                            right,
+                            span: span.clone(),
+                        }));
+                        Expression::Binary(Box::new(BinaryExpr {
+                            left,
+                            operator: BinaryOperator::Assign,
+                            right: value,
+                            span,
+                        }))
+                    }
+                    None => Expression::Binary(binary),
+                },
+                other => other,
+            }
+        }
+    }
+
+    #[test]
+    fn test_folder_desugars_compound_assignment() {
+        let program =
+            parse("fn f() { var mut x = 1; x += 2; if x > 0 { x <<= f(x -= 1); } }").unwrap();
+        let folded = DesugarCompound.fold_program(program);
+
+        assert_eq!(
+            print_program(&folded),
+            "fn f() {\n    var mut x = 1;\n    x = x + 2;\n    if x > 0 {\n        x = x << f(x = x - 1);\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_default_folder_is_identity() {
+        struct Identity;
+        impl Folder for Identity {}
+
+        let program = parse(
+            "struct P { x: [u8; 4] }\nfn g(a: i32 = 1) -> i32 { match a { 0 | 1 => 2, n => n } }",
+        )
+        .unwrap();
+        assert_eq!(Identity.fold_program(program.clone()), program);
+    }
+}
@@ -0,0 +1,373 @@
+use super::expressions::{Expression, Literal, MatchArm, Pattern};
+use super::statements::{ElseBranch, IfStatement, Statement};
+use super::{
+    Block, Declaration, EnumVariant, Identifier, Parameter, Program, Span, StructField, UnionField,
+};
+
+// A borrowed node of any kind, as found by `Program::node_at`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeRef<'a> {
+    Declaration(&'a Declaration),
+    Parameter(&'a Parameter),
+    StructField(&'a StructField),
+    EnumVariant(&'a EnumVariant),
+    UnionField(&'a UnionField),
+    Block(&'a Block),
+    Statement(&'a Statement),
+    MatchArm(&'a MatchArm),
+    Expression(&'a Expression),
+    Pattern(&'a Pattern),
+    Identifier(&'a Identifier),
+}
+
+impl Program {
+    // The narrowest node whose span contains the byte at `offset`. Nodes
+    // without a span of their own (literals, patterns, expression statements)
+    // are never returned, but the nodes inside them are searched.
+    pub fn node_at(&self, offset: usize) -> Option<NodeRef<'_>> {
+        self.items
+            .iter()
+            .find_map(|item| find(NodeRef::Declaration(item), offset))
+    }
+}
+
+fn find(node: NodeRef<'_>, offset: usize) -> Option<NodeRef<'_>> {
+    let span = node.span();
+    if let Some(span) = span
+        && !(span.start <= offset && offset < span.end)
+    {
+        return None;
+    }
+    node.children()
+        .into_iter()
+        .find_map(|child| find(child, offset))
+        .or_else(|| span.map(|_| node))
+}
+
+impl<'a> NodeRef<'a> {
+    pub fn span(&self) -> Option<&'a Span> {
+        match *self {
+            NodeRef::Declaration(declaration) => Some(declaration.span()),
+            NodeRef::Parameter(param) => Some(&param.span),
+            NodeRef::StructField(field) => Some(&field.span),
+            NodeRef::EnumVariant(variant) => Some(&variant.span),
+            NodeRef::UnionField(field) => Some(&field.span),
+            NodeRef::Block(block) => Some(&block.span),
+            NodeRef::Statement(statement) => statement_span(statement),
+            NodeRef::MatchArm(arm) => Some(&arm.span),
+            NodeRef::Expression(expr) => expression_span(expr),
+            NodeRef::Pattern(_) => None,
+            NodeRef::Identifier(ident) => Some(&ident.span),
+        }
+    }
+
+    // Direct children in source order.
+    pub fn children(&self) -> Vec<NodeRef<'a>> {
+        let mut children = Vec::new();
+        match *self {
+            NodeRef::Declaration(declaration) => declaration_children(declaration, &mut children),
+            NodeRef::Parameter(param) => {
+                children.push(NodeRef::Identifier(&param.name));
+                children.extend(param.default.as_ref().map(NodeRef::Expression));
+            }
+            NodeRef::StructField(field) => {
+                children.push(NodeRef::Identifier(&field.name));
+                children.extend(field.default.as_ref().map(NodeRef::Expression));
+            }
+            NodeRef::EnumVariant(variant) => {
+                children.push(NodeRef::Identifier(&variant.name));
+                children.extend(variant.discriminant.as_ref().map(NodeRef::Expression));
+            }
+            NodeRef::UnionField(field) => children.push(NodeRef::Identifier(&field.name)),
+            NodeRef::Block(block) => {
+                children.extend(block.statements.iter().map(NodeRef::Statement));
+                children.extend(block.tail.as_deref().map(NodeRef::Expression));
+            }
+            NodeRef::Statement(statement) => statement_children(statement, &mut children),
+            NodeRef::MatchArm(arm) => {
+                children.push(NodeRef::Pattern(&arm.pattern));
+                children.extend(arm.guard.as_ref().map(NodeRef::Expression));
+                children.push(NodeRef::Expression(&arm.body));
+            }
+            NodeRef::Expression(expr) => expression_children(expr, &mut children),
+            NodeRef::Pattern(pattern) => pattern_children(pattern, &mut children),
+            NodeRef::Identifier(_) => {}
+        }
+        children
+    }
+}
+
+fn declaration_children<'a>(declaration: &'a Declaration, children: &mut Vec<NodeRef<'a>>) {
+    match declaration {
+        Declaration::Function(function) => {
+            children.push(NodeRef::Identifier(&function.name));
+            children.extend(function.generics.iter().map(NodeRef::Identifier));
+            children.extend(function.params.iter().map(NodeRef::Parameter));
+            children.push(NodeRef::Block(&function.body));
+        }
+        Declaration::Struct(decl) => {
+            children.push(NodeRef::Identifier(&decl.name));
+            children.extend(decl.fields.iter().map(NodeRef::StructField));
+        }
+        Declaration::Enum(decl) => {
+            children.push(NodeRef::Identifier(&decl.name));
+            children.extend(decl.variants.iter().map(NodeRef::EnumVariant));
+        }
+        Declaration::Union(decl) => {
+            children.push(NodeRef::Identifier(&decl.name));
+            children.extend(decl.fields.iter().map(NodeRef::UnionField));
+        }
+        Declaration::Variable(decl) => {
+            children.push(NodeRef::Identifier(&decl.name));
+            children.extend(decl.initializer.as_ref().map(NodeRef::Expression));
+        }
+        Declaration::Constant(decl) => {
+            children.push(NodeRef::Identifier(&decl.name));
+            children.push(NodeRef::Expression(&decl.value));
+        }
+        Declaration::Static(decl) => {
+            children.push(NodeRef::Identifier(&decl.name));
+            children.push(NodeRef::Expression(&decl.value));
+        }
+        Declaration::Module(module) => {
+            children.push(NodeRef::Identifier(&module.name));
+            if let Some(items) = &module.items {
+                children.extend(items.iter().map(NodeRef::Declaration));
+            }
+        }
+        Declaration::Macro(decl) => children.push(NodeRef::Identifier(&decl.name)),
+    }
+}
+
+fn statement_span(statement: &Statement) -> Option<&Span> {
+    match statement {
+        Statement::Empty | Statement::Expression(_) => None,
+        Statement::Let(stmt) => Some(&stmt.span),
+        Statement::Return(stmt) => Some(&stmt.span),
+        Statement::Break(stmt) => Some(&stmt.span),
+        Statement::Continue(stmt) => Some(&stmt.span),
+        Statement::While(stmt) => Some(&stmt.span),
+        Statement::For(stmt) => Some(&stmt.span),
+        Statement::Loop(stmt) => Some(&stmt.span),
+        Statement::Block(block) => Some(&block.span),
+        Statement::If(stmt) => Some(&stmt.span),
+        Statement::Match(stmt) => Some(&stmt.span),
+        Statement::Panic(stmt) => Some(&stmt.span),
+    }
+}
+
+fn statement_children<'a>(statement: &'a Statement, children: &mut Vec<NodeRef<'a>>) {
+    match statement {
+        Statement::Empty => {}
+        Statement::Expression(expr) => children.push(NodeRef::Expression(expr)),
+        Statement::Let(stmt) => {
+            children.push(NodeRef::Pattern(&stmt.pattern));
+            children.extend(stmt.initializer.as_ref().map(NodeRef::Expression));
+        }
+        Statement::Return(stmt) => {
+            children.extend(stmt.expression.as_ref().map(NodeRef::Expression))
+        }
+        Statement::Break(stmt) => {
+            children.extend(stmt.label.as_ref().map(NodeRef::Identifier));
+            children.extend(stmt.expression.as_ref().map(NodeRef::Expression));
+        }
+        Statement::Continue(stmt) => children.extend(stmt.label.as_ref().map(NodeRef::Identifier)),
+        Statement::While(stmt) => {
+            children.push(NodeRef::Expression(&stmt.condition));
+            children.push(NodeRef::Block(&stmt.body));
+        }
+        Statement::For(stmt) => {
+            children.push(NodeRef::Pattern(&stmt.pattern));
+            children.push(NodeRef::Expression(&stmt.iterator));
+            children.push(NodeRef::Block(&stmt.body));
+        }
+        Statement::Loop(stmt) => children.push(NodeRef::Block(&stmt.body)),
+        // The statement and its block share a span; the block's contents are
+        // the statement's children.
+        Statement::Block(block) => children.extend(NodeRef::Block(block).children()),
+        Statement::If(stmt) => if_statement_children(stmt, children),
+        Statement::Match(stmt) => {
+            children.push(NodeRef::Expression(&stmt.expression));
+            children.extend(stmt.arms.iter().map(NodeRef::MatchArm));
+        }
+        Statement::Panic(stmt) => children.push(NodeRef::Expression(&stmt.message)),
+    }
+}
+
+fn if_statement_children<'a>(stmt: &'a IfStatement, children: &mut Vec<NodeRef<'a>>) {
+    children.push(NodeRef::Expression(&stmt.condition));
+    children.push(NodeRef::Block(&stmt.then_branch));
+    match &stmt.else_branch {
+        Some(ElseBranch::Block(block)) => children.push(NodeRef::Block(block)),
+        Some(ElseBranch::If(nested)) => if_statement_children(nested, children),
+        None => {}
+    }
+}
+
+fn expression_span(expr: &Expression) -> Option<&Span> {
+    match expr {
+        Expression::Literal(_) => None,
+        Expression::Identifier(ident) => Some(&ident.span),
+        Expression::Binary(binary) => Some(&binary.span),
+        Expression::Unary(unary) => Some(&unary.span),
+        Expression::Call(call) => Some(&call.span),
+        Expression::Member(member) => Some(&member.span),
+        Expression::Index(index) => Some(&index.span),
+        Expression::Cast(cast) => Some(&cast.span),
+        Expression::Block(block) => Some(&block.span),
+        Expression::If(if_expr) => Some(&if_expr.span),
+        Expression::Match(match_expr) => Some(&match_expr.span),
+        Expression::Loop(loop_expr) => Some(&loop_expr.span),
+        Expression::While(while_expr) => Some(&while_expr.span),
+        Expression::For(for_expr) => Some(&for_expr.span),
+        Expression::Range(range) => Some(&range.span),
+        Expression::MacroInvocation(invocation) => Some(&invocation.span),
+        Expression::Panic(panic) => Some(&panic.span),
+        Expression::Struct(literal) => Some(&literal.span),
+        Expression::Path(path) => Some(&path.span),
+        Expression::Paren(_, span) => Some(span),
+    }
+}
+
+fn expression_children<'a>(expr: &'a Expression, children: &mut Vec<NodeRef<'a>>) {
+    match expr {
+        Expression::Literal(Literal::Array(elements) | Literal::Tuple(elements)) => {
+            children.extend(elements.iter().map(NodeRef::Expression));
+        }
+        Expression::Literal(_) => {}
+        // An identifier expression is the identifier; there is nothing narrower.
+        Expression::Identifier(_) => {}
+        Expression::Binary(binary) => {
+            children.push(NodeRef::Expression(&binary.left));
+            children.push(NodeRef::Expression(&binary.right));
+        }
+        Expression::Unary(unary) => children.push(NodeRef::Expression(&unary.operand)),
+        Expression::Call(call) => {
+            children.push(NodeRef::Expression(&call.callee));
+            children.extend(call.arguments.iter().map(NodeRef::Expression));
+        }
+        Expression::Member(member) => {
+            children.push(NodeRef::Expression(&member.object));
+            children.push(NodeRef::Identifier(&member.member));
+        }
+        Expression::Index(index) => {
+            children.push(NodeRef::Expression(&index.array));
+            children.push(NodeRef::Expression(&index.index));
+        }
+        Expression::Cast(cast) => children.push(NodeRef::Expression(&cast.expr)),
+        Expression::Block(block) => children.extend(NodeRef::Block(block).children()),
+        Expression::If(if_expr) => {
+            children.push(NodeRef::Expression(&if_expr.condition));
+            children.push(NodeRef::Expression(&if_expr.then_branch));
+            children.extend(if_expr.else_branch.as_ref().map(NodeRef::Expression));
+        }
+        Expression::Match(match_expr) => {
+            children.push(NodeRef::Expression(&match_expr.value));
+            children.extend(match_expr.arms.iter().map(NodeRef::MatchArm));
+        }
+        Expression::Loop(loop_expr) => children.push(NodeRef::Expression(&loop_expr.body)),
+        Expression::While(while_expr) => {
+            children.push(NodeRef::Expression(&while_expr.condition));
+            children.push(NodeRef::Expression(&while_expr.body));
+        }
+        Expression::For(for_expr) => {
+            children.push(NodeRef::Pattern(&for_expr.pattern));
+            children.push(NodeRef::Expression(&for_expr.iterator));
+            children.push(NodeRef::Expression(&for_expr.body));
+        }
+        Expression::Range(range) => {
+            children.extend(range.start.as_ref().map(NodeRef::Expression));
+            children.extend(range.end.as_ref().map(NodeRef::Expression));
+        }
+        Expression::MacroInvocation(invocation) => {
+            children.push(NodeRef::Identifier(&invocation.name));
+            children.extend(invocation.arguments.iter().map(NodeRef::Expression));
+        }
+        Expression::Panic(panic) => children.push(NodeRef::Expression(&panic.message)),
+        Expression::Struct(literal) => {
+            children.push(NodeRef::Identifier(&literal.name));
+            for (field, value) in &literal.fields {
+                children.push(NodeRef::Identifier(field));
+                children.push(NodeRef::Expression(value));
+            }
+            children.extend(literal.rest.as_deref().map(NodeRef::Expression));
+        }
+        Expression::Path(path) => {
+            children.extend(
+                path.segments
+                    .iter()
+                    .map(|segment| NodeRef::Identifier(&segment.ident)),
+            );
+        }
+        Expression::Paren(inner, _) => children.push(NodeRef::Expression(inner)),
+    }
+}
+
+fn pattern_children<'a>(pattern: &'a Pattern, children: &mut Vec<NodeRef<'a>>) {
+    match pattern {
+        Pattern::Identifier(ident) => children.push(NodeRef::Identifier(ident)),
+        Pattern::Tuple(patterns) | Pattern::Or(patterns) => {
+            children.extend(patterns.iter().map(NodeRef::Pattern));
+        }
+        Pattern::Struct(name, fields, _) => {
+            children.push(NodeRef::Identifier(name));
+            for (field, pattern) in fields {
+                children.push(NodeRef::Identifier(field));
+                children.push(NodeRef::Pattern(pattern));
+            }
+        }
+        Pattern::Range(start, end) => {
+            children.push(NodeRef::Pattern(start));
+            children.push(NodeRef::Pattern(end));
+        }
+        Pattern::Literal(_) | Pattern::Wildcard | Pattern::Rest => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_node_at_finds_identifier_in_body() {
+        let source = "fn f(count: i32) -> i32 {\n    let doubled = count * 2;\n    doubled\n}";
+        let program = parse(source).unwrap();
+
+        let offset = source.find("count *").unwrap() + 2;
+        let Some(NodeRef::Expression(Expression::Identifier(ident))) = program.node_at(offset)
+        else {
+            panic!("expected an identifier expression");
+        };
+        assert_eq!(ident.name, "count");
+        assert_eq!(ident.span.line, 2);
+
+        let offset = source.rfind("doubled").unwrap();
+        let Some(NodeRef::Expression(Expression::Identifier(ident))) = program.node_at(offset)
+        else {
+            panic!("expected the tail expression");
+        };
+        assert_eq!(ident.span.line, 3);
+    }
+
+    #[test]
+    fn test_node_at_finds_bindings_and_enclosing_nodes() {
+        let source = "fn f(count: i32) -> i32 {\n    let doubled = count * 2;\n    doubled\n}";
+        let program = parse(source).unwrap();
+
+        let offset = source.find("doubled").unwrap();
+        assert!(matches!(
+            program.node_at(offset),
+            Some(NodeRef::Identifier(ident)) if ident.name == "doubled"
+        ));
+
+        // Between operands the binary expression itself is the narrowest node.
+        let offset = source.find('*').unwrap();
+        assert!(matches!(
+            program.node_at(offset),
+            Some(NodeRef::Expression(Expression::Binary(_)))
+        ));
+
+        assert_eq!(program.node_at(source.len()), None);
+    }
+}
@@ -1,12 +1,20 @@
+pub mod calls;
 pub mod expressions;
+pub mod fold;
+pub mod lookup;
 pub mod statements;
 pub mod types;
+pub mod visit;
 
+use crate::diagnostics::Diagnostic;
 use expressions::{Expression, Literal};
 use statements::Statement;
 use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+pub use types::Type;
 
-#[derive(Debug, Clone, PartialEq)]
+// Spans order by position: `start`, then `end`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -16,6 +24,12 @@ pub struct Span {
 
 impl Span {
     pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        debug_assert!(
+            start <= end,
+            "span starts at {} after its end {}",
+            start,
+            end
+        );
         Self {
             start,
             end,
@@ -24,6 +38,12 @@ impl Span {
         }
     }
 
+    // Like `new`, but `None` instead of a reversed span, for offsets that come
+    // from outside the lexer and parser.
+    pub fn checked(start: usize, end: usize, line: usize, column: usize) -> Option<Self> {
+        (start <= end).then(|| Span::new(start, end, line, column))
+    }
+
     pub fn dummy() -> Self {
         Self {
             start: 0,
@@ -32,6 +52,32 @@ impl Span {
             column: 0,
         }
     }
+
+    // Span from the start of `self` to the end of `end`.
+    pub fn join(&self, end: &Span) -> Span {
+        Span::new(self.start, end.end.max(self.start), self.line, self.column)
+    }
+
+    // Whether the span is ordered and lies within a source of `src_len` bytes.
+    pub fn is_valid(&self, src_len: usize) -> bool {
+        self.start <= self.end && self.end <= src_len
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    // The source text covered by this span, or "" if it does not lie within
+    // `src` on character boundaries.
+    pub fn slice<'a>(&self, src: &'a str) -> &'a str {
+        src.get(self.range()).unwrap_or("")
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Span::dummy()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -58,6 +104,149 @@ pub struct Program {
     pub span: Span,
 }
 
+impl Program {
+    pub fn main_function(&self) -> Option<&FunctionDecl> {
+        self.items.iter().find_map(|item| match item {
+            Declaration::Function(function) if function.name.name == "main" => Some(function),
+            _ => None,
+        })
+    }
+
+    // Combines the programs parsed from each file of a project into one, in
+    // order. Modules of the same name merge into one module, as do their
+    // nested modules; any other name declared twice at the same level is
+    // reported at the later declaration with a label at the first. Spans do
+    // not say which file they lie in, so each report carries the indices
+    // into `programs` of the files its span and label refer to.
+    pub fn merge(programs: Vec<Program>) -> (Program, Vec<MergeDiagnostic>) {
+        let span = programs
+            .first()
+            .map_or_else(Span::dummy, |program| program.span.clone());
+        let mut items = Vec::new();
+        let mut origins = Vec::new();
+        let mut diagnostics = Vec::new();
+        for (file, program) in programs.into_iter().enumerate() {
+            merge_items(
+                &mut items,
+                &mut origins,
+                program.items,
+                file,
+                &mut diagnostics,
+            );
+        }
+        (Program { items, span }, diagnostics)
+    }
+
+    // Every function in the program in source order, including those inside
+    // inline modules at any depth.
+    pub fn iter_functions_recursive(&self) -> impl Iterator<Item = &FunctionDecl> {
+        Functions {
+            stack: vec![self.items.iter()],
+        }
+    }
+}
+
+struct Functions<'a> {
+    stack: Vec<std::slice::Iter<'a, Declaration>>,
+}
+
+impl<'a> Iterator for Functions<'a> {
+    type Item = &'a FunctionDecl;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(items) = self.stack.last_mut() {
+            match items.next() {
+                Some(Declaration::Function(function)) => return Some(function),
+                Some(Declaration::Module(ModuleDecl {
+                    items: Some(items), ..
+                })) => self.stack.push(items.iter()),
+                Some(_) => {}
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeDiagnostic {
+    pub diagnostic: Diagnostic,
+    // Index of the program holding `diagnostic.span`.
+    pub file: usize,
+    // Index of the program holding the span of the diagnostic's label.
+    pub label_file: usize,
+}
+
+// The file each merged item came from, nested like the items of modules.
+struct Origin {
+    file: usize,
+    items: Vec<Origin>,
+}
+
+impl Origin {
+    fn new(file: usize, item: &Declaration) -> Self {
+        let items = match item {
+            Declaration::Module(ModuleDecl {
+                items: Some(items), ..
+            }) => Origin::all(file, items),
+            _ => Vec::new(),
+        };
+        Origin { file, items }
+    }
+
+    fn all(file: usize, items: &[Declaration]) -> Vec<Origin> {
+        items.iter().map(|item| Origin::new(file, item)).collect()
+    }
+}
+
+fn merge_items(
+    items: &mut Vec<Declaration>,
+    origins: &mut Vec<Origin>,
+    incoming: Vec<Declaration>,
+    file: usize,
+    diagnostics: &mut Vec<MergeDiagnostic>,
+) {
+    for item in incoming {
+        let Some(index) = items
+            .iter()
+            .position(|existing| existing.name().name == item.name().name)
+        else {
+            origins.push(Origin::new(file, &item));
+            items.push(item);
+            continue;
+        };
+        let origin = &mut origins[index];
+        match (&mut items[index], item) {
+            (Declaration::Module(module), Declaration::Module(other)) => {
+                match (&mut module.items, other.items) {
+                    (Some(items), Some(incoming)) => {
+                        merge_items(items, &mut origin.items, incoming, file, diagnostics)
+                    }
+                    (items @ None, Some(incoming)) => {
+                        origin.items = Origin::all(file, &incoming);
+                        *items = Some(incoming);
+                    }
+                    (_, None) => {}
+                }
+            }
+            (previous, item) => diagnostics.push(MergeDiagnostic {
+                diagnostic: Diagnostic::error(
+                    format!("`{}` is already declared", item.name()),
+                    item.name().span.clone(),
+                )
+                .with_label(
+                    format!("`{}` first declared here", previous.name()),
+                    previous.name().span.clone(),
+                ),
+                file,
+                label_file: origin.file,
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Declaration {
     Function(FunctionDecl),
@@ -66,13 +255,45 @@ pub enum Declaration {
     Union(UnionDecl),
     Variable(VarDecl),
     Constant(ConstDecl),
+    Static(StaticDecl),
     Module(ModuleDecl),
     Macro(MacroDecl),
 }
 
+impl Declaration {
+    pub fn span(&self) -> &Span {
+        match self {
+            Declaration::Function(decl) => &decl.span,
+            Declaration::Struct(decl) => &decl.span,
+            Declaration::Enum(decl) => &decl.span,
+            Declaration::Union(decl) => &decl.span,
+            Declaration::Variable(decl) => &decl.span,
+            Declaration::Constant(decl) => &decl.span,
+            Declaration::Static(decl) => &decl.span,
+            Declaration::Module(decl) => &decl.span,
+            Declaration::Macro(decl) => &decl.span,
+        }
+    }
+
+    pub fn name(&self) -> &Identifier {
+        match self {
+            Declaration::Function(decl) => &decl.name,
+            Declaration::Struct(decl) => &decl.name,
+            Declaration::Enum(decl) => &decl.name,
+            Declaration::Union(decl) => &decl.name,
+            Declaration::Variable(decl) => &decl.name,
+            Declaration::Constant(decl) => &decl.name,
+            Declaration::Static(decl) => &decl.name,
+            Declaration::Module(decl) => &decl.name,
+            Declaration::Macro(decl) => &decl.name,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionDecl {
     pub name: Identifier,
+    pub generics: Vec<Identifier>,
     pub params: Vec<Parameter>,
     pub return_type: Option<Box<Type>>,
     pub body: Block,
@@ -80,16 +301,93 @@ pub struct FunctionDecl {
     pub span: Span,
 }
 
+impl FunctionDecl {
+    // Starts a function with no parameters, no return type and an empty body,
+    // all with dummy spans; mainly for building ASTs in tools and tests.
+    pub fn builder(name: &str) -> FunctionBuilder {
+        FunctionBuilder {
+            function: FunctionDecl {
+                name: Identifier::new(name.to_string(), Span::dummy()),
+                generics: Vec::new(),
+                params: Vec::new(),
+                return_type: None,
+                body: Block::default(),
+                attributes: Vec::new(),
+                span: Span::dummy(),
+            },
+        }
+    }
+}
+
+pub struct FunctionBuilder {
+    function: FunctionDecl,
+}
+
+impl FunctionBuilder {
+    pub fn generic(mut self, name: &str) -> Self {
+        self.function
+            .generics
+            .push(Identifier::new(name.to_string(), Span::dummy()));
+        self
+    }
+
+    pub fn param(mut self, name: &str, ty: Type) -> Self {
+        self.function.params.push(Parameter {
+            name: Identifier::new(name.to_string(), Span::dummy()),
+            ty,
+            default: None,
+            span: Span::dummy(),
+        });
+        self
+    }
+
+    pub fn param_with_default(mut self, name: &str, ty: Type, default: Expression) -> Self {
+        self = self.param(name, ty);
+        if let Some(param) = self.function.params.last_mut() {
+            param.default = Some(default);
+        }
+        self
+    }
+
+    pub fn returns(mut self, ty: Type) -> Self {
+        self.function.return_type = Some(Box::new(ty));
+        self
+    }
+
+    pub fn body(mut self, body: Block) -> Self {
+        self.function.body = body;
+        self
+    }
+
+    pub fn attribute(mut self, attribute: Attribute) -> Self {
+        self.function.attributes.push(attribute);
+        self
+    }
+
+    pub fn span(mut self, span: Span) -> Self {
+        self.function.span = span;
+        self
+    }
+
+    pub fn build(self) -> FunctionDecl {
+        self.function
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Parameter {
     pub name: Identifier,
     pub ty: Type,
+    // Used when a call omits this argument; only trailing parameters may have one.
+    pub default: Option<Expression>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Block {
     pub statements: Vec<Statement>,
+    // Trailing expression without a semicolon; its value is the block's value.
+    pub tail: Option<Box<Expression>>,
     pub span: Span,
 }
 
@@ -104,6 +402,10 @@ pub struct Attribute {
 pub enum AttributeArg {
     Literal(Literal),
     Identifier(Identifier),
+    // `name = literal`, as in `feature = "x"`.
+    NameValue(Identifier, Literal),
+    // `name(args)`, as in `not(test)`.
+    List(Identifier, Vec<AttributeArg>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -114,10 +416,41 @@ pub struct StructDecl {
     pub span: Span,
 }
 
+impl StructDecl {
+    // Size and alignment under the default layout; see `types::fields_layout`.
+    pub fn layout(&self, ptr_width: usize) -> Option<(usize, usize)> {
+        let fields: Vec<Type> = self.fields.iter().map(|field| field.ty.clone()).collect();
+        types::fields_layout(&fields, ptr_width)
+    }
+
+    pub fn field_type(&self, name: &str) -> Option<&Type> {
+        self.fields
+            .iter()
+            .find(|field| field.name.name == name)
+            .map(|field| &field.ty)
+    }
+
+    // Every field's name and type, in declaration order.
+    pub fn field_types(&self) -> Vec<(&str, &Type)> {
+        self.fields
+            .iter()
+            .map(|field| (field.name.name.as_str(), &field.ty))
+            .collect()
+    }
+
+    // The type of the field at `index` in declaration order, for positional
+    // access such as tuple structs will need.
+    pub fn field_type_at(&self, index: usize) -> Option<&Type> {
+        self.fields.get(index).map(|field| &field.ty)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructField {
     pub name: Identifier,
     pub ty: Type,
+    // `= value`, used when a struct literal omits the field.
+    pub default: Option<Expression>,
     pub attributes: Vec<Attribute>,
     pub span: Span,
 }
@@ -134,6 +467,8 @@ pub struct EnumDecl {
 pub struct EnumVariant {
     pub name: Identifier,
     pub data: Option<Type>,
+    // Explicit `= value`; otherwise one more than the previous variant's.
+    pub discriminant: Option<Expression>,
     pub span: Span,
 }
 
@@ -169,10 +504,20 @@ pub struct ConstDecl {
     pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticDecl {
+    pub name: Identifier,
+    pub ty: Type,
+    pub mutable: bool,
+    pub value: Expression,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ModuleDecl {
     pub name: Identifier,
-    pub items: Vec<Declaration>,
+    // `None` for `mod name;`, whose items live in another file until loaded.
+    pub items: Option<Vec<Declaration>>,
     pub span: Span,
 }
 
@@ -204,19 +549,12 @@ pub enum MacroToken {
     Group(Vec<MacroToken>),
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Type {
-    Simple(Identifier),
-    Pointer(Box<Type>),
-    Reference(Box<Type>),
-    Array(Box<Type>, Box<Expression>),
-    Function(Vec<Type>, Box<Type>),
-    Generic(Box<Type>, Vec<Type>),
-}
-
 #[cfg(test)]
 mod tests {
+    use super::expressions::IntBase;
+    use super::types::Mutability;
     use super::*;
+    use crate::parser::parse;
 
     #[test]
     fn test_identifier() {
@@ -231,12 +569,10 @@ mod tests {
         let span = Span::new(0, 50, 1, 1);
         let fn_decl = FunctionDecl {
             name: Identifier::new("test".to_string(), Span::dummy()),
+            generics: vec![],
             params: vec![],
             return_type: None,
-            body: Block {
-                statements: vec![],
-                span: Span::dummy(),
-            },
+            body: Block::default(),
             attributes: vec![],
             span,
         };
@@ -253,7 +589,8 @@ mod tests {
             name: Identifier::new("Point".to_string(), Span::dummy()),
             fields: vec![StructField {
                 name: Identifier::new("x".to_string(), Span::dummy()),
-                ty: Type::Simple(Identifier::new("i32".to_string(), Span::dummy())),
+                ty: Type::I32,
+                default: None,
                 attributes: vec![],
                 span: Span::dummy(),
             }],
@@ -264,28 +601,224 @@ mod tests {
         assert_eq!(struct_decl.name.name, "Point");
         assert_eq!(struct_decl.fields.len(), 1);
         assert_eq!(struct_decl.fields[0].name.name, "x");
+        assert_eq!(struct_decl.layout(8), Some((4, 4)));
+    }
+
+    #[test]
+    fn test_struct_field_types() {
+        let field = |name: &str, ty: Type| StructField {
+            name: Identifier::new(name.to_string(), Span::dummy()),
+            ty,
+            default: None,
+            attributes: vec![],
+            span: Span::dummy(),
+        };
+        let struct_decl = StructDecl {
+            name: Identifier::new("Point".to_string(), Span::dummy()),
+            fields: vec![field("x", Type::I32), field("label", Type::Str)],
+            attributes: vec![],
+            span: Span::dummy(),
+        };
+
+        assert_eq!(struct_decl.field_type("label"), Some(&Type::Str));
+        assert_eq!(struct_decl.field_type("z"), None);
+        assert_eq!(
+            struct_decl.field_types(),
+            vec![("x", &Type::I32), ("label", &Type::Str)]
+        );
+        assert_eq!(struct_decl.field_type_at(0), Some(&Type::I32));
+        assert_eq!(struct_decl.field_type_at(2), None);
     }
 
     #[test]
     fn test_type_constructions() {
-        let i32_type = Type::Simple(Identifier::new("i32".to_string(), Span::dummy()));
-        let ptr_type = Type::Pointer(Box::new(i32_type.clone()));
-        let ref_type = Type::Reference(Box::new(i32_type.clone()));
+        let i32_type = Type::I32;
+        let ptr_type = Type::Pointer(Box::new(i32_type.clone()), Mutability::Immutable);
+        let ref_type = Type::Reference(Box::new(i32_type.clone()), Mutability::Immutable, None);
 
         match ptr_type {
-            Type::Pointer(inner) => match *inner {
-                Type::Simple(ident) => assert_eq!(ident.name, "i32"),
-                _ => panic!("Expected simple type"),
+            Type::Pointer(inner, _) => match *inner {
+                Type::I32 => {}
+                _ => panic!("Expected i32 type"),
             },
             _ => panic!("Expected pointer type"),
         }
 
         match ref_type {
-            Type::Reference(inner) => match *inner {
-                Type::Simple(ident) => assert_eq!(ident.name, "i32"),
-                _ => panic!("Expected simple type"),
+            Type::Reference(inner, _, _) => match *inner {
+                Type::I32 => {}
+                _ => panic!("Expected i32 type"),
             },
             _ => panic!("Expected reference type"),
         }
     }
+
+    #[test]
+    fn test_main_function_lookup() {
+        let function = |name: &str| Declaration::Function(FunctionDecl::builder(name).build());
+
+        let program = Program {
+            items: vec![function("helper"), function("main")],
+            span: Span::dummy(),
+        };
+        assert_eq!(program.main_function().unwrap().name.name, "main");
+
+        let program = Program {
+            items: vec![function("helper")],
+            span: Span::dummy(),
+        };
+        assert!(program.main_function().is_none());
+    }
+
+    #[test]
+    fn test_iter_functions_recursive() {
+        let program = crate::parser::parse(
+            "fn first() {}\nmod outer { struct S {} fn second() {} mod inner { fn third() {} } }\nfn fourth() {}",
+        )
+        .unwrap();
+        let names: Vec<&str> = program
+            .iter_functions_recursive()
+            .map(|function| function.name.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["first", "second", "third", "fourth"]);
+    }
+
+    #[test]
+    fn test_function_builder() {
+        let built = FunctionDecl::builder("add")
+            .param("a", Type::I32)
+            .param_with_default(
+                "b",
+                Type::I32,
+                Expression::Literal(Literal::Integer(1, None, IntBase::Dec)),
+            )
+            .returns(Type::I32)
+            .body(Block {
+                tail: Some(Box::new(Expression::Identifier(Identifier::new(
+                    "a".to_string(),
+                    Span::default(),
+                )))),
+                ..Block::default()
+            })
+            .build();
+
+        let param = |name: &str, default| Parameter {
+            name: Identifier::new(name.to_string(), Span::dummy()),
+            ty: Type::I32,
+            default,
+            span: Span::dummy(),
+        };
+        let written = FunctionDecl {
+            name: Identifier::new("add".to_string(), Span::dummy()),
+            generics: vec![],
+            params: vec![
+                param("a", None),
+                param(
+                    "b",
+                    Some(Expression::Literal(Literal::Integer(1, None, IntBase::Dec))),
+                ),
+            ],
+            return_type: Some(Box::new(Type::I32)),
+            body: Block {
+                statements: vec![],
+                tail: Some(Box::new(Expression::Identifier(Identifier::new(
+                    "a".to_string(),
+                    Span::dummy(),
+                )))),
+                span: Span::dummy(),
+            },
+            attributes: vec![],
+            span: Span::dummy(),
+        };
+        assert_eq!(built, written);
+    }
+
+    #[test]
+    fn test_span_slice() {
+        let src = "let name = \"héllo\";";
+        assert_eq!(Span::new(4, 8, 1, 5).range(), 4..8);
+        assert_eq!(Span::new(4, 8, 1, 5).slice(src), "name");
+        assert_eq!(Span::new(11, 19, 1, 12).slice(src), "\"héllo\"");
+        assert_eq!(Span::new(15, 40, 1, 16).slice(src), "");
+        assert_eq!(Span::new(13, 14, 1, 14).slice(src), "");
+    }
+
+    #[test]
+    fn test_reversed_span_is_invalid() {
+        assert_eq!(Span::checked(5, 2, 1, 6), None);
+        let span = Span {
+            start: 5,
+            end: 2,
+            line: 1,
+            column: 6,
+        };
+        assert!(!span.is_valid(10));
+        assert_eq!(span.slice("0123456789"), "");
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "span starts at 5 after its end 2")]
+    fn test_new_rejects_reversed_span() {
+        Span::new(5, 2, 1, 6);
+    }
+
+    #[test]
+    fn test_out_of_range_span_is_invalid() {
+        let src = "fn f() {}";
+        let span = Span::checked(4, 12, 1, 5).unwrap();
+        assert!(!span.is_valid(src.len()));
+        assert_eq!(span.slice(src), "");
+        assert!(Span::new(3, 4, 1, 4).is_valid(src.len()));
+        assert!(Span::new(9, 9, 1, 10).is_valid(src.len()));
+    }
+
+    #[test]
+    fn test_merge_programs() {
+        let first = parse("fn main() {}\nmod util { fn a() {} }").unwrap();
+        let second = parse("struct Point { x: i32 }\nmod util { fn b() {} }").unwrap();
+        let (program, diagnostics) = Program::merge(vec![first, second]);
+
+        assert!(diagnostics.is_empty());
+        let names: Vec<&str> = program
+            .items
+            .iter()
+            .map(|item| item.name().name.as_str())
+            .collect();
+        assert_eq!(names, vec!["main", "util", "Point"]);
+        let Declaration::Module(util) = &program.items[1] else {
+            panic!("expected module");
+        };
+        assert_eq!(util.items.as_ref().map(Vec::len), Some(2));
+    }
+
+    #[test]
+    fn test_merge_reports_duplicate_across_files() {
+        let first = parse("fn helper() {}").unwrap();
+        let second = parse("\nfn helper() {}").unwrap();
+        let (program, diagnostics) = Program::merge(vec![first, second]);
+
+        assert_eq!(program.items.len(), 1);
+        assert_eq!(diagnostics.len(), 1);
+        let MergeDiagnostic {
+            diagnostic,
+            file,
+            label_file,
+        } = &diagnostics[0];
+        assert_eq!(diagnostic.message, "`helper` is already declared");
+        assert_eq!(diagnostic.span.line, 2);
+        assert_eq!(diagnostic.labels[0].span.line, 1);
+        assert_eq!((*file, *label_file), (1, 0));
+    }
+
+    #[test]
+    fn test_merge_reports_files_of_nested_duplicates() {
+        let first = parse("mod util { fn a() {} }").unwrap();
+        let second = parse("mod util { fn b() {} }").unwrap();
+        let third = parse("mod util { fn b() {} }").unwrap();
+        let (_, diagnostics) = Program::merge(vec![first, second, third]);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].file, diagnostics[0].label_file), (2, 1));
+    }
 }
@@ -1,3 +1,4 @@
+pub use super::expressions::MatchArm;
 use super::expressions::{Expression, Pattern};
 use super::{Block, Identifier, Span, Type};
 
@@ -91,14 +92,6 @@ pub struct MatchStatement {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct MatchArm {
-    pub pattern: Pattern,
-    pub guard: Option<Expression>,
-    pub body: Block,
-    pub span: Span,
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct PanicStatement {
     pub message: Expression,
@@ -108,7 +101,7 @@ pub struct PanicStatement {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::expressions::{Expression, Literal};
+    use crate::ast::expressions::{Expression, IntBase, Literal};
 
     fn dummy_span() -> Span {
         Span {
@@ -124,7 +117,11 @@ mod tests {
         let stmt = Statement::Let(LetStatement {
             pattern: Pattern::Identifier(Identifier::new("x".to_string(), dummy_span())),
             type_annotation: None,
-            initializer: Some(Expression::Literal(Literal::Integer(42, None))),
+            initializer: Some(Expression::Literal(Literal::Integer(
+                42,
+                None,
+                IntBase::Dec,
+            ))),
             mutable: false,
             span: dummy_span(),
         });
@@ -135,7 +132,11 @@ mod tests {
             assert!(let_stmt.type_annotation.is_none());
             assert!(matches!(
                 let_stmt.initializer,
-                Some(Expression::Literal(Literal::Integer(42, None)))
+                Some(Expression::Literal(Literal::Integer(
+                    42,
+                    None,
+                    IntBase::Dec
+                )))
             ));
         } else {
             panic!("Expected let statement");
@@ -145,14 +146,22 @@ mod tests {
     #[test]
     fn test_return_statement() {
         let stmt = Statement::Return(ReturnStatement {
-            expression: Some(Expression::Literal(Literal::Integer(42, None))),
+            expression: Some(Expression::Literal(Literal::Integer(
+                42,
+                None,
+                IntBase::Dec,
+            ))),
             span: dummy_span(),
         });
 
         if let Statement::Return(return_stmt) = stmt {
             assert!(matches!(
                 return_stmt.expression,
-                Some(Expression::Literal(Literal::Integer(42, None)))
+                Some(Expression::Literal(Literal::Integer(
+                    42,
+                    None,
+                    IntBase::Dec
+                )))
             ));
         } else {
             panic!("Expected return statement");
@@ -165,6 +174,7 @@ mod tests {
             condition: Expression::Literal(Literal::Boolean(true)),
             body: Block {
                 statements: vec![],
+                tail: None,
                 span: dummy_span(),
             },
             label: Some(Identifier::new("loop1".to_string(), dummy_span())),
@@ -189,10 +199,12 @@ mod tests {
             condition: Expression::Literal(Literal::Boolean(true)),
             then_branch: Block {
                 statements: vec![],
+                tail: None,
                 span: dummy_span(),
             },
             else_branch: Some(ElseBranch::Block(Block {
                 statements: vec![],
+                tail: None,
                 span: dummy_span(),
             })),
             span: dummy_span(),
@@ -212,14 +224,15 @@ mod tests {
     #[test]
     fn test_match_statement() {
         let stmt = Statement::Match(MatchStatement {
-            expression: Expression::Literal(Literal::Integer(1, None)),
+            expression: Expression::Literal(Literal::Integer(1, None, IntBase::Dec)),
             arms: vec![MatchArm {
-                pattern: Pattern::Literal(Literal::Integer(1, None)),
+                pattern: Pattern::Literal(Literal::Integer(1, None, IntBase::Dec)),
                 guard: None,
-                body: Block {
+                body: Expression::Block(Box::new(Block {
                     statements: vec![],
+                    tail: None,
                     span: dummy_span(),
-                },
+                })),
                 span: dummy_span(),
             }],
             span: dummy_span(),
@@ -228,12 +241,12 @@ mod tests {
         if let Statement::Match(match_stmt) = stmt {
             assert!(matches!(
                 match_stmt.expression,
-                Expression::Literal(Literal::Integer(1, None))
+                Expression::Literal(Literal::Integer(1, None, IntBase::Dec))
             ));
             assert_eq!(match_stmt.arms.len(), 1);
             assert!(matches!(
                 match_stmt.arms[0].pattern,
-                Pattern::Literal(Literal::Integer(1, None))
+                Pattern::Literal(Literal::Integer(1, None, IntBase::Dec))
             ));
         } else {
             panic!("Expected match statement");
@@ -256,4 +269,37 @@ mod tests {
             panic!("Expected panic statement");
         }
     }
+
+    #[test]
+    fn test_match_arm_shared_with_expressions() {
+        use crate::ast::expressions::MatchExpr;
+
+        let arm = MatchArm {
+            pattern: Pattern::Wildcard,
+            guard: None,
+            body: Expression::Block(Box::new(Block {
+                statements: vec![],
+                tail: Some(Box::new(Expression::Literal(Literal::Integer(
+                    0,
+                    None,
+                    IntBase::Dec,
+                )))),
+                span: dummy_span(),
+            })),
+            span: dummy_span(),
+        };
+
+        let stmt = MatchStatement {
+            expression: Expression::Literal(Literal::Integer(1, None, IntBase::Dec)),
+            arms: vec![arm.clone()],
+            span: dummy_span(),
+        };
+        let expr = MatchExpr {
+            value: Expression::Literal(Literal::Integer(1, None, IntBase::Dec)),
+            arms: vec![arm],
+            span: dummy_span(),
+        };
+
+        assert_eq!(stmt.arms, expr.arms);
+    }
 }
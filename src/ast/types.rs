@@ -1,4 +1,9 @@
-use super::{Identifier, Span, expressions::Expression};
+use super::{
+    Identifier, Span,
+    expressions::{Expression, Literal},
+    fold::{Folder, walk_type},
+};
+use crate::printer::Printer;
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -8,11 +13,13 @@ pub enum Type {
     I32,
     I64,
     I128,
+    Isize,
     U8,
     U16,
     U32,
     U64,
     U128,
+    Usize,
     F32,
     F64,
     Bool,
@@ -24,27 +31,79 @@ pub enum Type {
     Array(Box<Type>, Option<Box<Expression>>),
     Slice(Box<Type>),
     Pointer(Box<Type>, Mutability),
-    Reference(Box<Type>, Mutability),
+    // The lifetime is `None` when elided, as in `&T`.
+    Reference(Box<Type>, Mutability, Option<Lifetime>),
     Tuple(Vec<Type>),
-    Function(Vec<Type>, Box<Type>),
+    Function(Vec<Type>, Box<Type>, FunctionAbi),
 
     Named(TypePath),
     Generic(Box<Type>, Vec<Type>),
+
+    // `_`: a type left for the checker to infer from context.
+    Infer(Span),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct TypePath {
     pub segments: Vec<TypePathSegment>,
     pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct TypePathSegment {
     pub ident: Identifier,
     pub generic_args: Option<Vec<Type>>,
     pub span: Span,
 }
 
+// Paths are equal when they name the same type; where they were written is
+// irrelevant.
+impl PartialEq for TypePath {
+    fn eq(&self, other: &Self) -> bool {
+        self.segments == other.segments
+    }
+}
+
+impl PartialEq for TypePathSegment {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident.name == other.ident.name && self.generic_args == other.generic_args
+    }
+}
+
+// How a function type is called: `extern "C" fn(*const u8, ...)` has the
+// `C` convention and takes extra arguments after its parameters. A plain
+// `fn` type has no convention and is not variadic.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionAbi {
+    pub convention: Option<String>,
+    pub variadic: bool,
+}
+
+// A named lifetime such as `'a`, stored without its quote. Like path
+// segments, lifetimes compare by name only.
+#[derive(Debug, Clone)]
+pub struct Lifetime {
+    pub ident: Identifier,
+}
+
+impl Lifetime {
+    pub fn new(ident: Identifier) -> Self {
+        Self { ident }
+    }
+}
+
+impl PartialEq for Lifetime {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident.name == other.ident.name
+    }
+}
+
+impl fmt::Display for Lifetime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}", self.ident.name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum Mutability {
     Mutable,
@@ -60,11 +119,13 @@ impl Type {
                 | Type::I32
                 | Type::I64
                 | Type::I128
+                | Type::Isize
                 | Type::U8
                 | Type::U16
                 | Type::U32
                 | Type::U64
                 | Type::U128
+                | Type::Usize
                 | Type::F32
                 | Type::F64
                 | Type::Bool
@@ -83,11 +144,13 @@ impl Type {
                 | Type::I32
                 | Type::I64
                 | Type::I128
+                | Type::Isize
                 | Type::U8
                 | Type::U16
                 | Type::U32
                 | Type::U64
                 | Type::U128
+                | Type::Usize
                 | Type::F32
                 | Type::F64
         )
@@ -101,11 +164,13 @@ impl Type {
                 | Type::I32
                 | Type::I64
                 | Type::I128
+                | Type::Isize
                 | Type::U8
                 | Type::U16
                 | Type::U32
                 | Type::U64
                 | Type::U128
+                | Type::Usize
         )
     }
 
@@ -116,24 +181,183 @@ impl Type {
     pub fn is_signed(&self) -> bool {
         matches!(
             self,
-            Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::I128 | Type::F32 | Type::F64
+            Type::I8
+                | Type::I16
+                | Type::I32
+                | Type::I64
+                | Type::I128
+                | Type::Isize
+                | Type::F32
+                | Type::F64
         )
     }
+
+    // The canonical spelling of this type, at every depth: the empty tuple is
+    // `()`, and a one-element tuple is its element, as there are no 1-tuples.
+    pub fn normalized(&self) -> Type {
+        Normalizer.fold_type(self.clone())
+    }
+
+    // This type with every reference lifetime elided. Lifetimes are not
+    // checked, so types that differ only in them are interchangeable.
+    pub fn without_lifetimes(&self) -> Type {
+        LifetimeEraser.fold_type(self.clone())
+    }
 }
 
-impl fmt::Display for Type {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+struct LifetimeEraser;
+
+impl Folder for LifetimeEraser {
+    fn fold_type(&mut self, ty: Type) -> Type {
+        match walk_type(self, ty) {
+            Type::Reference(target, mutability, _) => Type::Reference(target, mutability, None),
+            ty => ty,
+        }
+    }
+}
+
+struct Normalizer;
+
+impl Folder for Normalizer {
+    fn fold_type(&mut self, ty: Type) -> Type {
+        match walk_type(self, ty) {
+            Type::Tuple(types) if types.is_empty() => Type::Unit,
+            Type::Tuple(mut types) if types.len() == 1 => types.pop().unwrap(),
+            ty => ty,
+        }
+    }
+}
+
+impl Type {
+    // Size in bytes under the default layout, or `None` for unsized types,
+    // types whose layout is not known from the type alone (named types) and
+    // types too large for `usize`.
+    pub fn size_of(&self, ptr_width: usize) -> Option<usize> {
+        self.layout(ptr_width).map(|(size, _)| size)
+    }
+
+    pub fn align_of(&self, ptr_width: usize) -> Option<usize> {
+        self.layout(ptr_width).map(|(_, align)| align)
+    }
+
+    fn layout(&self, ptr_width: usize) -> Option<(usize, usize)> {
+        let scalar = |size: usize| Some((size, size));
+        match self {
+            Type::I8 | Type::U8 | Type::Bool => scalar(1),
+            Type::I16 | Type::U16 => scalar(2),
+            Type::I32 | Type::U32 | Type::F32 | Type::Char => scalar(4),
+            Type::I64 | Type::U64 | Type::F64 => scalar(8),
+            Type::I128 | Type::U128 => scalar(16),
+            Type::Isize
+            | Type::Usize
+            | Type::Pointer(_, _)
+            | Type::Reference(_, _, _)
+            | Type::Function(..) => scalar(ptr_width),
+            Type::Unit | Type::Never => Some((0, 1)),
+            Type::Array(element, Some(len)) => {
+                let Expression::Literal(Literal::Integer(len, _, _)) = len.as_ref() else {
+                    return None;
+                };
+                let (size, align) = element.layout(ptr_width)?;
+                Some((size.checked_mul(usize::try_from(*len).ok()?)?, align))
+            }
+            Type::Tuple(types) => fields_layout(types, ptr_width),
+            Type::Str
+            | Type::Slice(_)
+            | Type::Array(_, None)
+            | Type::Named(_)
+            | Type::Generic(_, _)
+            | Type::Infer(_) => None,
+        }
+    }
+}
+
+// Size and alignment of fields laid out in declaration order, each at the next
+// offset aligned for it, with trailing padding to the overall alignment.
+// `None` also when the size overflows `usize`.
+pub fn fields_layout(fields: &[Type], ptr_width: usize) -> Option<(usize, usize)> {
+    let mut size: usize = 0;
+    let mut align = 1;
+    for field in fields {
+        let (field_size, field_align) = field.layout(ptr_width)?;
+        size = size
+            .checked_next_multiple_of(field_align)?
+            .checked_add(field_size)?;
+        align = align.max(field_align);
+    }
+    Some((size.checked_next_multiple_of(align)?, align))
+}
+
+impl Type {
+    // Renders named types with their full path (`a::Foo`), as `Display`
+    // does, for messages where two types may share a name.
+    pub fn display_qualified(&self) -> String {
+        self.to_string()
+    }
+
+    // Renders named types by their final segment only (`Foo` for `a::Foo`).
+    // For diagnostics only: the short spelling may name a different type.
+    pub fn display_short(&self) -> String {
+        Short(self).to_string()
+    }
+
+    // The short spelling for diagnostics, at most `max_len` characters where
+    // possible: lists of tuple elements, parameters and generic arguments
+    // lose their middle elements to `…` until it fits.
+    pub fn display_truncated(&self, max_len: usize) -> String {
+        let full = self.display_short();
+        if full.chars().count() <= max_len {
+            return full;
+        }
+        let mut shortest = Truncated(self, 0).to_string();
+        for keep in 1.. {
+            let candidate = Truncated(self, keep).to_string();
+            if candidate.chars().count() > max_len {
+                break;
+            }
+            shortest = candidate;
+        }
+        shortest
+    }
+
+    // `keep` limits every list to its first and last few elements.
+    fn fmt_with(&self, f: &mut fmt::Formatter<'_>, qualified: bool, keep: usize) -> fmt::Result {
+        let fmt_list = |f: &mut fmt::Formatter<'_>, types: &[Type]| -> fmt::Result {
+            let (head, tail) = if types.len() > keep {
+                (keep.div_ceil(2), keep / 2)
+            } else {
+                (types.len(), 0)
+            };
+            let shown = types[..head]
+                .iter()
+                .map(Some)
+                .chain((head + tail < types.len()).then_some(None))
+                .chain(types[types.len() - tail..].iter().map(Some));
+            for (i, ty) in shown.enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                match ty {
+                    Some(ty) => ty.fmt_with(f, qualified, keep)?,
+                    None => write!(f, "…")?,
+                }
+            }
+            Ok(())
+        };
+
         match self {
             Type::I8 => write!(f, "i8"),
             Type::I16 => write!(f, "i16"),
             Type::I32 => write!(f, "i32"),
             Type::I64 => write!(f, "i64"),
             Type::I128 => write!(f, "i128"),
+            Type::Isize => write!(f, "isize"),
             Type::U8 => write!(f, "u8"),
             Type::U16 => write!(f, "u16"),
             Type::U32 => write!(f, "u32"),
             Type::U64 => write!(f, "u64"),
             Type::U128 => write!(f, "u128"),
+            Type::Usize => write!(f, "usize"),
             Type::F32 => write!(f, "f32"),
             Type::F64 => write!(f, "f64"),
             Type::Bool => write!(f, "bool"),
@@ -141,87 +365,112 @@ impl fmt::Display for Type {
             Type::Str => write!(f, "str"),
             Type::Unit => write!(f, "()"),
             Type::Never => write!(f, "!"),
+            Type::Infer(_) => write!(f, "_"),
             Type::Array(ty, size) => {
                 write!(f, "[")?;
-                ty.fmt(f)?;
+                ty.fmt_with(f, qualified, keep)?;
                 if let Some(size) = size {
-                    write!(f, "; {:?}", size)?;
+                    let mut printer = Printer::new();
+                    printer.print_expression(size);
+                    write!(f, "; {}", printer.finish())?;
                 }
                 write!(f, "]")
             }
-            Type::Slice(ty) => write!(f, "[{}]", ty),
+            Type::Slice(ty) => {
+                write!(f, "[")?;
+                ty.fmt_with(f, qualified, keep)?;
+                write!(f, "]")
+            }
             Type::Pointer(ty, mutability) => {
                 match mutability {
                     Mutability::Mutable => write!(f, "*mut ")?,
                     Mutability::Immutable => write!(f, "*const ")?,
                 }
-                ty.fmt(f)
+                ty.fmt_with(f, qualified, keep)
             }
-            Type::Reference(ty, mutability) => {
-                match mutability {
-                    Mutability::Mutable => write!(f, "&mut ")?,
-                    Mutability::Immutable => write!(f, "&")?,
+            Type::Reference(ty, mutability, lifetime) => {
+                write!(f, "&")?;
+                if let Some(lifetime) = lifetime {
+                    write!(f, "{} ", lifetime)?;
+                }
+                if *mutability == Mutability::Mutable {
+                    write!(f, "mut ")?;
                 }
-                ty.fmt(f)
+                ty.fmt_with(f, qualified, keep)
             }
             Type::Tuple(types) => {
                 write!(f, "(")?;
-                for (i, ty) in types.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    ty.fmt(f)?;
-                }
+                fmt_list(f, types)?;
                 write!(f, ")")
             }
-            Type::Function(params, return_ty) => {
+            Type::Function(params, return_ty, abi) => {
+                if let Some(convention) = &abi.convention {
+                    write!(f, "extern {:?} ", convention)?;
+                }
                 write!(f, "fn(")?;
-                for (i, param) in params.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    param.fmt(f)?;
+                fmt_list(f, params)?;
+                if abi.variadic {
+                    let separator = if params.is_empty() { "" } else { ", " };
+                    write!(f, "{}...", separator)?;
                 }
                 write!(f, ") -> ")?;
-                return_ty.fmt(f)
+                return_ty.fmt_with(f, qualified, keep)
             }
             Type::Named(path) => {
-                for (i, segment) in path.segments.iter().enumerate() {
+                let skip = if qualified {
+                    0
+                } else {
+                    path.segments.len().saturating_sub(1)
+                };
+                for (i, segment) in path.segments.iter().skip(skip).enumerate() {
                     if i > 0 {
                         write!(f, "::")?;
                     }
                     write!(f, "{}", segment.ident.name)?;
                     if let Some(args) = &segment.generic_args {
                         write!(f, "<")?;
-                        for (i, arg) in args.iter().enumerate() {
-                            if i > 0 {
-                                write!(f, ", ")?;
-                            }
-                            arg.fmt(f)?;
-                        }
+                        fmt_list(f, args)?;
                         write!(f, ">")?;
                     }
                 }
                 Ok(())
             }
             Type::Generic(base, args) => {
-                base.fmt(f)?;
+                base.fmt_with(f, qualified, keep)?;
                 write!(f, "<")?;
-                for (i, arg) in args.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    arg.fmt(f)?;
-                }
+                fmt_list(f, args)?;
                 write!(f, ">")
             }
         }
     }
 }
 
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with(f, true, usize::MAX)
+    }
+}
+
+struct Short<'a>(&'a Type);
+
+impl fmt::Display for Short<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_with(f, false, usize::MAX)
+    }
+}
+
+struct Truncated<'a>(&'a Type, usize);
+
+impl fmt::Display for Truncated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_with(f, false, self.1)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::expressions::IntBase;
 
     fn dummy_span() -> Span {
         Span {
@@ -250,13 +499,29 @@ mod tests {
         assert!(Type::F64.is_float());
     }
 
+    #[test]
+    fn test_tuple_normalization() {
+        assert_eq!(Type::Tuple(vec![]).normalized(), Type::Unit);
+        assert_eq!(Type::Tuple(vec![Type::I32]).normalized(), Type::I32);
+        assert_eq!(
+            Type::Slice(Box::new(Type::Tuple(vec![Type::Tuple(vec![])]))).normalized(),
+            Type::Slice(Box::new(Type::Unit))
+        );
+        let pair = Type::Tuple(vec![Type::I32, Type::Tuple(vec![Type::Bool])]);
+        assert_eq!(pair.normalized(), Type::Tuple(vec![Type::I32, Type::Bool]));
+    }
+
     #[test]
     fn test_complex_types() {
         let array_type = Type::Array(Box::new(Type::I32), None);
         let ptr_type = Type::Pointer(Box::new(Type::I32), Mutability::Immutable);
-        let ref_type = Type::Reference(Box::new(Type::I32), Mutability::Mutable);
+        let ref_type = Type::Reference(Box::new(Type::I32), Mutability::Mutable, None);
         let tuple_type = Type::Tuple(vec![Type::I32, Type::F64]);
-        let fn_type = Type::Function(vec![Type::I32], Box::new(Type::Bool));
+        let fn_type = Type::Function(
+            vec![Type::I32],
+            Box::new(Type::Bool),
+            FunctionAbi::default(),
+        );
 
         assert!(!array_type.is_primitive());
         assert!(!ptr_type.is_primitive());
@@ -293,4 +558,88 @@ mod tests {
             "(i32, f64)"
         );
     }
+
+    #[test]
+    fn test_qualified_display() {
+        let segment = |name: &str| TypePathSegment {
+            ident: Identifier::new(name.to_string(), dummy_span()),
+            generic_args: None,
+            span: dummy_span(),
+        };
+        let a_foo = Type::Named(TypePath {
+            segments: vec![segment("a"), segment("Foo")],
+            span: dummy_span(),
+        });
+        let b_foo = Type::Named(TypePath {
+            segments: vec![segment("b"), segment("Foo")],
+            span: dummy_span(),
+        });
+
+        assert_eq!(a_foo.to_string(), "a::Foo");
+        assert_eq!(b_foo.display_qualified(), "b::Foo");
+        assert_eq!(a_foo.display_short(), "Foo");
+        assert_eq!(b_foo.display_short(), "Foo");
+
+        let reference = Type::Reference(Box::new(a_foo), Mutability::Immutable, None);
+        assert_eq!(reference.to_string(), "&a::Foo");
+        assert_eq!(reference.display_short(), "&Foo");
+    }
+
+    #[test]
+    fn test_truncated_display() {
+        let pair = Type::Tuple(vec![Type::I32, Type::Bool]);
+        assert_eq!(pair.display_truncated(20), "(i32, bool)");
+
+        let long = Type::Tuple(vec![Type::U8; 10]);
+        assert_eq!(long.to_string().len(), 40);
+        assert_eq!(long.display_truncated(30), "(u8, u8, u8, …, u8, u8, u8)");
+        assert_eq!(long.display_truncated(1), "(…)");
+
+        let function = Type::Function(vec![long], Box::new(Type::Unit), Default::default());
+        assert_eq!(function.display_truncated(24), "fn((u8, …, u8)) -> ()");
+    }
+
+    #[test]
+    fn test_size_and_align() {
+        assert_eq!(Type::I64.size_of(8), Some(8));
+        assert_eq!(Type::Usize.size_of(4), Some(4));
+        assert_eq!(Type::Str.size_of(8), None);
+        assert_eq!(Type::Slice(Box::new(Type::U8)).align_of(8), None);
+
+        let tuple = Type::Tuple(vec![Type::I8, Type::I32]);
+        assert_eq!(tuple.size_of(8), Some(8));
+        assert_eq!(tuple.align_of(8), Some(4));
+
+        let array = Type::Array(
+            Box::new(Type::U8),
+            Some(Box::new(Expression::Literal(Literal::Integer(
+                4,
+                None,
+                IntBase::Dec,
+            )))),
+        );
+        assert_eq!(array.size_of(8), Some(4));
+        assert_eq!(array.align_of(8), Some(1));
+
+        let reference = Type::Reference(Box::new(Type::I8), Mutability::Immutable, None);
+        assert_eq!(reference.size_of(8), Some(8));
+        assert_eq!(fields_layout(&[Type::I32, Type::I8], 8), Some((8, 4)));
+    }
+
+    #[test]
+    fn test_oversized_layout() {
+        let parse = |source: &str| {
+            crate::parser::Parser::new(source)
+                .unwrap()
+                .parse_type()
+                .unwrap()
+        };
+        let nested = parse("[[u64; 1000000000000]; 1000000000000]");
+        assert_eq!(nested.size_of(8), None);
+        let tuple = parse("([u8; 18446744073709551615], u16)");
+        assert_eq!(tuple.size_of(8), None);
+        let padded = parse("(u8, [u64; 2305843009213693951])");
+        assert_eq!(padded.size_of(8), None);
+        assert_eq!(parse("[u32; 4294967296]").size_of(8), Some(1 << 34));
+    }
 }
@@ -0,0 +1,407 @@
+use super::expressions::{Expression, Literal, MatchArm, Pattern};
+use super::statements::{ElseBranch, IfStatement, Statement};
+use super::types::TypePath;
+use super::{Block, Declaration, FunctionDecl, Program, Type};
+
+// Walks an AST by reference, for passes that only read it. The defaults visit
+// every child through the matching `walk_*` function, in the same order as
+// `fold::Folder`, so an implementation only overrides the methods for the
+// nodes it looks at.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        walk_declaration(self, declaration)
+    }
+
+    fn visit_function(&mut self, function: &FunctionDecl) {
+        walk_function(self, function)
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression)
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        walk_type(self, ty)
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for item in &program.items {
+        visitor.visit_declaration(item);
+    }
+}
+
+pub fn walk_declaration<V: Visitor + ?Sized>(visitor: &mut V, declaration: &Declaration) {
+    match declaration {
+        Declaration::Function(function) => visitor.visit_function(function),
+        Declaration::Struct(decl) => {
+            for field in &decl.fields {
+                visitor.visit_type(&field.ty);
+                if let Some(default) = &field.default {
+                    visitor.visit_expression(default);
+                }
+            }
+        }
+        Declaration::Enum(decl) => {
+            for variant in &decl.variants {
+                if let Some(data) = &variant.data {
+                    visitor.visit_type(data);
+                }
+                if let Some(discriminant) = &variant.discriminant {
+                    visitor.visit_expression(discriminant);
+                }
+            }
+        }
+        Declaration::Union(decl) => {
+            for field in &decl.fields {
+                visitor.visit_type(&field.ty);
+            }
+        }
+        Declaration::Variable(decl) => {
+            if let Some(ty) = &decl.ty {
+                visitor.visit_type(ty);
+            }
+            if let Some(initializer) = &decl.initializer {
+                visitor.visit_expression(initializer);
+            }
+        }
+        Declaration::Constant(decl) => {
+            visitor.visit_type(&decl.ty);
+            visitor.visit_expression(&decl.value);
+        }
+        Declaration::Static(decl) => {
+            visitor.visit_type(&decl.ty);
+            visitor.visit_expression(&decl.value);
+        }
+        Declaration::Module(module) => {
+            for item in module.items.iter().flatten() {
+                visitor.visit_declaration(item);
+            }
+        }
+        // Macro bodies are unparsed token streams.
+        Declaration::Macro(_) => {}
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionDecl) {
+    for param in &function.params {
+        visitor.visit_type(&param.ty);
+        if let Some(default) = &param.default {
+            visitor.visit_expression(default);
+        }
+    }
+    if let Some(return_type) = &function.return_type {
+        visitor.visit_type(return_type);
+    }
+    visitor.visit_block(&function.body);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    for statement in &block.statements {
+        visitor.visit_statement(statement);
+    }
+    if let Some(tail) = &block.tail {
+        visitor.visit_expression(tail);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Empty | Statement::Continue(_) => {}
+        Statement::Expression(expr) => visitor.visit_expression(expr),
+        Statement::Let(stmt) => {
+            visitor.visit_pattern(&stmt.pattern);
+            if let Some(ty) = &stmt.type_annotation {
+                visitor.visit_type(ty);
+            }
+            if let Some(initializer) = &stmt.initializer {
+                visitor.visit_expression(initializer);
+            }
+        }
+        Statement::Return(stmt) => {
+            if let Some(expr) = &stmt.expression {
+                visitor.visit_expression(expr);
+            }
+        }
+        Statement::Break(stmt) => {
+            if let Some(expr) = &stmt.expression {
+                visitor.visit_expression(expr);
+            }
+        }
+        Statement::While(stmt) => {
+            visitor.visit_expression(&stmt.condition);
+            visitor.visit_block(&stmt.body);
+        }
+        Statement::For(stmt) => {
+            visitor.visit_pattern(&stmt.pattern);
+            visitor.visit_expression(&stmt.iterator);
+            visitor.visit_block(&stmt.body);
+        }
+        Statement::Loop(stmt) => visitor.visit_block(&stmt.body),
+        Statement::Block(block) => visitor.visit_block(block),
+        Statement::If(stmt) => walk_if_statement(visitor, stmt),
+        Statement::Match(stmt) => {
+            visitor.visit_expression(&stmt.expression);
+            walk_match_arms(visitor, &stmt.arms);
+        }
+        Statement::Panic(stmt) => visitor.visit_expression(&stmt.message),
+    }
+}
+
+fn walk_if_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &IfStatement) {
+    visitor.visit_expression(&stmt.condition);
+    visitor.visit_block(&stmt.then_branch);
+    match &stmt.else_branch {
+        Some(ElseBranch::Block(block)) => visitor.visit_block(block),
+        Some(ElseBranch::If(nested)) => walk_if_statement(visitor, nested),
+        None => {}
+    }
+}
+
+fn walk_match_arms<V: Visitor + ?Sized>(visitor: &mut V, arms: &[MatchArm]) {
+    for arm in arms {
+        visitor.visit_pattern(&arm.pattern);
+        if let Some(guard) = &arm.guard {
+            visitor.visit_expression(guard);
+        }
+        visitor.visit_expression(&arm.body);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Literal(Literal::Array(elements) | Literal::Tuple(elements)) => {
+            visit_all(visitor, elements);
+        }
+        Expression::Literal(
+            Literal::Integer(_, Some(suffix), _) | Literal::Float(_, Some(suffix)),
+        ) => {
+            visitor.visit_type(suffix);
+        }
+        Expression::Literal(_) | Expression::Identifier(_) => {}
+        Expression::Binary(binary) => {
+            visitor.visit_expression(&binary.left);
+            visitor.visit_expression(&binary.right);
+        }
+        Expression::Unary(unary) => visitor.visit_expression(&unary.operand),
+        Expression::Call(call) => {
+            visitor.visit_expression(&call.callee);
+            visit_all(visitor, &call.arguments);
+        }
+        Expression::Member(member) => visitor.visit_expression(&member.object),
+        Expression::Index(index) => {
+            visitor.visit_expression(&index.array);
+            visitor.visit_expression(&index.index);
+        }
+        Expression::Cast(cast) => {
+            visitor.visit_expression(&cast.expr);
+            visitor.visit_type(&cast.target_type);
+        }
+        Expression::Block(block) => visitor.visit_block(block),
+        Expression::If(if_expr) => {
+            visitor.visit_expression(&if_expr.condition);
+            visitor.visit_expression(&if_expr.then_branch);
+            if let Some(else_branch) = &if_expr.else_branch {
+                visitor.visit_expression(else_branch);
+            }
+        }
+        Expression::Match(match_expr) => {
+            visitor.visit_expression(&match_expr.value);
+            walk_match_arms(visitor, &match_expr.arms);
+        }
+        Expression::Loop(loop_expr) => visitor.visit_expression(&loop_expr.body),
+        Expression::While(while_expr) => {
+            visitor.visit_expression(&while_expr.condition);
+            visitor.visit_expression(&while_expr.body);
+        }
+        Expression::For(for_expr) => {
+            visitor.visit_pattern(&for_expr.pattern);
+            visitor.visit_expression(&for_expr.iterator);
+            visitor.visit_expression(&for_expr.body);
+        }
+        Expression::Range(range) => {
+            if let Some(start) = &range.start {
+                visitor.visit_expression(start);
+            }
+            if let Some(end) = &range.end {
+                visitor.visit_expression(end);
+            }
+        }
+        Expression::MacroInvocation(invocation) => visit_all(visitor, &invocation.arguments),
+        Expression::Panic(panic) => visitor.visit_expression(&panic.message),
+        Expression::Struct(literal) => {
+            for (_, value) in &literal.fields {
+                visitor.visit_expression(value);
+            }
+            if let Some(rest) = &literal.rest {
+                visitor.visit_expression(rest);
+            }
+        }
+        Expression::Path(path) => walk_path(visitor, path),
+        Expression::Paren(inner, _) => visitor.visit_expression(inner),
+    }
+}
+
+pub fn walk_pattern<V: Visitor + ?Sized>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Tuple(patterns) | Pattern::Or(patterns) => {
+            for pattern in patterns {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Pattern::Struct(_, fields, _) => {
+            for (_, pattern) in fields {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Pattern::Range(start, end) => {
+            visitor.visit_pattern(start);
+            visitor.visit_pattern(end);
+        }
+        Pattern::Literal(_) | Pattern::Identifier(_) | Pattern::Wildcard | Pattern::Rest => {}
+    }
+}
+
+pub fn walk_type<V: Visitor + ?Sized>(visitor: &mut V, ty: &Type) {
+    match ty {
+        Type::Array(element, size) => {
+            visitor.visit_type(element);
+            if let Some(size) = size {
+                visitor.visit_expression(size);
+            }
+        }
+        Type::Slice(target) | Type::Pointer(target, _) | Type::Reference(target, _, _) => {
+            visitor.visit_type(target);
+        }
+        Type::Tuple(types) => {
+            for ty in types {
+                visitor.visit_type(ty);
+            }
+        }
+        Type::Function(params, ret, _) => {
+            for param in params {
+                visitor.visit_type(param);
+            }
+            visitor.visit_type(ret);
+        }
+        Type::Named(path) => walk_path(visitor, path),
+        Type::Generic(base, args) => {
+            visitor.visit_type(base);
+            for arg in args {
+                visitor.visit_type(arg);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn walk_path<V: Visitor + ?Sized>(visitor: &mut V, path: &TypePath) {
+    for arg in path
+        .segments
+        .iter()
+        .flat_map(|segment| segment.generic_args.iter().flatten())
+    {
+        visitor.visit_type(arg);
+    }
+}
+
+fn visit_all<V: Visitor + ?Sized>(visitor: &mut V, expressions: &[Expression]) {
+    for expression in expressions {
+        visitor.visit_expression(expression);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::fold::{self, Folder};
+    use crate::parser::parse;
+
+    // Records the kind of every node reached, to compare a `Visitor` walk
+    // with the `Folder` walk over the same program.
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn visit_statement(&mut self, statement: &Statement) {
+            self.0.push("statement".to_string());
+            walk_statement(self, statement)
+        }
+
+        fn visit_expression(&mut self, expression: &Expression) {
+            self.0
+                .push(format!("{:?}", std::mem::discriminant(expression)));
+            walk_expression(self, expression)
+        }
+
+        fn visit_pattern(&mut self, pattern: &Pattern) {
+            self.0.push("pattern".to_string());
+            walk_pattern(self, pattern)
+        }
+
+        fn visit_type(&mut self, ty: &Type) {
+            self.0.push(ty.to_string());
+            walk_type(self, ty)
+        }
+    }
+
+    impl Folder for Trace {
+        fn fold_statement(&mut self, statement: Statement) -> Statement {
+            self.0.push("statement".to_string());
+            fold::walk_statement(self, statement)
+        }
+
+        fn fold_expression(&mut self, expression: Expression) -> Expression {
+            self.0
+                .push(format!("{:?}", std::mem::discriminant(&expression)));
+            fold::walk_expression(self, expression)
+        }
+
+        fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+            self.0.push("pattern".to_string());
+            fold::walk_pattern(self, pattern)
+        }
+
+        fn fold_type(&mut self, ty: Type) -> Type {
+            self.0.push(ty.to_string());
+            fold::walk_type(self, ty)
+        }
+    }
+
+    #[test]
+    fn test_visitor_reaches_what_folder_reaches() {
+        let program = parse(
+            "struct P { x: [u8; 4] = [0u8, 1, 2, 3] }\n\
+             enum E { A(i32) = 1 }\n\
+             const N: usize = 3;\n\
+             fn g(a: i32 = 1) -> (i32, &u8) {\n\
+                 let (b, _) = (a as i64, 2.0f32);\n\
+                 for i in 0..N { if i > 0 { break; } else if a < 0 { continue; } }\n\
+                 match a { 0 | 1 => f::<u8>(P { x: y, ..z }), n if n > 2 => -n, _ => loop { 1 } }\n\
+             }",
+        )
+        .unwrap();
+        let mut visited = Trace::default();
+        visited.visit_program(&program);
+        let mut folded = Trace::default();
+        folded.fold_program(program);
+        assert!(visited.0.len() > 40);
+        assert_eq!(visited.0, folded.0);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::ast::expressions::Literal;
+use crate::ast::{Attribute, AttributeArg, Declaration, Program};
+use crate::diagnostics::Diagnostic;
+use std::collections::HashSet;
+
+// What `#[cfg(..)]` predicates are evaluated against: enabled features, as
+// matched by `feature = "x"`, and bare flags such as `test` or `unix`.
+#[derive(Debug, Clone, Default)]
+pub struct CfgOptions {
+    features: HashSet<String>,
+    flags: HashSet<String>,
+}
+
+impl CfgOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_feature(mut self, name: &str) -> Self {
+        self.features.insert(name.to_string());
+        self
+    }
+
+    pub fn with_flag(mut self, name: &str) -> Self {
+        self.flags.insert(name.to_string());
+        self
+    }
+
+    // Every argument is evaluated, so a malformed predicate is reported even
+    // where `all`/`any` could have stopped early.
+    pub fn evaluate(&self, predicate: &AttributeArg) -> Result<bool, String> {
+        match predicate {
+            AttributeArg::Identifier(flag) => Ok(self.flags.contains(&flag.name)),
+            AttributeArg::NameValue(key, value) => match (key.name.as_str(), value) {
+                ("feature", Literal::String(name)) => Ok(self.features.contains(name)),
+                ("feature", _) => Err("`feature` must be given a string literal".to_string()),
+                (other, _) => Err(format!("unknown cfg key `{}`", other)),
+            },
+            AttributeArg::List(operator, args) => {
+                let values = args
+                    .iter()
+                    .map(|arg| self.evaluate(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match (operator.name.as_str(), &values[..]) {
+                    ("all", _) => Ok(values.iter().all(|&value| value)),
+                    ("any", _) => Ok(values.iter().any(|&value| value)),
+                    ("not", [value]) => Ok(!value),
+                    ("not", _) => Err(format!(
+                        "`not` takes exactly one predicate, but {} were given",
+                        values.len()
+                    )),
+                    (other, _) => Err(format!("unknown cfg predicate `{}`", other)),
+                }
+            }
+            AttributeArg::Literal(literal) => {
+                Err(format!("expected a cfg predicate, found `{}`", literal))
+            }
+        }
+    }
+}
+
+// Removes every item and struct field whose `#[cfg(..)]` attributes do not all
+// hold under `options`, including inside inline modules. Meant to run before
+// resolution, so later passes never see configured-out code. Items with a
+// malformed predicate are kept and reported.
+pub fn strip_cfg(program: &mut Program, options: &CfgOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    strip_items(&mut program.items, options, &mut diagnostics);
+    diagnostics
+}
+
+fn strip_items(
+    items: &mut Vec<Declaration>,
+    options: &CfgOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    items.retain(|item| match item {
+        Declaration::Function(function) => enabled(&function.attributes, options, diagnostics),
+        Declaration::Struct(decl) => enabled(&decl.attributes, options, diagnostics),
+        Declaration::Enum(decl) => enabled(&decl.attributes, options, diagnostics),
+        Declaration::Union(decl) => enabled(&decl.attributes, options, diagnostics),
+        _ => true,
+    });
+    for item in items {
+        match item {
+            Declaration::Struct(decl) => decl
+                .fields
+                .retain(|field| enabled(&field.attributes, options, diagnostics)),
+            Declaration::Module(module) => {
+                if let Some(items) = &mut module.items {
+                    strip_items(items, options, diagnostics);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn enabled(
+    attributes: &[Attribute],
+    options: &CfgOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> bool {
+    let mut enabled = true;
+    for attribute in attributes
+        .iter()
+        .filter(|attribute| attribute.name.name == "cfg")
+    {
+        let result = match &attribute.args[..] {
+            [predicate] => options.evaluate(predicate),
+            args => Err(format!(
+                "`cfg` takes exactly one predicate, but {} were given",
+                args.len()
+            )),
+        };
+        match result {
+            Ok(value) => enabled &= value,
+            Err(message) => diagnostics.push(Diagnostic::error(message, attribute.span.clone())),
+        }
+    }
+    enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::printer::print_program;
+
+    fn stripped(source: &str, options: &CfgOptions) -> String {
+        let mut program = parse(source).unwrap();
+        assert_eq!(strip_cfg(&mut program, options), Vec::new());
+        print_program(&program)
+    }
+
+    #[test]
+    fn test_feature_gated_function() {
+        let source = "#[cfg(feature = \"a\")]\nfn gated() {}\nfn always() {}";
+        let enabled = stripped(source, &CfgOptions::new().with_feature("a"));
+        assert!(enabled.contains("fn gated()"));
+        assert!(enabled.contains("fn always()"));
+
+        let disabled = stripped(source, &CfgOptions::new().with_feature("b"));
+        assert!(!disabled.contains("fn gated()"));
+        assert!(disabled.contains("fn always()"));
+    }
+
+    #[test]
+    fn test_combinators_fields_and_modules() {
+        let source = "struct S { a: i32, #[cfg(not(test))] b: i32 }\n\
+                      mod m {\n\
+                      #[cfg(all(unix, any(feature = \"x\", feature = \"y\")))]\n\
+                      fn f() {}\n\
+                      }";
+        let options = CfgOptions::new().with_flag("test").with_flag("unix");
+        let output = stripped(source, &options);
+        assert!(!output.contains("b: i32"));
+        assert!(!output.contains("fn f()"));
+
+        let output = stripped(source, &options.with_feature("y"));
+        assert!(output.contains("fn f()"));
+    }
+
+    #[test]
+    fn test_malformed_predicates_are_reported() {
+        let mut program = parse(
+            "#[cfg(not(a, b))]\nfn f() {}\n#[cfg(os = \"x\")]\nfn g() {}\n#[cfg(feature = 1)]\nfn h() {}",
+        )
+        .unwrap();
+        let diagnostics = strip_cfg(&mut program, &CfgOptions::new());
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "`not` takes exactly one predicate, but 2 were given",
+                "unknown cfg key `os`",
+                "`feature` must be given a string literal",
+            ]
+        );
+        assert!(diagnostics.iter().all(Diagnostic::is_error));
+        assert_eq!(program.items.len(), 3);
+    }
+}
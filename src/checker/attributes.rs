@@ -0,0 +1,180 @@
+use crate::ast::{Attribute, Declaration, Program};
+use crate::diagnostics::Diagnostic;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+// How many arguments an attribute takes: `#[test]` is `Exactly(0)`,
+// `#[cfg(unix)]` is `Exactly(1)` and `#[derive(A, B)]` is `AtLeast(1)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    AtMost(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exactly(n) => count == n,
+            Arity::AtMost(n) => count <= n,
+            Arity::AtLeast(n) => count >= n,
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Arity::Exactly(0) => f.write_str("no arguments"),
+            Arity::Exactly(n) => write!(f, "{} {}", n, arguments(n)),
+            Arity::AtMost(n) => write!(f, "at most {} {}", n, arguments(n)),
+            Arity::AtLeast(n) => write!(f, "at least {} {}", n, arguments(n)),
+        }
+    }
+}
+
+fn arguments(count: usize) -> &'static str {
+    if count == 1 { "argument" } else { "arguments" }
+}
+
+// The attributes a host understands, keyed by name. Attributes are otherwise
+// free-form, so anything not registered here is only warned about.
+#[derive(Debug, Clone, Default)]
+pub struct AttributeRegistry {
+    known: HashMap<String, Arity>,
+}
+
+impl AttributeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The attributes the compiler itself gives meaning to.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register("test", Arity::Exactly(0));
+        registry.register("cfg", Arity::Exactly(1));
+        registry.register("inline", Arity::AtMost(1));
+        registry.register("repr", Arity::Exactly(1));
+        registry.register("derive", Arity::AtLeast(1));
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, arity: Arity) {
+        self.known.insert(name.to_string(), arity);
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Arity> {
+        self.known.get(name).copied()
+    }
+}
+
+// Checks every attribute in `program`, including those on struct fields and
+// inside inline modules, against `registry`.
+pub fn validate_attributes(program: &Program, registry: &AttributeRegistry) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    collect(&program.items, registry, &mut diagnostics);
+    diagnostics
+}
+
+fn collect(items: &[Declaration], registry: &AttributeRegistry, diagnostics: &mut Vec<Diagnostic>) {
+    for item in items {
+        match item {
+            Declaration::Function(function) => {
+                validate(&function.attributes, registry, diagnostics)
+            }
+            Declaration::Struct(decl) => {
+                validate(&decl.attributes, registry, diagnostics);
+                for field in &decl.fields {
+                    validate(&field.attributes, registry, diagnostics);
+                }
+            }
+            Declaration::Enum(decl) => validate(&decl.attributes, registry, diagnostics),
+            Declaration::Union(decl) => validate(&decl.attributes, registry, diagnostics),
+            Declaration::Module(module) => {
+                if let Some(items) = &module.items {
+                    collect(items, registry, diagnostics);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn validate(
+    attributes: &[Attribute],
+    registry: &AttributeRegistry,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for attribute in attributes {
+        let Some(arity) = registry.lookup(&attribute.name.name) else {
+            diagnostics.push(Diagnostic::warning(
+                format!("unknown attribute `{}`", attribute.name),
+                attribute.span.clone(),
+            ));
+            continue;
+        };
+        let count = attribute.args.len();
+        if !arity.accepts(count) {
+            diagnostics.push(Diagnostic::error(
+                format!(
+                    "attribute `{}` takes {}, but {} {} given",
+                    attribute.name,
+                    arity,
+                    count,
+                    if count == 1 { "was" } else { "were" }
+                ),
+                attribute.span.clone(),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::parser::parse;
+
+    fn validate_source(source: &str) -> Vec<Diagnostic> {
+        validate_attributes(&parse(source).unwrap(), &AttributeRegistry::with_builtins())
+    }
+
+    #[test]
+    fn test_registered_attribute_is_accepted() {
+        assert!(validate_source("#[inline]\nfn f() {}").is_empty());
+        assert!(validate_source("#[inline(always)]\nfn f() {}").is_empty());
+        assert!(validate_source("#[test]\nfn f() {}").is_empty());
+    }
+
+    #[test]
+    fn test_attribute_arity_mismatch() {
+        let diagnostics = validate_source("#[inline(x, y)]\nfn f() {}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[0].message,
+            "attribute `inline` takes at most 1 argument, but 2 were given"
+        );
+
+        let diagnostics = validate_source("#[test(x)]\nfn f() {}");
+        assert_eq!(
+            diagnostics[0].message,
+            "attribute `test` takes no arguments, but 1 was given"
+        );
+    }
+
+    #[test]
+    fn test_unknown_attribute_warns() {
+        let diagnostics = validate_source("struct S {\n    #[frobnicate]\n    x: i32,\n}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].message, "unknown attribute `frobnicate`");
+        assert_eq!(diagnostics[0].span.line, 2);
+
+        let mut registry = AttributeRegistry::new();
+        registry.register("frobnicate", Arity::Exactly(0));
+        let program = parse("#[frobnicate]\nfn f() {}").unwrap();
+        assert!(validate_attributes(&program, &registry).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::ast::expressions::{Expression, Literal, MacroInvocation};
+use crate::diagnostics::Diagnostic;
+
+pub const BUILTIN_MACROS: &[&str] = &["print", "println"];
+
+pub fn is_builtin_macro(name: &str) -> bool {
+    BUILTIN_MACROS.contains(&name)
+}
+
+pub fn check_builtin_macro(invocation: &MacroInvocation) -> Vec<Diagnostic> {
+    match invocation.name.name.as_str() {
+        "print" | "println" => check_format_macro(invocation),
+        _ => vec![],
+    }
+}
+
+fn check_format_macro(invocation: &MacroInvocation) -> Vec<Diagnostic> {
+    let name = &invocation.name.name;
+
+    // `println` with no arguments just prints a newline.
+    let Some((format, rest)) = invocation.arguments.split_first() else {
+        if name == "println" {
+            return vec![];
+        }
+        return vec![Diagnostic::error(
+            format!("`{}` requires a format string argument", name),
+            invocation.span.clone(),
+        )];
+    };
+
+    let Expression::Literal(Literal::String(format)) = format else {
+        return vec![Diagnostic::error(
+            format!("`{}` expects a string literal as its first argument", name),
+            invocation.span.clone(),
+        )];
+    };
+
+    let Some(placeholders) = count_placeholders(format) else {
+        return vec![Diagnostic::error(
+            format!(
+                "invalid format string in `{}`: unmatched `{{` or `}}`",
+                name
+            ),
+            invocation.span.clone(),
+        )];
+    };
+
+    if placeholders != rest.len() {
+        return vec![Diagnostic::error(
+            format!(
+                "`{}` format string has {} placeholder{} but {} argument{} supplied",
+                name,
+                placeholders,
+                if placeholders == 1 { "" } else { "s" },
+                rest.len(),
+                if rest.len() == 1 { " was" } else { "s were" },
+            ),
+            invocation.span.clone(),
+        )];
+    }
+
+    vec![]
+}
+
+// Counts `{}` placeholders, treating `{{` and `}}` as escaped braces.
+// Returns `None` for any other use of a brace.
+pub fn count_placeholders(format: &str) -> Option<usize> {
+    let mut count = 0;
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                count += 1;
+            }
+            ('{', _) | ('}', _) => return None,
+            _ => {}
+        }
+    }
+
+    Some(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Identifier, Span};
+
+    fn ident(name: &str) -> Identifier {
+        Identifier::new(name.to_string(), Span::dummy())
+    }
+
+    fn invocation(name: &str, arguments: Vec<Expression>) -> MacroInvocation {
+        MacroInvocation {
+            name: ident(name),
+            arguments,
+            span: Span::dummy(),
+        }
+    }
+
+    fn string(s: &str) -> Expression {
+        Expression::Literal(Literal::String(s.to_string()))
+    }
+
+    #[test]
+    fn test_println_matching_arguments() {
+        let call = invocation(
+            "println",
+            vec![
+                string("{} {}"),
+                Expression::Identifier(ident("a")),
+                Expression::Identifier(ident("b")),
+            ],
+        );
+        assert!(check_builtin_macro(&call).is_empty());
+    }
+
+    #[test]
+    fn test_println_argument_count_mismatch() {
+        let call = invocation(
+            "println",
+            vec![string("{} {}"), Expression::Identifier(ident("a"))],
+        );
+        let diagnostics = check_builtin_macro(&call);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert_eq!(
+            diagnostics[0].message,
+            "`println` format string has 2 placeholders but 1 argument was supplied"
+        );
+    }
+
+    #[test]
+    fn test_print_requires_string_literal() {
+        let call = invocation("print", vec![Expression::Identifier(ident("a"))]);
+        assert_eq!(check_builtin_macro(&call).len(), 1);
+
+        let call = invocation("print", vec![]);
+        assert_eq!(check_builtin_macro(&call).len(), 1);
+
+        let call = invocation("println", vec![]);
+        assert!(check_builtin_macro(&call).is_empty());
+    }
+
+    #[test]
+    fn test_count_placeholders() {
+        assert_eq!(count_placeholders("hello"), Some(0));
+        assert_eq!(count_placeholders("{} and {}"), Some(2));
+        assert_eq!(count_placeholders("{{}} {}"), Some(1));
+        assert_eq!(count_placeholders("{x}"), None);
+        assert_eq!(count_placeholders("}"), None);
+    }
+}
@@ -0,0 +1,545 @@
+use super::integer_bits;
+use crate::ast::expressions::{BinaryOperator, Expression, IntBase, Literal, UnaryOperator};
+use crate::ast::fold::{Folder, walk_type};
+use crate::ast::{ConstDecl, Declaration, Program, Span, Type};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Integer(i128),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    String(String),
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Integer(value) => write!(f, "{}", value),
+            ConstValue::Float(value) => write!(f, "{:?}", value),
+            ConstValue::Bool(value) => write!(f, "{}", value),
+            ConstValue::Char(value) => write!(f, "{:?}", value),
+            ConstValue::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstEvalError {
+    UnknownConstant {
+        name: String,
+        span: Span,
+    },
+    // The constants involved, starting and ending with the same name.
+    Cycle {
+        names: Vec<String>,
+        span: Span,
+    },
+    Overflow {
+        span: Span,
+    },
+    DivisionByZero {
+        span: Span,
+    },
+    OutOfRange {
+        value: i128,
+        ty: Box<Type>,
+        span: Span,
+    },
+    InvalidOperands {
+        span: Span,
+    },
+    NotConstant {
+        span: Span,
+    },
+}
+
+impl ConstEvalError {
+    pub fn span(&self) -> &Span {
+        match self {
+            ConstEvalError::UnknownConstant { span, .. }
+            | ConstEvalError::Cycle { span, .. }
+            | ConstEvalError::Overflow { span }
+            | ConstEvalError::DivisionByZero { span }
+            | ConstEvalError::OutOfRange { span, .. }
+            | ConstEvalError::InvalidOperands { span }
+            | ConstEvalError::NotConstant { span } => span,
+        }
+    }
+}
+
+impl fmt::Display for ConstEvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstEvalError::UnknownConstant { name, .. } => {
+                write!(f, "cannot find constant `{}`", name)
+            }
+            ConstEvalError::Cycle { names, .. } => {
+                let path: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+                write!(f, "cycle in constant definitions: {}", path.join(" -> "))
+            }
+            ConstEvalError::Overflow { .. } => {
+                write!(f, "arithmetic overflow in constant expression")
+            }
+            ConstEvalError::DivisionByZero { .. } => {
+                write!(f, "division by zero in constant expression")
+            }
+            ConstEvalError::OutOfRange { value, ty, .. } => {
+                write!(f, "value {} does not fit in `{}`", value, ty)
+            }
+            ConstEvalError::InvalidOperands { .. } => {
+                write!(f, "invalid operands in constant expression")
+            }
+            ConstEvalError::NotConstant { .. } => {
+                write!(f, "expression cannot be evaluated at compile time")
+            }
+        }
+    }
+}
+
+type ConstResult = Result<ConstValue, ConstEvalError>;
+
+// The `const` declarations visible to constant expressions, by name.
+#[derive(Debug, Clone, Default)]
+pub struct ConstEnv {
+    constants: HashMap<String, ConstDecl>,
+}
+
+impl ConstEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_program(program: &Program) -> Self {
+        let mut env = Self::new();
+        for item in &program.items {
+            if let Declaration::Constant(constant) = item {
+                env.insert(constant.clone());
+            }
+        }
+        env
+    }
+
+    pub fn insert(&mut self, constant: ConstDecl) {
+        self.constants.insert(constant.name.name.clone(), constant);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ConstDecl> {
+        self.constants.get(name)
+    }
+}
+
+pub fn const_eval(expr: &Expression, env: &ConstEnv) -> ConstResult {
+    Evaluator::new(env).eval(expr, &Span::dummy())
+}
+
+// Evaluates a constant's value and checks that it fits the declared type.
+pub fn eval_constant(constant: &ConstDecl, env: &ConstEnv) -> ConstResult {
+    Evaluator::new(env).constant(constant, &constant.name.span)
+}
+
+// The length of an array type such as `[i32; N]`.
+pub fn array_length(size: &Expression, env: &ConstEnv) -> Result<usize, ConstEvalError> {
+    let span = expression_span(size).cloned().unwrap_or_else(Span::dummy);
+    match const_eval(size, env)? {
+        ConstValue::Integer(value) => {
+            usize::try_from(value).map_err(|_| ConstEvalError::OutOfRange {
+                value,
+                ty: Box::new(Type::Usize),
+                span,
+            })
+        }
+        _ => Err(ConstEvalError::InvalidOperands { span }),
+    }
+}
+
+// `ty` with every array length that evaluates written as its value, so that
+// `[i32; N]` and `[i32; 2 + 2]` compare equal to `[i32; 4]` when `N` is 4.
+// Lengths that do not evaluate are left as written.
+pub fn with_evaluated_lengths(ty: &Type, env: &ConstEnv) -> Type {
+    LengthEvaluator { env }.fold_type(ty.clone())
+}
+
+struct LengthEvaluator<'a> {
+    env: &'a ConstEnv,
+}
+
+impl Folder for LengthEvaluator<'_> {
+    fn fold_type(&mut self, ty: Type) -> Type {
+        match walk_type(self, ty) {
+            Type::Array(element, Some(size)) => match array_length(&size, self.env) {
+                Ok(length) => {
+                    let length = Literal::Integer(length as i128, None, IntBase::Dec);
+                    Type::Array(element, Some(Box::new(Expression::Literal(length))))
+                }
+                Err(_) => Type::Array(element, Some(size)),
+            },
+            ty => ty,
+        }
+    }
+}
+
+struct Evaluator<'a> {
+    env: &'a ConstEnv,
+    // Constants currently being evaluated, to detect cycles.
+    stack: Vec<String>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(env: &'a ConstEnv) -> Self {
+        Self {
+            env,
+            stack: Vec::new(),
+        }
+    }
+
+    fn constant(&mut self, constant: &ConstDecl, span: &Span) -> ConstResult {
+        let name = &constant.name.name;
+        if let Some(start) = self.stack.iter().position(|entry| entry == name) {
+            let mut names = self.stack[start..].to_vec();
+            names.push(name.clone());
+            return Err(ConstEvalError::Cycle {
+                names,
+                span: span.clone(),
+            });
+        }
+        self.stack.push(name.clone());
+        let value = self.eval(&constant.value, &constant.span);
+        self.stack.pop();
+        fit(value?, &constant.ty, &constant.span)
+    }
+
+    // `span` locates errors in expressions that carry no span of their own.
+    fn eval(&mut self, expr: &Expression, span: &Span) -> ConstResult {
+        let span = expression_span(expr).unwrap_or(span);
+        match expr {
+            Expression::Paren(inner, _) => self.eval(inner, span),
+            Expression::Literal(literal) => match literal {
+                Literal::Integer(value, _, _) => Ok(ConstValue::Integer(*value)),
+                Literal::Float(value, _) => Ok(ConstValue::Float(*value)),
+                Literal::Boolean(value) => Ok(ConstValue::Bool(*value)),
+                Literal::Character(value) => Ok(ConstValue::Char(*value)),
+                Literal::String(value) => Ok(ConstValue::String(value.clone())),
+                Literal::Unit | Literal::Array(_) | Literal::Tuple(_) => {
+                    Err(ConstEvalError::NotConstant { span: span.clone() })
+                }
+            },
+            Expression::Identifier(ident) => match self.env.get(&ident.name) {
+                Some(constant) => self.constant(constant, &ident.span),
+                None => Err(ConstEvalError::UnknownConstant {
+                    name: ident.name.clone(),
+                    span: ident.span.clone(),
+                }),
+            },
+            Expression::Unary(unary) => {
+                let operand = self.eval(&unary.operand, span)?;
+                eval_unary(&unary.operator, operand, span)
+            }
+            Expression::Binary(binary) => {
+                let left = self.eval(&binary.left, span)?;
+                let right = self.eval(&binary.right, span)?;
+                eval_binary(&binary.operator, left, right, span)
+            }
+            Expression::Cast(cast) => {
+                let value = self.eval(&cast.expr, span)?;
+                eval_cast(value, &cast.target_type, span)
+            }
+            Expression::Block(block) if block.statements.is_empty() => match &block.tail {
+                Some(tail) => self.eval(tail, span),
+                None => Err(ConstEvalError::NotConstant { span: span.clone() }),
+            },
+            _ => Err(ConstEvalError::NotConstant { span: span.clone() }),
+        }
+    }
+}
+
+pub(super) fn expression_span(expr: &Expression) -> Option<&Span> {
+    match expr {
+        Expression::Identifier(ident) => Some(&ident.span),
+        Expression::Binary(binary) => Some(&binary.span),
+        Expression::Unary(unary) => Some(&unary.span),
+        Expression::Call(call) => Some(&call.span),
+        Expression::Member(member) => Some(&member.span),
+        Expression::Index(index) => Some(&index.span),
+        Expression::Cast(cast) => Some(&cast.span),
+        Expression::Block(block) => Some(&block.span),
+        Expression::If(if_expr) => Some(&if_expr.span),
+        Expression::Match(match_expr) => Some(&match_expr.span),
+        Expression::Loop(loop_expr) => Some(&loop_expr.span),
+        Expression::While(while_expr) => Some(&while_expr.span),
+        Expression::For(for_expr) => Some(&for_expr.span),
+        Expression::Range(range) => Some(&range.span),
+        Expression::MacroInvocation(invocation) => Some(&invocation.span),
+        Expression::Panic(panic) => Some(&panic.span),
+        Expression::Struct(literal) => Some(&literal.span),
+        Expression::Path(path) => Some(&path.span),
+        Expression::Paren(_, span) => Some(span),
+        Expression::Literal(_) => None,
+    }
+}
+
+fn eval_unary(operator: &UnaryOperator, operand: ConstValue, span: &Span) -> ConstResult {
+    let overflow = || ConstEvalError::Overflow { span: span.clone() };
+    match (operator, operand) {
+        (UnaryOperator::Neg, ConstValue::Integer(value)) => value
+            .checked_neg()
+            .map(ConstValue::Integer)
+            .ok_or_else(overflow),
+        (UnaryOperator::Neg, ConstValue::Float(value)) => Ok(ConstValue::Float(-value)),
+        (UnaryOperator::Not, ConstValue::Bool(value)) => Ok(ConstValue::Bool(!value)),
+        (UnaryOperator::BitNot, ConstValue::Integer(value)) => Ok(ConstValue::Integer(!value)),
+        (UnaryOperator::Deref | UnaryOperator::Ref | UnaryOperator::RefMut, _) => {
+            Err(ConstEvalError::NotConstant { span: span.clone() })
+        }
+        _ => Err(ConstEvalError::InvalidOperands { span: span.clone() }),
+    }
+}
+
+fn eval_binary(
+    operator: &BinaryOperator,
+    left: ConstValue,
+    right: ConstValue,
+    span: &Span,
+) -> ConstResult {
+    use BinaryOperator::*;
+    use ConstValue::{Bool, Float, Integer};
+
+    let overflow = || ConstEvalError::Overflow { span: span.clone() };
+    let checked = |value: Option<i128>| value.map(Integer).ok_or_else(overflow);
+    match (left, right) {
+        (Integer(a), Integer(b)) => match operator {
+            Add => checked(a.checked_add(b)),
+            Sub => checked(a.checked_sub(b)),
+            Mul => checked(a.checked_mul(b)),
+            Div | Rem if b == 0 => Err(ConstEvalError::DivisionByZero { span: span.clone() }),
+            Div => checked(a.checked_div(b)),
+            Rem => checked(a.checked_rem(b)),
+            BitAnd => Ok(Integer(a & b)),
+            BitOr => Ok(Integer(a | b)),
+            BitXor => Ok(Integer(a ^ b)),
+            Shl | Shr => {
+                let amount = u32::try_from(b).map_err(|_| overflow())?;
+                if *operator == Shl {
+                    checked(a.checked_shl(amount))
+                } else {
+                    checked(a.checked_shr(amount))
+                }
+            }
+            _ => compare(operator, a.partial_cmp(&b), span),
+        },
+        (Float(a), Float(b)) => match operator {
+            Add => Ok(Float(a + b)),
+            Sub => Ok(Float(a - b)),
+            Mul => Ok(Float(a * b)),
+            Div => Ok(Float(a / b)),
+            Rem => Ok(Float(a % b)),
+            _ => compare(operator, a.partial_cmp(&b), span),
+        },
+        (Bool(a), Bool(b)) => match operator {
+            And | BitAnd => Ok(Bool(a && b)),
+            Or | BitOr => Ok(Bool(a || b)),
+            BitXor => Ok(Bool(a ^ b)),
+            _ => compare(operator, a.partial_cmp(&b), span),
+        },
+        (ConstValue::Char(a), ConstValue::Char(b)) => compare(operator, a.partial_cmp(&b), span),
+        (ConstValue::String(a), ConstValue::String(b)) => {
+            compare(operator, a.partial_cmp(&b), span)
+        }
+        _ => Err(ConstEvalError::InvalidOperands { span: span.clone() }),
+    }
+}
+
+fn compare(
+    operator: &BinaryOperator,
+    ordering: Option<std::cmp::Ordering>,
+    span: &Span,
+) -> ConstResult {
+    use std::cmp::Ordering::*;
+
+    let result = match operator {
+        BinaryOperator::Eq => ordering == Some(Equal),
+        BinaryOperator::NotEq => ordering != Some(Equal),
+        BinaryOperator::Lt => ordering == Some(Less),
+        BinaryOperator::LtEq => matches!(ordering, Some(Less | Equal)),
+        BinaryOperator::Gt => ordering == Some(Greater),
+        BinaryOperator::GtEq => matches!(ordering, Some(Greater | Equal)),
+        _ => return Err(ConstEvalError::InvalidOperands { span: span.clone() }),
+    };
+    Ok(ConstValue::Bool(result))
+}
+
+// Casts follow Rust: integers wrap to the target width and floats saturate.
+fn eval_cast(value: ConstValue, target: &Type, span: &Span) -> ConstResult {
+    let integer = match value {
+        ConstValue::Integer(value) => value,
+        ConstValue::Bool(value) => value as i128,
+        ConstValue::Char(value) => value as i128,
+        ConstValue::Float(value) if target.is_integer() => value as i128,
+        ConstValue::Float(value) if target.is_float() => return Ok(ConstValue::Float(value)),
+        _ => return Err(ConstEvalError::InvalidOperands { span: span.clone() }),
+    };
+    if target.is_float() {
+        return Ok(ConstValue::Float(integer as f64));
+    }
+    if *target == Type::Char {
+        return u32::try_from(integer)
+            .ok()
+            .and_then(char::from_u32)
+            .map(ConstValue::Char)
+            .ok_or_else(|| ConstEvalError::OutOfRange {
+                value: integer,
+                ty: Box::new(Type::Char),
+                span: span.clone(),
+            });
+    }
+    if !target.is_integer() {
+        return Err(ConstEvalError::InvalidOperands { span: span.clone() });
+    }
+    Ok(ConstValue::Integer(match integer_bits(target) {
+        Some(bits) if bits < 128 => {
+            let shift = 128 - bits;
+            if target.is_signed() {
+                (integer << shift) >> shift
+            } else {
+                ((integer as u128) << shift >> shift) as i128
+            }
+        }
+        _ => integer,
+    }))
+}
+
+fn fit(value: ConstValue, ty: &Type, span: &Span) -> ConstResult {
+    let ConstValue::Integer(integer) = value else {
+        return Ok(value);
+    };
+    let Some((min, max)) = integer_range(ty) else {
+        return Ok(value);
+    };
+    if integer < min || integer > max {
+        return Err(ConstEvalError::OutOfRange {
+            value: integer,
+            ty: Box::new(ty.clone()),
+            span: span.clone(),
+        });
+    }
+    Ok(value)
+}
+
+// The values of an integer type that an `i128` can hold; `u128` is capped at `i128::MAX`.
+pub(super) fn integer_range(ty: &Type) -> Option<(i128, i128)> {
+    Some(bits_range(integer_bits(ty)?, ty.is_signed()))
+}
+
+pub(super) fn bits_range(bits: u32, signed: bool) -> (i128, i128) {
+    if signed {
+        (i128::MIN >> (128 - bits), i128::MAX >> (128 - bits))
+    } else if bits == 128 {
+        (0, i128::MAX)
+    } else {
+        (0, (1i128 << bits) - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn env(source: &str) -> ConstEnv {
+        ConstEnv::from_program(&parse(source).unwrap())
+    }
+
+    #[test]
+    fn test_const_used_as_array_length() {
+        let program = parse("const N: i32 = 2 + 2; var grid: [i32; N * 2];").unwrap();
+        let env = ConstEnv::from_program(&program);
+
+        assert_eq!(
+            eval_constant(env.get("N").unwrap(), &env),
+            Ok(ConstValue::Integer(4))
+        );
+        let Declaration::Variable(grid) = &program.items[1] else {
+            panic!("expected variable");
+        };
+        let Some(Type::Array(_, Some(size))) = &grid.ty else {
+            panic!("expected sized array");
+        };
+        assert_eq!(array_length(size, &env), Ok(8));
+    }
+
+    #[test]
+    fn test_cycle_is_detected() {
+        let env = env("const A: i32 = B; const B: i32 = A + 1;");
+        let error = eval_constant(env.get("A").unwrap(), &env).unwrap_err();
+
+        assert!(matches!(error, ConstEvalError::Cycle { .. }));
+        assert_eq!(
+            error.to_string(),
+            "cycle in constant definitions: `A` -> `B` -> `A`"
+        );
+    }
+
+    #[test]
+    fn test_128_bit_constants() {
+        let env = env("const X: i128 = -5; const Y: u128 = 7; const Z: u128 = -1;");
+
+        assert_eq!(
+            eval_constant(env.get("X").unwrap(), &env),
+            Ok(ConstValue::Integer(-5))
+        );
+        assert_eq!(
+            eval_constant(env.get("Y").unwrap(), &env),
+            Ok(ConstValue::Integer(7))
+        );
+        assert_eq!(
+            eval_constant(env.get("Z").unwrap(), &env)
+                .unwrap_err()
+                .to_string(),
+            "value -1 does not fit in `u128`"
+        );
+        assert_eq!(bits_range(128, true), (i128::MIN, i128::MAX));
+        assert_eq!(bits_range(8, true), (-128, 127));
+    }
+
+    #[test]
+    fn test_folding_and_errors() {
+        let env = env("const BIG: u8 = 200 + 100; const MASK: u8 = 300 as u8;");
+
+        assert_eq!(
+            eval_constant(env.get("MASK").unwrap(), &env),
+            Ok(ConstValue::Integer(44))
+        );
+        assert_eq!(
+            eval_constant(env.get("BIG").unwrap(), &env)
+                .unwrap_err()
+                .to_string(),
+            "value 300 does not fit in `u8`"
+        );
+
+        let eval = |source: &str| {
+            let program = parse(&format!("const X: i64 = {};", source)).unwrap();
+            let Declaration::Constant(constant) = &program.items[0] else {
+                panic!("expected constant");
+            };
+            const_eval(&constant.value, &env)
+        };
+        assert_eq!(eval("-(1 << 4) % 3"), Ok(ConstValue::Integer(-1)));
+        assert_eq!(eval("-5 * -2"), Ok(ConstValue::Integer(10)));
+        assert_eq!(eval("-5"), Ok(ConstValue::Integer(-5)));
+        assert_eq!(eval("MASK > 40 && true"), Ok(ConstValue::Bool(true)));
+        assert!(matches!(
+            eval("1 / (2 - 2)"),
+            Err(ConstEvalError::DivisionByZero { .. })
+        ));
+        assert!(matches!(
+            eval("MISSING"),
+            Err(ConstEvalError::UnknownConstant { .. })
+        ));
+        assert!(matches!(
+            eval("f()"),
+            Err(ConstEvalError::NotConstant { .. })
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::ast::{Declaration, Identifier};
+use crate::diagnostics::Diagnostic;
+use std::collections::HashMap;
+
+// Fields, variants and parameters declared twice under the same name, in
+// every item including those of inline modules. Each duplicate is reported at
+// its own span with a label at the first declaration.
+pub fn check_duplicate_names(items: &[Declaration]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    collect(items, &mut diagnostics);
+    diagnostics
+}
+
+fn collect(items: &[Declaration], diagnostics: &mut Vec<Diagnostic>) {
+    for item in items {
+        match item {
+            Declaration::Function(function) => duplicates(
+                "parameter",
+                &function.name,
+                function.params.iter().map(|param| &param.name),
+                diagnostics,
+            ),
+            Declaration::Struct(decl) => duplicates(
+                "field",
+                &decl.name,
+                decl.fields.iter().map(|field| &field.name),
+                diagnostics,
+            ),
+            Declaration::Union(decl) => duplicates(
+                "field",
+                &decl.name,
+                decl.fields.iter().map(|field| &field.name),
+                diagnostics,
+            ),
+            Declaration::Enum(decl) => duplicates(
+                "variant",
+                &decl.name,
+                decl.variants.iter().map(|variant| &variant.name),
+                diagnostics,
+            ),
+            Declaration::Module(module) => {
+                if let Some(items) = &module.items {
+                    collect(items, diagnostics);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn duplicates<'a>(
+    what: &str,
+    owner: &Identifier,
+    names: impl Iterator<Item = &'a Identifier>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut first: HashMap<&str, &Identifier> = HashMap::new();
+    for name in names {
+        match first.get(name.name.as_str()) {
+            Some(previous) => diagnostics.push(
+                Diagnostic::error(
+                    format!("{} `{}` is already declared in `{}`", what, name, owner),
+                    name.span.clone(),
+                )
+                .with_label(
+                    format!("`{}` first declared here", name),
+                    previous.span.clone(),
+                ),
+            ),
+            None => {
+                first.insert(&name.name, name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn check(source: &str) -> Vec<Diagnostic> {
+        check_duplicate_names(&parse(source).unwrap().items)
+    }
+
+    #[test]
+    fn test_duplicate_struct_field() {
+        let diagnostics = check("struct S { x: i32, x: bool }");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "field `x` is already declared in `S`"
+        );
+        assert_eq!(diagnostics[0].span.column, 20);
+        assert_eq!(diagnostics[0].labels.len(), 1);
+        assert_eq!(diagnostics[0].labels[0].message, "`x` first declared here");
+        assert_eq!(diagnostics[0].labels[0].span.column, 12);
+    }
+
+    #[test]
+    fn test_duplicate_enum_variant() {
+        let diagnostics = check("enum E { A, B, A }");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "variant `A` is already declared in `E`"
+        );
+        assert!(diagnostics[0].labels[0].span < diagnostics[0].span);
+    }
+
+    #[test]
+    fn test_duplicate_parameters() {
+        let diagnostics = check("fn f(a: i32, b: i32, a: i32, a: bool) {}");
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[1].message,
+            "parameter `a` is already declared in `f`"
+        );
+        // Both repeats point back at the first `a`.
+        assert_eq!(diagnostics[0].labels[0].span, diagnostics[1].labels[0].span);
+    }
+
+    #[test]
+    fn test_distinct_names_are_accepted() {
+        assert!(
+            check("struct S { x: i32, y: i32 } union U { a: i32, b: f32 } fn f(x: i32) {}")
+                .is_empty()
+        );
+    }
+}
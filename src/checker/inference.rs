@@ -0,0 +1,361 @@
+use super::scope::{Interner, Symbol};
+use crate::ast::{Span, Type};
+use std::collections::{HashMap, HashSet};
+
+// Matches a declared parameter type against an argument type, binding the
+// named type parameters along the way. Returns false on a structural mismatch
+// or when a parameter would be bound to two different types.
+pub fn unify(
+    declared: &Type,
+    actual: &Type,
+    generics: &[String],
+    bindings: &mut HashMap<String, Type>,
+) -> bool {
+    if let Some(name) = type_parameter(declared, generics) {
+        return match bindings.get(name) {
+            Some(bound) => bound == actual,
+            None => {
+                bindings.insert(name.to_string(), actual.clone());
+                true
+            }
+        };
+    }
+    match (declared, actual) {
+        (Type::Array(declared, _), Type::Array(actual, _))
+        | (Type::Slice(declared), Type::Slice(actual)) => {
+            unify(declared, actual, generics, bindings)
+        }
+        (Type::Pointer(declared, a), Type::Pointer(actual, b))
+        | (Type::Reference(declared, a, _), Type::Reference(actual, b, _)) => {
+            a == b && unify(declared, actual, generics, bindings)
+        }
+        (Type::Tuple(declared), Type::Tuple(actual)) => {
+            unify_all(declared, actual, generics, bindings)
+        }
+        (
+            Type::Function(declared, declared_ret, declared_abi),
+            Type::Function(actual, actual_ret, actual_abi),
+        ) => {
+            declared_abi == actual_abi
+                && unify_all(declared, actual, generics, bindings)
+                && unify(declared_ret, actual_ret, generics, bindings)
+        }
+        (Type::Named(declared), Type::Named(actual)) => {
+            declared.segments.len() == actual.segments.len()
+                && declared
+                    .segments
+                    .iter()
+                    .zip(&actual.segments)
+                    .all(|(d, a)| {
+                        d.ident.name == a.ident.name
+                            && match (&d.generic_args, &a.generic_args) {
+                                (Some(d), Some(a)) => unify_all(d, a, generics, bindings),
+                                (None, None) => true,
+                                _ => false,
+                            }
+                    })
+        }
+        (Type::Generic(declared, declared_args), Type::Generic(actual, actual_args)) => {
+            unify(declared, actual, generics, bindings)
+                && unify_all(declared_args, actual_args, generics, bindings)
+        }
+        _ => declared == actual,
+    }
+}
+
+fn unify_all(
+    declared: &[Type],
+    actual: &[Type],
+    generics: &[String],
+    bindings: &mut HashMap<String, Type>,
+) -> bool {
+    declared.len() == actual.len()
+        && declared
+            .iter()
+            .zip(actual)
+            .all(|(declared, actual)| unify(declared, actual, generics, bindings))
+}
+
+// Replaces bound type parameters in `ty`; unbound ones are left as written.
+pub fn substitute(ty: &Type, bindings: &HashMap<String, Type>) -> Type {
+    substitute_with(ty, &|name| bindings.get(name))
+}
+
+// `substitute` for bindings keyed by interned parameter names, as collected
+// by `free_vars`. Like `free_vars`, this takes the `Interner` rather than
+// being a `Type` method, since a `Symbol` only has meaning through it.
+pub fn substitute_symbols(ty: &Type, map: &HashMap<Symbol, Type>, interner: &Interner) -> Type {
+    substitute_with(ty, &|name| {
+        interner.get(name).and_then(|symbol| map.get(&symbol))
+    })
+}
+
+fn substitute_with<'a>(ty: &Type, bound: &impl Fn(&str) -> Option<&'a Type>) -> Type {
+    if let Some(bound) = parameter_name(ty).and_then(bound) {
+        return bound.clone();
+    }
+    let one = |ty: &Type| Box::new(substitute_with(ty, bound));
+    let all = |types: &[Type]| types.iter().map(|ty| substitute_with(ty, bound)).collect();
+    match ty {
+        Type::Array(element, size) => Type::Array(one(element), size.clone()),
+        Type::Slice(element) => Type::Slice(one(element)),
+        Type::Pointer(inner, mutability) => Type::Pointer(one(inner), *mutability),
+        Type::Reference(inner, mutability, lifetime) => {
+            Type::Reference(one(inner), *mutability, lifetime.clone())
+        }
+        Type::Tuple(types) => Type::Tuple(all(types)),
+        Type::Function(params, ret, abi) => Type::Function(all(params), one(ret), abi.clone()),
+        Type::Named(path) => {
+            let mut path = path.clone();
+            for segment in &mut path.segments {
+                if let Some(args) = &segment.generic_args {
+                    segment.generic_args = Some(all(args));
+                }
+            }
+            Type::Named(path)
+        }
+        Type::Generic(base, args) => Type::Generic(one(base), all(args)),
+        _ => ty.clone(),
+    }
+}
+
+// Fills each `_` in `annotation` with the matching part of `actual`, e.g.
+// `[_; 3]` against `[i32; 3]` gives `[i32; 3]`. Returns `None` when the two
+// differ anywhere outside the placeholders.
+pub fn fill_placeholders(annotation: &Type, actual: &Type) -> Option<Type> {
+    let fill = |a: &Type, b: &Type| fill_placeholders(a, b).map(Box::new);
+    let fill_all = |a: &[Type], b: &[Type]| -> Option<Vec<Type>> {
+        if a.len() != b.len() {
+            return None;
+        }
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| fill_placeholders(a, b))
+            .collect()
+    };
+    Some(match (annotation, actual) {
+        (Type::Infer(_), actual) => actual.clone(),
+        (Type::Array(a, size), Type::Array(b, _)) => Type::Array(fill(a, b)?, size.clone()),
+        (Type::Slice(a), Type::Slice(b)) => Type::Slice(fill(a, b)?),
+        (Type::Pointer(a, m), Type::Pointer(b, n)) if m == n => Type::Pointer(fill(a, b)?, *m),
+        (Type::Reference(a, m, lifetime), Type::Reference(b, n, _)) if m == n => {
+            Type::Reference(fill(a, b)?, *m, lifetime.clone())
+        }
+        (Type::Tuple(a), Type::Tuple(b)) => Type::Tuple(fill_all(a, b)?),
+        (Type::Function(a, a_ret, abi), Type::Function(b, b_ret, other)) if abi == other => {
+            Type::Function(fill_all(a, b)?, fill(a_ret, b_ret)?, abi.clone())
+        }
+        (Type::Named(a), Type::Named(b)) if a.segments.len() == b.segments.len() => {
+            let mut path = a.clone();
+            for (segment, other) in path.segments.iter_mut().zip(&b.segments) {
+                if segment.ident.name != other.ident.name {
+                    return None;
+                }
+                segment.generic_args = match (&segment.generic_args, &other.generic_args) {
+                    (Some(a), Some(b)) => Some(fill_all(a, b)?),
+                    (None, None) => None,
+                    _ => return None,
+                };
+            }
+            Type::Named(path)
+        }
+        (Type::Generic(a, a_args), Type::Generic(b, b_args)) => {
+            Type::Generic(fill(a, b)?, fill_all(a_args, b_args)?)
+        }
+        _ if annotation == actual => annotation.clone(),
+        _ => return None,
+    })
+}
+
+// The span of the first `_` placeholder in `ty`, if it has any.
+pub fn placeholder(ty: &Type) -> Option<&Span> {
+    fn first(types: &[Type]) -> Option<&Span> {
+        types.iter().find_map(placeholder)
+    }
+    match ty {
+        Type::Infer(span) => Some(span),
+        Type::Array(inner, _)
+        | Type::Slice(inner)
+        | Type::Pointer(inner, _)
+        | Type::Reference(inner, _, _) => placeholder(inner),
+        Type::Tuple(types) => first(types),
+        Type::Function(params, ret, _) => first(params).or_else(|| placeholder(ret)),
+        Type::Named(path) => path
+            .segments
+            .iter()
+            .find_map(|segment| segment.generic_args.as_deref().and_then(first)),
+        Type::Generic(base, args) => placeholder(base).or_else(|| first(args)),
+        _ => None,
+    }
+}
+
+// The type parameters among `params` that `ty` mentions, at any depth. A
+// parameter is a single-segment name without generic arguments, as in
+// `type_parameter`; names never interned cannot be parameters. This is a
+// checker function rather than a `Type` method because types spell names as
+// strings, and a `Symbol` only means something to the `Interner` that made it.
+pub fn free_vars(ty: &Type, params: &HashSet<Symbol>, interner: &Interner) -> HashSet<Symbol> {
+    let mut vars = HashSet::new();
+    collect_free_vars(ty, params, interner, &mut vars);
+    vars
+}
+
+fn collect_free_vars(
+    ty: &Type,
+    params: &HashSet<Symbol>,
+    interner: &Interner,
+    vars: &mut HashSet<Symbol>,
+) {
+    if let Some(name) = parameter_name(ty) {
+        if let Some(symbol) = interner.get(name)
+            && params.contains(&symbol)
+        {
+            vars.insert(symbol);
+        }
+        return;
+    }
+    let children: Vec<&Type> = match ty {
+        Type::Array(inner, _)
+        | Type::Slice(inner)
+        | Type::Pointer(inner, _)
+        | Type::Reference(inner, _, _) => vec![inner],
+        Type::Tuple(types) => types.iter().collect(),
+        Type::Function(param_types, ret, _) => param_types.iter().chain([&**ret]).collect(),
+        Type::Named(path) => path
+            .segments
+            .iter()
+            .filter_map(|segment| segment.generic_args.as_deref())
+            .flatten()
+            .collect(),
+        Type::Generic(base, args) => [&**base].into_iter().chain(args).collect(),
+        _ => Vec::new(),
+    };
+    for child in children {
+        collect_free_vars(child, params, interner, vars);
+    }
+}
+
+fn type_parameter<'a>(ty: &'a Type, generics: &[String]) -> Option<&'a str> {
+    parameter_name(ty).filter(|name| generics.iter().any(|generic| generic == name))
+}
+
+// The name `ty` would refer to if it were a type parameter: a single path
+// segment without generic arguments.
+fn parameter_name(ty: &Type) -> Option<&str> {
+    match ty {
+        Type::Named(path) => match path.segments.as_slice() {
+            [segment] if segment.generic_args.is_none() => Some(&segment.ident.name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::types::{TypePath, TypePathSegment};
+    use crate::ast::{Identifier, Span};
+
+    fn named(name: &str) -> Type {
+        Type::Named(TypePath {
+            segments: vec![TypePathSegment {
+                ident: Identifier::new(name.to_string(), Span::dummy()),
+                generic_args: None,
+                span: Span::dummy(),
+            }],
+            span: Span::dummy(),
+        })
+    }
+
+    #[test]
+    fn test_unify_binds_nested_parameter() {
+        let generics = vec!["T".to_string()];
+        let mut bindings = HashMap::new();
+        let declared = Type::Tuple(vec![named("T"), Type::Slice(Box::new(named("T")))]);
+        let actual = Type::Tuple(vec![Type::Bool, Type::Slice(Box::new(Type::Bool))]);
+
+        assert!(unify(&declared, &actual, &generics, &mut bindings));
+        assert_eq!(bindings["T"], Type::Bool);
+        assert_eq!(substitute(&named("T"), &bindings), Type::Bool);
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let hole = || Type::Infer(Span::dummy());
+        let annotation = Type::Tuple(vec![hole(), Type::Slice(Box::new(hole()))]);
+        let actual = Type::Tuple(vec![Type::Bool, Type::Slice(Box::new(Type::U8))]);
+
+        assert_eq!(placeholder(&annotation), Some(&Span::dummy()));
+        assert_eq!(
+            fill_placeholders(&annotation, &actual),
+            Some(actual.clone())
+        );
+        assert_eq!(placeholder(&actual), None);
+        assert_eq!(
+            fill_placeholders(&Type::Tuple(vec![Type::I32, hole()]), &actual),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unify_rejects_conflicting_bindings() {
+        let generics = vec!["T".to_string()];
+        let mut bindings = HashMap::new();
+        let declared = Type::Tuple(vec![named("T"), named("T")]);
+        let actual = Type::Tuple(vec![Type::I32, Type::Bool]);
+
+        assert!(!unify(&declared, &actual, &generics, &mut bindings));
+    }
+
+    #[test]
+    fn test_free_vars() {
+        let mut interner = Interner::new();
+        let params: HashSet<Symbol> = ["T", "U"].iter().map(|p| interner.intern(p)).collect();
+        interner.intern("Vec");
+        let vars = |source: &str| {
+            let ty = crate::parser::Parser::new(source)
+                .unwrap()
+                .parse_type()
+                .unwrap();
+            let mut names: Vec<&str> = free_vars(&ty, &params, &interner)
+                .into_iter()
+                .map(|symbol| interner.resolve(symbol))
+                .collect();
+            names.sort();
+            names.join(", ")
+        };
+
+        assert_eq!(vars("Vec<T>"), "T");
+        assert_eq!(vars("(T, U)"), "T, U");
+        assert_eq!(vars("i32"), "");
+        assert_eq!(vars("fn(&[T]) -> std::Map<Vec<U>, T>"), "T, U");
+        assert_eq!(vars("Vec<V>"), "");
+        assert_eq!(vars("T::Item"), "");
+    }
+
+    #[test]
+    fn test_substitute_symbols() {
+        let mut interner = Interner::new();
+        let map = HashMap::from([(interner.intern("T"), Type::I32)]);
+        let substituted = |source: &str| {
+            let ty = crate::parser::Parser::new(source)
+                .unwrap()
+                .parse_type()
+                .unwrap();
+            substitute_symbols(&ty, &map, &interner).to_string()
+        };
+
+        assert_eq!(substituted("Vec<T>"), "Vec<i32>");
+        assert_eq!(substituted("fn(T) -> (T, bool)"), "fn(i32) -> (i32, bool)");
+        assert_eq!(substituted("Map<U, &[T]>"), "Map<U, &[i32]>");
+        let path = crate::parser::Parser::new("T::Item")
+            .unwrap()
+            .parse_type()
+            .unwrap();
+        assert_eq!(
+            substitute_symbols(&path, &map, &interner).display_qualified(),
+            "T::Item"
+        );
+    }
+}
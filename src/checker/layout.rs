@@ -0,0 +1,235 @@
+use super::consts::{self, ConstEnv, ConstEvalError, ConstValue};
+use super::integer_bits;
+use crate::ast::{AttributeArg, EnumDecl, Span, Type};
+use std::fmt;
+
+// Integer types an enum tag may use, smallest first within each signedness.
+const UNSIGNED_TAGS: [Type; 5] = [Type::U8, Type::U16, Type::U32, Type::U64, Type::U128];
+const SIGNED_TAGS: [Type; 5] = [Type::I8, Type::I16, Type::I32, Type::I64, Type::I128];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumLayout {
+    // The declared `#[repr(..)]` type, or the smallest type holding every discriminant.
+    pub tag: Type,
+    // One per variant, in declaration order.
+    pub discriminants: Vec<i128>,
+}
+
+impl EnumLayout {
+    pub fn tag_size(&self) -> usize {
+        integer_bits(&self.tag).map_or(0, |bits| bits as usize / 8)
+    }
+
+    // Tag values no variant uses, which an enclosing type could use to encode
+    // its own states (e.g. `None` of an optional enum) without a separate tag.
+    pub fn niche_count(&self) -> u128 {
+        let bits = integer_bits(&self.tag).unwrap_or(0);
+        let mut used = self.discriminants.clone();
+        used.sort_unstable();
+        used.dedup();
+        let capacity = 1u128.checked_shl(bits).map_or(u128::MAX, |n| n);
+        capacity.saturating_sub(used.len() as u128)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutError {
+    Const(ConstEvalError),
+    NonIntegerDiscriminant {
+        variant: String,
+        span: Span,
+    },
+    DiscriminantOutOfRange {
+        variant: String,
+        value: i128,
+        repr: Box<Type>,
+        span: Span,
+    },
+}
+
+impl LayoutError {
+    pub fn span(&self) -> &Span {
+        match self {
+            LayoutError::Const(error) => error.span(),
+            LayoutError::NonIntegerDiscriminant { span, .. }
+            | LayoutError::DiscriminantOutOfRange { span, .. } => span,
+        }
+    }
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Const(error) => error.fmt(f),
+            LayoutError::NonIntegerDiscriminant { variant, .. } => {
+                write!(f, "discriminant of `{}` must be an integer", variant)
+            }
+            LayoutError::DiscriminantOutOfRange {
+                variant,
+                value,
+                repr,
+                ..
+            } => write!(
+                f,
+                "discriminant {} of `{}` does not fit in `#[repr({})]`",
+                value, variant, repr
+            ),
+        }
+    }
+}
+
+impl From<ConstEvalError> for LayoutError {
+    fn from(error: ConstEvalError) -> Self {
+        LayoutError::Const(error)
+    }
+}
+
+// Computes each variant's discriminant and the tag type that stores it.
+pub fn enum_layout(decl: &EnumDecl, env: &ConstEnv) -> Result<EnumLayout, LayoutError> {
+    let discriminants = discriminants(decl, env)?;
+    let Some(repr) = declared_repr(decl) else {
+        return Ok(EnumLayout {
+            tag: minimal_tag_type(&discriminants),
+            discriminants,
+        });
+    };
+    let (min, max) = consts::integer_range(&repr).expect("repr types are integers");
+    for (variant, &value) in decl.variants.iter().zip(&discriminants) {
+        if value < min || value > max {
+            return Err(LayoutError::DiscriminantOutOfRange {
+                variant: format!("{}::{}", decl.name, variant.name),
+                value,
+                repr: Box::new(repr),
+                span: variant.span.clone(),
+            });
+        }
+    }
+    Ok(EnumLayout {
+        tag: repr,
+        discriminants,
+    })
+}
+
+pub fn discriminants(decl: &EnumDecl, env: &ConstEnv) -> Result<Vec<i128>, LayoutError> {
+    let mut values = Vec::with_capacity(decl.variants.len());
+    let mut next = Some(0i128);
+    for variant in &decl.variants {
+        let value = match &variant.discriminant {
+            Some(expr) => match consts::const_eval(expr, env)? {
+                ConstValue::Integer(value) => value,
+                _ => {
+                    return Err(LayoutError::NonIntegerDiscriminant {
+                        variant: format!("{}::{}", decl.name, variant.name),
+                        span: variant.span.clone(),
+                    });
+                }
+            },
+            None => next.ok_or_else(|| ConstEvalError::Overflow {
+                span: variant.span.clone(),
+            })?,
+        };
+        values.push(value);
+        next = value.checked_add(1);
+    }
+    Ok(values)
+}
+
+// The smallest integer type holding every value; unsigned unless a value is negative.
+pub fn minimal_tag_type(values: &[i128]) -> Type {
+    let candidates = if values.iter().any(|&value| value < 0) {
+        &SIGNED_TAGS
+    } else {
+        &UNSIGNED_TAGS
+    };
+    candidates
+        .iter()
+        .find(|ty| {
+            let (min, max) = consts::integer_range(ty).expect("tag types are integers");
+            values.iter().all(|value| (min..=max).contains(value))
+        })
+        .cloned()
+        .unwrap_or(Type::I128)
+}
+
+// The integer type named by `#[repr(..)]`, if any. Other reprs are ignored.
+pub fn declared_repr(decl: &EnumDecl) -> Option<Type> {
+    decl.attributes
+        .iter()
+        .filter(|attribute| attribute.name.name == "repr")
+        .flat_map(|attribute| &attribute.args)
+        .find_map(|arg| match arg {
+            AttributeArg::Identifier(ident) => UNSIGNED_TAGS
+                .iter()
+                .chain(&SIGNED_TAGS)
+                .find(|ty| ty.to_string() == ident.name)
+                .cloned(),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Declaration;
+    use crate::parser::parse;
+
+    fn layout(source: &str) -> Result<EnumLayout, LayoutError> {
+        let program = parse(source).unwrap();
+        let env = ConstEnv::from_program(&program);
+        let Some(Declaration::Enum(decl)) = program.items.last() else {
+            panic!("expected an enum last");
+        };
+        enum_layout(decl, &env)
+    }
+
+    #[test]
+    fn test_repr_u8_fits() {
+        let layout = layout("#[repr(u8)]\nenum Small { A, B = 200, C }").unwrap();
+        assert_eq!(layout.tag, Type::U8);
+        assert_eq!(layout.tag_size(), 1);
+        assert_eq!(layout.discriminants, vec![0, 200, 201]);
+        assert_eq!(layout.niche_count(), 253);
+    }
+
+    #[test]
+    fn test_repr_u8_overflows() {
+        let error =
+            layout("const BASE: i32 = 250;\n#[repr(u8)]\nenum Big { A = BASE, B, C, D, E, F, G }")
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "discriminant 256 of `Big::G` does not fit in `#[repr(u8)]`"
+        );
+        assert_eq!(error.span().line, 3);
+    }
+
+    #[test]
+    fn test_tag_is_sized_without_repr() {
+        assert_eq!(layout("enum E { A, B }").unwrap().tag, Type::U8);
+        assert_eq!(layout("enum E { A = 255, B }").unwrap().tag, Type::U16);
+        let layout = layout("enum E { A = -1, B = 70000 }").unwrap();
+        assert_eq!(layout.tag, Type::I32);
+        assert_eq!(layout.tag_size(), 4);
+    }
+
+    #[test]
+    fn test_repr_128_bit() {
+        let layout = layout("#[repr(i128)]\nenum E { A = -5, B }").unwrap();
+        assert_eq!(layout.tag, Type::I128);
+        assert_eq!(layout.tag_size(), 16);
+        assert_eq!(
+            self::layout("#[repr(u128)]\nenum E { A, B }").unwrap().tag,
+            Type::U128
+        );
+        assert_eq!(minimal_tag_type(&[i128::MIN, i128::MAX]), Type::I128);
+        assert_eq!(minimal_tag_type(&[i128::MAX]), Type::U128);
+    }
+
+    #[test]
+    fn test_discriminant_must_be_integer() {
+        assert_eq!(
+            layout("enum E { A = true }").unwrap_err().to_string(),
+            "discriminant of `E::A` must be an integer"
+        );
+    }
+}
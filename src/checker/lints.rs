@@ -0,0 +1,564 @@
+use super::is_assignment;
+use super::is_comparison;
+use super::resolver::{BindingKind, Resolution, resolve_program};
+use crate::ast::expressions::{BinaryExpr, BinaryOperator, Expression, MemberExpr, Pattern};
+use crate::ast::statements::{ElseBranch, Statement};
+use crate::ast::visit::{Visitor, walk_block, walk_expression, walk_function, walk_statement};
+use crate::ast::{Block, Declaration, FunctionDecl, Identifier, Program, Span, Type};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::printer::Printer;
+use std::collections::{HashMap, HashSet};
+
+pub fn check_unused_variables(program: &Program) -> Vec<Diagnostic> {
+    unused_variables(&resolve_program(program))
+}
+
+// `let`/`var` bindings that are never read. Names starting with `_` are
+// intentionally unused and never reported.
+pub fn unused_variables(resolution: &Resolution) -> Vec<Diagnostic> {
+    resolution
+        .bindings
+        .iter()
+        .filter(|binding| {
+            binding.kind == BindingKind::Let
+                && binding.uses.is_empty()
+                && !binding.name.starts_with('_')
+        })
+        .map(|binding| {
+            Diagnostic::warning(
+                format!(
+                    "unused variable `{}`; if this is intentional, prefix it with an underscore: `_{}`",
+                    binding.name, binding.name
+                ),
+                binding.span.clone(),
+            )
+        })
+        .collect()
+}
+
+// `a < b < c` parses as `(a < b) < c`, comparing a `bool` with `c`, which is
+// almost never what was meant. Explicit grouping such as `(a == b) == c`
+// produces a `Paren` operand and is left alone.
+pub fn check_chained_comparisons(program: &Program) -> Vec<Diagnostic> {
+    let mut lint = ChainedComparisons::default();
+    lint.visit_program(program);
+    lint.diagnostics
+}
+
+#[derive(Default)]
+struct ChainedComparisons {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Visitor for ChainedComparisons {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Binary(outer) = expression
+            && let Expression::Binary(inner) = &outer.left
+            && is_comparison(&outer.operator)
+            && is_comparison(&inner.operator)
+        {
+            self.diagnostics.push(chained_comparison(outer, inner));
+        }
+        walk_expression(self, expression)
+    }
+}
+
+fn chained_comparison(outer: &BinaryExpr, inner: &BinaryExpr) -> Diagnostic {
+    let render = |expr: &Expression| {
+        let mut printer = Printer::new();
+        printer.print_expression(expr);
+        printer.finish()
+    };
+    let (a, b, c) = (
+        render(&inner.left),
+        render(&inner.right),
+        render(&outer.right),
+    );
+    Diagnostic::warning(
+        format!(
+            "comparison operators cannot be chained: `{a} {} {b} {} {c}` compares the `bool` result of `{a} {} {b}` with `{c}`; use `{a} {} {b} && {b} {} {c}`",
+            inner.operator, outer.operator, inner.operator, inner.operator, outer.operator
+        ),
+        outer.span.clone(),
+    )
+}
+
+// `if x = 5 { .. }` assigns where `if x == 5 { .. }` was almost certainly
+// meant. Only the top-level operator of an `if` or `while` condition is
+// looked at; an assignment nested deeper is left to the type checker.
+pub fn check_assignment_in_conditions(program: &Program) -> Vec<Diagnostic> {
+    let mut lint = AssignmentInConditions::default();
+    lint.visit_program(program);
+    lint.diagnostics
+}
+
+#[derive(Default)]
+struct AssignmentInConditions {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl AssignmentInConditions {
+    fn check(&mut self, keyword: &str, condition: &Expression) {
+        let mut condition = condition;
+        while let Expression::Paren(inner, _) = condition {
+            condition = inner;
+        }
+        let Expression::Binary(binary) = condition else {
+            return;
+        };
+        if !is_assignment(&binary.operator) {
+            return;
+        }
+        let mut printer = Printer::new();
+        printer.print_expression(condition);
+        let mut message = format!(
+            "`{}` in `{}` condition is an assignment, not a comparison",
+            printer.finish(),
+            keyword
+        );
+        if binary.operator == BinaryOperator::Assign {
+            message.push_str("; use `==` to compare");
+        }
+        self.diagnostics
+            .push(Diagnostic::warning(message, binary.span.clone()));
+    }
+}
+
+impl Visitor for AssignmentInConditions {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::If(stmt) => {
+                let mut stmt = stmt;
+                self.check("if", &stmt.condition);
+                while let Some(ElseBranch::If(nested)) = &stmt.else_branch {
+                    self.check("if", &nested.condition);
+                    stmt = nested;
+                }
+            }
+            Statement::While(stmt) => self.check("while", &stmt.condition),
+            _ => {}
+        }
+        walk_statement(self, statement)
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::If(if_expr) => self.check("if", &if_expr.condition),
+            Expression::While(while_expr) => self.check("while", &while_expr.condition),
+            _ => {}
+        }
+        walk_expression(self, expression)
+    }
+}
+
+// Reading a union field reinterprets whatever field was last written, so
+// every read is flagged; plain `u.field = value` writes are fine. Only locals
+// and parameters whose union type is evident from an annotation or a union
+// literal are tracked.
+pub fn check_union_field_reads(program: &Program) -> Vec<Diagnostic> {
+    let mut lint = UnionFieldReads::default();
+    collect_unions(&program.items, &mut lint.unions);
+    if !lint.unions.is_empty() {
+        lint.visit_program(program);
+    }
+    lint.diagnostics
+}
+
+fn collect_unions(items: &[Declaration], unions: &mut HashSet<String>) {
+    for item in items {
+        match item {
+            Declaration::Union(decl) => {
+                unions.insert(decl.name.name.clone());
+            }
+            Declaration::Module(module) => {
+                if let Some(items) = &module.items {
+                    collect_unions(items, unions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct UnionFieldReads {
+    unions: HashSet<String>,
+    // Locals of the current function known to hold a union, by union name.
+    locals: HashMap<String, String>,
+    // Member expressions on the left of `=`, keyed by span.
+    writes: HashSet<(usize, usize)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl UnionFieldReads {
+    fn union_of_type(&self, ty: &Type) -> Option<String> {
+        match ty {
+            Type::Reference(inner, _, _) => self.union_of_type(inner),
+            Type::Named(path) => match path.segments.as_slice() {
+                [segment] if self.unions.contains(&segment.ident.name) => {
+                    Some(segment.ident.name.clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn union_of_expression(&self, expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::Identifier(ident) => self.locals.get(&ident.name).cloned(),
+            Expression::Paren(inner, _) => self.union_of_expression(inner),
+            Expression::Struct(literal) if self.unions.contains(&literal.name.name) => {
+                Some(literal.name.name.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn check_read(&mut self, member: &MemberExpr) {
+        let span = &member.span;
+        if self.writes.contains(&(span.start, span.end)) {
+            return;
+        }
+        if let Some(union) = self.union_of_expression(&member.object) {
+            self.diagnostics.push(Diagnostic::warning(
+                format!(
+                    "read of field `{}` of union `{}` may reinterpret the value of another field",
+                    member.member, union
+                ),
+                span.clone(),
+            ));
+        }
+    }
+}
+
+impl Visitor for UnionFieldReads {
+    fn visit_function(&mut self, function: &FunctionDecl) {
+        self.locals = function
+            .params
+            .iter()
+            .filter_map(|param| {
+                let union = self.union_of_type(&param.ty)?;
+                Some((param.name.name.clone(), union))
+            })
+            .collect();
+        walk_function(self, function)
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+        // Bound after its initializer is walked, so `let u = u.a;` reads the
+        // outer `u`.
+        if let Statement::Let(stmt) = statement
+            && let Pattern::Identifier(name) = &stmt.pattern
+        {
+            let union = match &stmt.type_annotation {
+                Some(ty) => self.union_of_type(ty),
+                None => stmt
+                    .initializer
+                    .as_ref()
+                    .and_then(|init| self.union_of_expression(init)),
+            };
+            match union {
+                Some(union) => self.locals.insert(name.name.clone(), union),
+                None => self.locals.remove(&name.name),
+            };
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Binary(binary) if binary.operator == BinaryOperator::Assign => {
+                if let Expression::Member(member) = &binary.left {
+                    self.writes.insert((member.span.start, member.span.end));
+                }
+            }
+            Expression::Member(member) => self.check_read(member),
+            _ => {}
+        }
+        walk_expression(self, expression)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LintLevel {
+    #[default]
+    Off,
+    Note,
+    Warn,
+}
+
+impl LintLevel {
+    fn severity(self) -> Option<Severity> {
+        match self {
+            LintLevel::Off => None,
+            LintLevel::Note => Some(Severity::Note),
+            LintLevel::Warn => Some(Severity::Warning),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShadowingOptions {
+    pub level: LintLevel,
+    // Also report a `let` shadowing a binding of an enclosing block or a
+    // parameter. Off by default, since that is how shadowing is usually used.
+    pub inner_scopes: bool,
+}
+
+// `let x = 1; let x = "s";` is legal, but a rebinding in the same block often
+// hides a mistake, especially when the type changes. Off unless enabled.
+pub fn check_shadowed_lets(program: &Program, options: ShadowingOptions) -> Vec<Diagnostic> {
+    let Some(severity) = options.level.severity() else {
+        return Vec::new();
+    };
+    let mut lint = ShadowedLets {
+        severity,
+        inner_scopes: options.inner_scopes,
+        scopes: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    lint.visit_program(program);
+    lint.diagnostics
+}
+
+struct ShadowedLets {
+    severity: Severity,
+    inner_scopes: bool,
+    // Innermost last; each maps a name to where it was bound.
+    scopes: Vec<HashMap<String, Span>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ShadowedLets {
+    fn bind(&mut self, name: &Identifier) {
+        let Some((scope, outer)) = self.scopes.split_last_mut() else {
+            return;
+        };
+        let previous = match scope.get(&name.name) {
+            Some(span) => Some((span, "in the same block")),
+            None if self.inner_scopes => outer
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(&name.name))
+                .map(|span| (span, "from an enclosing scope")),
+            None => None,
+        };
+        if let Some((span, place)) = previous {
+            self.diagnostics.push(
+                Diagnostic::new(
+                    self.severity,
+                    format!("`{}` shadows an earlier binding {}", name, place),
+                    name.span.clone(),
+                )
+                .with_label(format!("`{}` was first bound here", name), span.clone()),
+            );
+        }
+        scope.insert(name.name.clone(), name.span.clone());
+    }
+}
+
+impl Visitor for ShadowedLets {
+    fn visit_function(&mut self, function: &FunctionDecl) {
+        let params = function
+            .params
+            .iter()
+            .map(|param| (param.name.name.clone(), param.name.span.clone()))
+            .collect();
+        self.scopes.push(params);
+        walk_function(self, function);
+        self.scopes.pop();
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        walk_block(self, block);
+        self.scopes.pop();
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+        if let Statement::Let(stmt) = statement
+            && let Ok(names) = stmt.pattern.bindings()
+        {
+            for name in names {
+                self.bind(name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::parser::parse;
+
+    fn lint(source: &str) -> Vec<Diagnostic> {
+        check_unused_variables(&parse(source).unwrap())
+    }
+
+    #[test]
+    fn test_unused_let() {
+        let diagnostics = lint("fn main() {\n    let count = 1;\n}");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].message,
+            "unused variable `count`; if this is intentional, prefix it with an underscore: `_count`"
+        );
+        assert_eq!(diagnostics[0].span.line, 2);
+        assert_eq!(diagnostics[0].span.column, 9);
+    }
+
+    #[test]
+    fn test_used_let() {
+        assert!(lint("fn main() -> i32 { let count = 1; count }").is_empty());
+    }
+
+    #[test]
+    fn test_underscore_prefix_is_ignored() {
+        assert!(lint("fn main() { let _tmp = 1; var _ = 2; }").is_empty());
+    }
+
+    #[test]
+    fn test_chained_comparison_is_flagged() {
+        let program = parse("fn f(a: i32, b: i32, c: i32) -> bool {\n    a < b < c\n}").unwrap();
+        let diagnostics = check_chained_comparisons(&program);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].message,
+            "comparison operators cannot be chained: `a < b < c` compares the `bool` result of `a < b` with `c`; use `a < b && b < c`"
+        );
+        assert_eq!(diagnostics[0].span.line, 2);
+        assert_eq!(diagnostics[0].span.column, 5);
+
+        let program = parse("fn f() { if x + 1 <= y == z { g(p > q > r); } }").unwrap();
+        assert_eq!(check_chained_comparisons(&program).len(), 2);
+    }
+
+    #[test]
+    fn test_unchained_comparisons_are_not_flagged() {
+        for source in [
+            "fn f() -> bool { a < b && b < c }",
+            "fn f() -> bool { (a == b) == c }",
+            "fn f() -> bool { a < (b < c) }",
+        ] {
+            let program = parse(source).unwrap();
+            assert!(check_chained_comparisons(&program).is_empty(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_assignment_in_condition_is_flagged() {
+        let program = parse("fn f() {\n    if x = 5 { }\n    while a = b { }\n}").unwrap();
+        let diagnostics = check_assignment_in_conditions(&program);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].message,
+            "`x = 5` in `if` condition is an assignment, not a comparison; use `==` to compare"
+        );
+        assert_eq!(diagnostics[0].span.line, 2);
+        assert_eq!(
+            diagnostics[1].message,
+            "`a = b` in `while` condition is an assignment, not a comparison; use `==` to compare"
+        );
+
+        let program = parse("fn f() { if c { } else if (n += 1) { } }").unwrap();
+        let diagnostics = check_assignment_in_conditions(&program);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`n += 1` in `if` condition is an assignment, not a comparison"
+        );
+    }
+
+    #[test]
+    fn test_comparison_in_condition_is_not_flagged() {
+        let program = parse("fn f() { if x == 5 { } while a != b { x = 1; } }").unwrap();
+        assert!(check_assignment_in_conditions(&program).is_empty());
+    }
+
+    #[test]
+    fn test_union_field_read_is_flagged() {
+        let source = "union Bits { i: i32, f: f32 }\n\
+                      fn f(b: Bits) -> i32 {\n    let c = Bits { f: 1.0 };\n    b.i + c.i\n}";
+        let diagnostics = check_union_field_reads(&parse(source).unwrap());
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].message,
+            "read of field `i` of union `Bits` may reinterpret the value of another field"
+        );
+        assert_eq!(diagnostics[0].span.slice(source), "b.i");
+        assert_eq!(diagnostics[1].span.slice(source), "c.i");
+    }
+
+    #[test]
+    fn test_union_field_write_is_not_flagged() {
+        let source = "union Bits { i: i32, f: f32 }\n\
+                      struct Pair { i: i32 }\n\
+                      fn f(p: Pair) -> i32 {\n    var b: Bits = Bits { i: 0 };\n    b.f = 2.0;\n    p.i\n}";
+        assert!(check_union_field_reads(&parse(source).unwrap()).is_empty());
+    }
+
+    fn shadowing(source: &str, options: ShadowingOptions) -> Vec<Diagnostic> {
+        check_shadowed_lets(&parse(source).unwrap(), options)
+    }
+
+    #[test]
+    fn test_same_block_shadow_is_reported() {
+        let source = "fn f() -> &str {\n    let x = 1;\n    let x = \"s\";\n    x\n}";
+        let warn = ShadowingOptions {
+            level: LintLevel::Warn,
+            ..ShadowingOptions::default()
+        };
+        let diagnostics = shadowing(source, warn);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].message,
+            "`x` shadows an earlier binding in the same block"
+        );
+        assert_eq!(diagnostics[0].span.line, 3);
+        assert_eq!(diagnostics[0].labels[0].span.line, 2);
+        assert_eq!(diagnostics[0].labels[0].message, "`x` was first bound here");
+
+        let note = ShadowingOptions {
+            level: LintLevel::Note,
+            ..ShadowingOptions::default()
+        };
+        assert_eq!(shadowing(source, note)[0].severity, Severity::Note);
+        assert!(shadowing(source, ShadowingOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_inner_block_shadow_is_not_reported_by_default() {
+        let source = "fn f(y: i32) -> i32 {\n    let x = 1;\n    {\n        let x = 2;\n        let y = x;\n    }\n    x\n}";
+        let mut options = ShadowingOptions {
+            level: LintLevel::Warn,
+            ..ShadowingOptions::default()
+        };
+        assert!(shadowing(source, options).is_empty());
+
+        options.inner_scopes = true;
+        let messages: Vec<String> = shadowing(source, options)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "`x` shadows an earlier binding from an enclosing scope",
+                "`y` shadows an earlier binding from an enclosing scope",
+            ]
+        );
+    }
+}
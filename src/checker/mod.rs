@@ -0,0 +1,3199 @@
+pub mod attributes;
+pub mod builtins;
+pub mod consts;
+pub mod duplicates;
+pub mod inference;
+pub mod layout;
+pub mod lints;
+pub mod operators;
+pub mod purity;
+pub mod ranges;
+pub mod resolver;
+pub mod returns;
+pub mod scope;
+pub mod target;
+pub mod widening;
+
+use crate::ast::expressions::{
+    BinaryExpr, BinaryOperator, CallExpr, Expression, IntBase, Literal, MatchArm, MemberExpr,
+    Pattern, StructExpr, UnaryExpr, UnaryOperator,
+};
+use crate::ast::statements::{BreakStatement, ElseBranch, IfStatement, Statement};
+use crate::ast::types::{FunctionAbi, Mutability, TypePath, TypePathSegment};
+use crate::ast::{
+    Block, Declaration, FunctionDecl, Identifier, Parameter, Program, Span, StructDecl, Type,
+};
+use crate::diagnostics::Diagnostic;
+use operators::{OperatorTable, ResolvedOperator};
+use std::collections::{HashMap, HashSet};
+use target::TargetConfig;
+use widening::ImplicitCast;
+
+// Expression checking returns `None` when a type cannot be determined, either
+// because an error has already been reported or because the construct is not
+// understood yet. Callers treat `None` as "anything goes" to avoid cascades.
+#[derive(Debug, Default)]
+pub struct Checker {
+    diagnostics: Vec<Diagnostic>,
+    globals: HashMap<String, Type>,
+    scopes: Vec<HashMap<String, Type>>,
+    mutable_statics: HashSet<String>,
+    signatures: HashMap<String, Signature>,
+    structs: HashMap<String, StructDecl>,
+    operators: OperatorTable,
+    resolved_operators: Vec<ResolvedOperator>,
+    target: TargetConfig,
+    // Types found for expressions and bindings, keyed by their span's range.
+    types: HashMap<(usize, usize), Type>,
+    // Span of the innermost node being checked, for nodes without one (literals).
+    enclosing_span: Option<Span>,
+    // Loops enclosing the node being checked, innermost last.
+    loops: Vec<LoopContext>,
+    // Declared return type of the function being checked.
+    return_type: Option<Type>,
+    // Accept lossless numeric widening without `as`; see `widening`.
+    implicit_widening: bool,
+    implicit_casts: Vec<ImplicitCast>,
+    // Top-level constants, for evaluating array lengths in types.
+    constants: consts::ConstEnv,
+}
+
+#[derive(Debug)]
+struct LoopContext {
+    label: Option<String>,
+    // Only `loop` can produce a value; `while` and `for` always give `()`.
+    accepts_value: bool,
+    broken: bool,
+    // Type of the first `break` out of this loop, `()` for a bare `break`.
+    break_type: Option<Type>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_operators(operators: OperatorTable) -> Self {
+        Self {
+            operators,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_target(target: TargetConfig) -> Self {
+        Self {
+            target,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_implicit_widening(implicit_widening: bool) -> Self {
+        Self {
+            implicit_widening,
+            ..Self::default()
+        }
+    }
+
+    // Conversions accepted under implicit widening, to be made explicit with
+    // `widening::insert_implicit_casts`.
+    pub fn implicit_casts(&self) -> &[ImplicitCast] {
+        &self.implicit_casts
+    }
+
+    pub fn resolved_operators(&self) -> &[ResolvedOperator] {
+        &self.resolved_operators
+    }
+
+    // The type found for the expression or binding written at `span`.
+    pub fn type_at(&self, span: &Span) -> Option<&Type> {
+        self.types.get(&(span.start, span.end))
+    }
+
+    // The type found for `expr`; literals have no span to record it under.
+    pub fn type_of(&self, expr: &Expression) -> Option<&Type> {
+        consts::expression_span(expr).and_then(|span| self.type_at(span))
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    pub fn check_program(&mut self, program: &Program) {
+        if let Some(main) = program.main_function() {
+            self.diagnostics.extend(check_main_signature(main));
+        }
+        self.diagnostics
+            .extend(duplicates::check_duplicate_names(&program.items));
+        self.collect_globals(&program.items);
+        self.check_constants(program);
+        self.check_enum_layouts(program);
+        for item in &program.items {
+            self.check_declaration(item);
+        }
+    }
+
+    // Evaluates every top-level `const`. Errors found while evaluating a
+    // dependency are reported once, not again for each constant using it, and
+    // not at all where the purity check already rejects the initializer.
+    fn check_constants(&mut self, program: &Program) {
+        let env = consts::ConstEnv::from_program(program);
+        self.constants = env.clone();
+        let impure: Vec<Span> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Declaration::Constant(constant) => Some(constant),
+                _ => None,
+            })
+            .flat_map(|constant| {
+                purity::check_initializer(&constant.value, "const", &self.mutable_statics)
+            })
+            .map(|diagnostic| diagnostic.span)
+            .collect();
+        let mut reported: Vec<consts::ConstEvalError> = Vec::new();
+        let mut in_cycle = HashSet::new();
+        for item in &program.items {
+            let Declaration::Constant(constant) = item else {
+                continue;
+            };
+            if in_cycle.contains(&constant.name.name) {
+                continue;
+            }
+            let Err(error) = consts::eval_constant(constant, &env) else {
+                continue;
+            };
+            if let consts::ConstEvalError::Cycle { names, .. } = &error {
+                in_cycle.extend(names.iter().cloned());
+            }
+            // A bare literal that does not fit is reported by `expect_type`.
+            if matches!(error, consts::ConstEvalError::OutOfRange { .. })
+                && integer_literal_value(&constant.value).is_some()
+            {
+                continue;
+            }
+            if !reported.contains(&error) && !impure.contains(error.span()) {
+                self.error(error.to_string(), error.span());
+                reported.push(error);
+            }
+        }
+    }
+
+    fn check_enum_layouts(&mut self, program: &Program) {
+        let env = consts::ConstEnv::from_program(program);
+        for item in &program.items {
+            if let Declaration::Enum(decl) = item
+                && let Err(error) = layout::enum_layout(decl, &env)
+            {
+                self.error(error.to_string(), error.span());
+            }
+        }
+    }
+
+    fn collect_globals(&mut self, items: &[Declaration]) {
+        for item in items {
+            match item {
+                Declaration::Function(function) => {
+                    self.globals
+                        .insert(function.name.name.clone(), function_type(function));
+                    self.signatures
+                        .insert(function.name.name.clone(), Signature::of(function));
+                }
+                Declaration::Constant(constant) => {
+                    self.globals
+                        .insert(constant.name.name.clone(), constant.ty.clone());
+                }
+                Declaration::Variable(var) => {
+                    if let Some(ty) = &var.ty {
+                        self.globals.insert(var.name.name.clone(), ty.clone());
+                    }
+                }
+                Declaration::Static(decl) => {
+                    self.globals.insert(decl.name.name.clone(), decl.ty.clone());
+                    if decl.mutable {
+                        self.mutable_statics.insert(decl.name.name.clone());
+                    }
+                }
+                Declaration::Struct(decl) => {
+                    self.structs.insert(decl.name.name.clone(), decl.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn check_declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::Function(function) => self.check_function(function),
+            Declaration::Variable(var) => {
+                if let Some(initializer) = &var.initializer {
+                    let found = self.check_expression(initializer);
+                    match &var.ty {
+                        Some(expected) => {
+                            if let Some(expected) =
+                                self.solve_placeholders(expected, found.as_ref())
+                            {
+                                self.expect_type(&expected, found.as_ref(), initializer, &var.span);
+                                self.globals.insert(var.name.name.clone(), expected);
+                            }
+                        }
+                        None => {
+                            if let Some(found) = found {
+                                self.globals.insert(var.name.name.clone(), found);
+                            }
+                        }
+                    }
+                }
+            }
+            Declaration::Constant(constant) => {
+                self.diagnostics.extend(purity::check_initializer(
+                    &constant.value,
+                    "const",
+                    &self.mutable_statics,
+                ));
+                let found = self.check_expression(&constant.value);
+                if let Some(expected) = self.solve_placeholders(&constant.ty, found.as_ref()) {
+                    self.expect_type(&expected, found.as_ref(), &constant.value, &constant.span);
+                    self.globals.insert(constant.name.name.clone(), expected);
+                }
+            }
+            Declaration::Static(decl) => {
+                self.diagnostics.extend(purity::check_initializer(
+                    &decl.value,
+                    "static",
+                    &self.mutable_statics,
+                ));
+                let found = self.check_expression(&decl.value);
+                if let Some(expected) = self.solve_placeholders(&decl.ty, found.as_ref()) {
+                    self.expect_type(&expected, found.as_ref(), &decl.value, &decl.span);
+                    self.globals.insert(decl.name.name.clone(), expected);
+                }
+            }
+            Declaration::Module(module) => {
+                let Some(items) = &module.items else {
+                    return;
+                };
+                let outer = std::mem::take(&mut self.globals);
+                self.collect_globals(items);
+                for item in items {
+                    self.check_declaration(item);
+                }
+                self.globals = outer;
+            }
+            Declaration::Struct(decl) => {
+                for field in &decl.fields {
+                    self.solve_placeholders(&field.ty, None);
+                    if let Some(default) = &field.default {
+                        let found = self.check_expression(default);
+                        self.expect_type(&field.ty, found.as_ref(), default, &field.span);
+                    }
+                }
+            }
+            Declaration::Enum(decl) => {
+                for data in decl.variants.iter().filter_map(|v| v.data.as_ref()) {
+                    self.solve_placeholders(data, None);
+                }
+            }
+            Declaration::Union(decl) => {
+                for field in &decl.fields {
+                    self.solve_placeholders(&field.ty, None);
+                }
+            }
+            Declaration::Macro(_) => {}
+        }
+    }
+
+    // Resolves the `_` placeholders in an annotation from the type of the
+    // value it annotates. Without such a value (as for parameters and fields)
+    // a placeholder cannot be resolved and is an error, and the type is
+    // unknown from then on so that nothing else is reported against it.
+    fn solve_placeholders(&mut self, annotation: &Type, found: Option<&Type>) -> Option<Type> {
+        let Some(span) = inference::placeholder(annotation) else {
+            return Some(annotation.clone());
+        };
+        match found {
+            Some(found) => Some(
+                inference::fill_placeholders(annotation, found)
+                    .unwrap_or_else(|| annotation.clone()),
+            ),
+            None => {
+                let span = span.clone();
+                self.error("cannot infer the type of `_`", &span);
+                None
+            }
+        }
+    }
+
+    pub fn check_function(&mut self, function: &FunctionDecl) {
+        // A parameter whose type cannot be inferred is left unbound, and so
+        // has an unknown type in the body.
+        let mut params = HashMap::new();
+        for param in &function.params {
+            if let Some(ty) = self.solve_placeholders(&param.ty, None) {
+                let span = &param.name.span;
+                self.types.insert((span.start, span.end), ty.clone());
+                params.insert(param.name.name.clone(), ty);
+            }
+        }
+        if let Some(return_type) = &function.return_type {
+            self.solve_placeholders(return_type, None);
+        }
+        self.check_parameter_defaults(&function.params);
+        self.scopes.push(params);
+        let declared = function
+            .return_type
+            .as_deref()
+            .cloned()
+            .unwrap_or(Type::Unit);
+        let outer = self.return_type.replace(declared.clone());
+        let found = self.check_block(&function.body);
+        self.return_type = outer;
+        self.scopes.pop();
+        self.check_function_result(function, &declared, found);
+    }
+
+    // A function returning a value must produce it on every path: through
+    // its tail expression, or by returning or diverging before the end.
+    fn check_function_result(
+        &mut self,
+        function: &FunctionDecl,
+        declared: &Type,
+        found: Option<Type>,
+    ) {
+        if matches!(declared, Type::Unit | Type::Never)
+            || found == Some(Type::Never)
+            || inference::placeholder(declared).is_some()
+        {
+            return;
+        }
+        match &function.body.tail {
+            Some(tail) => {
+                let span = consts::expression_span(tail)
+                    .unwrap_or(&function.body.span)
+                    .clone();
+                self.expect_type(declared, found.as_ref(), tail, &span);
+            }
+            None => self.error(
+                format!(
+                    "function `{}` must return a value of type `{}` on every path, but can reach the end of its body",
+                    function.name,
+                    declared.display_qualified()
+                ),
+                &function.body.span,
+            ),
+        }
+    }
+
+    fn check_parameter_defaults(&mut self, params: &[Parameter]) {
+        for (i, param) in params.iter().enumerate() {
+            let Some(default) = &param.default else {
+                continue;
+            };
+            if let Some(next) = params[i + 1..].iter().find(|p| p.default.is_none()) {
+                self.error(
+                    format!(
+                        "parameter `{}` has a default but `{}` after it does not; only trailing parameters may have defaults",
+                        param.name, next.name
+                    ),
+                    &param.span,
+                );
+            }
+            let found = self.check_expression(default);
+            self.expect_type(&param.ty, found.as_ref(), default, &param.span);
+        }
+    }
+
+    pub fn check_block(&mut self, block: &Block) -> Option<Type> {
+        self.scopes.push(HashMap::new());
+        for statement in &block.statements {
+            self.check_statement(statement);
+        }
+        let ty = match &block.tail {
+            Some(tail) => self.check_expression(tail),
+            None => Some(Type::Unit),
+        };
+        self.scopes.pop();
+        // Past a `break` or `continue` the rest of the block is unreachable
+        // too, even though the function itself carries on.
+        if block.statements.iter().any(|statement| {
+            returns::statement_diverges(statement)
+                || matches!(statement, Statement::Break(_) | Statement::Continue(_))
+        }) {
+            return Some(Type::Never);
+        }
+        ty
+    }
+
+    pub fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Empty | Statement::Continue(_) => {}
+            Statement::Expression(expr) => {
+                self.check_expression(expr);
+            }
+            Statement::Let(let_stmt) => {
+                let outer = self.enclosing_span.replace(let_stmt.span.clone());
+                let found = let_stmt
+                    .initializer
+                    .as_ref()
+                    .and_then(|initializer| self.check_expression(initializer));
+                let ty = match (&let_stmt.type_annotation, &let_stmt.initializer) {
+                    (Some(expected), Some(initializer)) => {
+                        let expected = self.solve_placeholders(expected, found.as_ref());
+                        if let Some(expected) = &expected {
+                            self.expect_type(expected, found.as_ref(), initializer, &let_stmt.span);
+                        }
+                        expected
+                    }
+                    (Some(expected), None) => self.solve_placeholders(expected, None),
+                    (None, Some(Expression::Literal(Literal::Array(elements))))
+                        if elements.is_empty() =>
+                    {
+                        self.error(
+                            "cannot infer the element type of an empty array; add a type annotation",
+                            &let_stmt.span,
+                        );
+                        None
+                    }
+                    (None, _) => found,
+                };
+                self.bind_pattern(&let_stmt.pattern, ty);
+                self.enclosing_span = outer;
+            }
+            Statement::Return(ret) => {
+                // Outside a function, such as in a `const` initializer, there
+                // is nothing to return from.
+                let Some(declared) = self.return_type.clone() else {
+                    self.error("`return` outside of a function body", &ret.span);
+                    if let Some(expr) = &ret.expression {
+                        self.check_expression(expr);
+                    }
+                    return;
+                };
+                match &ret.expression {
+                    Some(expr) => {
+                        let found = self.check_expression(expr);
+                        self.expect_type(&declared, found.as_ref(), expr, &ret.span);
+                    }
+                    None if !matches!(declared, Type::Unit | Type::Never)
+                        && inference::placeholder(&declared).is_none() =>
+                    {
+                        self.error(
+                            format!(
+                                "`return` without a value in a function returning `{}`",
+                                declared.display_qualified()
+                            ),
+                            &ret.span,
+                        )
+                    }
+                    None => {}
+                }
+            }
+            Statement::Break(brk) => self.check_break(brk),
+            Statement::While(while_stmt) => {
+                self.check_condition(&while_stmt.condition, &while_stmt.span);
+                self.loops.push(LoopContext::new(&while_stmt.label, false));
+                let body = self.check_block(&while_stmt.body);
+                self.loops.pop();
+                let tail = while_stmt.body.tail.as_deref();
+                self.check_loop_body("while", body, tail, &while_stmt.span);
+            }
+            Statement::For(for_stmt) => {
+                let element = self.check_iterator(&for_stmt.iterator, &for_stmt.span);
+                self.scopes.push(HashMap::new());
+                self.bind_pattern(&for_stmt.pattern, element);
+                self.loops.push(LoopContext::new(&for_stmt.label, false));
+                let body = self.check_block(&for_stmt.body);
+                self.loops.pop();
+                self.scopes.pop();
+                let tail = for_stmt.body.tail.as_deref();
+                self.check_loop_body("for", body, tail, &for_stmt.span);
+            }
+            Statement::Loop(loop_stmt) => {
+                self.loops.push(LoopContext::new(&loop_stmt.label, true));
+                self.check_block(&loop_stmt.body);
+                self.loops.pop();
+            }
+            Statement::Block(block) => {
+                self.check_block(block);
+            }
+            Statement::If(if_stmt) => self.check_if_statement(if_stmt),
+            Statement::Match(match_stmt) => {
+                let scrutinee = self.check_expression(&match_stmt.expression);
+                self.check_match_coverage(scrutinee.clone(), &match_stmt.arms, &match_stmt.span);
+                for arm in &match_stmt.arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_pattern(&arm.pattern, scrutinee.clone());
+                    if let Some(guard) = &arm.guard {
+                        self.check_condition(guard, &arm.span);
+                    }
+                    self.check_expression(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            Statement::Panic(panic_stmt) => {
+                self.check_panic(&panic_stmt.message);
+            }
+        }
+    }
+
+    fn check_if_statement(&mut self, if_stmt: &IfStatement) {
+        self.check_condition(&if_stmt.condition, &if_stmt.span);
+        self.check_block(&if_stmt.then_branch);
+        match &if_stmt.else_branch {
+            Some(ElseBranch::Block(block)) => {
+                self.check_block(block);
+            }
+            Some(ElseBranch::If(nested)) => self.check_if_statement(nested),
+            None => {}
+        }
+    }
+
+    // The condition of an `if` or `while`, or a match guard, which must be a
+    // `bool`. Reported at `fallback` when the condition has no span of its own.
+    fn check_condition(&mut self, condition: &Expression, fallback: &Span) {
+        let Some(found) = self.check_expression(condition) else {
+            return;
+        };
+        if !matches!(found.normalized(), Type::Bool | Type::Never) {
+            let span = consts::expression_span(condition)
+                .unwrap_or(fallback)
+                .clone();
+            self.error(
+                format!("expected `bool`, found `{}`", found.display_qualified()),
+                &span,
+            );
+        }
+    }
+
+    pub fn check_expression(&mut self, expr: &Expression) -> Option<Type> {
+        let Some(span) = consts::expression_span(expr) else {
+            return self.check_expression_kind(expr);
+        };
+        let outer = self.enclosing_span.replace(span.clone());
+        let ty = self.check_expression_kind(expr);
+        self.enclosing_span = outer;
+        if let Some(ty) = &ty {
+            self.types.insert((span.start, span.end), ty.clone());
+        }
+        ty
+    }
+
+    fn check_expression_kind(&mut self, expr: &Expression) -> Option<Type> {
+        match expr {
+            Expression::Literal(literal) => {
+                let span = self.enclosing_span.clone().unwrap_or_else(Span::dummy);
+                self.check_integer_literal(literal, false, &span);
+                self.check_literal(literal)
+            }
+            Expression::Paren(inner, _) => self.check_expression(inner),
+            Expression::Struct(literal) => self.check_struct_expression(literal),
+            Expression::Identifier(ident) => {
+                let is_local = self
+                    .scopes
+                    .iter()
+                    .any(|scope| scope.contains_key(&ident.name));
+                if !is_local && self.mutable_statics.contains(&ident.name) {
+                    self.diagnostics.push(Diagnostic::note(
+                        format!(
+                            "`{}` is a `static mut`; accesses are unsynchronized and need care",
+                            ident.name
+                        ),
+                        ident.span.clone(),
+                    ));
+                }
+                self.lookup(&ident.name).cloned()
+            }
+            // Paths through modules or types are not typed yet; a generic
+            // function with explicit arguments is instantiated with them.
+            Expression::Path(path) => {
+                let [segment] = path.segments.as_slice() else {
+                    return None;
+                };
+                let signature = self.call_signature(&segment.ident)?;
+                let args = segment.generic_args.as_ref()?;
+                if args.len() != signature.generics.len() {
+                    return None;
+                }
+                let bindings = signature.generics.iter().cloned().zip(args.iter().cloned());
+                let ty = self.lookup(&segment.ident.name)?;
+                Some(inference::substitute(ty, &bindings.collect()))
+            }
+            Expression::Binary(binary) => self.check_binary(binary),
+            Expression::Unary(unary) => self.check_unary(unary),
+            Expression::Call(call) => {
+                let callee = self.check_expression(&call.callee);
+                let signature = match &call.callee {
+                    Expression::Identifier(ident) => self.call_signature(ident),
+                    Expression::Path(path) => match path.segments.as_slice() {
+                        [segment] => self.call_signature(&segment.ident),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some(signature) = signature {
+                    return self.check_call(call, &signature);
+                }
+                for argument in &call.arguments {
+                    self.check_expression(argument);
+                }
+                match callee {
+                    Some(Type::Function(_, return_type, _)) => Some(*return_type),
+                    _ => None,
+                }
+            }
+            Expression::Member(member) => {
+                self.check_expression(&member.object);
+                None
+            }
+            Expression::Index(index) => {
+                let array = self.check_expression(&index.array);
+                self.check_expression(&index.index);
+                match array {
+                    Some(Type::Array(element, _)) | Some(Type::Slice(element)) => Some(*element),
+                    _ => None,
+                }
+            }
+            Expression::Cast(cast) => {
+                self.check_expression(&cast.expr);
+                Some(cast.target_type.clone())
+            }
+            Expression::Block(block) => self.check_block(block),
+            Expression::If(if_expr) => {
+                self.check_condition(&if_expr.condition, &if_expr.span);
+                let then_ty = self.check_expression(&if_expr.then_branch);
+                match &if_expr.else_branch {
+                    // A branch that never completes takes the other's type.
+                    Some(else_branch) => {
+                        let else_ty = self.check_expression(else_branch);
+                        match then_ty {
+                            Some(Type::Never) => else_ty.or(then_ty),
+                            _ => then_ty.or(else_ty),
+                        }
+                    }
+                    None => Some(Type::Unit),
+                }
+            }
+            Expression::Match(match_expr) => {
+                let scrutinee = self.check_expression(&match_expr.value);
+                self.check_match_coverage(scrutinee.clone(), &match_expr.arms, &match_expr.span);
+                let mut ty = None;
+                for arm in &match_expr.arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_pattern(&arm.pattern, scrutinee.clone());
+                    if let Some(guard) = &arm.guard {
+                        self.check_condition(guard, &arm.span);
+                    }
+                    let arm_ty = self.check_expression(&arm.body);
+                    if matches!(ty, None | Some(Type::Never)) && arm_ty.is_some() {
+                        ty = arm_ty;
+                    }
+                    self.scopes.pop();
+                }
+                ty
+            }
+            // Without any `break` the loop never completes; its type is left
+            // unknown rather than `!`.
+            Expression::Loop(loop_expr) => {
+                self.loops.push(LoopContext::new(&loop_expr.label, true));
+                self.check_expression(&loop_expr.body);
+                let context = self.loops.pop()?;
+                context.break_type.filter(|_| context.broken)
+            }
+            Expression::While(while_expr) => {
+                self.check_condition(&while_expr.condition, &while_expr.span);
+                self.loops.push(LoopContext::new(&while_expr.label, false));
+                let body = self.check_expression(&while_expr.body);
+                self.loops.pop();
+                let tail = body_tail(&while_expr.body);
+                self.check_loop_body("while", body, tail, &while_expr.span);
+                Some(Type::Unit)
+            }
+            Expression::For(for_expr) => {
+                let element = self.check_iterator(&for_expr.iterator, &for_expr.span);
+                self.scopes.push(HashMap::new());
+                self.bind_pattern(&for_expr.pattern, element);
+                self.loops.push(LoopContext::new(&for_expr.label, false));
+                let body = self.check_expression(&for_expr.body);
+                self.loops.pop();
+                self.scopes.pop();
+                let tail = body_tail(&for_expr.body);
+                self.check_loop_body("for", body, tail, &for_expr.span);
+                Some(Type::Unit)
+            }
+            Expression::Range(range) => {
+                if let Some(start) = &range.start {
+                    self.check_expression(start);
+                }
+                if let Some(end) = &range.end {
+                    self.check_expression(end);
+                }
+                None
+            }
+            Expression::Panic(panic) => self.check_panic(&panic.message),
+            Expression::MacroInvocation(invocation) => {
+                for argument in &invocation.arguments {
+                    self.check_expression(argument);
+                }
+                if builtins::is_builtin_macro(&invocation.name.name) {
+                    self.diagnostics
+                        .extend(builtins::check_builtin_macro(invocation));
+                    return Some(Type::Unit);
+                }
+                None
+            }
+        }
+    }
+
+    // `panic` never returns, whether written as a statement or an expression.
+    fn check_panic(&mut self, message: &Expression) -> Option<Type> {
+        self.check_expression(message);
+        Some(Type::Never)
+    }
+
+    // Reports a suffixed integer literal whose value its type cannot hold.
+    fn check_integer_literal(&mut self, literal: &Literal, negated: bool, span: &Span) {
+        let Literal::Integer(value, Some(ty), _) = literal else {
+            return;
+        };
+        let Some((min, max)) = self.target.integer_range(ty) else {
+            return;
+        };
+        let value = if negated {
+            value.checked_neg()
+        } else {
+            Some(*value)
+        };
+        if value.is_some_and(|value| (min..=max).contains(&value)) {
+            return;
+        }
+        let sign = if negated { "-" } else { "" };
+        let message = if matches!(ty, Type::Isize | Type::Usize) {
+            format!(
+                "literal `{}{}` does not fit in `{}` on a {}-bit target",
+                sign, literal, ty, self.target.ptr_width
+            )
+        } else {
+            format!("literal `{}{}` does not fit in `{}`", sign, literal, ty)
+        };
+        self.error(message, span);
+    }
+
+    fn check_literal(&mut self, literal: &Literal) -> Option<Type> {
+        match literal {
+            Literal::Integer(_, ty, _) => Some(ty.clone().unwrap_or(Type::I32)),
+            Literal::Float(_, ty) => Some(ty.clone().unwrap_or(Type::F64)),
+            Literal::String(_) => Some(Type::Reference(
+                Box::new(Type::Str),
+                Mutability::Immutable,
+                None,
+            )),
+            Literal::Character(_) => Some(Type::Char),
+            Literal::Boolean(_) => Some(Type::Bool),
+            Literal::Unit => Some(Type::Unit),
+            Literal::Array(elements) => {
+                let types: Vec<Option<Type>> = elements
+                    .iter()
+                    .map(|element| self.check_expression(element))
+                    .collect();
+                let element_ty = self.unify_array_elements(elements, &types)?;
+                let length = Expression::Literal(Literal::Integer(
+                    elements.len() as i128,
+                    None,
+                    IntBase::Dec,
+                ));
+                Some(Type::Array(Box::new(element_ty), Some(Box::new(length))))
+            }
+            Literal::Tuple(elements) => {
+                let types: Vec<Option<Type>> = elements
+                    .iter()
+                    .map(|element| self.check_expression(element))
+                    .collect();
+                types
+                    .into_iter()
+                    .collect::<Option<Vec<Type>>>()
+                    .map(|types| Type::Tuple(types).normalized())
+            }
+        }
+    }
+
+    // The common type of an array literal's elements: the first element's,
+    // unless that is an unsuffixed literal, or an array of them, and a later
+    // element has another type the literal fits, as in `[1, 2u8]`. Only the
+    // first mismatch is reported.
+    fn unify_array_elements(
+        &mut self,
+        elements: &[Expression],
+        types: &[Option<Type>],
+    ) -> Option<Type> {
+        let element_ty = match elements.iter().zip(types).find_map(definite_element) {
+            Some((first, ty)) if is_literal_only(first) => elements
+                .iter()
+                .zip(types)
+                .filter_map(definite_element)
+                .find(|(element, other)| {
+                    !is_literal_only(element) && self.literal_adopts(first, other)
+                })
+                .map_or(ty, |(_, other)| other),
+            Some((_, ty)) => ty,
+            None => types.iter().flatten().next()?,
+        }
+        .clone();
+        for (element, ty) in elements.iter().zip(types) {
+            let Some(ty) = ty else {
+                continue;
+            };
+            let fits = *ty == Type::Never
+                || ty.normalized().without_lifetimes()
+                    == element_ty.normalized().without_lifetimes()
+                || self.literal_adopts(element, &element_ty);
+            if !fits {
+                let span = consts::expression_span(element)
+                    .or(self.enclosing_span.as_ref())
+                    .cloned()
+                    .unwrap_or_else(Span::dummy);
+                self.error(
+                    format!(
+                        "mismatched types in array literal: expected `{}`, found `{}`",
+                        element_ty.display_qualified(),
+                        ty.display_qualified()
+                    ),
+                    &span,
+                );
+                break;
+            }
+        }
+        Some(element_ty)
+    }
+
+    // Parameters of a direct call to a top-level function not shadowed by a local.
+    fn call_signature(&self, ident: &Identifier) -> Option<Signature> {
+        if self
+            .scopes
+            .iter()
+            .any(|scope| scope.contains_key(&ident.name))
+        {
+            return None;
+        }
+        self.signatures.get(&ident.name).cloned()
+    }
+
+    fn check_struct_expression(&mut self, literal: &StructExpr) -> Option<Type> {
+        let decl = self.structs.get(&literal.name.name).cloned();
+        let mut seen = HashSet::new();
+        for (field, value) in &literal.fields {
+            let found = self.check_expression(value);
+            if !seen.insert(&field.name) {
+                self.error(
+                    format!("field `{}` is specified more than once", field),
+                    &field.span,
+                );
+            }
+            let Some(decl) = &decl else {
+                continue;
+            };
+            match decl.field_type(&field.name) {
+                Some(declared) => self.expect_type(declared, found.as_ref(), value, &field.span),
+                None => self.error(
+                    format!("struct `{}` has no field `{}`", decl.name, field),
+                    &field.span,
+                ),
+            }
+        }
+        let base = literal
+            .rest
+            .as_ref()
+            .map(|rest| self.check_expression(rest));
+        let Some(decl) = decl else {
+            self.error(
+                format!("unknown struct `{}`", literal.name),
+                &literal.name.span,
+            );
+            return None;
+        };
+        let ty = Type::Named(TypePath {
+            segments: vec![TypePathSegment {
+                ident: decl.name.clone(),
+                generic_args: None,
+                span: literal.name.span.clone(),
+            }],
+            span: literal.name.span.clone(),
+        });
+        if let Some(Some(base)) = &base
+            && *base != ty
+        {
+            self.error(
+                format!(
+                    "functional update base must be `{}`, found `{}`",
+                    ty,
+                    base.display_qualified()
+                ),
+                &literal.span,
+            );
+        }
+        if let Err(missing) = fill_struct_fields(&decl, literal) {
+            self.error(
+                format!(
+                    "missing field `{}` in `{}` literal, and it has no default",
+                    missing, decl.name
+                ),
+                &literal.span,
+            );
+        }
+        Some(ty)
+    }
+
+    // Checks the arguments of a direct call and returns its result type, with
+    // type parameters taken from a turbofish (`f::<T>`) or inferred from the
+    // argument types.
+    fn check_call(&mut self, call: &CallExpr, signature: &Signature) -> Option<Type> {
+        let params = &signature.params;
+        let (name, explicit) = match &call.callee {
+            Expression::Identifier(ident) => (ident.name.as_str(), None),
+            Expression::Path(path) => match path.segments.as_slice() {
+                [segment] => (segment.ident.name.as_str(), segment.generic_args.as_deref()),
+                _ => ("function", None),
+            },
+            _ => ("function", None),
+        };
+        let arguments = match fill_default_arguments(params, &call.arguments) {
+            Ok(arguments) => arguments,
+            Err(error) => {
+                self.error(
+                    format!(
+                        "`{}` takes {} argument{} but {} {} supplied{}",
+                        name,
+                        params.len(),
+                        if params.len() == 1 { "" } else { "s" },
+                        call.arguments.len(),
+                        if call.arguments.len() == 1 {
+                            "was"
+                        } else {
+                            "were"
+                        },
+                        match error {
+                            Some(missing) => format!("; `{}` has no default", missing),
+                            None => String::new(),
+                        }
+                    ),
+                    &call.span,
+                );
+                for argument in &call.arguments {
+                    self.check_expression(argument);
+                }
+                return (signature.generics.is_empty()
+                    && inference::placeholder(&signature.return_type).is_none())
+                .then(|| signature.return_type.clone());
+            }
+        };
+
+        let mut bindings = HashMap::new();
+        if let Some(explicit) = explicit {
+            if explicit.len() == signature.generics.len() {
+                bindings.extend(
+                    signature
+                        .generics
+                        .iter()
+                        .cloned()
+                        .zip(explicit.iter().cloned()),
+                );
+            } else {
+                self.error(
+                    format!(
+                        "`{}` takes {} generic argument{} but {} {} supplied",
+                        name,
+                        signature.generics.len(),
+                        if signature.generics.len() == 1 {
+                            ""
+                        } else {
+                            "s"
+                        },
+                        explicit.len(),
+                        if explicit.len() == 1 { "was" } else { "were" },
+                    ),
+                    &call.span,
+                );
+            }
+        }
+        for (argument, param) in arguments.iter().zip(params) {
+            let found = self.check_expression(argument);
+            let Some(found) = found else {
+                continue;
+            };
+            if signature.generics.is_empty()
+                || !inference::unify(&param.ty, &found, &signature.generics, &mut bindings)
+            {
+                let expected = inference::substitute(&param.ty, &bindings);
+                self.expect_type(&expected, Some(&found), argument, &call.span);
+            }
+        }
+
+        let mut inferred = true;
+        for generic in &signature.generics {
+            if !bindings.contains_key(generic) {
+                self.error(
+                    format!("cannot infer `{}` in call to `{}`", generic, name),
+                    &call.span,
+                );
+                inferred = false;
+            }
+        }
+        let return_type = inference::substitute(&signature.return_type, &bindings);
+        (inferred && inference::placeholder(&return_type).is_none()).then_some(return_type)
+    }
+
+    fn check_binary(&mut self, binary: &BinaryExpr) -> Option<Type> {
+        let left = self.check_expression(&binary.left);
+        let right = self.check_expression(&binary.right);
+        let (left, right) = match (left, right) {
+            (Some(left), Some(right)) => (left, right),
+            _ => {
+                return match &binary.operator {
+                    op if is_comparison(op) || is_logical(op) => Some(Type::Bool),
+                    op if is_assignment(op) => Some(Type::Unit),
+                    _ => None,
+                };
+            }
+        };
+
+        // An unsuffixed literal takes on the type of the other operand. A
+        // shift amount may have any integer type, so its value is not checked.
+        let (left, right) = match (
+            is_unsuffixed_literal(&binary.left),
+            is_unsuffixed_literal(&binary.right),
+        ) {
+            (false, true) if is_shift(&binary.operator) && literal_fits(&right, &left) => {
+                (left.clone(), left)
+            }
+            (true, false) => {
+                let left = self.literal_operand_type(&binary.left, left, &right, &binary.span);
+                (left, right)
+            }
+            (false, true) => {
+                let right = self.literal_operand_type(&binary.right, right, &left, &binary.span);
+                (left, right)
+            }
+            _ => (left, right),
+        };
+
+        let op = &binary.operator;
+        if operators::method_name(op).is_some()
+            && (operators::is_user_type(&left) || operators::is_user_type(&right))
+        {
+            return self.resolve_operator(binary, &left, &right);
+        }
+
+        if is_logical(op) {
+            // A diverging operand, as in `ok || panic("..")`, fits as `bool`.
+            let is_bool = |ty: &Type| matches!(ty, Type::Bool | Type::Never);
+            if !is_bool(&left) || !is_bool(&right) {
+                self.error(
+                    format!(
+                        "operator `{}` expects `bool` operands, found `{}` and `{}`",
+                        op.symbol(),
+                        left,
+                        right
+                    ),
+                    &binary.span,
+                );
+            }
+            return Some(Type::Bool);
+        }
+
+        if is_bitwise(op) || is_shift(op) {
+            if !left.is_integer() || !right.is_integer() {
+                self.error(
+                    format!(
+                        "operator `{}` expects integer operands, found `{}` and `{}`",
+                        op.symbol(),
+                        left,
+                        right
+                    ),
+                    &binary.span,
+                );
+                return if is_assignment(op) {
+                    Some(Type::Unit)
+                } else {
+                    None
+                };
+            }
+            // The shift amount may have any integer type.
+            if is_shift(op) {
+                self.check_shift_amount(&left, &binary.right, &binary.span);
+                return if is_assignment(op) {
+                    Some(Type::Unit)
+                } else {
+                    Some(left)
+                };
+            }
+        }
+
+        // `char` and `bool` are primitives but not numbers.
+        if is_arithmetic(op)
+            && let Some(ty) = [&left, &right]
+                .into_iter()
+                .find(|ty| matches!(ty, Type::Char | Type::Bool))
+        {
+            self.error(
+                format!(
+                    "`{}` does not support `{}`; cast to an integer first",
+                    ty,
+                    op.symbol()
+                ),
+                &binary.span,
+            );
+            return if is_assignment(op) {
+                Some(Type::Unit)
+            } else {
+                None
+            };
+        }
+
+        if *op == BinaryOperator::Assign && self.try_widen(&right, &left, &binary.right) {
+            return Some(Type::Unit);
+        }
+
+        if left != right {
+            let message = if left.is_integer()
+                && right.is_integer()
+                && left.is_signed() != right.is_signed()
+            {
+                format!(
+                    "mixed signedness: `{}` {} `{}`; cast one side with `as` so both have the same signedness",
+                    left,
+                    op.symbol(),
+                    right
+                )
+            } else {
+                format!(
+                    "mismatched types: `{}` {} `{}`",
+                    left.display_qualified(),
+                    op.symbol(),
+                    right.display_qualified()
+                )
+            };
+            self.error(message, &binary.span);
+            return if is_comparison(op) {
+                Some(Type::Bool)
+            } else if is_assignment(op) {
+                Some(Type::Unit)
+            } else {
+                None
+            };
+        }
+
+        if is_comparison(op) {
+            Some(Type::Bool)
+        } else if is_assignment(op) {
+            Some(Type::Unit)
+        } else {
+            Some(left)
+        }
+    }
+
+    // The type of unsuffixed literal operand `literal`, of type `ty`, beside an
+    // operand of type `other`: `other` if the value fits. A value too large
+    // for `other` is reported here, but a negative one beside an unsigned
+    // operand keeps its signed type for the signedness check to report.
+    fn literal_operand_type(
+        &mut self,
+        literal: &Expression,
+        ty: Type,
+        other: &Type,
+        span: &Span,
+    ) -> Type {
+        if self.literal_adopts(literal, other) {
+            return other.clone();
+        }
+        match integer_literal_value(literal) {
+            Some(value) if other.is_integer() && (value >= 0 || other.is_signed()) => {
+                let span = consts::expression_span(literal).unwrap_or(span).clone();
+                self.error(
+                    format!("literal `{}` does not fit in `{}`", value, other),
+                    &span,
+                );
+                other.clone()
+            }
+            _ => ty,
+        }
+    }
+
+    // Whether unsuffixed literal `expr` can take on type `target`: an integer
+    // needs an integer type holding its value, a float a float type. An array
+    // literal of them can take an array type of its length whose element
+    // type each element can take, as in `var xs: [u8; 2] = [1, 2];`.
+    fn literal_adopts(&self, expr: &Expression, target: &Type) -> bool {
+        if let Expression::Literal(Literal::Array(elements)) = expr {
+            let Type::Array(element, Some(length)) = target else {
+                return false;
+            };
+            return matches!(
+                length.as_ref(),
+                Expression::Literal(Literal::Integer(length, _, _))
+                    if *length == elements.len() as i128
+            ) && !elements.is_empty()
+                && elements.iter().all(|item| {
+                    self.literal_adopts(item, element)
+                        || consts::expression_span(item)
+                            .and_then(|span| self.type_at(span))
+                            .is_some_and(|ty| ty == element.as_ref())
+                });
+        }
+        if let Expression::Paren(inner, _) = expr {
+            return self.literal_adopts(inner, target);
+        }
+        if !is_unsuffixed_literal(expr) {
+            return false;
+        }
+        match integer_literal_value(expr) {
+            Some(value) => self
+                .target
+                .integer_range(target)
+                .is_some_and(|(min, max)| (min..=max).contains(&value)),
+            None => target.is_float(),
+        }
+    }
+
+    fn check_shift_amount(&mut self, ty: &Type, amount: &Expression, span: &Span) {
+        let value = match amount {
+            Expression::Literal(literal) => literal.as_integer(),
+            Expression::Unary(unary) if unary.operator == UnaryOperator::Neg => {
+                match &unary.operand {
+                    Expression::Literal(literal) => literal.as_integer().map(|n| -n),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let (Some(value), Some(bits)) = (value, integer_bits(ty)) else {
+            return;
+        };
+        if value < 0 || value >= i128::from(bits) {
+            self.diagnostics.push(Diagnostic::warning(
+                format!(
+                    "shift amount {} is out of range for `{}`, which is {} bits wide",
+                    value, ty, bits
+                ),
+                span.clone(),
+            ));
+        }
+    }
+
+    fn resolve_operator(&mut self, binary: &BinaryExpr, left: &Type, right: &Type) -> Option<Type> {
+        let op = &binary.operator;
+        match self.operators.lookup(left, op) {
+            Some(found) if &found.rhs == right => {
+                let output = found.output.clone();
+                self.resolved_operators.push(ResolvedOperator {
+                    operator: op.clone(),
+                    method: found.method.clone(),
+                    output: output.clone(),
+                    span: binary.span.clone(),
+                });
+                Some(output)
+            }
+            _ => {
+                self.error(
+                    format!(
+                        "no implementation of `{}` for `{}` {} `{}`",
+                        operators::method_name(op).unwrap_or_default(),
+                        left,
+                        op.symbol(),
+                        right
+                    ),
+                    &binary.span,
+                );
+                None
+            }
+        }
+    }
+
+    fn check_unary(&mut self, unary: &UnaryExpr) -> Option<Type> {
+        let operand = match (&unary.operator, &unary.operand) {
+            // `-128i8` is in range even though `128i8` alone is not.
+            (UnaryOperator::Neg, Expression::Literal(literal)) => {
+                self.check_integer_literal(literal, true, &unary.span);
+                self.check_literal(literal)?
+            }
+            _ => self.check_expression(&unary.operand)?,
+        };
+        match unary.operator {
+            UnaryOperator::BitNot if !operand.is_integer() => {
+                self.error(
+                    format!(
+                        "operator `~` expects an integer operand, found `{}`",
+                        operand
+                    ),
+                    &unary.span,
+                );
+                None
+            }
+            UnaryOperator::Neg | UnaryOperator::Not | UnaryOperator::BitNot => Some(operand),
+            UnaryOperator::Deref => match operand {
+                Type::Pointer(inner, _) | Type::Reference(inner, _, _) => Some(*inner),
+                other => {
+                    self.error(
+                        format!("type `{}` cannot be dereferenced", other),
+                        &unary.span,
+                    );
+                    None
+                }
+            },
+            UnaryOperator::Ref => Some(Type::Reference(
+                Box::new(operand),
+                Mutability::Immutable,
+                None,
+            )),
+            UnaryOperator::RefMut => Some(Type::Reference(
+                Box::new(operand),
+                Mutability::Mutable,
+                None,
+            )),
+        }
+    }
+
+    fn expect_type(
+        &mut self,
+        expected: &Type,
+        found: Option<&Type>,
+        expr: &Expression,
+        span: &Span,
+    ) {
+        let Some(found) = found else {
+            return;
+        };
+        // A `_` left in a parameter or return type has already been reported
+        // as uninferable, so nothing is known to fit it or not.
+        if inference::placeholder(expected).is_some() {
+            return;
+        }
+        // A diverging expression never produces a value, so it fits anywhere.
+        if *found == Type::Never {
+            return;
+        }
+        // Array lengths compare by value: `[i32; N]` fits `[i32; 4]` when `N`
+        // is 4.
+        let written = expected;
+        let expected = &consts::with_evaluated_lengths(expected, &self.constants);
+        let found = &consts::with_evaluated_lengths(found, &self.constants);
+        if found.normalized().without_lifetimes() == expected.normalized().without_lifetimes()
+            || self.literal_adopts(expr, expected)
+        {
+            return;
+        }
+        if let Some(value) = integer_literal_value(expr)
+            && expected.is_integer()
+        {
+            self.error(
+                format!("literal `{}` does not fit in `{}`", value, expected),
+                span,
+            );
+            return;
+        }
+        if self.try_widen(found, expected, expr) {
+            return;
+        }
+        self.error(
+            format!(
+                "mismatched types: expected `{}`, found `{}`",
+                written.display_qualified(),
+                found.display_qualified()
+            ),
+            span,
+        );
+    }
+
+    fn check_match_coverage(&mut self, scrutinee: Option<Type>, arms: &[MatchArm], span: &Span) {
+        if let Some(ty) = scrutinee
+            && let Some(bounds) = self.target.integer_range(&ty)
+        {
+            self.diagnostics
+                .extend(ranges::check_integer_match(&ty, bounds, arms, span));
+        }
+    }
+
+    fn check_break(&mut self, brk: &BreakStatement) {
+        let found = brk
+            .expression
+            .as_ref()
+            .and_then(|expr| self.check_expression(expr));
+        let target = match &brk.label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rposition(|context| context.label.as_ref() == Some(&label.name)),
+            None => self.loops.len().checked_sub(1),
+        };
+        let Some(index) = target else {
+            return;
+        };
+        if brk.expression.is_some() && !self.loops[index].accepts_value {
+            self.error(
+                "`break` with a value is only allowed inside `loop`",
+                &brk.span,
+            );
+            return;
+        }
+        let found = if brk.expression.is_some() {
+            found
+        } else {
+            Some(Type::Unit)
+        };
+        let context = &mut self.loops[index];
+        if !context.broken {
+            context.broken = true;
+            context.break_type = found;
+            return;
+        }
+        let Some(expected) = context.break_type.clone() else {
+            return;
+        };
+        match &brk.expression {
+            Some(expr) => self.expect_type(&expected, found.as_ref(), expr, &brk.span),
+            // A bare `break` gives `()`, which must agree with earlier ones.
+            None if !matches!(expected.normalized(), Type::Unit | Type::Never) => self.error(
+                format!(
+                    "mismatched types: expected `{}`, found `()`",
+                    expected.display_qualified()
+                ),
+                &brk.span,
+            ),
+            None => {}
+        }
+    }
+
+    // The element type a `for` loop binds: a range yields its integer bound
+    // type, an array or slice its element type, and a reference to either a
+    // reference to the element. Nothing else can be iterated; see `desugar`.
+    fn check_iterator(&mut self, iterator: &Expression, for_span: &Span) -> Option<Type> {
+        let span = consts::expression_span(iterator)
+            .unwrap_or(for_span)
+            .clone();
+        if let Expression::Range(range) = iterator {
+            let start = range.start.as_ref().and_then(|e| self.check_expression(e));
+            let end = range.end.as_ref().and_then(|e| self.check_expression(e));
+            // An unsuffixed literal bound takes the other bound's type, as in `0..n`.
+            let ty = match (start, end) {
+                (Some(_), Some(end))
+                    if range
+                        .start
+                        .as_ref()
+                        .is_some_and(|start| self.literal_adopts(start, &end)) =>
+                {
+                    end
+                }
+                (start, end) => start.or(end)?,
+            };
+            if !ty.is_integer() {
+                self.error(
+                    format!(
+                        "cannot iterate over a range of `{}`; bounds must be integers",
+                        ty
+                    ),
+                    &span,
+                );
+                return None;
+            }
+            return Some(ty);
+        }
+        match self.check_expression(iterator)? {
+            Type::Array(element, _) | Type::Slice(element) => Some(*element),
+            Type::Reference(inner, mutability, lifetime) => match *inner {
+                Type::Array(element, _) | Type::Slice(element) => {
+                    Some(Type::Reference(element, mutability, None))
+                }
+                inner => self.not_iterable(
+                    &Type::Reference(Box::new(inner), mutability, lifetime),
+                    &span,
+                ),
+            },
+            Type::Never => None,
+            ty => self.not_iterable(&ty, &span),
+        }
+    }
+
+    fn not_iterable(&mut self, ty: &Type, span: &Span) -> Option<Type> {
+        self.error(
+            format!(
+                "`{}` is not iterable; expected a range, array or slice",
+                ty.display_short()
+            ),
+            span,
+        );
+        None
+    }
+
+    // The body of a `while` or `for` loop is evaluated for its effects only
+    // and must not leave a value behind.
+    fn check_loop_body(
+        &mut self,
+        keyword: &str,
+        body: Option<Type>,
+        tail: Option<&Expression>,
+        span: &Span,
+    ) {
+        let Some(found) = body else {
+            return;
+        };
+        if found == Type::Unit || found == Type::Never {
+            return;
+        }
+        let span = tail
+            .and_then(consts::expression_span)
+            .unwrap_or(span)
+            .clone();
+        self.error(
+            format!(
+                "`{}` loop body must have type `()`, found `{}`",
+                keyword,
+                found.display_qualified()
+            ),
+            &span,
+        );
+    }
+
+    // Accepts `expr` of type `found` where `expected` is wanted if implicit
+    // widening is on and the conversion is lossless. An expression without a
+    // span of its own (a suffixed literal) cannot be marked for a cast and
+    // is not widened.
+    fn try_widen(&mut self, found: &Type, expected: &Type, expr: &Expression) -> bool {
+        if !self.implicit_widening || !widening::is_lossless_widening(found, expected) {
+            return false;
+        }
+        let Some(span) = consts::expression_span(expr) else {
+            return false;
+        };
+        self.implicit_casts.push(ImplicitCast {
+            from: found.clone(),
+            to: expected.clone(),
+            span: span.clone(),
+        });
+        true
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern, ty: Option<Type>) {
+        let mut bindings = Vec::new();
+        self.pattern_bindings(pattern, ty, &mut bindings);
+        for (ident, ty) in bindings {
+            if let (Some(scope), Some(ty)) = (self.scopes.last_mut(), ty) {
+                self.types
+                    .insert((ident.span.start, ident.span.end), ty.clone());
+                scope.insert(ident.name.clone(), ty);
+            }
+        }
+    }
+
+    fn report_tuple_arity(&mut self, patterns: &[Pattern], arity: usize) {
+        let has_rest = patterns.contains(&Pattern::Rest);
+        let fixed = patterns.len() - usize::from(has_rest);
+        let message = format!(
+            "mismatched types: expected a tuple with {}{} element{}, found one with {}",
+            if has_rest { "at least " } else { "" },
+            fixed,
+            if fixed == 1 { "" } else { "s" },
+            arity
+        );
+        // Patterns carry no span of their own, so point at the statement or
+        // expression that introduces them.
+        let span = self.enclosing_span.clone().unwrap_or_else(Span::dummy);
+        self.error(message, &span);
+    }
+
+    // The names `pattern` binds and their types, where known. An or-pattern
+    // contributes its first alternative's bindings, once every alternative
+    // has been checked to bind the same names with the same types.
+    fn pattern_bindings<'p>(
+        &mut self,
+        pattern: &'p Pattern,
+        ty: Option<Type>,
+        bindings: &mut Vec<(&'p Identifier, Option<Type>)>,
+    ) {
+        match pattern {
+            Pattern::Identifier(ident) => bindings.push((ident, ty)),
+            Pattern::Tuple(patterns) => {
+                let types = match ty {
+                    Some(Type::Tuple(types)) => {
+                        let arity = types.len();
+                        tuple_pattern_types(patterns, types).unwrap_or_else(|| {
+                            self.report_tuple_arity(patterns, arity);
+                            vec![None; patterns.len()]
+                        })
+                    }
+                    _ => vec![None; patterns.len()],
+                };
+                for (pattern, ty) in patterns.iter().zip(types) {
+                    self.pattern_bindings(pattern, ty, bindings);
+                }
+            }
+            Pattern::Struct(name, fields, _) => {
+                let decl = self.structs.get(&name.name).cloned();
+                for (field, pattern) in fields {
+                    let ty = decl.as_ref().and_then(|decl| {
+                        decl.fields
+                            .iter()
+                            .find(|f| f.name.name == field.name)
+                            .map(|f| f.ty.clone())
+                    });
+                    self.pattern_bindings(pattern, ty, bindings);
+                }
+            }
+            Pattern::Or(alternatives) => {
+                if let Err(error) = pattern.bindings() {
+                    self.error(error.to_string(), &error.span);
+                }
+                let mut first: Option<Vec<(&Identifier, Option<Type>)>> = None;
+                for alternative in alternatives {
+                    let mut found = Vec::new();
+                    self.pattern_bindings(alternative, ty.clone(), &mut found);
+                    for (ident, ty) in &found {
+                        if let Some(ty) = ty {
+                            self.types
+                                .insert((ident.span.start, ident.span.end), ty.clone());
+                        }
+                    }
+                    match &first {
+                        Some(expected) => self.check_alternative_types(expected, &found),
+                        None => first = Some(found),
+                    }
+                }
+                bindings.extend(first.unwrap_or_default());
+            }
+            Pattern::Literal(_) | Pattern::Range(_, _) | Pattern::Wildcard | Pattern::Rest => {}
+        }
+    }
+
+    fn check_alternative_types(
+        &mut self,
+        expected: &[(&Identifier, Option<Type>)],
+        found: &[(&Identifier, Option<Type>)],
+    ) {
+        for (ident, ty) in found {
+            let Some((first, Some(expected_ty))) =
+                expected.iter().find(|(first, _)| first.name == ident.name)
+            else {
+                continue;
+            };
+            if let Some(ty) = ty
+                && ty != expected_ty
+            {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!(
+                            "mismatched types: `{}` is bound as `{}` here but as `{}` in the first alternative",
+                            ident, ty, expected_ty
+                        ),
+                        ident.span.clone(),
+                    )
+                    .with_label(
+                        format!("`{}` first bound as `{}` here", first, expected_ty),
+                        first.span.clone(),
+                    ),
+                );
+            }
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+    }
+
+    fn error(&mut self, message: impl Into<String>, span: &Span) {
+        self.diagnostics
+            .push(Diagnostic::error(message, span.clone()));
+    }
+}
+
+// Pairs each element of a tuple pattern with its type. A `..` element absorbs
+// the elements between the patterns before it and the patterns after it, so
+// `(a, .., z)` matches any tuple with at least two elements. Patterns whose
+// arity cannot match get no types.
+impl LoopContext {
+    fn new(label: &Option<Identifier>, accepts_value: bool) -> Self {
+        Self {
+            label: label.as_ref().map(|label| label.name.clone()),
+            accepts_value,
+            broken: false,
+            break_type: None,
+        }
+    }
+}
+
+// The expression a loop body evaluates to: a block's tail, or the body itself.
+fn body_tail(body: &Expression) -> Option<&Expression> {
+    match body {
+        Expression::Block(block) => block.tail.as_deref(),
+        body => Some(body),
+    }
+}
+
+// The type each element of a tuple pattern binds, or `None` when the
+// pattern's arity does not fit the tuple.
+fn tuple_pattern_types(patterns: &[Pattern], types: Vec<Type>) -> Option<Vec<Option<Type>>> {
+    let rest = patterns.iter().position(|p| *p == Pattern::Rest);
+    let fixed = patterns.len() - usize::from(rest.is_some());
+    match rest {
+        None if types.len() == fixed => Some(types.into_iter().map(Some).collect()),
+        Some(rest) if types.len() >= fixed => {
+            let skipped = types.len() - fixed;
+            let mut types = types.into_iter();
+            let mut result: Vec<Option<Type>> = types.by_ref().take(rest).map(Some).collect();
+            result.push(None);
+            result.extend(types.skip(skipped).map(Some));
+            Some(result)
+        }
+        _ => None,
+    }
+}
+
+pub fn check_program(program: &Program) -> Vec<Diagnostic> {
+    let mut checker = Checker::new();
+    checker.check_program(program);
+    checker.into_diagnostics()
+}
+
+#[derive(Debug, Clone)]
+struct Signature {
+    generics: Vec<String>,
+    params: Vec<Parameter>,
+    return_type: Type,
+}
+
+impl Signature {
+    fn of(function: &FunctionDecl) -> Self {
+        Self {
+            generics: function.generics.iter().map(|g| g.name.clone()).collect(),
+            params: function.params.clone(),
+            return_type: function
+                .return_type
+                .as_deref()
+                .cloned()
+                .unwrap_or(Type::Unit),
+        }
+    }
+}
+
+// Completes `arguments` with the defaults of the omitted trailing parameters.
+// On failure returns the first omitted parameter without a default, or `None`
+// when there are more arguments than parameters.
+pub fn fill_default_arguments(
+    params: &[Parameter],
+    arguments: &[Expression],
+) -> Result<Vec<Expression>, Option<Identifier>> {
+    if arguments.len() > params.len() {
+        return Err(None);
+    }
+    let mut filled = arguments.to_vec();
+    for param in &params[arguments.len()..] {
+        match &param.default {
+            Some(default) => filled.push(default.clone()),
+            None => return Err(Some(param.name.clone())),
+        }
+    }
+    Ok(filled)
+}
+
+// Appends the fields a struct literal omits, in declaration order: from
+// `..base` as `base.field` when present, otherwise from the field's default.
+// On failure returns the first omitted field with neither.
+pub fn fill_struct_fields(
+    decl: &StructDecl,
+    literal: &StructExpr,
+) -> Result<Vec<(Identifier, Expression)>, Identifier> {
+    let mut filled = literal.fields.clone();
+    for field in &decl.fields {
+        if literal
+            .fields
+            .iter()
+            .any(|(name, _)| name.name == field.name.name)
+        {
+            continue;
+        }
+        let value = match (&literal.rest, &field.default) {
+            (Some(base), _) => Expression::Member(Box::new(MemberExpr {
+                object: (**base).clone(),
+                member: field.name.clone(),
+                span: literal.span.clone(),
+            })),
+            (None, Some(default)) => default.clone(),
+            (None, None) => return Err(field.name.clone()),
+        };
+        filled.push((field.name.clone(), value));
+    }
+    Ok(filled)
+}
+
+pub fn function_type(function: &FunctionDecl) -> Type {
+    Type::Function(
+        function
+            .params
+            .iter()
+            .map(|param| param.ty.clone())
+            .collect(),
+        Box::new(
+            function
+                .return_type
+                .as_deref()
+                .cloned()
+                .unwrap_or(Type::Unit),
+        ),
+        FunctionAbi::default(),
+    )
+}
+
+pub fn check_main_signature(main: &FunctionDecl) -> Option<Diagnostic> {
+    let return_type = main.return_type.as_deref().unwrap_or(&Type::Unit);
+    if !main.params.is_empty() {
+        return Some(Diagnostic::error(
+            format!(
+                "`main` must take no parameters, found {}",
+                main.params.len()
+            ),
+            main.span.clone(),
+        ));
+    }
+    if !matches!(return_type, Type::Unit | Type::I32) {
+        return Some(Diagnostic::error(
+            format!("`main` must return `()` or `i32`, found `{}`", return_type),
+            main.span.clone(),
+        ));
+    }
+    None
+}
+
+fn is_unsuffixed_literal(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(Literal::Integer(_, None, _) | Literal::Float(_, None)) => true,
+        Expression::Unary(unary) => {
+            unary.operator == UnaryOperator::Neg && is_unsuffixed_literal(&unary.operand)
+        }
+        Expression::Paren(inner, _) => is_unsuffixed_literal(inner),
+        _ => false,
+    }
+}
+
+// An unsuffixed literal, or a non-empty array literal of them, whose type
+// comes from where it is used.
+fn is_literal_only(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(Literal::Array(elements)) => {
+            !elements.is_empty() && elements.iter().all(is_literal_only)
+        }
+        Expression::Paren(inner, _) => is_literal_only(inner),
+        expr => is_unsuffixed_literal(expr),
+    }
+}
+
+// The value of an unsuffixed integer literal, negated as written.
+fn integer_literal_value(expr: &Expression) -> Option<i128> {
+    match expr {
+        Expression::Literal(Literal::Integer(value, None, _)) => Some(*value),
+        Expression::Unary(unary) if unary.operator == UnaryOperator::Neg => {
+            integer_literal_value(&unary.operand)?.checked_neg()
+        }
+        Expression::Paren(inner, _) => integer_literal_value(inner),
+        _ => None,
+    }
+}
+
+// An array element whose type is known and does not diverge.
+fn definite_element<'a>(
+    (element, ty): (&'a Expression, &'a Option<Type>),
+) -> Option<(&'a Expression, &'a Type)> {
+    match ty {
+        Some(ty) if *ty != Type::Never => Some((element, ty)),
+        _ => None,
+    }
+}
+
+// Whether an unsuffixed literal of type `literal` may be used as `target`.
+fn literal_fits(literal: &Type, target: &Type) -> bool {
+    (literal.is_integer() && target.is_integer()) || (literal.is_float() && target.is_float())
+}
+
+fn is_comparison(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Lt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::GtEq
+    )
+}
+
+fn is_arithmetic(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Add
+            | BinaryOperator::Sub
+            | BinaryOperator::Mul
+            | BinaryOperator::Div
+            | BinaryOperator::Rem
+            | BinaryOperator::AddAssign
+            | BinaryOperator::SubAssign
+            | BinaryOperator::MulAssign
+            | BinaryOperator::DivAssign
+            | BinaryOperator::RemAssign
+    )
+}
+
+fn is_bitwise(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::BitAnd
+            | BinaryOperator::BitOr
+            | BinaryOperator::BitXor
+            | BinaryOperator::BitAndAssign
+            | BinaryOperator::BitOrAssign
+            | BinaryOperator::BitXorAssign
+    )
+}
+
+fn is_shift(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Shl
+            | BinaryOperator::Shr
+            | BinaryOperator::ShlAssign
+            | BinaryOperator::ShrAssign
+    )
+}
+
+// Width of fixed-size integer types; `isize`/`usize` depend on the target.
+fn integer_bits(ty: &Type) -> Option<u32> {
+    match ty {
+        Type::I8 | Type::U8 => Some(8),
+        Type::I16 | Type::U16 => Some(16),
+        Type::I32 | Type::U32 => Some(32),
+        Type::I64 | Type::U64 => Some(64),
+        Type::I128 | Type::U128 => Some(128),
+        _ => None,
+    }
+}
+
+fn is_logical(op: &BinaryOperator) -> bool {
+    matches!(op, BinaryOperator::And | BinaryOperator::Or)
+}
+
+fn is_assignment(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Assign
+            | BinaryOperator::AddAssign
+            | BinaryOperator::SubAssign
+            | BinaryOperator::MulAssign
+            | BinaryOperator::DivAssign
+            | BinaryOperator::RemAssign
+            | BinaryOperator::BitAndAssign
+            | BinaryOperator::BitOrAssign
+            | BinaryOperator::BitXorAssign
+            | BinaryOperator::ShlAssign
+            | BinaryOperator::ShrAssign
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::expressions::MacroInvocation;
+    use crate::ast::statements::{LetStatement, Statement};
+    use crate::ast::{Identifier, Span, StaticDecl};
+    use crate::diagnostics::Severity;
+
+    fn function(name: &str, statements: Vec<Statement>) -> Declaration {
+        Declaration::Function(
+            FunctionDecl::builder(name)
+                .body(Block {
+                    statements,
+                    ..Block::default()
+                })
+                .build(),
+        )
+    }
+
+    fn program(items: Vec<Declaration>) -> Program {
+        Program {
+            items,
+            span: Span::dummy(),
+        }
+    }
+
+    #[test]
+    fn test_main_signature() {
+        let mut main = FunctionDecl::builder("main")
+            .returns(Type::I32)
+            .body(Block {
+                tail: Some(Box::new(Expression::Literal(Literal::Integer(
+                    0,
+                    None,
+                    IntBase::Dec,
+                )))),
+                ..Block::default()
+            })
+            .build();
+        assert!(check_main_signature(&main).is_none());
+
+        main.params.push(crate::ast::Parameter {
+            name: Identifier::new("x".to_string(), Span::dummy()),
+            ty: Type::I32,
+            default: None,
+            span: Span::dummy(),
+        });
+        let diagnostics = check_program(&program(vec![Declaration::Function(main.clone())]));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`main` must take no parameters, found 1"
+        );
+
+        main.params.clear();
+        main.return_type = Some(Box::new(Type::Bool));
+        assert!(check_main_signature(&main).is_some());
+    }
+
+    #[test]
+    fn test_checker_reports_builtin_macro_errors() {
+        let call = Expression::MacroInvocation(Box::new(MacroInvocation {
+            name: Identifier::new("println".to_string(), Span::dummy()),
+            arguments: vec![Expression::Literal(Literal::String("{}".to_string()))],
+            span: Span::dummy(),
+        }));
+        let program = program(vec![function("main", vec![Statement::Expression(call)])]);
+
+        let diagnostics = check_program(&program);
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_unit_literal_type() {
+        let unit = Expression::Literal(Literal::Unit);
+        let mut checker = Checker::new();
+
+        assert_eq!(checker.check_expression(&unit), Some(Type::Unit));
+        assert_eq!(Literal::Unit.to_string(), "()");
+    }
+
+    fn binary(left: Expression, operator: BinaryOperator, right: Expression) -> Expression {
+        Expression::Binary(Box::new(BinaryExpr {
+            left,
+            operator,
+            right,
+            span: Span::dummy(),
+        }))
+    }
+
+    fn int(value: i128, ty: Type) -> Expression {
+        Expression::Literal(Literal::Integer(value, Some(ty), IntBase::Dec))
+    }
+
+    #[test]
+    fn test_mixed_signedness_comparison() {
+        let mut checker = Checker::new();
+        let expr = binary(int(1, Type::I32), BinaryOperator::Lt, int(2, Type::U32));
+
+        assert_eq!(checker.check_expression(&expr), Some(Type::Bool));
+        assert_eq!(checker.diagnostics().len(), 1);
+        assert!(
+            checker.diagnostics()[0]
+                .message
+                .starts_with("mixed signedness: `i32` < `u32`")
+        );
+    }
+
+    #[test]
+    fn test_same_signedness_operations() {
+        let mut checker = Checker::new();
+        let comparison = binary(int(1, Type::I32), BinaryOperator::Lt, int(2, Type::I32));
+        let addition = binary(int(1, Type::U8), BinaryOperator::Add, int(2, Type::U8));
+
+        assert_eq!(checker.check_expression(&comparison), Some(Type::Bool));
+        assert_eq!(checker.check_expression(&addition), Some(Type::U8));
+        assert!(checker.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_mixed_signedness_arithmetic() {
+        let mut checker = Checker::new();
+        let expr = binary(int(1, Type::I64), BinaryOperator::Add, int(2, Type::U64));
+
+        assert_eq!(checker.check_expression(&expr), None);
+        assert!(
+            checker.diagnostics()[0]
+                .message
+                .contains("mixed signedness")
+        );
+    }
+
+    #[test]
+    fn test_unsuffixed_literal_operands() {
+        let messages = |source: &str| -> Vec<String> {
+            check_source(source)
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect()
+        };
+        assert_eq!(
+            messages("fn f() -> bool { -1 < 2u32 }"),
+            vec![
+                "mixed signedness: `i32` < `u32`; cast one side with `as` so both have the same signedness"
+            ]
+        );
+        assert_eq!(messages("fn f(a: u32) -> bool { -1 < a }").len(), 1);
+        assert_eq!(
+            messages("fn f(a: u8) -> bool { a < 300 }"),
+            vec!["literal `300` does not fit in `u8`"]
+        );
+        assert!(messages("fn f(a: u32, b: i8) -> bool { 1 < a && b > -128 }").is_empty());
+
+        assert_eq!(
+            messages("fn f() { var x: u32 = -1; var y: u8 = 300; var z: u8 = 255; }"),
+            vec![
+                "literal `-1` does not fit in `u32`",
+                "literal `300` does not fit in `u8`",
+            ]
+        );
+        assert_eq!(
+            messages("const Z: i8 = 200;"),
+            vec!["literal `200` does not fit in `i8`"]
+        );
+    }
+
+    fn point() -> Type {
+        Type::Named(crate::ast::types::TypePath {
+            segments: vec![crate::ast::types::TypePathSegment {
+                ident: Identifier::new("Point".to_string(), Span::dummy()),
+                generic_args: None,
+                span: Span::dummy(),
+            }],
+            span: Span::dummy(),
+        })
+    }
+
+    fn typed_local(checker: &mut Checker, name: &str, ty: Type) -> Expression {
+        checker.scopes.push(HashMap::from([(name.to_string(), ty)]));
+        Expression::Identifier(Identifier::new(name.to_string(), Span::dummy()))
+    }
+
+    #[test]
+    fn test_operator_overload_resolution() {
+        let mut table = OperatorTable::new();
+        table.register(&point(), BinaryOperator::Add, point(), point());
+        let mut checker = Checker::with_operators(table);
+
+        let a = typed_local(&mut checker, "a", point());
+        let b = typed_local(&mut checker, "b", point());
+        let expr = binary(a, BinaryOperator::Add, b);
+
+        assert_eq!(checker.check_expression(&expr), Some(point()));
+        assert!(checker.diagnostics().is_empty());
+        assert_eq!(checker.resolved_operators()[0].method, "add");
+    }
+
+    #[test]
+    fn test_operator_overload_missing() {
+        let mut checker = Checker::new();
+        let a = typed_local(&mut checker, "a", point());
+        let b = typed_local(&mut checker, "b", point());
+        let expr = binary(a, BinaryOperator::Mul, b);
+
+        assert_eq!(checker.check_expression(&expr), None);
+        assert_eq!(
+            checker.diagnostics()[0].message,
+            "no implementation of `mul` for `Point` * `Point`"
+        );
+    }
+
+    #[test]
+    fn test_mismatch_uses_qualified_paths() {
+        let named = |module: &str| {
+            Type::Named(crate::ast::types::TypePath {
+                segments: [module, "Foo"]
+                    .iter()
+                    .map(|name| crate::ast::types::TypePathSegment {
+                        ident: Identifier::new(name.to_string(), Span::dummy()),
+                        generic_args: None,
+                        span: Span::dummy(),
+                    })
+                    .collect(),
+                span: Span::dummy(),
+            })
+        };
+        let mut checker = Checker::new();
+        let value = typed_local(&mut checker, "value", named("a"));
+        checker.expect_type(&named("b"), Some(&named("a")), &value, &Span::dummy());
+
+        assert_eq!(
+            checker.diagnostics()[0].message,
+            "mismatched types: expected `b::Foo`, found `a::Foo`"
+        );
+    }
+
+    #[test]
+    fn test_let_type_mismatch() {
+        let stmt = Statement::Let(LetStatement {
+            pattern: Pattern::Identifier(Identifier::new("x".to_string(), Span::dummy())),
+            type_annotation: Some(Type::Unit),
+            initializer: Some(Expression::Literal(Literal::Boolean(true))),
+            mutable: false,
+            span: Span::dummy(),
+        });
+        let diagnostics = check_program(&program(vec![function("main", vec![stmt])]));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `()`, found `bool`"
+        );
+    }
+
+    fn static_decl(name: &str, mutable: bool) -> Declaration {
+        Declaration::Static(StaticDecl {
+            name: Identifier::new(name.to_string(), Span::dummy()),
+            ty: Type::I32,
+            mutable,
+            value: Expression::Literal(Literal::Integer(0, None, IntBase::Dec)),
+            span: Span::dummy(),
+        })
+    }
+
+    fn read(name: &str) -> Statement {
+        Statement::Expression(Expression::Identifier(Identifier::new(
+            name.to_string(),
+            Span::dummy(),
+        )))
+    }
+
+    #[test]
+    fn test_static_access() {
+        let diagnostics = check_program(&program(vec![
+            static_decl("LIMIT", false),
+            static_decl("COUNTER", true),
+            function("main", vec![read("LIMIT"), read("COUNTER")]),
+        ]));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Note);
+        assert_eq!(
+            diagnostics[0].message,
+            "`COUNTER` is a `static mut`; accesses are unsynchronized and need care"
+        );
+    }
+
+    #[test]
+    fn test_static_value_type_mismatch() {
+        let decl = Declaration::Static(StaticDecl {
+            name: Identifier::new("FLAG".to_string(), Span::dummy()),
+            ty: Type::Bool,
+            mutable: false,
+            value: Expression::Literal(Literal::Integer(1, None, IntBase::Dec)),
+            span: Span::dummy(),
+        });
+        let diagnostics = check_program(&program(vec![decl]));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+    }
+
+    fn check_source(source: &str) -> Vec<Diagnostic> {
+        check_program(&crate::parser::parse(source).unwrap())
+    }
+
+    fn check_for_target(source: &str, ptr_width: u32) -> Vec<Diagnostic> {
+        let mut checker = Checker::with_target(TargetConfig::new(ptr_width));
+        checker.check_program(&crate::parser::parse(source).unwrap());
+        checker.into_diagnostics()
+    }
+
+    #[test]
+    fn test_usize_literal_range_depends_on_target() {
+        let source = "fn f() {\n    var n = 4294967296usize;\n}";
+        assert!(check_for_target(source, 64).is_empty());
+
+        let diagnostics = check_for_target(source, 32);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "literal `4294967296usize` does not fit in `usize` on a 32-bit target"
+        );
+        assert_eq!(diagnostics[0].span.line, 2);
+    }
+
+    #[test]
+    fn test_suffixed_literal_range() {
+        let diagnostics = check_source(
+            "fn f() { var a = -128i8; var b = 128i8; var c = -2147483648isize; g(256u8); }",
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "literal `128i8` does not fit in `i8`",
+                "literal `256u8` does not fit in `u8`"
+            ]
+        );
+        let diagnostics = check_for_target("fn f() { var c = -2147483649isize; }", 32);
+        assert_eq!(
+            diagnostics[0].message,
+            "literal `-2147483649isize` does not fit in `isize` on a 32-bit target"
+        );
+        assert!(
+            check_source("const X: i128 = 5;\nfn f() { var x = 5i128; var y = 5u128; }").is_empty()
+        );
+    }
+
+    #[test]
+    fn test_struct_field_defaults() {
+        let source = "struct Point { x: i32 = 0, y: i32 = 0, label: bool }\n\
+                      fn f() { var p = Point { label: true }; var q = Point { x: 1 }; }";
+        let diagnostics = check_source(source);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "missing field `label` in `Point` literal, and it has no default"
+        );
+
+        let program = crate::parser::parse(source).unwrap();
+        let Declaration::Struct(decl) = &program.items[0] else {
+            panic!("expected a struct");
+        };
+        let literal = StructExpr {
+            name: decl.name.clone(),
+            fields: Vec::new(),
+            rest: None,
+            span: Span::dummy(),
+        };
+        assert_eq!(
+            fill_struct_fields(decl, &literal).unwrap_err().name,
+            "label"
+        );
+    }
+
+    #[test]
+    fn test_functional_update_literal() {
+        let diagnostics = check_source(
+            "struct Point { x: i32, y: i32 }\n\
+             fn f(base: Point) { var p: Point = Point { x: 1, ..base }; var q = Point { ..p }; }",
+        );
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let diagnostics = check_source(
+            "struct Point { x: i32, y: i32 }\nstruct Size { w: i32 }\n\
+             fn f(s: Size) { var p = Point { x: true, z: 2, ..s }; }",
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "mismatched types: expected `i32`, found `bool`",
+                "struct `Point` has no field `z`",
+                "functional update base must be `Point`, found `Size`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_struct_fields_from_base() {
+        let program = crate::parser::parse(
+            "struct Point { x: i32, y: i32 = 5 }\nfn f() { Point { y: 1, ..b }; }",
+        )
+        .unwrap();
+        let Declaration::Struct(decl) = &program.items[0] else {
+            panic!("expected a struct");
+        };
+        let Declaration::Function(function) = &program.items[1] else {
+            panic!("expected a function");
+        };
+        let Some(Statement::Expression(Expression::Struct(literal))) =
+            function.body.statements.first()
+        else {
+            panic!("expected a struct literal");
+        };
+        let filled = fill_struct_fields(decl, literal).unwrap();
+        let printed: Vec<String> = filled
+            .iter()
+            .map(|(name, value)| {
+                let mut printer = crate::printer::Printer::new();
+                printer.print_expression(value);
+                format!("{}: {}", name, printer.finish())
+            })
+            .collect();
+        assert_eq!(printed, vec!["y: 1", "x: b.x"]);
+    }
+
+    #[test]
+    fn test_turbofish_binds_type_parameters() {
+        let diagnostics = check_source(
+            "fn id<T>(x: T) -> T { x }\n\
+             fn f() { var a: u8 = id::<u8>(1); var b: bool = id::<i64>(2); var g = id::<bool>; var h = id::<u8, u8>(3); }",
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "mismatched types: expected `bool`, found `i64`",
+                "`id` takes 1 generic argument but 2 were supplied",
+            ]
+        );
+
+        let mut checker = Checker::new();
+        checker.check_program(&crate::parser::parse("fn id<T>(x: T) -> T { x }").unwrap());
+        let path = crate::parser::Parser::new("id::<bool>")
+            .unwrap()
+            .parse_expression()
+            .unwrap();
+        assert_eq!(
+            checker.check_expression(&path),
+            Some(Type::Function(
+                vec![Type::Bool],
+                Box::new(Type::Bool),
+                FunctionAbi::default()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_or_pattern_must_bind_same_names() {
+        let diagnostics = check_source(
+            "fn f(p: (i32, i32)) { match p { (0, y) | (y, 0) => y, (x, _) | _ => x } }",
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "variable `x` is not bound in all alternatives of the pattern"
+        );
+    }
+
+    #[test]
+    fn test_or_pattern_bindings_must_have_same_types() {
+        assert!(
+            check_source(
+                "struct P { a: i32, b: i32 }\n\
+                 fn f(t: (i32, i32), p: P) -> i32 {\n\
+                 let n = match t { (x, 0) | (0, x) => x, _ => 0 };\n\
+                 match p { P { a: x, .. } | P { b: x, .. } => x + n }\n}"
+            )
+            .is_empty()
+        );
+
+        let source =
+            "fn f(t: (i32, bool)) -> i32 {\n    match t { (x, true) | (_, x) => 1, _ => 0 }\n}";
+        let diagnostics = check_source(source);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: `x` is bound as `bool` here but as `i32` in the first alternative"
+        );
+        assert_eq!(diagnostics[0].span.slice(source), "x");
+        assert_eq!(diagnostics[0].span.column, 31);
+        assert_eq!(diagnostics[0].labels[0].span.column, 16);
+    }
+
+    #[test]
+    fn test_infer_placeholder_from_initializer() {
+        let diagnostics = check_source(
+            "fn f() { var xs: [_; 3] = [1, 2, 3]; var t: (_, bool) = (1, true); var flag: bool = t; }",
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `bool`, found `(i32, bool)`"
+        );
+    }
+
+    #[test]
+    fn test_unresolved_infer_placeholder() {
+        let diagnostics = check_source("fn f(a: _) -> Vec<_> { var x: _; panic(\"todo\"); }");
+        let messages: Vec<(&str, usize)> = diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), d.span.column))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("cannot infer the type of `_`", 9),
+                ("cannot infer the type of `_`", 19),
+                ("cannot infer the type of `_`", 31),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_array_lengths_compare_by_value() {
+        let source = "const N: i32 = 2 + 2; fn f() { let a: [i32; N] = [1, 2, 3, 4]; }";
+        assert!(check_source(source).is_empty());
+
+        let diagnostics =
+            check_source("const N: i32 = 2 + 2; fn f() { let a: [i32; N] = [1, 2, 3]; }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `[i32; N]`, found `[i32; 3]`"
+        );
+    }
+
+    #[test]
+    fn test_uninferable_placeholder_does_not_cascade() {
+        let messages = |source: &str| -> Vec<String> {
+            check_source(source)
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect()
+        };
+        assert_eq!(
+            messages("fn f(x: _) -> _ { x }"),
+            vec![
+                "cannot infer the type of `_`",
+                "cannot infer the type of `_`"
+            ]
+        );
+        assert_eq!(
+            messages(
+                "fn f(x: _) -> (_, i32) { let y = x + 1; return; }\n\
+                 fn g() { let z: bool = f(1); var w: _; let v: i32 = w; }"
+            ),
+            vec![
+                "cannot infer the type of `_`",
+                "cannot infer the type of `_`",
+                "cannot infer the type of `_`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tuple_literal_type() {
+        assert!(check_source("fn f() { var t: (i32, bool) = (1, true,); }").is_empty());
+
+        let diagnostics = check_source("fn f() { var t: (i32, bool) = (1, 2); }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `(i32, bool)`, found `(i32, i32)`"
+        );
+    }
+
+    #[test]
+    fn test_tuple_rest_pattern_binds_outer_elements() {
+        let source = "fn last(t: (i32, bool, bool, u8)) -> u8 { var (first, .., z) = t; z }";
+        assert!(check_source(source).is_empty());
+
+        let diagnostics =
+            check_source("fn last(t: (i32, bool, u8)) { var (.., z) = t; var flag: bool = z; }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `bool`, found `u8`"
+        );
+    }
+
+    #[test]
+    fn test_tuple_pattern_arity_mismatch_is_reported() {
+        let diagnostics = check_source("fn f(t: (i32, bool, u8)) { var (a, b) = t; }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected a tuple with 2 elements, found one with 3"
+        );
+
+        let diagnostics = check_source("fn f(t: (i32, bool)) { var (a, .., b, c) = t; }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected a tuple with at least 3 elements, found one with 2"
+        );
+    }
+
+    #[test]
+    fn test_enum_repr_overflow_is_reported() {
+        assert!(check_source("#[repr(u8)]\nenum E { A = 255 }").is_empty());
+
+        let diagnostics = check_source("#[repr(i8)]\nenum E { A = -128, B = 128 }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "discriminant 128 of `E::B` does not fit in `#[repr(i8)]`"
+        );
+    }
+
+    #[test]
+    fn test_call_omits_defaulted_argument() {
+        let source = "fn scale(x: i32, factor: i32 = 2) -> i32 { x * factor }\n\
+                      fn main() -> i32 { scale(3) + scale(3, 4) }";
+        assert!(check_source(source).is_empty());
+
+        let program = crate::parser::parse(source).unwrap();
+        let Declaration::Function(scale) = &program.items[0] else {
+            panic!("expected function");
+        };
+        let filled = fill_default_arguments(&scale.params, &[int(3, Type::I32)]).unwrap();
+        assert_eq!(
+            filled[1],
+            Expression::Literal(Literal::Integer(2, None, IntBase::Dec))
+        );
+    }
+
+    #[test]
+    fn test_call_missing_required_argument() {
+        let diagnostics =
+            check_source("fn scale(x: i32, factor: i32 = 2) {}\nfn main() { scale(); }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`scale` takes 2 arguments but 0 were supplied; `x` has no default"
+        );
+    }
+
+    #[test]
+    fn test_non_trailing_default_parameter() {
+        let diagnostics = check_source("fn f(a: i32 = 0, b: i32) {}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "parameter `a` has a default but `b` after it does not; only trailing parameters may have defaults"
+        );
+    }
+
+    #[test]
+    fn test_infer_type_parameter_from_argument() {
+        let diagnostics = check_source(
+            "fn id<T>(x: T) -> T { x }\n\
+             fn main() -> i32 { id(5) }",
+        );
+        assert!(diagnostics.is_empty());
+
+        let diagnostics = check_source(
+            "fn id<T>(x: T) -> T { x }\n\
+             fn main() { var flag: bool = id(5); }",
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `bool`, found `i32`"
+        );
+    }
+
+    #[test]
+    fn test_cannot_infer_unused_type_parameter() {
+        let diagnostics = check_source(
+            "fn make<T>() -> i32 { 0 }\n\
+             fn main() { make(); }",
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "cannot infer `T` in call to `make`");
+    }
+
+    #[test]
+    fn test_bitwise_and_on_integers() {
+        let mut checker = Checker::new();
+        let expr = binary(
+            int(255, Type::U8),
+            BinaryOperator::BitAnd,
+            int(15, Type::U8),
+        );
+
+        assert_eq!(checker.check_expression(&expr), Some(Type::U8));
+        assert!(checker.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_bitwise_on_float_is_error() {
+        let mut checker = Checker::new();
+        let float = Expression::Literal(Literal::Float(1.5, None));
+        let expr = binary(
+            float,
+            BinaryOperator::BitAnd,
+            Expression::Literal(Literal::Integer(2, None, IntBase::Dec)),
+        );
+
+        assert_eq!(checker.check_expression(&expr), None);
+        assert_eq!(
+            checker.diagnostics()[0].message,
+            "operator `&` expects integer operands, found `f64` and `i32`"
+        );
+
+        let mut checker = Checker::new();
+        let expr = binary(
+            Expression::Literal(Literal::Boolean(true)),
+            BinaryOperator::Shl,
+            Expression::Literal(Literal::Integer(1, None, IntBase::Dec)),
+        );
+        checker.check_expression(&expr);
+        assert_eq!(
+            checker.diagnostics()[0].message,
+            "operator `<<` expects integer operands, found `bool` and `i32`"
+        );
+    }
+
+    #[test]
+    fn test_oversized_shift_warns() {
+        let mut checker = Checker::new();
+        let expr = binary(int(1, Type::U8), BinaryOperator::Shl, int(9, Type::U32));
+
+        assert_eq!(checker.check_expression(&expr), Some(Type::U8));
+        assert_eq!(checker.diagnostics().len(), 1);
+        assert_eq!(checker.diagnostics()[0].severity, Severity::Warning);
+        assert_eq!(
+            checker.diagnostics()[0].message,
+            "shift amount 9 is out of range for `u8`, which is 8 bits wide"
+        );
+    }
+
+    #[test]
+    fn test_constant_errors_are_reported_once() {
+        let diagnostics = check_source(
+            "const A: i32 = B; const B: i32 = A;\n\
+             const C: i32 = 1 / 0; const D: i32 = C + 1;\n\
+             const N: i32 = 2 + 2;",
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "cycle in constant definitions: `A` -> `B` -> `A`",
+                "division by zero in constant expression",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_initializers_must_be_pure() {
+        assert!(check_source("const N: i32 = 2 + 3;").is_empty());
+
+        let diagnostics = check_source(
+            "fn rand() -> i32 { 4 }\n\
+             const N: i32 = rand(); const M: i32 = N + 1;\n\
+             static S: i32 = rand();",
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "cannot call functions in a `const` initializer",
+                "cannot call functions in a `static` initializer",
+            ]
+        );
+        assert_eq!(diagnostics[0].span.line, 2);
+        assert_eq!(diagnostics[0].span.column, 16);
+    }
+
+    #[test]
+    fn test_while_body_must_be_unit() {
+        let diagnostics = check_source("fn f(c: bool) {\n    while c { 5 }\n}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`while` loop body must have type `()`, found `i32`"
+        );
+        assert_eq!(diagnostics[0].span.line, 2);
+
+        assert!(check_source("fn f(c: bool) { var mut n = 0; while c { n += 1; } }").is_empty());
+    }
+
+    #[test]
+    fn test_condition_must_be_bool() {
+        let diagnostics = check_source("fn f() {\n    if 5 {}\n}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "expected `bool`, found `i32`");
+        assert_eq!(diagnostics[0].span.line, 2);
+
+        assert!(check_source("fn f(x: bool) { while x {} }").is_empty());
+
+        let diagnostics = check_source("fn f(n: i32) -> i32 { match n { m if m => 1, _ => 0 } }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "expected `bool`, found `i32`");
+        assert_eq!(diagnostics[0].span.column, 38);
+    }
+
+    #[test]
+    fn test_loop_value_comes_from_break() {
+        assert!(check_source("fn f() -> i32 { let x: i32 = loop { break 5; }; x }").is_empty());
+
+        let diagnostics = check_source("fn f() { let x: bool = loop { break 5; }; }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `bool`, found `i32`"
+        );
+    }
+
+    #[test]
+    fn test_bare_break_must_match_loop_value() {
+        let diagnostics =
+            check_source("fn f(c: bool) { loop { if c { break 1; } else { break; } } }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `i32`, found `()`"
+        );
+        assert_eq!(diagnostics[0].span.column, 49);
+
+        let diagnostics =
+            check_source("fn f(c: bool) { loop { if c { break; } else { break 1; } } }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `()`, found `i32`"
+        );
+
+        assert!(check_source("fn f(c: bool) { loop { if c { break; } break (); } }").is_empty());
+    }
+
+    #[test]
+    fn test_break_with_value_outside_loop() {
+        let diagnostics =
+            check_source("fn f(c: bool) -> i32 { loop { while c { break 1; } break 2; } }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`break` with a value is only allowed inside `loop`"
+        );
+    }
+
+    #[test]
+    fn test_missing_return_on_one_branch() {
+        let diagnostics = check_source("fn f(c: bool) -> i32 {\n    if c { return 1; }\n}");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "function `f` must return a value of type `i32` on every path, but can reach the end of its body"
+        );
+
+        assert!(
+            check_source("fn f(c: bool) -> i32 { if c { return 1; } else { return 2; } }")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_tail_expression_return() {
+        assert!(check_source("fn f(c: bool) -> i32 { if c { return 1; } 2 }").is_empty());
+        assert!(check_source("fn f(c: bool) -> i32 { if c { 1 } else { return 2; } }").is_empty());
+
+        let diagnostics = check_source("fn f() -> i32 { true }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `i32`, found `bool`"
+        );
+    }
+
+    #[test]
+    fn test_return_outside_function() {
+        assert!(crate::parser::parse("return 1;").is_err());
+
+        let source = "const X: i32 = { return 1; };\nstatic Y: i32 = { 2 };";
+        // The initializer is also not a constant expression; only the
+        // `return` matters here.
+        let diagnostics: Vec<Diagnostic> = check_source(source)
+            .into_iter()
+            .filter(|diagnostic| diagnostic.message.contains("`return`"))
+            .collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`return` outside of a function body"
+        );
+        assert_eq!(diagnostics[0].span.slice(source), "return 1;");
+
+        assert!(check_source("fn f() -> i32 { let x = { return 1; }; x }").is_empty());
+    }
+
+    #[test]
+    fn test_return_value_must_match() {
+        let diagnostics = check_source("fn f() -> i32 { return true; }\nfn g() -> i32 { return; }");
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "mismatched types: expected `i32`, found `bool`",
+                "`return` without a value in a function returning `i32`",
+            ]
+        );
+    }
+
+    fn check_widening(source: &str) -> (Vec<Diagnostic>, Vec<ImplicitCast>) {
+        let mut checker = Checker::with_implicit_widening(true);
+        checker.check_program(&crate::parser::parse(source).unwrap());
+        let casts = checker.implicit_casts().to_vec();
+        (checker.into_diagnostics(), casts)
+    }
+
+    #[test]
+    fn test_implicit_widening_is_opt_in() {
+        let source = "fn g(x: i64) {}\nfn f(a: i32) { let x: i64 = a; g(a); }";
+        let (diagnostics, casts) = check_widening(source);
+        assert!(diagnostics.is_empty());
+        assert_eq!(casts.len(), 2);
+        assert_eq!(casts[0].from, Type::I32);
+        assert_eq!(casts[0].to, Type::I64);
+
+        let diagnostics = check_source(source);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `i64`, found `i32`"
+        );
+    }
+
+    #[test]
+    fn test_narrowing_is_always_rejected() {
+        let source = "fn f(a: i64) { let x: i32 = a; var mut y: i32 = 0; y = a; }";
+        let (diagnostics, casts) = check_widening(source);
+        assert!(casts.is_empty());
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "mismatched types: expected `i32`, found `i64`",
+                "mismatched types: `i32` = `i64`",
+            ]
+        );
+        assert_eq!(check_source(source).len(), 2);
+    }
+
+    #[test]
+    fn test_implicit_casts_are_inserted() {
+        let source = "fn f(a: i32, b: f32) { var mut x: i64 = 0; x = a; let y: f64 = b; }";
+        let mut program = crate::parser::parse(source).unwrap();
+        let (diagnostics, casts) = check_widening(source);
+        assert!(diagnostics.is_empty());
+
+        widening::insert_implicit_casts(&mut program, &casts);
+        let printed = crate::printer::print_program(&program);
+        assert!(printed.contains("x = a as i64;"), "{}", printed);
+        assert!(printed.contains("var y: f64 = b as f64;"), "{}", printed);
+    }
+
+    #[test]
+    fn test_char_and_bool_arithmetic() {
+        let errors = check_source("fn f() -> i32 { let c = 'a' + 1; 0 }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].message,
+            "`char` does not support `+`; cast to an integer first"
+        );
+
+        let errors = check_source("fn f(b: bool) { let x = true + true; let y = b * false; }");
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(
+            errors[0].message,
+            "`bool` does not support `+`; cast to an integer first"
+        );
+        assert_eq!(
+            errors[1].message,
+            "`bool` does not support `*`; cast to an integer first"
+        );
+
+        // The suggested cast is accepted.
+        assert!(check_source("fn f() -> u32 { ('a' as u32) + 1 }").is_empty());
+    }
+
+    #[test]
+    fn test_lifetimes_do_not_affect_type_equality() {
+        let errors = check_source("fn f(x: &'a i32) -> &i32 { let y: &'b i32 = x; y }");
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_unit_and_empty_tuple_are_equal() {
+        let mut program =
+            crate::parser::parse("fn f() { let x: () = (); let y: i32 = (1,); }").unwrap();
+        let Declaration::Function(function) = &mut program.items[0] else {
+            unreachable!();
+        };
+        let Statement::Let(stmt) = &mut function.body.statements[0] else {
+            unreachable!();
+        };
+        stmt.type_annotation = Some(Type::Tuple(vec![]));
+        assert!(check_program(&program).is_empty());
+
+        let errors = check_source("fn f() { let y: bool = (1,); }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "mismatched types: expected `bool`, found `i32`"
+        );
+    }
+
+    #[test]
+    fn test_never_branch_coerces() {
+        let errors = check_source(
+            "fn f(c: bool) { let x: i32 = if c { 1 } else { panic(\"no\"); }; let y: i32 = if c { return; } else { 2 }; }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let errors =
+            check_source("fn f() { let x: i32 = { return; }; loop { let y: i32 = { break; }; } }");
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // The other branch still has to match.
+        let errors =
+            check_source("fn f(c: bool) { let x: i32 = if c { true } else { panic(\"no\"); }; }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_diverging_match_arm() {
+        let errors = check_source(
+            "fn f(c: i32) -> i32 { let x: i32 = match c { 0 => 1, _ => { return 2; } }; x }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_block_expression_takes_tail_type() {
+        assert!(check_source("fn f() -> i32 { let x = { let y = 1; y }; { x; } x }").is_empty());
+
+        let diagnostics = check_source("fn f() { let x: bool = { let y = 1; y }; }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `bool`, found `i32`"
+        );
+    }
+
+    fn binding_type(source: &str, name: &str) -> Option<Type> {
+        let mut checker = Checker::new();
+        checker.check_program(&crate::parser::parse(source).unwrap());
+        assert!(
+            checker.diagnostics().is_empty(),
+            "{:?}",
+            checker.diagnostics()
+        );
+        let start = source.find(&format!("for {} in", name)).unwrap() + 4;
+        checker
+            .type_at(&Span::new(start, start + name.len(), 1, 1))
+            .cloned()
+    }
+
+    #[test]
+    fn test_for_binds_element_type() {
+        assert_eq!(
+            binding_type("fn f() { for i in 0..10 {} }", "i"),
+            Some(Type::I32)
+        );
+        assert_eq!(
+            binding_type("fn f(n: u8) { for i in 0..=n {} }", "i"),
+            Some(Type::U8)
+        );
+        assert_eq!(
+            binding_type(
+                "fn f(arr: [bool; 3]) { for x in arr { let y: bool = x; } }",
+                "x"
+            ),
+            Some(Type::Bool)
+        );
+        assert_eq!(
+            binding_type("fn f(xs: &[char]) { for x in xs {} }", "x"),
+            Some(Type::Reference(
+                Box::new(Type::Char),
+                Mutability::Immutable,
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_for_over_non_iterable() {
+        let errors = check_source("fn f() { for x in 5 {} }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "`i32` is not iterable; expected a range, array or slice"
+        );
+
+        let errors = check_source(
+            "fn f() { var v = [1, 2]; for x in 0.5..2.0 { let y: i32 = x; } for x in v { let y: bool = x; } }",
+        );
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert_eq!(
+            messages[0],
+            "cannot iterate over a range of `f64`; bounds must be integers"
+        );
+
+        let errors = check_source(
+            "struct List { len: i32 }\nfn f(l: List, r: &i32) { for x in l {} for y in r {} }",
+        );
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "`List` is not iterable; expected a range, array or slice",
+                "`&i32` is not iterable; expected a range, array or slice",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_array_literal_unification() {
+        assert!(
+            check_source(
+                "fn f(a: u8) { var y: [u8; 3] = [1, 2, 3]; var m: [i64; 2] = [1, -2]; var n: [[u8; 2]; 2] = [[1, 2], [a, 4]]; }"
+            )
+            .is_empty()
+        );
+        let errors = check_source("fn f() { var y: [u8; 2] = [1, 300]; var z: [u8; 3] = [1, 2]; }");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[1].message,
+            "mismatched types: expected `[u8; 3]`, found `[i32; 2]`"
+        );
+
+        let source = "fn f() { let a = [1u8, 2, 3]; let b: [u8; 3] = a; }";
+        assert!(
+            check_source(source).is_empty(),
+            "{:?}",
+            check_source(source)
+        );
+        assert_eq!(
+            binding_type("fn f() { for x in [1, 2, 3] {} }", "x"),
+            Some(Type::I32)
+        );
+
+        let errors = check_source("fn f() { let a = [1, true, 'c']; }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "mismatched types in array literal: expected `i32`, found `bool`"
+        );
+        let source = "fn f(flag: bool) { let a = [1, flag]; }";
+        let errors = check_source(source);
+        assert_eq!(errors[0].span.start, source.find("flag]").unwrap());
+
+        let errors = check_source("fn f() { let a = []; let b: [i32; 0] = []; }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "cannot infer the element type of an empty array; add a type annotation"
+        );
+    }
+
+    #[test]
+    fn test_panic_expression_is_never() {
+        let source = "fn f(ok: bool) -> i32 { let checked = ok || panic(\"bad\"); if ok { 1 } else { panic(\"no\") } }";
+        let mut checker = Checker::new();
+        checker.check_program(&crate::parser::parse(source).unwrap());
+        assert!(
+            checker.diagnostics().is_empty(),
+            "{:?}",
+            checker.diagnostics()
+        );
+        let start = source.find("panic(\"bad\")").unwrap();
+        assert_eq!(
+            checker.type_at(&Span::new(start, start + 12, 1, 1)),
+            Some(&Type::Never)
+        );
+        let start = source.find("checked").unwrap();
+        assert_eq!(
+            checker.type_at(&Span::new(start, start + 7, 1, 1)),
+            Some(&Type::Bool)
+        );
+    }
+}
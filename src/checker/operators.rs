@@ -0,0 +1,86 @@
+use crate::ast::expressions::BinaryOperator;
+use crate::ast::{Span, Type};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorImpl {
+    pub method: String,
+    pub rhs: Type,
+    pub output: Type,
+}
+
+// Operator implementations for user types, keyed by the rendered left-hand
+// type and the operator. Stands in for trait impls until those exist.
+#[derive(Debug, Clone, Default)]
+pub struct OperatorTable {
+    impls: HashMap<(String, BinaryOperator), OperatorImpl>,
+}
+
+impl OperatorTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, lhs: &Type, operator: BinaryOperator, rhs: Type, output: Type) {
+        let Some(method) = method_name(&operator) else {
+            return;
+        };
+        self.impls.insert(
+            (lhs.display_qualified(), operator),
+            OperatorImpl {
+                method: method.to_string(),
+                rhs,
+                output,
+            },
+        );
+    }
+
+    pub fn lookup(&self, lhs: &Type, operator: &BinaryOperator) -> Option<&OperatorImpl> {
+        self.impls.get(&(lhs.display_qualified(), operator.clone()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedOperator {
+    pub operator: BinaryOperator,
+    pub method: String,
+    pub output: Type,
+    pub span: Span,
+}
+
+pub fn method_name(operator: &BinaryOperator) -> Option<&'static str> {
+    match operator {
+        BinaryOperator::Add => Some("add"),
+        BinaryOperator::Sub => Some("sub"),
+        BinaryOperator::Mul => Some("mul"),
+        BinaryOperator::Div => Some("div"),
+        BinaryOperator::Rem => Some("rem"),
+        BinaryOperator::BitAnd => Some("bitand"),
+        BinaryOperator::BitOr => Some("bitor"),
+        BinaryOperator::BitXor => Some("bitxor"),
+        BinaryOperator::Shl => Some("shl"),
+        BinaryOperator::Shr => Some("shr"),
+        _ => None,
+    }
+}
+
+pub fn is_user_type(ty: &Type) -> bool {
+    matches!(ty, Type::Named(_) | Type::Generic(_, _))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_lookup() {
+        let mut table = OperatorTable::new();
+        table.register(&Type::I32, BinaryOperator::Add, Type::I32, Type::I32);
+        table.register(&Type::I32, BinaryOperator::Eq, Type::I32, Type::Bool);
+
+        let found = table.lookup(&Type::I32, &BinaryOperator::Add).unwrap();
+        assert_eq!(found.method, "add");
+        assert!(table.lookup(&Type::I32, &BinaryOperator::Sub).is_none());
+        assert!(table.lookup(&Type::I32, &BinaryOperator::Eq).is_none());
+    }
+}
@@ -0,0 +1,102 @@
+use super::is_assignment;
+use crate::ast::expressions::{Expression, UnaryOperator};
+use crate::ast::lookup::NodeRef;
+use crate::diagnostics::Diagnostic;
+use std::collections::HashSet;
+
+// Parts of a `const` or `static` initializer that cannot run at compile time:
+// calls, macro invocations, assignments, `&mut` borrows and reads of a
+// `static mut`. `kind` names the item, as in "`const`". Each is reported at
+// its own span, and nothing inside it is reported again. Loops are allowed as
+// long as their bodies are.
+pub fn check_initializer(
+    value: &Expression,
+    kind: &str,
+    mutable_statics: &HashSet<String>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    collect(
+        NodeRef::Expression(value),
+        kind,
+        mutable_statics,
+        &mut diagnostics,
+    );
+    diagnostics
+}
+
+fn collect(
+    node: NodeRef<'_>,
+    kind: &str,
+    mutable_statics: &HashSet<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let NodeRef::Expression(expr) = node else {
+        for child in node.children() {
+            collect(child, kind, mutable_statics, diagnostics);
+        }
+        return;
+    };
+    let (what, span) = match expr {
+        Expression::Call(call) => ("call functions".to_string(), &call.span),
+        Expression::MacroInvocation(invocation) => ("invoke macros".to_string(), &invocation.span),
+        Expression::Binary(binary) if is_assignment(&binary.operator) => {
+            ("assign".to_string(), &binary.span)
+        }
+        Expression::Unary(unary) if unary.operator == UnaryOperator::RefMut => {
+            ("borrow mutably".to_string(), &unary.span)
+        }
+        Expression::Identifier(ident) if mutable_statics.contains(&ident.name) => {
+            (format!("read `static mut` `{}`", ident), &ident.span)
+        }
+        _ => {
+            for child in node.children() {
+                collect(child, kind, mutable_statics, diagnostics);
+            }
+            return;
+        }
+    };
+    diagnostics.push(Diagnostic::error(
+        format!("cannot {} in a `{}` initializer", what, kind),
+        span.clone(),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Declaration;
+    use crate::parser::parse;
+
+    fn messages(source: &str) -> Vec<String> {
+        let program = parse(source).unwrap();
+        let Some(Declaration::Constant(constant)) = program.items.last() else {
+            panic!("expected a constant");
+        };
+        let statics = HashSet::from(["COUNTER".to_string()]);
+        check_initializer(&constant.value, "const", &statics)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_pure_initializer() {
+        assert!(messages("const N: i32 = 2 + 3;").is_empty());
+        assert!(messages("const N: i32 = { let x = 2; x * 3 };").is_empty());
+    }
+
+    #[test]
+    fn test_impure_initializer() {
+        assert_eq!(
+            messages("const N: i32 = rand(f());"),
+            vec!["cannot call functions in a `const` initializer"]
+        );
+        assert_eq!(
+            messages("const N: i32 = { var mut x = 0; x += 1; COUNTER };"),
+            vec![
+                "cannot assign in a `const` initializer",
+                "cannot read `static mut` `COUNTER` in a `const` initializer",
+            ]
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use crate::ast::expressions::{Literal, MatchArm, Pattern};
+use crate::ast::{Span, Type};
+use crate::diagnostics::Diagnostic;
+use crate::printer::Printer;
+
+// How much of an integer type a single pattern matches.
+enum Coverage {
+    All,
+    // Inclusive bounds; possibly empty, possibly overlapping each other.
+    Intervals(Vec<(i128, i128)>),
+    // A pattern this check does not understand.
+    Unknown,
+}
+
+// Checks the arms of a `match` on a value of integer type `ty`, whose values
+// are `min..=max`: an arm overlapping values an earlier arm already matches
+// is warned about, and values no arm matches make the match non-exhaustive.
+// Guarded arms may not match, so they cover nothing.
+pub fn check_integer_match(
+    ty: &Type,
+    (min, max): (i128, i128),
+    arms: &[MatchArm],
+    span: &Span,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut covered: Vec<(i128, i128)> = Vec::new();
+    let mut exhaustive = false;
+    for arm in arms.iter().filter(|arm| arm.guard.is_none()) {
+        match coverage(&arm.pattern) {
+            Coverage::All => exhaustive = true,
+            Coverage::Intervals(intervals) => {
+                // A negative literal can never match an unsigned value, nor
+                // `300` a `u8`.
+                if intervals
+                    .iter()
+                    .any(|&(start, end)| start < min || end > max)
+                {
+                    diagnostics.push(Diagnostic::error(
+                        format!(
+                            "pattern `{}` is out of range for `{}`",
+                            print_pattern(&arm.pattern),
+                            ty
+                        ),
+                        arm.span.clone(),
+                    ));
+                }
+                for (start, end) in intervals {
+                    let (start, end) = (start.max(min), end.min(max));
+                    if start > end {
+                        continue;
+                    }
+                    let overlap = covered
+                        .iter()
+                        .filter_map(|&(a, b)| {
+                            let (a, b) = (a.max(start), b.min(end));
+                            (a <= b).then_some((a, b))
+                        })
+                        .next();
+                    if let Some(overlap) = overlap {
+                        diagnostics.push(Diagnostic::warning(
+                            format!(
+                                "pattern `{}` overlaps `{}`, already matched by an earlier arm",
+                                print_pattern(&arm.pattern),
+                                interval(ty, overlap, (min, max))
+                            ),
+                            arm.span.clone(),
+                        ));
+                    }
+                    insert(&mut covered, (start, end));
+                }
+            }
+            Coverage::Unknown => return Vec::new(),
+        }
+    }
+    if exhaustive {
+        return diagnostics;
+    }
+
+    let gaps = gaps(&covered, (min, max));
+    if !gaps.is_empty() {
+        let mut listed: Vec<String> = gaps
+            .iter()
+            .take(3)
+            .map(|&gap| format!("`{}`", interval(ty, gap, (min, max))))
+            .collect();
+        if gaps.len() > 3 {
+            listed.push(format!("{} more", gaps.len() - 3));
+        }
+        let listed = match listed.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+            _ => listed.join(""),
+        };
+        diagnostics.push(Diagnostic::error(
+            format!("non-exhaustive patterns: {} not covered", listed),
+            span.clone(),
+        ));
+    }
+    diagnostics
+}
+
+fn coverage(pattern: &Pattern) -> Coverage {
+    match pattern {
+        Pattern::Wildcard | Pattern::Identifier(_) => Coverage::All,
+        Pattern::Literal(Literal::Integer(value, _, _)) => {
+            Coverage::Intervals(vec![(*value, *value)])
+        }
+        Pattern::Range(start, end) => match (start.as_ref(), end.as_ref()) {
+            (
+                Pattern::Literal(Literal::Integer(start, _, _)),
+                Pattern::Literal(Literal::Integer(end, _, _)),
+            ) => Coverage::Intervals(vec![(*start, *end)]),
+            _ => Coverage::Unknown,
+        },
+        Pattern::Or(alternatives) => {
+            let mut intervals = Vec::new();
+            for alternative in alternatives {
+                match coverage(alternative) {
+                    Coverage::All => return Coverage::All,
+                    Coverage::Intervals(more) => intervals.extend(more),
+                    Coverage::Unknown => return Coverage::Unknown,
+                }
+            }
+            Coverage::Intervals(intervals)
+        }
+        _ => Coverage::Unknown,
+    }
+}
+
+// Adds `new` to the sorted, disjoint and non-adjacent `covered`, merging it
+// with any interval it touches.
+fn insert(covered: &mut Vec<(i128, i128)>, (mut start, mut end): (i128, i128)) {
+    covered.retain(|&(a, b)| {
+        let touches = a <= end.saturating_add(1) && start <= b.saturating_add(1);
+        if touches {
+            start = start.min(a);
+            end = end.max(b);
+        }
+        !touches
+    });
+    let at = covered.partition_point(|&(a, _)| a < start);
+    covered.insert(at, (start, end));
+}
+
+fn gaps(covered: &[(i128, i128)], (min, max): (i128, i128)) -> Vec<(i128, i128)> {
+    let mut gaps = Vec::new();
+    let mut next = Some(min);
+    for &(start, end) in covered {
+        if let Some(from) = next
+            && from < start
+        {
+            gaps.push((from, start - 1));
+        }
+        next = end.checked_add(1).filter(|&value| value <= max);
+    }
+    if let Some(from) = next {
+        gaps.push((from, max));
+    }
+    gaps
+}
+
+fn interval(ty: &Type, (start, end): (i128, i128), bounds: (i128, i128)) -> String {
+    if start == end {
+        bound(ty, start, bounds)
+    } else {
+        format!("{}..={}", bound(ty, start, bounds), bound(ty, end, bounds))
+    }
+}
+
+// `i32::MIN` and `i32::MAX` read better than their values; an unsigned
+// type's minimum is just `0`.
+fn bound(ty: &Type, value: i128, (min, max): (i128, i128)) -> String {
+    if value == max {
+        format!("{}::MAX", ty)
+    } else if value == min && min != 0 {
+        format!("{}::MIN", ty)
+    } else {
+        value.to_string()
+    }
+}
+
+fn print_pattern(pattern: &Pattern) -> String {
+    let mut printer = Printer::new();
+    printer.print_pattern(pattern);
+    printer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checker::check_program;
+    use crate::diagnostics::Severity;
+    use crate::parser::parse;
+
+    fn check_source(source: &str) -> Vec<Diagnostic> {
+        check_program(&parse(source).unwrap())
+    }
+
+    #[test]
+    fn test_overlapping_ranges_warn() {
+        let diagnostics =
+            check_source("fn f(x: u8) -> i32 { match x { 0..=9 => 1, 5..=14 => 2, _ => 3 } }");
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].message,
+            "pattern `5..=14` overlaps `5..=9`, already matched by an earlier arm"
+        );
+    }
+
+    #[test]
+    fn test_gap_is_non_exhaustive() {
+        let diagnostics =
+            check_source("fn f(x: u8) -> i32 { match x { 0..=9 => 1, 11..=255 => 2 } }");
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert!(diagnostics[0].is_error());
+        assert_eq!(
+            diagnostics[0].message,
+            "non-exhaustive patterns: `10` not covered"
+        );
+
+        let diagnostics = check_source(
+            "fn f(x: i32) -> i32 { match x { -5..=-1 | 1 => 1, 3..=7 => 2, n if n > 7 => 3 } }",
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "non-exhaustive patterns: `i32::MIN..=-6`, `0`, `2` and 1 more not covered"
+        );
+    }
+
+    #[test]
+    fn test_negative_literal_patterns() {
+        assert!(
+            check_source(
+                "fn f(x: i32) -> i32 { match x { -1 => 0, -9..=-2 | 0 => 1, -2147483648..=-10 => 2, _ => 3 } }"
+            )
+            .is_empty()
+        );
+
+        let diagnostics =
+            check_source("fn f(x: u8) -> i32 { match x { -1 => 0, 0..=300 => 1, _ => 2 } }");
+        let messages: Vec<&str> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "pattern `-1` is out of range for `u8`",
+                "pattern `0..=300` is out of range for `u8`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_full_coverage() {
+        assert!(check_source("fn f(x: i32) -> i32 { match x { 0..=9 => 1, _ => 2 } }").is_empty());
+        assert!(
+            check_source("fn f(x: u8) -> i32 { match x { 0..=127 => 1, 128..=255 => 2 } }")
+                .is_empty()
+        );
+        assert!(
+            check_source("fn f(x: i8) -> i32 { match x { -128..=-1 => 1, 0 | 1..=127 => 2 } }")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_128_bit_scrutinee() {
+        let diagnostics = check_source("fn f(x: i128) -> i32 { match x { 0 => 1, 1..=5 => 2 } }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "non-exhaustive patterns: `i128::MIN..=-1` and `6..=i128::MAX` not covered"
+        );
+        assert!(check_source("fn f(x: u128) -> i32 { match x { 0 => 1, _ => 2 } }").is_empty());
+    }
+}
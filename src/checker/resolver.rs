@@ -0,0 +1,611 @@
+use super::scope::{BindingId, Interner, Scopes};
+use crate::ast::expressions::{BinaryOperator, Expression, Literal, MatchArm, Pattern};
+use crate::ast::statements::{ElseBranch, IfStatement, Statement};
+use crate::ast::{Block, Declaration, FunctionDecl, Identifier, Program, Span, Type};
+use crate::diagnostics::Diagnostic;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Parameter,
+    Let,
+    Pattern,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub name: String,
+    pub kind: BindingKind,
+    pub span: Span,
+    // Spans of every read of this binding; assignments to it are not reads.
+    pub uses: Vec<Span>,
+}
+
+// Local bindings of every function in a program and where each is read.
+// Names that are not locals resolve to items of the program, then to symbols
+// the host supplies; any other name is unresolved.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Resolution {
+    pub bindings: Vec<Binding>,
+    // Host symbols the program uses, each once, in order of first use.
+    pub externals: Vec<ExternalSymbol>,
+    pub unresolved: Vec<UnresolvedName>,
+    // `break`/`continue` labels naming no enclosing loop.
+    pub undeclared_labels: Vec<Identifier>,
+}
+
+impl Resolution {
+    pub fn unresolved_diagnostics(&self) -> Vec<Diagnostic> {
+        let names = self.unresolved.iter().map(|unresolved| {
+            let mut message = format!("cannot find `{}` in this scope", unresolved.name);
+            if let Some(suggestion) = &unresolved.suggestion {
+                message.push_str(&format!("; did you mean `{}`?", suggestion));
+            }
+            Diagnostic::error(message, unresolved.name.span.clone())
+        });
+        let labels = self.undeclared_labels.iter().map(|label| {
+            Diagnostic::error(
+                format!("use of undeclared label `'{}`", label),
+                label.span.clone(),
+            )
+        });
+        names.chain(labels).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnresolvedName {
+    pub name: Identifier,
+    // The closest name in scope where it was used, if any is close enough to
+    // be a likely typo.
+    pub suggestion: Option<String>,
+}
+
+// Symbols an embedding host provides without declaring them in source, such
+// as a built-in `print`.
+pub trait Resolver {
+    fn resolve_external(&self, name: &str) -> Option<ExternalSymbol>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalSymbol {
+    pub name: String,
+    pub kind: ExternalKind,
+    // `None` when the host does not describe the symbol's type.
+    pub ty: Option<Type>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalKind {
+    Function,
+    Value,
+    Type,
+}
+
+// Supplies nothing; every name must be declared in the program.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmptyResolver;
+
+impl Resolver for EmptyResolver {
+    fn resolve_external(&self, _name: &str) -> Option<ExternalSymbol> {
+        None
+    }
+}
+
+pub fn resolve_program(program: &Program) -> Resolution {
+    resolve_program_with(program, &EmptyResolver)
+}
+
+pub fn resolve_program_with(program: &Program, external: &dyn Resolver) -> Resolution {
+    let mut resolver = NameResolver::new(external);
+    collect_item_names(&program.items, &mut resolver.items);
+    resolver.resolve_items(&program.items);
+    resolver.finish()
+}
+
+// Resolves one function on its own. Without the rest of the program, names of
+// other items are unresolved unless `EmptyResolver` is replaced by one that
+// knows them.
+pub fn resolve_function(function: &FunctionDecl) -> Resolution {
+    let mut resolver = NameResolver::new(&EmptyResolver);
+    resolver.resolve_function(function);
+    resolver.finish()
+}
+
+// Items are visible everywhere in the program, wherever they are declared.
+fn collect_item_names(items: &[Declaration], names: &mut HashSet<String>) {
+    for item in items {
+        let name = match item {
+            Declaration::Function(decl) => &decl.name,
+            Declaration::Struct(decl) => &decl.name,
+            Declaration::Enum(decl) => &decl.name,
+            Declaration::Union(decl) => &decl.name,
+            Declaration::Variable(decl) => &decl.name,
+            Declaration::Constant(decl) => &decl.name,
+            Declaration::Static(decl) => &decl.name,
+            Declaration::Macro(decl) => &decl.name,
+            Declaration::Module(module) => {
+                if let Some(items) = &module.items {
+                    collect_item_names(items, names);
+                }
+                &module.name
+            }
+        };
+        names.insert(name.name.clone());
+    }
+}
+
+struct NameResolver<'r> {
+    bindings: Vec<Binding>,
+    interner: Interner,
+    scopes: Scopes,
+    items: HashSet<String>,
+    external: &'r dyn Resolver,
+    externals: Vec<ExternalSymbol>,
+    unresolved: Vec<UnresolvedName>,
+    // Labels of the loops enclosing the current point, innermost last.
+    labels: Vec<String>,
+    undeclared_labels: Vec<Identifier>,
+}
+
+impl<'r> NameResolver<'r> {
+    fn new(external: &'r dyn Resolver) -> Self {
+        Self {
+            bindings: Vec::new(),
+            interner: Interner::default(),
+            scopes: Scopes::default(),
+            items: HashSet::new(),
+            external,
+            externals: Vec::new(),
+            unresolved: Vec::new(),
+            labels: Vec::new(),
+            undeclared_labels: Vec::new(),
+        }
+    }
+
+    fn finish(self) -> Resolution {
+        Resolution {
+            bindings: self.bindings,
+            externals: self.externals,
+            unresolved: self.unresolved,
+            undeclared_labels: self.undeclared_labels,
+        }
+    }
+
+    fn resolve_items(&mut self, items: &[Declaration]) {
+        for item in items {
+            match item {
+                Declaration::Function(function) => self.resolve_function(function),
+                Declaration::Module(module) => {
+                    if let Some(items) = &module.items {
+                        self.resolve_items(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, function: &FunctionDecl) {
+        self.scopes.push();
+        for param in &function.params {
+            self.declare(&param.name.name, BindingKind::Parameter, &param.name.span);
+        }
+        self.resolve_block(&function.body);
+        self.scopes.pop();
+    }
+
+    fn in_loop(&mut self, label: &Option<Identifier>, body: impl FnOnce(&mut Self)) {
+        match label {
+            Some(label) => {
+                self.labels.push(label.name.clone());
+                body(self);
+                self.labels.pop();
+            }
+            None => body(self),
+        }
+    }
+
+    fn use_label(&mut self, label: &Option<Identifier>) {
+        if let Some(label) = label
+            && !self.labels.contains(&label.name)
+        {
+            self.undeclared_labels.push(label.clone());
+        }
+    }
+
+    fn resolve_block(&mut self, block: &Block) {
+        self.scopes.push();
+        for statement in &block.statements {
+            self.resolve_statement(statement);
+        }
+        if let Some(tail) = &block.tail {
+            self.resolve_expression(tail);
+        }
+        self.scopes.pop();
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Empty => {}
+            Statement::Continue(stmt) => self.use_label(&stmt.label),
+            Statement::Expression(expr) => self.resolve_expression(expr),
+            Statement::Let(stmt) => {
+                if let Some(initializer) = &stmt.initializer {
+                    self.resolve_expression(initializer);
+                }
+                self.bind_pattern(&stmt.pattern, BindingKind::Let);
+            }
+            Statement::Return(stmt) => {
+                if let Some(expr) = &stmt.expression {
+                    self.resolve_expression(expr);
+                }
+            }
+            Statement::Break(stmt) => {
+                self.use_label(&stmt.label);
+                if let Some(expr) = &stmt.expression {
+                    self.resolve_expression(expr);
+                }
+            }
+            Statement::While(stmt) => {
+                self.resolve_expression(&stmt.condition);
+                self.in_loop(&stmt.label, |r| r.resolve_block(&stmt.body));
+            }
+            Statement::For(stmt) => {
+                self.resolve_expression(&stmt.iterator);
+                self.scopes.push();
+                self.bind_pattern(&stmt.pattern, BindingKind::Pattern);
+                self.in_loop(&stmt.label, |r| r.resolve_block(&stmt.body));
+                self.scopes.pop();
+            }
+            Statement::Loop(stmt) => self.in_loop(&stmt.label, |r| r.resolve_block(&stmt.body)),
+            Statement::Block(block) => self.resolve_block(block),
+            Statement::If(stmt) => self.resolve_if_statement(stmt),
+            Statement::Match(stmt) => {
+                self.resolve_expression(&stmt.expression);
+                self.resolve_match_arms(&stmt.arms);
+            }
+            Statement::Panic(stmt) => self.resolve_expression(&stmt.message),
+        }
+    }
+
+    fn resolve_if_statement(&mut self, stmt: &IfStatement) {
+        self.resolve_expression(&stmt.condition);
+        self.resolve_block(&stmt.then_branch);
+        match &stmt.else_branch {
+            Some(ElseBranch::Block(block)) => self.resolve_block(block),
+            Some(ElseBranch::If(nested)) => self.resolve_if_statement(nested),
+            None => {}
+        }
+    }
+
+    fn resolve_match_arms(&mut self, arms: &[MatchArm]) {
+        for arm in arms {
+            self.scopes.push();
+            self.bind_pattern(&arm.pattern, BindingKind::Pattern);
+            if let Some(guard) = &arm.guard {
+                self.resolve_expression(guard);
+            }
+            self.resolve_expression(&arm.body);
+            self.scopes.pop();
+        }
+    }
+
+    fn resolve_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Literal(Literal::Array(elements) | Literal::Tuple(elements)) => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expression::Literal(_) => {}
+            Expression::Identifier(ident) => self.use_name(&ident.name, &ident.span),
+            // Only a single-segment path can name a local; longer ones name items.
+            Expression::Path(path) => {
+                if let [segment] = path.segments.as_slice() {
+                    self.use_name(&segment.ident.name, &segment.ident.span);
+                }
+            }
+            Expression::Paren(inner, _) => self.resolve_expression(inner),
+            Expression::Binary(binary) => {
+                // Plain assignment writes its target without reading it.
+                match (&binary.operator, &binary.left) {
+                    (BinaryOperator::Assign, Expression::Identifier(_)) => {}
+                    _ => self.resolve_expression(&binary.left),
+                }
+                self.resolve_expression(&binary.right);
+            }
+            Expression::Unary(unary) => self.resolve_expression(&unary.operand),
+            Expression::Call(call) => {
+                self.resolve_expression(&call.callee);
+                for argument in &call.arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            Expression::Member(member) => self.resolve_expression(&member.object),
+            Expression::Index(index) => {
+                self.resolve_expression(&index.array);
+                self.resolve_expression(&index.index);
+            }
+            Expression::Cast(cast) => self.resolve_expression(&cast.expr),
+            Expression::Block(block) => self.resolve_block(block),
+            Expression::If(if_expr) => {
+                self.resolve_expression(&if_expr.condition);
+                self.resolve_expression(&if_expr.then_branch);
+                if let Some(else_branch) = &if_expr.else_branch {
+                    self.resolve_expression(else_branch);
+                }
+            }
+            Expression::Match(match_expr) => {
+                self.resolve_expression(&match_expr.value);
+                self.resolve_match_arms(&match_expr.arms);
+            }
+            Expression::Loop(loop_expr) => {
+                self.in_loop(&loop_expr.label, |r| r.resolve_expression(&loop_expr.body))
+            }
+            Expression::While(while_expr) => {
+                self.resolve_expression(&while_expr.condition);
+                self.in_loop(&while_expr.label, |r| {
+                    r.resolve_expression(&while_expr.body)
+                });
+            }
+            Expression::For(for_expr) => {
+                self.resolve_expression(&for_expr.iterator);
+                self.scopes.push();
+                self.bind_pattern(&for_expr.pattern, BindingKind::Pattern);
+                self.in_loop(&for_expr.label, |r| r.resolve_expression(&for_expr.body));
+                self.scopes.pop();
+            }
+            Expression::Range(range) => {
+                if let Some(start) = &range.start {
+                    self.resolve_expression(start);
+                }
+                if let Some(end) = &range.end {
+                    self.resolve_expression(end);
+                }
+            }
+            Expression::MacroInvocation(invocation) => {
+                for argument in &invocation.arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            Expression::Panic(panic) => self.resolve_expression(&panic.message),
+            Expression::Struct(literal) => {
+                for (_, value) in &literal.fields {
+                    self.resolve_expression(value);
+                }
+                if let Some(rest) = &literal.rest {
+                    self.resolve_expression(rest);
+                }
+            }
+        }
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern, kind: BindingKind) {
+        match pattern {
+            Pattern::Identifier(ident) => self.declare(&ident.name, kind, &ident.span),
+            Pattern::Tuple(patterns) => {
+                for pattern in patterns {
+                    self.bind_pattern(pattern, kind);
+                }
+            }
+            Pattern::Struct(_, fields, _) => {
+                for (_, pattern) in fields {
+                    self.bind_pattern(pattern, kind);
+                }
+            }
+            // Every alternative binds the same names; the first one declares them.
+            Pattern::Or(alternatives) => {
+                if let Some(first) = alternatives.first() {
+                    self.bind_pattern(first, kind);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Range(_, _) | Pattern::Wildcard | Pattern::Rest => {}
+        }
+    }
+
+    fn declare(&mut self, name: &str, kind: BindingKind, span: &Span) {
+        let id = BindingId(self.bindings.len());
+        self.bindings.push(Binding {
+            name: name.to_string(),
+            kind,
+            span: span.clone(),
+            uses: Vec::new(),
+        });
+        let symbol = self.interner.intern(name);
+        self.scopes.declare(symbol, id);
+    }
+
+    fn use_name(&mut self, name: &str, span: &Span) {
+        // A name that was never interned was never declared.
+        let found = self
+            .interner
+            .get(name)
+            .and_then(|symbol| self.scopes.lookup(symbol));
+        if let Some(BindingId(index)) = found {
+            self.bindings[index].uses.push(span.clone());
+            return;
+        }
+        if self.items.contains(name) || self.externals.iter().any(|s| s.name == name) {
+            return;
+        }
+        match self.external.resolve_external(name) {
+            Some(symbol) => self.externals.push(symbol),
+            None => {
+                let suggestion = self.suggest(name);
+                self.unresolved.push(UnresolvedName {
+                    name: Identifier::new(name.to_string(), span.clone()),
+                    suggestion,
+                });
+            }
+        }
+    }
+
+    // The visible local, item or host symbol nearest to `name` by edit
+    // distance, if it is at most 2 edits away and shorter than the name, so
+    // `y` is never offered for `x`. Ties go to the alphabetically first.
+    fn suggest(&self, name: &str) -> Option<String> {
+        let locals = self
+            .scopes
+            .visible()
+            .map(|symbol| self.interner.resolve(symbol));
+        let items = self.items.iter().map(String::as_str);
+        let externals = self.externals.iter().map(|symbol| symbol.name.as_str());
+        let length = name.chars().count();
+        locals
+            .chain(items)
+            .chain(externals)
+            .map(|candidate| (edit_distance(name, candidate), candidate))
+            .filter(|&(distance, _)| distance <= 2 && distance < length)
+            .min()
+            .map(|(_, candidate)| candidate.to_string())
+    }
+}
+
+// Levenshtein distance in characters: the fewest single-character
+// insertions, deletions and substitutions turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::types::FunctionAbi;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_shadowed_binding_uses() {
+        let program = parse("fn f(x: i32) -> i32 { let y = x; let y = y + 1; y }").unwrap();
+        let resolution = resolve_program(&program);
+
+        let uses: Vec<(&str, usize)> = resolution
+            .bindings
+            .iter()
+            .map(|binding| (binding.name.as_str(), binding.uses.len()))
+            .collect();
+        assert_eq!(uses, vec![("x", 1), ("y", 1), ("y", 1)]);
+        assert_eq!(resolution.bindings[0].kind, BindingKind::Parameter);
+    }
+
+    #[test]
+    fn test_assignment_is_not_a_read() {
+        let program = parse("fn f() { var mut a = 0; a = 1; var mut b = 0; b += 1; }").unwrap();
+        let resolution = resolve_program(&program);
+
+        assert!(resolution.bindings[0].uses.is_empty());
+        assert_eq!(resolution.bindings[1].uses.len(), 1);
+    }
+
+    #[test]
+    fn test_resolves_thousands_of_bindings() {
+        let mut source = String::from("fn f() {\n");
+        for i in 0..5000 {
+            source.push_str(&format!("    var v{} = {};\n", i, i));
+        }
+        for i in (0..5000).step_by(2) {
+            source.push_str(&format!("    {{ var w = v{}; }}\n", i));
+        }
+        source.push('}');
+        let resolution = resolve_program(&parse(&source).unwrap());
+
+        assert_eq!(resolution.bindings.len(), 7500);
+        assert_eq!(resolution.bindings[0].uses.len(), 1);
+        assert!(resolution.bindings[1].uses.is_empty());
+        assert_eq!(resolution.bindings[4998].uses.len(), 1);
+    }
+
+    struct Host;
+
+    impl Resolver for Host {
+        fn resolve_external(&self, name: &str) -> Option<ExternalSymbol> {
+            (name == "print").then(|| ExternalSymbol {
+                name: name.to_string(),
+                kind: ExternalKind::Function,
+                ty: Some(Type::Function(
+                    vec![Type::I32],
+                    Box::new(Type::Unit),
+                    FunctionAbi::default(),
+                )),
+            })
+        }
+    }
+
+    #[test]
+    fn test_external_symbols() {
+        let program = parse("fn helper() {} fn main() { helper(); print(1); print(2); }").unwrap();
+
+        let resolution = resolve_program_with(&program, &Host);
+        assert!(resolution.unresolved.is_empty());
+        assert_eq!(resolution.externals.len(), 1);
+        assert_eq!(resolution.externals[0].name, "print");
+
+        let resolution = resolve_program(&program);
+        assert!(resolution.externals.is_empty());
+        let diagnostics = resolution.unresolved_diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "cannot find `print` in this scope");
+        assert_eq!(diagnostics[0].span.column, 38);
+    }
+
+    #[test]
+    fn test_unresolved_name_suggestions() {
+        let program = parse(
+            "fn helper() {}\n\
+             fn f(length: i32) -> i32 { helpr(); { let total = 1; } lenght + totl + zebra }",
+        )
+        .unwrap();
+        let diagnostics = resolve_program(&program).unresolved_diagnostics();
+        let messages: Vec<&str> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "cannot find `helpr` in this scope; did you mean `helper`?",
+                "cannot find `lenght` in this scope; did you mean `length`?",
+                "cannot find `totl` in this scope",
+                "cannot find `zebra` in this scope",
+            ]
+        );
+
+        assert_eq!(edit_distance("lenght", "length"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_labeled_break_across_nested_loops() {
+        let program = parse(
+            "fn f(c: bool) { 'outer: loop { while c { for i in 0..3 { break 'outer; } continue 'outer; } } }",
+        )
+        .unwrap();
+        let resolution = resolve_program(&program);
+        assert!(resolution.undeclared_labels.is_empty());
+        assert!(resolution.unresolved_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_undeclared_label() {
+        // A label is only in scope inside its own loop.
+        let program =
+            parse("fn f() { 'a: loop { break 'a; } loop { break 'missing; } loop { break 'a; } }")
+                .unwrap();
+        let diagnostics = resolve_program(&program).unresolved_diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "use of undeclared label `'missing`");
+        assert_eq!(diagnostics[1].message, "use of undeclared label `'a`");
+        assert_eq!(diagnostics[0].span.column, 46);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::ast::expressions::{Expression, MatchArm};
+use crate::ast::lookup::NodeRef;
+use crate::ast::statements::{ElseBranch, IfStatement, Statement};
+use crate::ast::{Block, Identifier};
+
+// Whether control can never reach the end of `block`: every path through it
+// returns, panics or loops forever. A `break` or `continue` leaves the
+// enclosing loop, not the function, so it does not count.
+pub fn block_diverges(block: &Block) -> bool {
+    block.statements.iter().any(statement_diverges)
+        || block.tail.as_deref().is_some_and(expression_diverges)
+}
+
+pub fn statement_diverges(statement: &Statement) -> bool {
+    match statement {
+        Statement::Return(_) | Statement::Panic(_) => true,
+        Statement::Expression(expr) => expression_diverges(expr),
+        Statement::Let(stmt) => stmt.initializer.as_ref().is_some_and(expression_diverges),
+        Statement::Block(block) => block_diverges(block),
+        Statement::If(stmt) => if_diverges(stmt),
+        Statement::Match(stmt) => expression_diverges(&stmt.expression) || arms_diverge(&stmt.arms),
+        // The condition may be false on entry, and the iterator empty.
+        Statement::While(stmt) => expression_diverges(&stmt.condition),
+        Statement::For(stmt) => expression_diverges(&stmt.iterator),
+        Statement::Loop(stmt) => !breaks_out(NodeRef::Block(&stmt.body), &stmt.label),
+        Statement::Empty | Statement::Break(_) | Statement::Continue(_) => false,
+    }
+}
+
+pub fn expression_diverges(expr: &Expression) -> bool {
+    match expr {
+        Expression::Panic(_) => true,
+        Expression::Block(block) => block_diverges(block),
+        Expression::Paren(inner, _) => expression_diverges(inner),
+        Expression::If(if_expr) => {
+            expression_diverges(&if_expr.condition)
+                || (expression_diverges(&if_expr.then_branch)
+                    && if_expr
+                        .else_branch
+                        .as_ref()
+                        .is_some_and(expression_diverges))
+        }
+        Expression::Match(match_expr) => {
+            expression_diverges(&match_expr.value) || arms_diverge(&match_expr.arms)
+        }
+        Expression::Loop(loop_expr) => {
+            !breaks_out(NodeRef::Expression(&loop_expr.body), &loop_expr.label)
+        }
+        _ => false,
+    }
+}
+
+fn if_diverges(stmt: &IfStatement) -> bool {
+    if expression_diverges(&stmt.condition) {
+        return true;
+    }
+    block_diverges(&stmt.then_branch)
+        && match &stmt.else_branch {
+            Some(ElseBranch::Block(block)) => block_diverges(block),
+            Some(ElseBranch::If(nested)) => if_diverges(nested),
+            None => false,
+        }
+}
+
+// A `match` with no arms has no way to complete either, but whether it can be
+// reached at all is the exhaustiveness check's concern, not this one's.
+fn arms_diverge(arms: &[MatchArm]) -> bool {
+    !arms.is_empty() && arms.iter().all(|arm| expression_diverges(&arm.body))
+}
+
+// Whether `node`, the body of a loop labeled `label`, contains a `break` that
+// leaves that loop. An unlabeled `break` inside a nested loop leaves the
+// nested one instead.
+fn breaks_out(node: NodeRef<'_>, label: &Option<Identifier>) -> bool {
+    node.children().into_iter().any(|child| match child {
+        NodeRef::Statement(Statement::Break(brk)) => match (&brk.label, label) {
+            (None, _) => true,
+            (Some(target), Some(label)) => target.name == label.name,
+            (Some(_), None) => false,
+        },
+        NodeRef::Statement(Statement::Loop(_) | Statement::While(_) | Statement::For(_))
+        | NodeRef::Expression(Expression::Loop(_) | Expression::While(_) | Expression::For(_)) => {
+            label.is_some() && breaks_out_labeled(child, label)
+        }
+        child => breaks_out(child, label),
+    })
+}
+
+// Inside a nested loop only a `break` naming the outer label leaves it.
+fn breaks_out_labeled(node: NodeRef<'_>, label: &Option<Identifier>) -> bool {
+    node.children().into_iter().any(|child| match child {
+        NodeRef::Statement(Statement::Break(brk)) => {
+            matches!((&brk.label, label), (Some(target), Some(label)) if target.name == label.name)
+        }
+        child => breaks_out_labeled(child, label),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Declaration;
+    use crate::parser::parse;
+
+    fn body(source: &str) -> Block {
+        let program = parse(source).unwrap();
+        let Some(Declaration::Function(function)) = program.items.into_iter().next() else {
+            panic!("expected a function");
+        };
+        function.body
+    }
+
+    #[test]
+    fn test_divergence() {
+        assert!(block_diverges(&body("fn f() { return; }")));
+        assert!(block_diverges(&body(
+            "fn f(c: bool) { if c { return; } else { panic(\"no\"); } }"
+        )));
+        assert!(!block_diverges(&body("fn f(c: bool) { if c { return; } }")));
+        assert!(block_diverges(&body("fn f() { loop { } }")));
+        assert!(!block_diverges(&body("fn f() { loop { break; } }")));
+        // The `break` leaves the inner loop; the outer one never ends.
+        assert!(block_diverges(&body(
+            "fn f(c: bool) { loop { while c { break; } } }"
+        )));
+        assert!(!block_diverges(&body(
+            "fn f() { 'outer: loop { loop { break 'outer; } } }"
+        )));
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{HashMap, HashSet};
+
+// An interned name. Two symbols from the same `Interner` are equal exactly
+// when their strings are, so scopes can hash and compare them as integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+#[derive(Debug, Default)]
+pub struct Interner {
+    symbols: HashMap<String, Symbol>,
+    names: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(name) {
+            return symbol;
+        }
+        let symbol = Symbol(self.names.len() as u32);
+        self.names.push(name.to_string());
+        self.symbols.insert(name.to_string(), symbol);
+        symbol
+    }
+
+    // Looks up a name without interning it; a name never interned cannot be bound.
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+}
+
+// Index of a binding in the resolver's binding list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BindingId(pub usize);
+
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    pub bindings: HashMap<Symbol, BindingId>,
+}
+
+// Nested lexical scopes, innermost last.
+#[derive(Debug, Clone, Default)]
+pub struct Scopes {
+    stack: Vec<Scope>,
+}
+
+impl Scopes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self) {
+        self.stack.push(Scope::default());
+    }
+
+    pub fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // Binds `symbol` in the innermost scope, shadowing any outer binding and
+    // replacing an earlier one in the same scope. Does nothing outside a scope.
+    pub fn declare(&mut self, symbol: Symbol, binding: BindingId) {
+        if let Some(scope) = self.stack.last_mut() {
+            scope.bindings.insert(symbol, binding);
+        }
+    }
+
+    // Every name bound in some enclosing scope, shadowed ones included once.
+    pub fn visible(&self) -> impl Iterator<Item = Symbol> + '_ {
+        let mut seen = HashSet::new();
+        self.stack
+            .iter()
+            .flat_map(|scope| scope.bindings.keys().copied())
+            .filter(move |symbol| seen.insert(*symbol))
+    }
+
+    // Walks outward from the innermost scope; the nearest binding wins.
+    pub fn lookup(&self, symbol: Symbol) -> Option<BindingId> {
+        self.stack
+            .iter()
+            .rev()
+            .find_map(|scope| scope.bindings.get(&symbol).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interner_reuses_symbols() {
+        let mut interner = Interner::new();
+        let x = interner.intern("x");
+        let y = interner.intern("y");
+
+        assert_eq!(interner.intern("x"), x);
+        assert_ne!(x, y);
+        assert_eq!(interner.resolve(y), "y");
+        assert_eq!(interner.get("z"), None);
+    }
+
+    #[test]
+    fn test_inner_scope_shadows_outer() {
+        let mut interner = Interner::new();
+        let x = interner.intern("x");
+        let mut scopes = Scopes::new();
+
+        scopes.push();
+        scopes.declare(x, BindingId(0));
+        scopes.push();
+        scopes.declare(x, BindingId(1));
+        assert_eq!(scopes.lookup(x), Some(BindingId(1)));
+
+        scopes.pop();
+        assert_eq!(scopes.lookup(x), Some(BindingId(0)));
+    }
+
+    #[test]
+    fn test_lookup_falls_through_to_outer_scope() {
+        let mut interner = Interner::new();
+        let (x, y) = (interner.intern("x"), interner.intern("y"));
+        let mut scopes = Scopes::new();
+
+        scopes.push();
+        scopes.declare(x, BindingId(0));
+        scopes.push();
+        scopes.push();
+        scopes.declare(y, BindingId(1));
+
+        assert_eq!(scopes.depth(), 3);
+        assert_eq!(scopes.lookup(x), Some(BindingId(0)));
+        assert_eq!(scopes.lookup(y), Some(BindingId(1)));
+        scopes.pop();
+        assert_eq!(scopes.lookup(y), None);
+    }
+}
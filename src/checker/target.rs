@@ -0,0 +1,60 @@
+use super::consts;
+use super::integer_bits;
+use crate::ast::Type;
+
+// Properties of the machine the program is compiled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetConfig {
+    // Width of `isize`/`usize` and pointers, in bits.
+    pub ptr_width: u32,
+}
+
+impl Default for TargetConfig {
+    fn default() -> Self {
+        Self { ptr_width: 64 }
+    }
+}
+
+impl TargetConfig {
+    pub fn new(ptr_width: u32) -> Self {
+        Self { ptr_width }
+    }
+
+    // Like `integer_bits`, but also sizes `isize`/`usize` for this target.
+    pub fn integer_bits(&self, ty: &Type) -> Option<u32> {
+        match ty {
+            Type::Isize | Type::Usize => Some(self.ptr_width),
+            _ => integer_bits(ty),
+        }
+    }
+
+    pub fn integer_range(&self, ty: &Type) -> Option<(i128, i128)> {
+        let bits = self.integer_bits(ty)?;
+        Some(consts::bits_range(bits, ty.is_signed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer_sized_ranges_follow_width() {
+        let narrow = TargetConfig::new(32);
+        assert_eq!(narrow.integer_bits(&Type::Usize), Some(32));
+        assert_eq!(
+            narrow.integer_range(&Type::Usize),
+            Some((0, u32::MAX as i128))
+        );
+        assert_eq!(
+            TargetConfig::default().integer_range(&Type::Isize),
+            Some((i64::MIN as i128, i64::MAX as i128))
+        );
+        assert_eq!(narrow.integer_range(&Type::U8), Some((0, 255)));
+        assert_eq!(
+            narrow.integer_range(&Type::I128),
+            Some((i128::MIN, i128::MAX))
+        );
+        assert_eq!(narrow.integer_range(&Type::U128), Some((0, i128::MAX)));
+    }
+}
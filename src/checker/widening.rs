@@ -0,0 +1,102 @@
+use super::consts::expression_span;
+use crate::ast::expressions::{CastExpr, Expression};
+use crate::ast::fold::{Folder, walk_expression};
+use crate::ast::{Program, Span, Type};
+use std::collections::HashMap;
+
+// A conversion the checker accepted without an `as`, under implicit widening.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplicitCast {
+    pub from: Type,
+    pub to: Type,
+    // Span of the converted expression.
+    pub span: Span,
+}
+
+// Whether every value of `from` is exactly representable in `to`. Integers
+// widen to larger integers of the same signedness, or unsigned to a larger
+// signed type, and to floats whose mantissa holds all their bits. `isize` and
+// `usize` never widen implicitly since their width depends on the target.
+pub fn is_lossless_widening(from: &Type, to: &Type) -> bool {
+    if from.is_float() {
+        return matches!((from, to), (Type::F32, Type::F64));
+    }
+    let Some(from_bits) = fixed_bits(from) else {
+        return false;
+    };
+    match to {
+        Type::F32 => from_bits <= 16,
+        Type::F64 => from_bits <= 32,
+        to => match fixed_bits(to) {
+            Some(to_bits) if from.is_signed() == to.is_signed() => from_bits < to_bits,
+            Some(to_bits) => !from.is_signed() && from_bits < to_bits,
+            None => false,
+        },
+    }
+}
+
+fn fixed_bits(ty: &Type) -> Option<u32> {
+    match ty {
+        Type::Isize | Type::Usize => None,
+        ty => super::integer_bits(ty),
+    }
+}
+
+// Makes the checker's implicit conversions explicit, wrapping each converted
+// expression in an `as` cast so later passes need not know about widening.
+pub fn insert_implicit_casts(program: &mut Program, casts: &[ImplicitCast]) {
+    let empty = Program {
+        items: Vec::new(),
+        span: program.span.clone(),
+    };
+    let owned = std::mem::replace(program, empty);
+    let mut inserter = CastInserter {
+        casts: casts
+            .iter()
+            .map(|cast| ((cast.span.start, cast.span.end), cast.to.clone()))
+            .collect(),
+    };
+    *program = inserter.fold_program(owned);
+}
+
+struct CastInserter {
+    casts: HashMap<(usize, usize), Type>,
+}
+
+impl Folder for CastInserter {
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        let target = expression_span(&expression).and_then(|span| {
+            self.casts
+                .remove(&(span.start, span.end))
+                .map(|ty| (ty, span.clone()))
+        });
+        let expression = walk_expression(self, expression);
+        match target {
+            Some((target_type, span)) => Expression::Cast(Box::new(CastExpr {
+                expr: expression,
+                target_type,
+                span,
+            })),
+            None => expression,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lossless_widening() {
+        assert!(is_lossless_widening(&Type::I32, &Type::I64));
+        assert!(is_lossless_widening(&Type::U8, &Type::I16));
+        assert!(is_lossless_widening(&Type::I32, &Type::F64));
+        assert!(is_lossless_widening(&Type::F32, &Type::F64));
+
+        assert!(!is_lossless_widening(&Type::I64, &Type::I32));
+        assert!(!is_lossless_widening(&Type::I8, &Type::U16));
+        assert!(!is_lossless_widening(&Type::U32, &Type::I32));
+        assert!(!is_lossless_widening(&Type::I64, &Type::F64));
+        assert!(!is_lossless_widening(&Type::I32, &Type::Isize));
+    }
+}
@@ -0,0 +1,240 @@
+use crate::ast::lookup::NodeRef;
+use crate::ast::statements::Statement;
+use crate::ast::{Program, Span};
+use crate::lexer::{LexError, SpannedToken, Token, tokenize_with_trivia};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentPlacement {
+    // On its own line before the node.
+    Leading,
+    // After the node, on the line where the node ends.
+    Trailing,
+    // On its own line after the node, when no node follows it.
+    After,
+}
+
+// A source comment and the item, statement or block tail it belongs to. The
+// AST itself has no room for comments; printers look them up by `node`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+    pub node: Span,
+    pub placement: CommentPlacement,
+}
+
+// Attaches every comment in `source` to a node of `program`, parsed from the
+// same source. A comment on the line where a node ends trails it; otherwise it
+// leads the node starting right after it, or follows the node ending right
+// before it, as at the end of a block. Comments inside a node that is not an
+// anchor, such as between call arguments or parameters, lead the innermost
+// anchor around them; outside every anchor they go to the next anchor in the
+// source, or else follow the one before them. With no anchor at all, a comment
+// follows the whole program; none is dropped.
+pub fn collect_comments(source: &str, program: &Program) -> Result<Vec<Comment>, LexError> {
+    let tokens = tokenize_with_trivia(source)?;
+    let code: Vec<&SpannedToken> = tokens.iter().filter(|t| !t.token.is_trivia()).collect();
+    let mut anchors = Vec::new();
+    for item in &program.items {
+        collect_anchors(NodeRef::Declaration(item), &code, &mut anchors);
+    }
+
+    let mut comments = Vec::new();
+    for token in tokens.iter().filter(|t| t.token.is_trivia()) {
+        if token.token == Token::Whitespace {
+            continue;
+        }
+        let start = token.span.start;
+        let previous = code[..code.partition_point(|t| t.span.end <= start)].last();
+        let next = code.get(code.partition_point(|t| t.span.start < token.span.end));
+        let same_line = previous.is_some_and(|t| !source[t.span.end..start].contains('\n'));
+        let ending = |t: &&SpannedToken| {
+            anchors
+                .iter()
+                .filter(|node: &&Span| node.end == t.span.end)
+                .min_by_key(|node| node.start)
+        };
+        let starting = |t: &&SpannedToken| {
+            anchors
+                .iter()
+                .filter(|node: &&Span| node.start == t.span.start)
+                .max_by_key(|node| node.end)
+        };
+        let attached = match (previous.and_then(ending), next.and_then(starting)) {
+            (Some(node), _) if same_line => Some((node, CommentPlacement::Trailing)),
+            (_, Some(node)) => Some((node, CommentPlacement::Leading)),
+            (Some(node), None) => Some((node, CommentPlacement::After)),
+            (None, None) => anchors
+                .iter()
+                .filter(|node| node.start <= start && token.span.end <= node.end)
+                .min_by_key(|node| node.end - node.start)
+                .map(|node| (node, CommentPlacement::Leading))
+                .or_else(|| {
+                    anchors
+                        .iter()
+                        .filter(|node| node.start >= token.span.end)
+                        .min_by_key(|node| node.start)
+                        .map(|node| (node, CommentPlacement::Leading))
+                })
+                .or_else(|| {
+                    anchors
+                        .iter()
+                        .filter(|node| node.end <= start)
+                        .max_by_key(|node| node.end)
+                        .map(|node| (node, CommentPlacement::After))
+                }),
+        };
+        let (node, placement) = attached.unwrap_or((&program.span, CommentPlacement::After));
+        comments.push(Comment {
+            text: token.span.slice(source).to_string(),
+            span: token.span.clone(),
+            node: node.clone(),
+            placement,
+        });
+    }
+    Ok(comments)
+}
+
+// The span comments are keyed by for a statement. Expression statements have
+// no span of their own, so their expression's is used.
+pub fn statement_span(statement: &Statement) -> Option<&Span> {
+    match statement {
+        Statement::Expression(expr) => NodeRef::Expression(expr).span(),
+        _ => NodeRef::Statement(statement).span(),
+    }
+}
+
+// Items, fields, variants, statements and block tails, each extended over a
+// `;` or `,` right after it so a trailing comment after the separator still
+// finds its node.
+fn collect_anchors(node: NodeRef<'_>, code: &[&SpannedToken], anchors: &mut Vec<Span>) {
+    let span = match node {
+        NodeRef::Declaration(item) => Some(item.span()),
+        NodeRef::StructField(field) => Some(&field.span),
+        NodeRef::UnionField(field) => Some(&field.span),
+        NodeRef::EnumVariant(variant) => Some(&variant.span),
+        NodeRef::Statement(statement) => statement_span(statement),
+        _ => None,
+    };
+    if let Some(span) = span {
+        let mut span = span.clone();
+        let next = code.partition_point(|t| t.span.start < span.end);
+        if let Some(separator) = code
+            .get(next)
+            .filter(|t| matches!(t.token, Token::Semicolon | Token::Comma))
+        {
+            span.end = separator.span.end;
+        }
+        anchors.push(span);
+    }
+    if let NodeRef::Block(block) = node
+        && let Some(tail) = block.tail.as_deref()
+        && let Some(span) = NodeRef::Expression(tail).span()
+    {
+        anchors.push(span.clone());
+    }
+    for child in node.children() {
+        collect_anchors(child, code, anchors);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn comments(source: &str) -> Vec<(String, String, CommentPlacement)> {
+        let program = parse(source).unwrap();
+        collect_comments(source, &program)
+            .unwrap()
+            .into_iter()
+            .map(|comment| {
+                let node = comment.node.slice(source).to_string();
+                (comment.text, node, comment.placement)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_comment_placement() {
+        let source = "// about f\nfn f() {\n    x = 1; // note\n    /* lead */ y\n    // end\n}\n";
+        assert_eq!(
+            comments(source),
+            [
+                (
+                    "// about f".to_string(),
+                    source[11..source.len() - 1].to_string(),
+                    CommentPlacement::Leading
+                ),
+                (
+                    "// note".to_string(),
+                    "x = 1;".to_string(),
+                    CommentPlacement::Trailing
+                ),
+                (
+                    "/* lead */".to_string(),
+                    "y".to_string(),
+                    CommentPlacement::Leading
+                ),
+                (
+                    "// end".to_string(),
+                    "y".to_string(),
+                    CommentPlacement::After
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comment_inside_statement_leads_it() {
+        let source = "fn f() {\n    g(a, // first\n      b);\n    h(1, /* inline */ 2);\n}";
+        assert_eq!(
+            comments(source),
+            [
+                (
+                    "// first".to_string(),
+                    "g(a, // first\n      b);".to_string(),
+                    CommentPlacement::Leading
+                ),
+                (
+                    "/* inline */".to_string(),
+                    "h(1, /* inline */ 2);".to_string(),
+                    CommentPlacement::Leading
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_every_comment_is_attached() {
+        let source = "fn f(a: i32, /* why */ b: i32) {}\nstruct S {\n    // field doc\n    xs: i32, // xs\n}\n";
+        assert_eq!(
+            comments(source),
+            [
+                (
+                    "/* why */".to_string(),
+                    "fn f(a: i32, /* why */ b: i32) {}".to_string(),
+                    CommentPlacement::Leading
+                ),
+                (
+                    "// field doc".to_string(),
+                    "xs: i32,".to_string(),
+                    CommentPlacement::Leading
+                ),
+                (
+                    "// xs".to_string(),
+                    "xs: i32,".to_string(),
+                    CommentPlacement::Trailing
+                ),
+            ]
+        );
+        assert_eq!(
+            comments("// nothing else"),
+            [(
+                "// nothing else".to_string(),
+                "// nothing else".to_string(),
+                CommentPlacement::After
+            )]
+        );
+    }
+}
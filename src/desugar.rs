@@ -0,0 +1,435 @@
+use crate::ast::expressions::{
+    BinaryExpr, BinaryOperator, Expression, ForExpr, IndexExpr, IntBase, Literal, LoopExpr,
+    Pattern, RangeExpr, UnaryExpr, UnaryOperator, WhileExpr,
+};
+use crate::ast::fold::{Folder, walk_expression, walk_statement};
+use crate::ast::statements::{
+    ForStatement, IfStatement, LetStatement, LoopStatement, Statement, WhileStatement,
+};
+use crate::ast::types::Mutability;
+use crate::ast::{Block, Identifier, Program, Span, Type};
+use crate::checker::Checker;
+
+// Lowers surface sugar into the smaller core language backends consume:
+//
+//   a op= b                 =>  a = a op b
+//   for p in a..b { body }  =>  { var mut __iterN = a; var __endN = b;
+//                                 while __iterN < __endN {
+//                                     var p = __iterN; __iterN = __iterN + 1; body } }
+//   for p in e { body }     =>  { var __iterN = e; var mut __indexN = 0usize;
+//                                 while __indexN < len {
+//                                     var p = __iterN[__indexN]; __indexN = __indexN + 1; body } }
+//
+// The language has no methods, so `for` only walks what the checker accepts
+// as iterable: an integer range, or an array of `len` elements, possibly
+// behind a reference, in which case `p` binds `&__iterN[__indexN]`. `a..=b`
+// also tracks whether `b` was reached, so that it never steps past `b`; `a..`
+// becomes a `loop` and `..b` starts from 0. A slice's length is only known at
+// run time, so loops over slices, and over anything the checker found no type
+// for, are left as they are. The element is bound and the counter advanced
+// before `body`, so `continue` moves to the next element. Labels move onto the
+// generated loop, so `break`/`continue` keep their targets. Generated nodes
+// reuse the span of the construct they replace.
+pub fn desugar(program: &mut Program, checker: &Checker) {
+    let empty = Program {
+        items: Vec::new(),
+        span: program.span.clone(),
+    };
+    let owned = std::mem::replace(program, empty);
+    *program = Desugarer {
+        checker,
+        next_loop: 0,
+    }
+    .fold_program(owned);
+}
+
+struct Desugarer<'a> {
+    checker: &'a Checker,
+    next_loop: usize,
+}
+
+// A `for` loop taken apart: statements run once before the loop, the
+// condition checked before each iteration (`None` for a `loop`), and the
+// statements that bind the element and advance, run before the body.
+struct LoweredFor {
+    setup: Vec<Statement>,
+    condition: Option<Expression>,
+    step: Vec<Statement>,
+}
+
+impl Desugarer<'_> {
+    fn lower_for(
+        &mut self,
+        pattern: &Pattern,
+        iterator: &Expression,
+        span: &Span,
+    ) -> Option<LoweredFor> {
+        let suffix = self.next_loop;
+        let name = |prefix: &str| Identifier::new(format!("{}{}", prefix, suffix), span.clone());
+        if let Expression::Range(range) = iterator {
+            self.next_loop += 1;
+            return Some(lower_range(pattern, range, name("__iter"), name, span));
+        }
+        let (len, reference) = match self.checker.type_of(iterator) {
+            Some(Type::Array(_, Some(len))) => ((**len).clone(), None),
+            Some(Type::Reference(inner, mutability, _)) => match inner.as_ref() {
+                Type::Array(_, Some(len)) => ((**len).clone(), Some(*mutability)),
+                _ => return None,
+            },
+            _ => match iterator {
+                Expression::Literal(Literal::Array(elements)) => {
+                    (integer(elements.len(), None), None)
+                }
+                _ => return None,
+            },
+        };
+        self.next_loop += 1;
+        let (array, index) = (name("__iter"), name("__index"));
+        let mut element = Expression::Index(Box::new(IndexExpr {
+            array: Expression::Identifier(array.clone()),
+            index: Expression::Identifier(index.clone()),
+            span: span.clone(),
+        }));
+        if let Some(mutability) = reference {
+            let operator = match mutability {
+                Mutability::Mutable => UnaryOperator::RefMut,
+                Mutability::Immutable => UnaryOperator::Ref,
+            };
+            element = Expression::Unary(Box::new(UnaryExpr {
+                operator,
+                operand: element,
+                span: span.clone(),
+            }));
+        }
+        Some(LoweredFor {
+            setup: vec![
+                bind(Pattern::Identifier(array), iterator.clone(), false, span),
+                bind(
+                    Pattern::Identifier(index.clone()),
+                    integer(0, Some(Type::Usize)),
+                    true,
+                    span,
+                ),
+            ],
+            condition: Some(binary(
+                Expression::Identifier(index.clone()),
+                BinaryOperator::Lt,
+                len,
+                span,
+            )),
+            step: vec![
+                bind(pattern.clone(), element, false, span),
+                increment(&index, span),
+            ],
+        })
+    }
+}
+
+fn lower_range(
+    pattern: &Pattern,
+    range: &RangeExpr,
+    counter: Identifier,
+    name: impl Fn(&str) -> Identifier,
+    span: &Span,
+) -> LoweredFor {
+    let start = range.start.clone().unwrap_or_else(|| integer(0, None));
+    let current = || Expression::Identifier(counter.clone());
+    let mut lowered = LoweredFor {
+        setup: vec![bind(
+            Pattern::Identifier(counter.clone()),
+            start,
+            true,
+            span,
+        )],
+        condition: None,
+        step: vec![bind(pattern.clone(), current(), false, span)],
+    };
+    let Some(end_value) = &range.end else {
+        lowered.step.push(increment(&counter, span));
+        return lowered;
+    };
+    let end = name("__end");
+    lowered.setup.push(bind(
+        Pattern::Identifier(end.clone()),
+        end_value.clone(),
+        false,
+        span,
+    ));
+    let end = || Expression::Identifier(end.clone());
+    if !range.inclusive {
+        lowered.condition = Some(binary(current(), BinaryOperator::Lt, end(), span));
+        lowered.step.push(increment(&counter, span));
+        return lowered;
+    }
+    // `var mut __doneN = __iterN > __endN;` ... `while !__doneN`, where each
+    // iteration sets `__doneN = __iterN == __endN;` and only steps if not done.
+    let done = name("__done");
+    let not_done = || {
+        Expression::Unary(Box::new(UnaryExpr {
+            operator: UnaryOperator::Not,
+            operand: Expression::Identifier(done.clone()),
+            span: span.clone(),
+        }))
+    };
+    lowered.setup.push(bind(
+        Pattern::Identifier(done.clone()),
+        binary(current(), BinaryOperator::Gt, end(), span),
+        true,
+        span,
+    ));
+    lowered.condition = Some(not_done());
+    lowered.step.push(assign(
+        &done,
+        binary(current(), BinaryOperator::Eq, end(), span),
+        span,
+    ));
+    lowered.step.push(Statement::If(IfStatement {
+        condition: not_done(),
+        then_branch: Block {
+            statements: vec![increment(&counter, span)],
+            tail: None,
+            span: span.clone(),
+        },
+        else_branch: None,
+        span: span.clone(),
+    }));
+    lowered
+}
+
+// `var p = value;`, or `var mut p = value;`.
+fn bind(pattern: Pattern, value: Expression, mutable: bool, span: &Span) -> Statement {
+    Statement::Let(LetStatement {
+        pattern,
+        type_annotation: None,
+        initializer: Some(value),
+        mutable,
+        span: span.clone(),
+    })
+}
+
+// `name = name + 1;`
+fn increment(name: &Identifier, span: &Span) -> Statement {
+    let value = binary(
+        Expression::Identifier(name.clone()),
+        BinaryOperator::Add,
+        integer(1, None),
+        span,
+    );
+    assign(name, value, span)
+}
+
+fn assign(name: &Identifier, value: Expression, span: &Span) -> Statement {
+    Statement::Expression(binary(
+        Expression::Identifier(name.clone()),
+        BinaryOperator::Assign,
+        value,
+        span,
+    ))
+}
+
+fn binary(
+    left: Expression,
+    operator: BinaryOperator,
+    right: Expression,
+    span: &Span,
+) -> Expression {
+    Expression::Binary(Box::new(BinaryExpr {
+        left,
+        operator,
+        right,
+        span: span.clone(),
+    }))
+}
+
+fn integer(value: usize, suffix: Option<Type>) -> Expression {
+    Expression::Literal(Literal::Integer(value as i128, suffix, IntBase::Dec))
+}
+
+impl Folder for Desugarer<'_> {
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        match walk_statement(self, statement) {
+            Statement::For(stmt) => {
+                let Some(lowered) = self.lower_for(&stmt.pattern, &stmt.iterator, &stmt.span)
+                else {
+                    return Statement::For(stmt);
+                };
+                let ForStatement {
+                    mut body,
+                    label,
+                    span,
+                    ..
+                } = stmt;
+                let LoweredFor {
+                    mut setup,
+                    condition,
+                    step,
+                } = lowered;
+                body.statements.splice(0..0, step);
+                setup.push(match condition {
+                    Some(condition) => Statement::While(WhileStatement {
+                        condition,
+                        body,
+                        label,
+                        span: span.clone(),
+                    }),
+                    None => Statement::Loop(LoopStatement {
+                        body,
+                        label,
+                        span: span.clone(),
+                    }),
+                });
+                Statement::Block(Block {
+                    statements: setup,
+                    tail: None,
+                    span,
+                })
+            }
+            other => other,
+        }
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        match walk_expression(self, expression) {
+            Expression::Binary(binary) => match binary.operator.compound_base() {
+                Some(operator) => {
+                    let BinaryExpr {
+                        left, right, span, ..
+                    } = *binary;
+                    let value = Expression::Binary(Box::new(BinaryExpr {
+                        left: left.clone(),
+                        operator,
+                        right,
+                        span: span.clone(),
+                    }));
+                    Expression::Binary(Box::new(BinaryExpr {
+                        left,
+                        operator: BinaryOperator::Assign,
+                        right: value,
+                        span,
+                    }))
+                }
+                None => Expression::Binary(binary),
+            },
+            Expression::For(for_expr) => {
+                let Some(lowered) =
+                    self.lower_for(&for_expr.pattern, &for_expr.iterator, &for_expr.span)
+                else {
+                    return Expression::For(for_expr);
+                };
+                let ForExpr {
+                    body, label, span, ..
+                } = *for_expr;
+                let LoweredFor {
+                    setup,
+                    condition,
+                    step,
+                } = lowered;
+                let body = Expression::Block(Box::new(Block {
+                    statements: step,
+                    tail: Some(Box::new(body)),
+                    span: span.clone(),
+                }));
+                let looped = match condition {
+                    Some(condition) => Expression::While(Box::new(WhileExpr {
+                        condition,
+                        body,
+                        label,
+                        span: span.clone(),
+                    })),
+                    None => Expression::Loop(Box::new(LoopExpr {
+                        body,
+                        label,
+                        span: span.clone(),
+                    })),
+                };
+                Expression::Block(Box::new(Block {
+                    statements: setup,
+                    tail: Some(Box::new(looped)),
+                    span,
+                }))
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::printer::print_program;
+
+    fn desugared(source: &str) -> String {
+        let mut program = parse(source).unwrap();
+        let mut checker = Checker::new();
+        checker.check_program(&program);
+        desugar(&mut program, &checker);
+        print_program(&program)
+    }
+
+    #[test]
+    fn test_desugar_compound_assignment() {
+        assert_eq!(
+            desugared("fn f() { var mut x = 0; x += 1; x *= x; }"),
+            "fn f() {\n    var mut x = 0;\n    x = x + 1;\n    x = x * x;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_desugar_range_loops() {
+        assert_eq!(
+            desugared("fn f(n: i32) { for i in 0..n { g(i); } }"),
+            "fn f(n: i32) {\n    {\n        var mut __iter0 = 0;\n        var __end0 = n;\n        while __iter0 < __end0 {\n            var i = __iter0;\n            __iter0 = __iter0 + 1;\n            g(i);\n        }\n    }\n}\n"
+        );
+        assert_eq!(
+            desugared("fn f() { for i in 1..=3u8 { g(i); } }"),
+            "fn f() {\n    {\n        var mut __iter0 = 1;\n        var __end0 = 3u8;\n        var mut __done0 = __iter0 > __end0;\n        while !__done0 {\n            var i = __iter0;\n            __done0 = __iter0 == __end0;\n            if !__done0 {\n                __iter0 = __iter0 + 1;\n            }\n            g(i);\n        }\n    }\n}\n"
+        );
+        assert_eq!(
+            desugared("fn f() { 'outer: for i in 5.. { continue 'outer; } }"),
+            "fn f() {\n    {\n        var mut __iter0 = 5;\n        'outer: loop {\n            var i = __iter0;\n            __iter0 = __iter0 + 1;\n            continue 'outer;\n        }\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_desugar_array_loops() {
+        assert_eq!(
+            desugared("fn f(xs: [i32; 4]) { for x in xs { g(x); } }"),
+            "fn f(xs: [i32; 4]) {\n    {\n        var __iter0 = xs;\n        var mut __index0 = 0usize;\n        while __index0 < 4 {\n            var x = __iter0[__index0];\n            __index0 = __index0 + 1;\n            g(x);\n        }\n    }\n}\n"
+        );
+        assert_eq!(
+            desugared("fn f(xs: &mut [i32; 2]) { for x in xs { *x += 1; } for y in [7, 8, 9] {} }"),
+            "fn f(xs: &mut [i32; 2]) {\n    {\n        var __iter0 = xs;\n        var mut __index0 = 0usize;\n        while __index0 < 2 {\n            var x = &mut __iter0[__index0];\n            __index0 = __index0 + 1;\n            *x = *x + 1;\n        }\n    }\n    {\n        var __iter1 = [7, 8, 9];\n        var mut __index1 = 0usize;\n        while __index1 < 3 {\n            var y = __iter1[__index1];\n            __index1 = __index1 + 1;\n        }\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_desugar_keeps_slice_loops() {
+        assert_eq!(
+            desugared("fn f(xs: &[i32]) { for x in xs { g(x); } }"),
+            "fn f(xs: &[i32]) {\n    for x in xs {\n        g(x);\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_desugar_preserves_spans() {
+        let mut program = parse("fn f(n: i32) {\n    for x in 0..n {}\n}").unwrap();
+        desugar(&mut program, &Checker::new());
+
+        let crate::ast::Declaration::Function(function) = &program.items[0] else {
+            panic!("expected a function");
+        };
+        let Statement::Block(block) = &function.body.statements[0] else {
+            panic!("expected the for loop to become a block");
+        };
+        let Statement::While(while_loop) = &block.statements[2] else {
+            panic!("expected a while loop");
+        };
+        assert_eq!(block.span.line, 2);
+        assert_eq!(while_loop.span, block.span);
+        let Expression::Binary(condition) = &while_loop.condition else {
+            panic!("expected a comparison");
+        };
+        assert_eq!(condition.span, block.span);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::ast::Span;
+use std::fmt::{self, Display, Formatter};
+
+// Ordered from most to least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+            Severity::Note => f.write_str("note"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    // Other locations relevant to the message, such as an earlier definition.
+    pub labels: Vec<Label>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn with_label(mut self, message: impl Into<String>, span: Span) -> Self {
+        self.labels.push(Label {
+            message: message.into(),
+            span,
+        });
+        self
+    }
+
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Error, message, span)
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Warning, message, span)
+    }
+
+    pub fn note(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Note, message, span)
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} at {}:{}",
+            self.severity, self.message, self.span.line, self.span.column
+        )
+    }
+}
+
+// Diagnostics collected from several passes, possibly out of source order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiagnosticBag {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticBag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    // The diagnostics in source order; at the same span, errors come before
+    // warnings and warnings before notes, otherwise insertion order is kept.
+    pub fn sorted(&self) -> Vec<Diagnostic> {
+        let mut sorted = self.diagnostics.clone();
+        sorted.sort_by(|a, b| a.span.cmp(&b.span).then(a.severity.cmp(&b.severity)));
+        sorted
+    }
+
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+impl Extend<Diagnostic> for DiagnosticBag {
+    fn extend<I: IntoIterator<Item = Diagnostic>>(&mut self, iter: I) {
+        self.diagnostics.extend(iter);
+    }
+}
+
+impl FromIterator<Diagnostic> for DiagnosticBag {
+    fn from_iter<I: IntoIterator<Item = Diagnostic>>(iter: I) -> Self {
+        Self {
+            diagnostics: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic_constructors() {
+        let error = Diagnostic::error("bad", Span::dummy());
+        let warning = Diagnostic::warning("hmm", Span::dummy());
+
+        assert!(error.is_error());
+        assert!(!warning.is_error());
+        assert_eq!(warning.severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let diagnostic = Diagnostic::error("unexpected token", Span::new(4, 5, 2, 3));
+        assert_eq!(diagnostic.to_string(), "error: unexpected token at 2:3");
+    }
+
+    #[test]
+    fn test_sorted_diagnostics_are_in_source_order() {
+        let bag: DiagnosticBag = vec![
+            Diagnostic::warning("third", Span::new(10, 12, 2, 1)),
+            Diagnostic::error("first", Span::new(0, 3, 1, 1)),
+            Diagnostic::warning("tied warning", Span::new(4, 6, 1, 5)),
+            Diagnostic::error("second", Span::new(4, 5, 1, 5)),
+            Diagnostic::error("tied error", Span::new(4, 6, 1, 5)),
+        ]
+        .into_iter()
+        .collect();
+
+        let messages: Vec<String> = bag.sorted().into_iter().map(|d| d.message).collect();
+        assert_eq!(
+            messages,
+            vec!["first", "second", "tied error", "tied warning", "third"]
+        );
+        assert_eq!(bag.len(), 5);
+        assert!(bag.has_errors());
+    }
+
+    #[test]
+    fn test_span_ordering() {
+        assert!(Span::new(0, 5, 1, 1) < Span::new(1, 2, 1, 2));
+        assert!(Span::new(1, 2, 1, 2) < Span::new(1, 3, 1, 2));
+        assert_eq!(
+            Span::new(3, 4, 1, 4).cmp(&Span::new(3, 4, 1, 4)),
+            std::cmp::Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,1087 @@
+use crate::ast::expressions::{
+    BinaryOperator, Expression, IntBase, Literal, MatchArm, Pattern, RangeExpr, UnaryOperator,
+};
+use crate::ast::statements::{ElseBranch, IfStatement, Statement};
+use crate::ast::types::Mutability;
+use crate::ast::{Block, Declaration, FunctionDecl, Identifier, Program, Span, Type};
+use crate::checker::Checker;
+use crate::checker::resolver::Resolution;
+use crate::checker::scope::{BindingId, Interner, Scopes};
+use std::collections::HashMap;
+
+// A lowered form of the AST for backends. Compared to the AST:
+//
+//   - `for` loops and compound assignments are desugared as in `desugar`,
+//     except that every array or slice loop is bounded by a `Len`;
+//   - statements are only `let` and expressions; loops, `return`, `break` and
+//     the like are expressions;
+//   - parentheses are gone;
+//   - local names are `BindingId`s from the resolver, other names are globals;
+//   - every expression carries the type the checker found for it, or `None`
+//     when the checker could not determine one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hir {
+    pub functions: Vec<HirFunction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HirFunction {
+    pub name: String,
+    pub params: Vec<HirBinding>,
+    pub return_type: Type,
+    pub body: HirBlock,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HirBinding {
+    pub id: BindingId,
+    pub name: String,
+    pub mutable: bool,
+    pub ty: Option<Type>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HirBlock {
+    pub statements: Vec<HirStatement>,
+    pub tail: Option<Box<HirExpr>>,
+    pub ty: Option<Type>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HirStatement {
+    Let(HirPattern, Option<HirExpr>),
+    Expression(HirExpr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HirExpr {
+    pub kind: HirExprKind,
+    pub ty: Option<Type>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HirExprKind {
+    // Scalar literals only; arrays and tuples have their own kinds.
+    Literal(Literal),
+    Array(Vec<HirExpr>),
+    Tuple(Vec<HirExpr>),
+    Local(BindingId),
+    // A function, constant, static or any path not naming a local.
+    Global(String),
+    // Never a compound assignment; see `Assign`.
+    Binary(BinaryOperator, Box<HirExpr>, Box<HirExpr>),
+    Assign(Box<HirExpr>, Box<HirExpr>),
+    Unary(UnaryOperator, Box<HirExpr>),
+    Call(Box<HirExpr>, Vec<HirExpr>),
+    Field(Box<HirExpr>, Identifier),
+    Index(Box<HirExpr>, Box<HirExpr>),
+    Cast(Box<HirExpr>, Type),
+    Struct(Identifier, Vec<(Identifier, HirExpr)>, Option<Box<HirExpr>>),
+    Range(Option<Box<HirExpr>>, Option<Box<HirExpr>>, bool),
+    Block(HirBlock),
+    If(Box<HirExpr>, Box<HirExpr>, Option<Box<HirExpr>>),
+    Match(Box<HirExpr>, Vec<HirArm>),
+    Loop(Box<HirExpr>, Option<String>),
+    While(Box<HirExpr>, Box<HirExpr>, Option<String>),
+    Break(Option<String>, Option<Box<HirExpr>>),
+    Continue(Option<String>),
+    Return(Option<Box<HirExpr>>),
+    Panic(Box<HirExpr>),
+    Macro(Identifier, Vec<HirExpr>),
+    // The number of elements of an array or slice, or of one behind a
+    // reference, as a `usize`. Only lowered `for` loops produce it.
+    Len(Box<HirExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HirArm {
+    pub pattern: HirPattern,
+    pub guard: Option<HirExpr>,
+    pub body: HirExpr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HirPattern {
+    Binding(HirBinding),
+    Tuple(Vec<HirPattern>),
+    Struct(Identifier, Vec<(Identifier, HirPattern)>, bool),
+    Or(Vec<HirPattern>),
+    Literal(Literal),
+    Range(Box<HirPattern>, Box<HirPattern>),
+    Wildcard,
+    Rest,
+}
+
+// Lowers every function in `program`, including those in inline modules.
+// `resolved` must come from `resolve_program` on the same program; its
+// binding ids are reused, and bindings the lowering introduces (`for` loop
+// iterators) are numbered after them. Types come from checking the program.
+pub fn lower(program: &Program, resolved: &Resolution) -> Hir {
+    let mut checker = Checker::new();
+    checker.check_program(program);
+    let mut lowerer = Lowerer {
+        checker,
+        declarations: resolved
+            .bindings
+            .iter()
+            .enumerate()
+            .map(|(i, binding)| ((binding.span.start, binding.span.end), BindingId(i)))
+            .collect(),
+        next_binding: resolved.bindings.len(),
+        interner: Interner::new(),
+        scopes: Scopes::new(),
+        functions: Vec::new(),
+    };
+    lowerer.lower_items(&program.items);
+    Hir {
+        functions: lowerer.functions,
+    }
+}
+
+struct Lowerer {
+    checker: Checker,
+    // Resolver bindings by the span of the name that declares them.
+    declarations: HashMap<(usize, usize), BindingId>,
+    next_binding: usize,
+    interner: Interner,
+    scopes: Scopes,
+    functions: Vec<HirFunction>,
+}
+
+impl Lowerer {
+    fn lower_items(&mut self, items: &[Declaration]) {
+        for item in items {
+            match item {
+                Declaration::Function(function) => {
+                    let function = self.lower_function(function);
+                    self.functions.push(function);
+                }
+                Declaration::Module(module) => {
+                    if let Some(items) = &module.items {
+                        self.lower_items(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn lower_function(&mut self, function: &FunctionDecl) -> HirFunction {
+        self.scopes.push();
+        let params = function
+            .params
+            .iter()
+            .map(|param| self.declare(&param.name, false))
+            .collect();
+        let body = self.lower_block(&function.body);
+        self.scopes.pop();
+        HirFunction {
+            name: function.name.name.clone(),
+            params,
+            return_type: function
+                .return_type
+                .as_deref()
+                .cloned()
+                .unwrap_or(Type::Unit),
+            body,
+            span: function.span.clone(),
+        }
+    }
+
+    fn declare(&mut self, name: &Identifier, mutable: bool) -> HirBinding {
+        let id = match self.declarations.get(&(name.span.start, name.span.end)) {
+            Some(&id) => id,
+            None => self.fresh_binding(),
+        };
+        let symbol = self.interner.intern(&name.name);
+        self.scopes.declare(symbol, id);
+        HirBinding {
+            id,
+            name: name.name.clone(),
+            mutable,
+            ty: self.checker.type_at(&name.span).cloned(),
+            span: name.span.clone(),
+        }
+    }
+
+    fn fresh_binding(&mut self) -> BindingId {
+        self.next_binding += 1;
+        BindingId(self.next_binding - 1)
+    }
+
+    fn lower_block(&mut self, block: &Block) -> HirBlock {
+        self.scopes.push();
+        let mut statements = Vec::new();
+        for statement in &block.statements {
+            self.lower_statement(statement, &mut statements);
+        }
+        let tail = block
+            .tail
+            .as_ref()
+            .map(|tail| Box::new(self.lower_expression(tail)));
+        self.scopes.pop();
+        let ty = match &tail {
+            Some(tail) => tail.ty.clone(),
+            None => Some(Type::Unit),
+        };
+        HirBlock {
+            statements,
+            tail,
+            ty,
+            span: block.span.clone(),
+        }
+    }
+
+    fn lower_statement(&mut self, statement: &Statement, out: &mut Vec<HirStatement>) {
+        let expression = match statement {
+            Statement::Empty => return,
+            Statement::Let(stmt) => {
+                let initializer = stmt
+                    .initializer
+                    .as_ref()
+                    .map(|init| self.lower_expression(init));
+                let pattern = self.lower_pattern(&stmt.pattern, stmt.mutable);
+                out.push(HirStatement::Let(pattern, initializer));
+                return;
+            }
+            Statement::Expression(expr) => self.lower_expression(expr),
+            Statement::Return(stmt) => {
+                let value = self.lower_optional(stmt.expression.as_ref());
+                never(HirExprKind::Return(value), &stmt.span)
+            }
+            Statement::Break(stmt) => {
+                let value = self.lower_optional(stmt.expression.as_ref());
+                never(HirExprKind::Break(label(&stmt.label), value), &stmt.span)
+            }
+            Statement::Continue(stmt) => {
+                never(HirExprKind::Continue(label(&stmt.label)), &stmt.span)
+            }
+            Statement::While(stmt) => {
+                let condition = self.lower_expression(&stmt.condition);
+                let body = self.lower_block_expression(&stmt.body);
+                unit(
+                    HirExprKind::While(Box::new(condition), Box::new(body), label(&stmt.label)),
+                    &stmt.span,
+                )
+            }
+            Statement::For(stmt) => self.lower_for(
+                &stmt.pattern,
+                &stmt.iterator,
+                |lowerer| lowerer.lower_block_expression(&stmt.body),
+                &stmt.label,
+                &stmt.span,
+            ),
+            Statement::Loop(stmt) => {
+                let body = self.lower_block_expression(&stmt.body);
+                HirExpr {
+                    kind: HirExprKind::Loop(Box::new(body), label(&stmt.label)),
+                    ty: None,
+                    span: stmt.span.clone(),
+                }
+            }
+            Statement::Block(block) => self.lower_block_expression(block),
+            Statement::If(stmt) => self.lower_if_statement(stmt),
+            Statement::Match(stmt) => {
+                let value = self.lower_expression(&stmt.expression);
+                let arms = self.lower_arms(&stmt.arms);
+                HirExpr {
+                    kind: HirExprKind::Match(Box::new(value), arms),
+                    ty: None,
+                    span: stmt.span.clone(),
+                }
+            }
+            Statement::Panic(stmt) => {
+                let message = self.lower_expression(&stmt.message);
+                never(HirExprKind::Panic(Box::new(message)), &stmt.span)
+            }
+        };
+        out.push(HirStatement::Expression(expression));
+    }
+
+    fn lower_if_statement(&mut self, stmt: &IfStatement) -> HirExpr {
+        let condition = self.lower_expression(&stmt.condition);
+        let then_branch = self.lower_block_expression(&stmt.then_branch);
+        let else_branch = match &stmt.else_branch {
+            Some(ElseBranch::Block(block)) => Some(self.lower_block_expression(block)),
+            Some(ElseBranch::If(nested)) => Some(self.lower_if_statement(nested)),
+            None => None,
+        };
+        unit(
+            HirExprKind::If(
+                Box::new(condition),
+                Box::new(then_branch),
+                else_branch.map(Box::new),
+            ),
+            &stmt.span,
+        )
+    }
+
+    // `for` loops become the counter and index loops `desugar` produces, with
+    // generated bindings numbered after the resolver's.
+    fn lower_for(
+        &mut self,
+        pattern: &Pattern,
+        iterator: &Expression,
+        body: impl FnOnce(&mut Self) -> HirExpr,
+        loop_label: &Option<Identifier>,
+        span: &Span,
+    ) -> HirExpr {
+        let element_type = match pattern {
+            Pattern::Identifier(name) => self.checker.type_at(&name.span).cloned(),
+            _ => None,
+        };
+        let (setup, condition, element, step) = match iterator {
+            Expression::Range(range) => self.lower_range_loop(range, element_type, span),
+            _ => self.lower_index_loop(iterator, element_type, span),
+        };
+
+        self.scopes.push();
+        let element_pattern = self.lower_pattern(pattern, false);
+        let body = body(self);
+        self.scopes.pop();
+
+        let mut statements = vec![HirStatement::Let(element_pattern, Some(element))];
+        statements.extend(step.into_iter().map(HirStatement::Expression));
+        let body = block_expression(statements, Some(body), span);
+        let looped = match condition {
+            Some(condition) => unit(
+                HirExprKind::While(Box::new(condition), Box::new(body), label(loop_label)),
+                span,
+            ),
+            None => HirExpr {
+                kind: HirExprKind::Loop(Box::new(body), label(loop_label)),
+                ty: None,
+                span: span.clone(),
+            },
+        };
+        let mut statements = setup;
+        statements.push(HirStatement::Expression(looped));
+        block_expression(statements, None, span)
+    }
+
+    // `var mut __iterN = a; var __endN = b;` and `while __iterN < __endN`;
+    // `a..=b` adds a `__doneN` flag and `a..` loops forever, as in `desugar`.
+    fn lower_range_loop(
+        &mut self,
+        range: &RangeExpr,
+        element_type: Option<Type>,
+        span: &Span,
+    ) -> LoweredLoop {
+        let start = match &range.start {
+            Some(start) => self.lower_expression(start),
+            None => HirExpr {
+                kind: HirExprKind::Literal(Literal::Integer(0, None, IntBase::Dec)),
+                ty: element_type.clone(),
+                span: span.clone(),
+            },
+        };
+        let end = range.end.as_ref().map(|end| self.lower_expression(end));
+        let counter = self.generated("__iter", true, element_type.clone(), span);
+        let mut setup = vec![HirStatement::Let(
+            HirPattern::Binding(counter.clone()),
+            Some(start),
+        )];
+        let element = local(&counter);
+        let one = HirExpr {
+            kind: HirExprKind::Literal(Literal::Integer(1, None, IntBase::Dec)),
+            ty: element_type.clone(),
+            span: span.clone(),
+        };
+        let advance = assign(
+            &counter,
+            binary(
+                BinaryOperator::Add,
+                local(&counter),
+                one,
+                element_type,
+                span,
+            ),
+            span,
+        );
+        let Some(end) = end else {
+            return (setup, None, element, vec![advance]);
+        };
+        let end_binding = self.generated("__end", false, end.ty.clone(), span);
+        setup.push(HirStatement::Let(
+            HirPattern::Binding(end_binding.clone()),
+            Some(end),
+        ));
+        let compare = |operator| {
+            binary(
+                operator,
+                local(&counter),
+                local(&end_binding),
+                Some(Type::Bool),
+                span,
+            )
+        };
+        if !range.inclusive {
+            return (
+                setup,
+                Some(compare(BinaryOperator::Lt)),
+                element,
+                vec![advance],
+            );
+        }
+        let done = self.generated("__done", true, Some(Type::Bool), span);
+        setup.push(HirStatement::Let(
+            HirPattern::Binding(done.clone()),
+            Some(compare(BinaryOperator::Gt)),
+        ));
+        let not_done = || HirExpr {
+            kind: HirExprKind::Unary(UnaryOperator::Not, Box::new(local(&done))),
+            ty: Some(Type::Bool),
+            span: span.clone(),
+        };
+        let step = vec![
+            assign(&done, compare(BinaryOperator::Eq), span),
+            unit(
+                HirExprKind::If(
+                    Box::new(not_done()),
+                    Box::new(block_expression(Vec::new(), Some(advance), span)),
+                    None,
+                ),
+                span,
+            ),
+        ];
+        (setup, Some(not_done()), element, step)
+    }
+
+    // `var __iterN = e; var mut __indexN = 0usize;` and
+    // `while __indexN < len(__iterN)`, binding `__iterN[__indexN]`, or a
+    // reference to it when `e` is a reference.
+    fn lower_index_loop(
+        &mut self,
+        iterator: &Expression,
+        element_type: Option<Type>,
+        span: &Span,
+    ) -> LoweredLoop {
+        let iterator = self.lower_expression(iterator);
+        let reference = match &iterator.ty {
+            Some(Type::Reference(inner, mutability, _)) => Some((inner.as_ref(), *mutability)),
+            _ => None,
+        };
+        let stored = match reference.map(|(inner, _)| inner).or(iterator.ty.as_ref()) {
+            Some(Type::Array(element, _) | Type::Slice(element)) => Some((**element).clone()),
+            _ => None,
+        };
+        let reference = reference.map(|(_, mutability)| mutability);
+        let array = self.generated("__iter", false, iterator.ty.clone(), span);
+        let index = self.generated("__index", true, Some(Type::Usize), span);
+        let setup = vec![
+            HirStatement::Let(HirPattern::Binding(array.clone()), Some(iterator)),
+            HirStatement::Let(
+                HirPattern::Binding(index.clone()),
+                Some(HirExpr {
+                    kind: HirExprKind::Literal(Literal::Integer(
+                        0,
+                        Some(Type::Usize),
+                        IntBase::Dec,
+                    )),
+                    ty: Some(Type::Usize),
+                    span: span.clone(),
+                }),
+            ),
+        ];
+        let len = HirExpr {
+            kind: HirExprKind::Len(Box::new(local(&array))),
+            ty: Some(Type::Usize),
+            span: span.clone(),
+        };
+        let condition = binary(
+            BinaryOperator::Lt,
+            local(&index),
+            len,
+            Some(Type::Bool),
+            span,
+        );
+        let mut element = HirExpr {
+            kind: HirExprKind::Index(Box::new(local(&array)), Box::new(local(&index))),
+            ty: stored,
+            span: span.clone(),
+        };
+        if let Some(mutability) = reference {
+            let operator = match mutability {
+                Mutability::Mutable => UnaryOperator::RefMut,
+                Mutability::Immutable => UnaryOperator::Ref,
+            };
+            element = HirExpr {
+                kind: HirExprKind::Unary(operator, Box::new(element)),
+                ty: element_type,
+                span: span.clone(),
+            };
+        }
+        let one = HirExpr {
+            kind: HirExprKind::Literal(Literal::Integer(1, Some(Type::Usize), IntBase::Dec)),
+            ty: Some(Type::Usize),
+            span: span.clone(),
+        };
+        let advance = assign(
+            &index,
+            binary(
+                BinaryOperator::Add,
+                local(&index),
+                one,
+                Some(Type::Usize),
+                span,
+            ),
+            span,
+        );
+        (setup, Some(condition), element, vec![advance])
+    }
+
+    // A binding `lower_for` introduces, such as `__iter7` for binding 7.
+    fn generated(
+        &mut self,
+        prefix: &str,
+        mutable: bool,
+        ty: Option<Type>,
+        span: &Span,
+    ) -> HirBinding {
+        let id = self.fresh_binding();
+        HirBinding {
+            id,
+            name: format!("{}{}", prefix, id.0),
+            mutable,
+            ty,
+            span: span.clone(),
+        }
+    }
+
+    fn lower_block_expression(&mut self, block: &Block) -> HirExpr {
+        let block = self.lower_block(block);
+        HirExpr {
+            ty: block.ty.clone(),
+            span: block.span.clone(),
+            kind: HirExprKind::Block(block),
+        }
+    }
+
+    fn lower_optional(&mut self, expr: Option<&Expression>) -> Option<Box<HirExpr>> {
+        expr.map(|expr| Box::new(self.lower_expression(expr)))
+    }
+
+    fn lower_all(&mut self, exprs: &[Expression]) -> Vec<HirExpr> {
+        exprs.iter().map(|e| self.lower_expression(e)).collect()
+    }
+
+    fn lower_arms(&mut self, arms: &[MatchArm]) -> Vec<HirArm> {
+        arms.iter()
+            .map(|arm| {
+                self.scopes.push();
+                let pattern = self.lower_pattern(&arm.pattern, false);
+                let guard = arm.guard.as_ref().map(|guard| self.lower_expression(guard));
+                let body = self.lower_expression(&arm.body);
+                self.scopes.pop();
+                HirArm {
+                    pattern,
+                    guard,
+                    body,
+                }
+            })
+            .collect()
+    }
+
+    fn lower_expression(&mut self, expr: &Expression) -> HirExpr {
+        let span = expression_span(expr);
+        let ty = self.checker.type_at(&span).cloned();
+        let kind = match expr {
+            Expression::Paren(inner, _) => return self.lower_expression(inner),
+            Expression::Literal(literal) => return self.lower_literal(literal, span),
+            Expression::Identifier(ident) => self.lower_name(ident),
+            Expression::Path(path) => match path.segments.as_slice() {
+                [segment] if segment.generic_args.is_none() => self.lower_name(&segment.ident),
+                _ => HirExprKind::Global(
+                    path.segments
+                        .iter()
+                        .map(|segment| segment.ident.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join("::"),
+                ),
+            },
+            Expression::Binary(binary) => {
+                let left = self.lower_expression(&binary.left);
+                let right = self.lower_expression(&binary.right);
+                match binary.operator.compound_base() {
+                    // `a op= b` is `a = a op b`.
+                    Some(operator) => {
+                        let value = HirExpr {
+                            ty: left.ty.clone(),
+                            span: span.clone(),
+                            kind: HirExprKind::Binary(
+                                operator,
+                                Box::new(left.clone()),
+                                Box::new(right),
+                            ),
+                        };
+                        HirExprKind::Assign(Box::new(left), Box::new(value))
+                    }
+                    None if binary.operator == BinaryOperator::Assign => {
+                        HirExprKind::Assign(Box::new(left), Box::new(right))
+                    }
+                    None => HirExprKind::Binary(
+                        binary.operator.clone(),
+                        Box::new(left),
+                        Box::new(right),
+                    ),
+                }
+            }
+            Expression::Unary(unary) => HirExprKind::Unary(
+                unary.operator.clone(),
+                Box::new(self.lower_expression(&unary.operand)),
+            ),
+            Expression::Call(call) => HirExprKind::Call(
+                Box::new(self.lower_expression(&call.callee)),
+                self.lower_all(&call.arguments),
+            ),
+            Expression::Member(member) => HirExprKind::Field(
+                Box::new(self.lower_expression(&member.object)),
+                member.member.clone(),
+            ),
+            Expression::Index(index) => HirExprKind::Index(
+                Box::new(self.lower_expression(&index.array)),
+                Box::new(self.lower_expression(&index.index)),
+            ),
+            Expression::Cast(cast) => HirExprKind::Cast(
+                Box::new(self.lower_expression(&cast.expr)),
+                cast.target_type.clone(),
+            ),
+            Expression::Struct(literal) => HirExprKind::Struct(
+                literal.name.clone(),
+                literal
+                    .fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), self.lower_expression(value)))
+                    .collect(),
+                self.lower_optional(literal.rest.as_deref()),
+            ),
+            Expression::Range(range) => HirExprKind::Range(
+                self.lower_optional(range.start.as_ref()),
+                self.lower_optional(range.end.as_ref()),
+                range.inclusive,
+            ),
+            Expression::Block(block) => return self.lower_block_expression(block),
+            Expression::If(if_expr) => HirExprKind::If(
+                Box::new(self.lower_expression(&if_expr.condition)),
+                Box::new(self.lower_expression(&if_expr.then_branch)),
+                self.lower_optional(if_expr.else_branch.as_ref()),
+            ),
+            Expression::Match(match_expr) => HirExprKind::Match(
+                Box::new(self.lower_expression(&match_expr.value)),
+                self.lower_arms(&match_expr.arms),
+            ),
+            Expression::Loop(loop_expr) => HirExprKind::Loop(
+                Box::new(self.lower_expression(&loop_expr.body)),
+                label(&loop_expr.label),
+            ),
+            Expression::While(while_expr) => HirExprKind::While(
+                Box::new(self.lower_expression(&while_expr.condition)),
+                Box::new(self.lower_expression(&while_expr.body)),
+                label(&while_expr.label),
+            ),
+            Expression::For(for_expr) => {
+                return self.lower_for(
+                    &for_expr.pattern,
+                    &for_expr.iterator,
+                    |lowerer| lowerer.lower_expression(&for_expr.body),
+                    &for_expr.label,
+                    &span,
+                );
+            }
+            Expression::MacroInvocation(invocation) => HirExprKind::Macro(
+                invocation.name.clone(),
+                self.lower_all(&invocation.arguments),
+            ),
+            Expression::Panic(panic) => {
+                HirExprKind::Panic(Box::new(self.lower_expression(&panic.message)))
+            }
+        };
+        HirExpr { kind, ty, span }
+    }
+
+    fn lower_name(&mut self, ident: &Identifier) -> HirExprKind {
+        let local = self
+            .interner
+            .get(&ident.name)
+            .and_then(|symbol| self.scopes.lookup(symbol));
+        match local {
+            Some(id) => HirExprKind::Local(id),
+            None => HirExprKind::Global(ident.name.clone()),
+        }
+    }
+
+    // Literals have no span of their own; they take their parent's.
+    fn lower_literal(&mut self, literal: &Literal, span: Span) -> HirExpr {
+        let (kind, ty) = match literal {
+            Literal::Array(elements) => {
+                let elements = self.lower_all(elements);
+                let ty = elements.first().and_then(|e| e.ty.clone()).map(|element| {
+                    let length = Literal::Integer(elements.len() as i128, None, Default::default());
+                    Type::Array(
+                        Box::new(element),
+                        Some(Box::new(Expression::Literal(length))),
+                    )
+                });
+                (HirExprKind::Array(elements), ty)
+            }
+            Literal::Tuple(elements) => {
+                let elements = self.lower_all(elements);
+                let ty = elements
+                    .iter()
+                    .map(|e| e.ty.clone())
+                    .collect::<Option<Vec<Type>>>()
+                    .map(|types| Type::Tuple(types).normalized());
+                (HirExprKind::Tuple(elements), ty)
+            }
+            Literal::Integer(_, suffix, _) => (
+                HirExprKind::Literal(literal.clone()),
+                Some(suffix.clone().unwrap_or(Type::I32)),
+            ),
+            Literal::Float(_, suffix) => (
+                HirExprKind::Literal(literal.clone()),
+                Some(suffix.clone().unwrap_or(Type::F64)),
+            ),
+            Literal::String(_) => (
+                HirExprKind::Literal(literal.clone()),
+                Some(Type::Reference(
+                    Box::new(Type::Str),
+                    Mutability::Immutable,
+                    None,
+                )),
+            ),
+            Literal::Character(_) => (HirExprKind::Literal(literal.clone()), Some(Type::Char)),
+            Literal::Boolean(_) => (HirExprKind::Literal(literal.clone()), Some(Type::Bool)),
+            Literal::Unit => (HirExprKind::Literal(literal.clone()), Some(Type::Unit)),
+        };
+        HirExpr { kind, ty, span }
+    }
+
+    fn lower_pattern(&mut self, pattern: &Pattern, mutable: bool) -> HirPattern {
+        match pattern {
+            Pattern::Identifier(ident) => HirPattern::Binding(self.declare(ident, mutable)),
+            Pattern::Tuple(patterns) => HirPattern::Tuple(
+                patterns
+                    .iter()
+                    .map(|p| self.lower_pattern(p, mutable))
+                    .collect(),
+            ),
+            Pattern::Struct(name, fields, rest) => HirPattern::Struct(
+                name.clone(),
+                fields
+                    .iter()
+                    .map(|(field, p)| (field.clone(), self.lower_pattern(p, mutable)))
+                    .collect(),
+                *rest,
+            ),
+            // The resolver declares an or-pattern's names from its first
+            // alternative; the others refer to those same bindings.
+            Pattern::Or(alternatives) => {
+                let mut lowered = Vec::new();
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i == 0 {
+                        lowered.push(self.lower_pattern(alternative, mutable));
+                    } else {
+                        lowered.push(self.lower_alternative(alternative, mutable));
+                    }
+                }
+                HirPattern::Or(lowered)
+            }
+            Pattern::Literal(literal) => HirPattern::Literal(literal.clone()),
+            Pattern::Range(start, end) => HirPattern::Range(
+                Box::new(self.lower_pattern(start, mutable)),
+                Box::new(self.lower_pattern(end, mutable)),
+            ),
+            Pattern::Wildcard => HirPattern::Wildcard,
+            Pattern::Rest => HirPattern::Rest,
+        }
+    }
+
+    // A later or-pattern alternative, whose names are already declared.
+    fn lower_alternative(&mut self, pattern: &Pattern, mutable: bool) -> HirPattern {
+        let Pattern::Identifier(ident) = pattern else {
+            return match pattern {
+                Pattern::Tuple(patterns) => HirPattern::Tuple(
+                    patterns
+                        .iter()
+                        .map(|p| self.lower_alternative(p, mutable))
+                        .collect(),
+                ),
+                Pattern::Struct(name, fields, rest) => HirPattern::Struct(
+                    name.clone(),
+                    fields
+                        .iter()
+                        .map(|(field, p)| (field.clone(), self.lower_alternative(p, mutable)))
+                        .collect(),
+                    *rest,
+                ),
+                Pattern::Or(alternatives) => HirPattern::Or(
+                    alternatives
+                        .iter()
+                        .map(|p| self.lower_alternative(p, mutable))
+                        .collect(),
+                ),
+                other => self.lower_pattern(other, mutable),
+            };
+        };
+        let HirExprKind::Local(id) = self.lower_name(ident) else {
+            return HirPattern::Binding(self.declare(ident, mutable));
+        };
+        HirPattern::Binding(HirBinding {
+            id,
+            name: ident.name.clone(),
+            mutable,
+            ty: self.checker.type_at(&ident.span).cloned(),
+            span: ident.span.clone(),
+        })
+    }
+}
+
+// A lowered `for` loop: the statements before the loop, its condition
+// (`None` for a `loop`), the element bound at the top of each iteration, and
+// the expressions that advance it, run before the body.
+type LoweredLoop = (Vec<HirStatement>, Option<HirExpr>, HirExpr, Vec<HirExpr>);
+
+fn local(binding: &HirBinding) -> HirExpr {
+    HirExpr {
+        kind: HirExprKind::Local(binding.id),
+        ty: binding.ty.clone(),
+        span: binding.span.clone(),
+    }
+}
+
+fn assign(binding: &HirBinding, value: HirExpr, span: &Span) -> HirExpr {
+    unit(
+        HirExprKind::Assign(Box::new(local(binding)), Box::new(value)),
+        span,
+    )
+}
+
+fn binary(
+    operator: BinaryOperator,
+    left: HirExpr,
+    right: HirExpr,
+    ty: Option<Type>,
+    span: &Span,
+) -> HirExpr {
+    HirExpr {
+        kind: HirExprKind::Binary(operator, Box::new(left), Box::new(right)),
+        ty,
+        span: span.clone(),
+    }
+}
+
+fn label(label: &Option<Identifier>) -> Option<String> {
+    label.as_ref().map(|label| label.name.clone())
+}
+
+fn unit(kind: HirExprKind, span: &Span) -> HirExpr {
+    HirExpr {
+        kind,
+        ty: Some(Type::Unit),
+        span: span.clone(),
+    }
+}
+
+fn never(kind: HirExprKind, span: &Span) -> HirExpr {
+    HirExpr {
+        kind,
+        ty: Some(Type::Never),
+        span: span.clone(),
+    }
+}
+
+fn block_expression(statements: Vec<HirStatement>, tail: Option<HirExpr>, span: &Span) -> HirExpr {
+    let ty = match &tail {
+        Some(tail) => tail.ty.clone(),
+        None => Some(Type::Unit),
+    };
+    HirExpr {
+        kind: HirExprKind::Block(HirBlock {
+            statements,
+            tail: tail.map(Box::new),
+            ty: ty.clone(),
+            span: span.clone(),
+        }),
+        ty,
+        span: span.clone(),
+    }
+}
+
+fn expression_span(expr: &Expression) -> Span {
+    match expr {
+        Expression::Literal(_) => Span::dummy(),
+        Expression::Identifier(ident) => ident.span.clone(),
+        Expression::Binary(binary) => binary.span.clone(),
+        Expression::Unary(unary) => unary.span.clone(),
+        Expression::Call(call) => call.span.clone(),
+        Expression::Member(member) => member.span.clone(),
+        Expression::Index(index) => index.span.clone(),
+        Expression::Cast(cast) => cast.span.clone(),
+        Expression::Block(block) => block.span.clone(),
+        Expression::If(if_expr) => if_expr.span.clone(),
+        Expression::Match(match_expr) => match_expr.span.clone(),
+        Expression::Loop(loop_expr) => loop_expr.span.clone(),
+        Expression::While(while_expr) => while_expr.span.clone(),
+        Expression::For(for_expr) => for_expr.span.clone(),
+        Expression::Range(range) => range.span.clone(),
+        Expression::MacroInvocation(invocation) => invocation.span.clone(),
+        Expression::Panic(panic) => panic.span.clone(),
+        Expression::Struct(literal) => literal.span.clone(),
+        Expression::Path(path) => path.span.clone(),
+        Expression::Paren(_, span) => span.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checker::resolver::resolve_program;
+    use crate::parser::parse;
+
+    fn lower_source(source: &str) -> (Hir, Resolution) {
+        let program = parse(source).unwrap();
+        let resolved = resolve_program(&program);
+        (lower(&program, &resolved), resolved)
+    }
+
+    #[test]
+    fn test_lower_resolves_locals_and_attaches_types() {
+        let (hir, resolved) = lower_source("fn f(x: i32) -> i32 { let y = x + 1; y }");
+        let function = &hir.functions[0];
+
+        assert_eq!(function.params[0].id, BindingId(0));
+        assert_eq!(function.params[0].ty, Some(Type::I32));
+        let HirStatement::Let(HirPattern::Binding(y), Some(init)) = &function.body.statements[0]
+        else {
+            panic!("expected a let binding");
+        };
+        assert_eq!(resolved.bindings[y.id.0].name, "y");
+        assert_eq!(init.ty, Some(Type::I32));
+        let HirExprKind::Binary(BinaryOperator::Add, left, _) = &init.kind else {
+            panic!("expected an addition");
+        };
+        assert_eq!(left.kind, HirExprKind::Local(BindingId(0)));
+        assert_eq!(left.ty, Some(Type::I32));
+
+        let tail = function.body.tail.as_ref().unwrap();
+        assert_eq!(tail.kind, HirExprKind::Local(y.id));
+        assert_eq!(function.body.ty, Some(Type::I32));
+    }
+
+    #[test]
+    fn test_lower_names_globals() {
+        let (hir, _) = lower_source("fn g() -> i32 { 1 } fn f() -> i32 { g() }");
+        let tail = hir.functions[1].body.tail.as_ref().unwrap();
+        let HirExprKind::Call(callee, _) = &tail.kind else {
+            panic!("expected a call");
+        };
+        assert_eq!(callee.kind, HirExprKind::Global("g".to_string()));
+        assert_eq!(tail.ty, Some(Type::I32));
+    }
+
+    #[test]
+    fn test_lower_desugars_compound_assignment() {
+        let (hir, _) = lower_source("fn f() { var mut a = 0; a += 2; }");
+        let HirStatement::Expression(assign) = &hir.functions[0].body.statements[1] else {
+            panic!("expected an expression statement");
+        };
+        let HirExprKind::Assign(target, value) = &assign.kind else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(target.kind, HirExprKind::Local(BindingId(0)));
+        assert!(matches!(
+            &value.kind,
+            HirExprKind::Binary(BinaryOperator::Add, left, _) if left.kind == target.kind
+        ));
+    }
+
+    #[test]
+    fn test_lower_desugars_for_loops() {
+        let (hir, resolved) = lower_source("fn f() { for i in 0..3 { let j = i; } }");
+        let HirStatement::Expression(lowered) = &hir.functions[0].body.statements[0] else {
+            panic!("expected an expression statement");
+        };
+        let HirExprKind::Block(block) = &lowered.kind else {
+            panic!("expected a block");
+        };
+        let HirStatement::Let(HirPattern::Binding(iterator), _) = &block.statements[0] else {
+            panic!("expected the iterator binding");
+        };
+        assert_eq!(iterator.id, BindingId(resolved.bindings.len()));
+        assert_eq!(iterator.ty, Some(Type::I32));
+        let HirStatement::Expression(HirExpr {
+            kind: HirExprKind::While(condition, body, _),
+            ..
+        }) = &block.statements[2]
+        else {
+            panic!("expected a while loop");
+        };
+        assert!(matches!(
+            &condition.kind,
+            HirExprKind::Binary(BinaryOperator::Lt, left, _)
+                if left.kind == HirExprKind::Local(iterator.id)
+        ));
+        let HirExprKind::Block(body) = &body.kind else {
+            panic!("expected a block body");
+        };
+        let HirStatement::Let(HirPattern::Binding(element), _) = &body.statements[0] else {
+            panic!("expected the element binding");
+        };
+        assert_eq!(resolved.bindings[element.id.0].name, "i");
+        assert!(matches!(
+            &body.statements[1],
+            HirStatement::Expression(HirExpr {
+                kind: HirExprKind::Assign(..),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_lower_for_over_slice_indexes_it() {
+        let (hir, _) = lower_source("fn f(xs: &[u8]) { for x in xs {} }");
+        let HirStatement::Expression(lowered) = &hir.functions[0].body.statements[0] else {
+            panic!("expected an expression statement");
+        };
+        let HirExprKind::Block(block) = &lowered.kind else {
+            panic!("expected a block");
+        };
+        let HirStatement::Let(HirPattern::Binding(array), _) = &block.statements[0] else {
+            panic!("expected the iterator binding");
+        };
+        let HirStatement::Expression(HirExpr {
+            kind: HirExprKind::While(condition, body, _),
+            ..
+        }) = &block.statements[2]
+        else {
+            panic!("expected a while loop");
+        };
+        let HirExprKind::Binary(BinaryOperator::Lt, _, len) = &condition.kind else {
+            panic!("expected an index comparison");
+        };
+        assert_eq!(len.kind, HirExprKind::Len(Box::new(local(array))));
+        let HirExprKind::Block(body) = &body.kind else {
+            panic!("expected a block body");
+        };
+        let HirStatement::Let(HirPattern::Binding(element), Some(value)) = &body.statements[0]
+        else {
+            panic!("expected the element binding");
+        };
+        assert!(matches!(
+            &value.kind,
+            HirExprKind::Unary(UnaryOperator::Ref, _)
+        ));
+        assert_eq!(
+            element.ty,
+            Some(Type::Reference(
+                Box::new(Type::U8),
+                Mutability::Immutable,
+                None
+            ))
+        );
+    }
+}
@@ -1,7 +1,10 @@
+use crate::ast::expressions::IntBase;
+use crate::ast::{Span, Type};
 use logos::Logos;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
-#[derive(Logos, Debug, PartialEq)]
+#[derive(Logos, Debug, Clone, Copy, PartialEq, Eq)]
 #[logos(skip r"[ \t\n\f]+")] // Skip whitespace
 pub enum Token {
     // Keywords
@@ -37,6 +40,20 @@ pub enum Token {
     Return,
     #[token("panic")]
     Panic,
+    #[token("let")]
+    Let,
+    #[token("static")]
+    Static,
+    #[token("break")]
+    Break,
+    #[token("continue")]
+    Continue,
+    #[token("in")]
+    In,
+    #[token("as")]
+    As,
+    #[token("extern")]
+    Extern,
 
     // Built-in Types
     #[token("i8")]
@@ -75,21 +92,28 @@ pub enum Token {
     Str,
 
     // Literals
-    #[regex(r"[0-9]+")]
+    #[regex(r"[0-9][0-9_]*|0x[0-9a-fA-F_]+|0o[0-7_]+|0b[01_]+")]
     IntegerLiteral,
-    #[regex(r"[0-9]+\.[0-9]+")]
+    #[regex(r"[0-9]+\.[0-9]+([eE][+-]?[0-9]+)?|[0-9]+[eE][+-]?[0-9]+")]
     FloatLiteral,
-    #[regex(r#""[^"]*""#)]
+    #[regex(r#""([^"\\]|\\(.|\n))*""#)]
     StringLiteral,
-    #[regex(r"'[^']'")]
+    #[regex(r"'([^'\\]|\\[nrt0\\'\x22]|\\u\{[0-9a-fA-F]+\})'")]
     CharLiteral,
     #[token("true")]
     True,
     #[token("false")]
     False,
 
-    #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
+    // Any run of identifier-like characters; `is_identifier` then applies the
+    // Unicode XID rules, so `café` lexes but an emoji is an error. Keywords
+    // are plain ASCII tokens and win over this rule.
+    #[regex(r"([a-zA-Z_]|[^\x00-\x7F])([a-zA-Z0-9_]|[^\x00-\x7F])*", |lex| is_identifier(lex.slice()))]
     Identifier,
+    // A loop label such as `'outer`. A quoted single character is a
+    // `CharLiteral` instead, being the longer match.
+    #[regex(r"'([a-zA-Z_]|[^\x00-\x7F])([a-zA-Z0-9_]|[^\x00-\x7F])*", |lex| is_identifier(&lex.slice()[1..]))]
+    Label,
 
     #[token("+")]
     Plus,
@@ -133,6 +157,26 @@ pub enum Token {
     Shl,
     #[token(">>")]
     Shr,
+    #[token("+=")]
+    PlusAssign,
+    #[token("-=")]
+    MinusAssign,
+    #[token("*=")]
+    StarAssign,
+    #[token("/=")]
+    SlashAssign,
+    #[token("%=")]
+    PercentAssign,
+    #[token("&=")]
+    BitAndAssign,
+    #[token("|=")]
+    BitOrAssign,
+    #[token("^=")]
+    BitXorAssign,
+    #[token("<<=")]
+    ShlAssign,
+    #[token(">>=")]
+    ShrAssign,
 
     #[token("(")]
     LParen,
@@ -156,6 +200,16 @@ pub enum Token {
     Dot,
     #[token("->")]
     Arrow,
+    #[token("=>")]
+    FatArrow,
+    #[token("::")]
+    PathSep,
+    #[token("..")]
+    DotDot,
+    #[token("..=")]
+    DotDotEq,
+    #[token("...")]
+    Ellipsis,
 
     #[regex(r"//[^\n]*")]
     SingleLineComment,
@@ -167,30 +221,46 @@ pub enum Token {
 
     #[token("@")]
     MacroInvoke,
+
+    // Never produced by the lexer itself; `tokenize_with_trivia` fills the
+    // gaps between tokens with it.
+    Whitespace,
+
+    // Dialect keywords: lexed as identifiers and only turned into these by a
+    // `Keywords` table that includes them.
+    Yield,
 }
 
-impl fmt::Display for Token {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Token::Var
-            | Token::Mut
-            | Token::Fn
-            | Token::Const
-            | Token::Struct
-            | Token::Enum
-            | Token::Union
-            | Token::If
-            | Token::Else
-            | Token::While
-            | Token::For
-            | Token::Loop
-            | Token::Match
-            | Token::Mod
-            | Token::Return
-            | Token::Panic
-            | Token::True
-            | Token::False => write!(f, "{:?}", self),
+// What sort of token a `Token` is, for checks that care about the category
+// rather than the exact token. `true` and `false` are literals here, though
+// `is_keyword` counts them as reserved words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum TokenKind {
+    Keyword,
+    PrimitiveType,
+    Literal,
+    Identifier,
+    Label,
+    Operator,
+    Delimiter,
+    Punctuation,
+    Comment,
+    Whitespace,
+    Attribute,
+    MacroInvoke,
+}
 
+impl From<&Token> for TokenKind {
+    fn from(token: &Token) -> Self {
+        match token {
+            Token::IntegerLiteral
+            | Token::FloatLiteral
+            | Token::StringLiteral
+            | Token::CharLiteral
+            | Token::True
+            | Token::False => TokenKind::Literal,
+            token if token.is_keyword() => TokenKind::Keyword,
             Token::I8
             | Token::I16
             | Token::I32
@@ -207,54 +277,726 @@ impl fmt::Display for Token {
             | Token::Usize
             | Token::Bool
             | Token::Char
-            | Token::Str => write!(f, "{:?}", self),
-
-            Token::IntegerLiteral => f.write_str("IntegerLiteral"),
-            Token::FloatLiteral => f.write_str("FloatLiteral"),
-            Token::StringLiteral => f.write_str("StringLiteral"),
-            Token::CharLiteral => f.write_str("CharLiteral"),
-            Token::Identifier => f.write_str("Identifier"),
-
-            Token::Plus => f.write_str("+"),
-            Token::Minus => f.write_str("-"),
-            Token::Star => f.write_str("*"),
-            Token::Slash => f.write_str("/"),
-            Token::Percent => f.write_str("%"),
-            Token::Assign => f.write_str("="),
-            Token::Eq => f.write_str("=="),
-            Token::NotEq => f.write_str("!="),
-            Token::Lt => f.write_str("<"),
-            Token::LtEq => f.write_str("<="),
-            Token::Gt => f.write_str(">"),
-            Token::GtEq => f.write_str(">="),
-            Token::And => f.write_str("&&"),
-            Token::Or => f.write_str("||"),
-            Token::Not => f.write_str("!"),
-            Token::BitAnd => f.write_str("&"),
-            Token::BitOr => f.write_str("|"),
-            Token::BitXor => f.write_str("^"),
-            Token::BitNot => f.write_str("~"),
-            Token::Shl => f.write_str("<<"),
-            Token::Shr => f.write_str(">>"),
-
-            Token::LParen => f.write_str("("),
-            Token::RParen => f.write_str(")"),
-            Token::LBrace => f.write_str("{"),
-            Token::RBrace => f.write_str("}"),
-            Token::LBracket => f.write_str("["),
-            Token::RBracket => f.write_str("]"),
-            Token::Semicolon => f.write_str(";"),
-            Token::Colon => f.write_str(":"),
-            Token::Comma => f.write_str(","),
-            Token::Dot => f.write_str("."),
-            Token::Arrow => f.write_str("->"),
-
-            Token::SingleLineComment => f.write_str("SingleLineComment"),
-            Token::MultiLineComment => f.write_str("MultiLineComment"),
-            Token::Attribute => f.write_str("Attribute"),
-            Token::MacroInvoke => f.write_str("@"),
+            | Token::Str => TokenKind::PrimitiveType,
+            Token::Identifier => TokenKind::Identifier,
+            Token::Label => TokenKind::Label,
+            Token::LParen
+            | Token::RParen
+            | Token::LBrace
+            | Token::RBrace
+            | Token::LBracket
+            | Token::RBracket => TokenKind::Delimiter,
+            Token::Semicolon
+            | Token::Colon
+            | Token::Comma
+            | Token::Dot
+            | Token::Arrow
+            | Token::FatArrow
+            | Token::PathSep
+            | Token::Ellipsis => TokenKind::Punctuation,
+            Token::SingleLineComment | Token::MultiLineComment => TokenKind::Comment,
+            Token::Whitespace => TokenKind::Whitespace,
+            Token::Attribute => TokenKind::Attribute,
+            Token::MacroInvoke => TokenKind::MacroInvoke,
+            _ => TokenKind::Operator,
+        }
+    }
+}
+
+impl Token {
+    pub fn kind(&self) -> TokenKind {
+        TokenKind::from(self)
+    }
+
+    pub fn matches_kind(&self, kind: TokenKind) -> bool {
+        self.kind() == kind
+    }
+
+    // Tokens that carry no meaning for the parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            Token::Whitespace | Token::SingleLineComment | Token::MultiLineComment
+        )
+    }
+
+    // Reserved words; these can never be used as identifiers.
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            Token::Var
+                | Token::Mut
+                | Token::Fn
+                | Token::Const
+                | Token::Struct
+                | Token::Enum
+                | Token::Union
+                | Token::If
+                | Token::Else
+                | Token::While
+                | Token::For
+                | Token::Loop
+                | Token::Match
+                | Token::Mod
+                | Token::Return
+                | Token::Panic
+                | Token::Let
+                | Token::Static
+                | Token::Break
+                | Token::Continue
+                | Token::In
+                | Token::As
+                | Token::Extern
+                | Token::True
+                | Token::False
+                | Token::Yield
+        )
+    }
+
+    // The token's display form: operator and punctuation tokens print as
+    // written, everything else by kind, e.g. `Fn` or `Identifier`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Token::Var => "Var",
+            Token::Mut => "Mut",
+            Token::Fn => "Fn",
+            Token::Const => "Const",
+            Token::Struct => "Struct",
+            Token::Enum => "Enum",
+            Token::Union => "Union",
+            Token::If => "If",
+            Token::Else => "Else",
+            Token::While => "While",
+            Token::For => "For",
+            Token::Loop => "Loop",
+            Token::Match => "Match",
+            Token::Mod => "Mod",
+            Token::Return => "Return",
+            Token::Panic => "Panic",
+            Token::Let => "Let",
+            Token::Static => "Static",
+            Token::Break => "Break",
+            Token::Continue => "Continue",
+            Token::In => "In",
+            Token::As => "As",
+            Token::Extern => "Extern",
+            Token::True => "True",
+            Token::False => "False",
+
+            Token::I8 => "I8",
+            Token::I16 => "I16",
+            Token::I32 => "I32",
+            Token::I64 => "I64",
+            Token::I128 => "I128",
+            Token::U8 => "U8",
+            Token::U16 => "U16",
+            Token::U32 => "U32",
+            Token::U64 => "U64",
+            Token::U128 => "U128",
+            Token::F32 => "F32",
+            Token::F64 => "F64",
+            Token::Isize => "Isize",
+            Token::Usize => "Usize",
+            Token::Bool => "Bool",
+            Token::Char => "Char",
+            Token::Str => "Str",
+
+            Token::IntegerLiteral => "IntegerLiteral",
+            Token::FloatLiteral => "FloatLiteral",
+            Token::StringLiteral => "StringLiteral",
+            Token::CharLiteral => "CharLiteral",
+            Token::Identifier => "Identifier",
+            Token::Label => "Label",
+
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Assign => "=",
+            Token::Eq => "==",
+            Token::NotEq => "!=",
+            Token::Lt => "<",
+            Token::LtEq => "<=",
+            Token::Gt => ">",
+            Token::GtEq => ">=",
+            Token::And => "&&",
+            Token::Or => "||",
+            Token::Not => "!",
+            Token::BitAnd => "&",
+            Token::BitOr => "|",
+            Token::BitXor => "^",
+            Token::BitNot => "~",
+            Token::Shl => "<<",
+            Token::Shr => ">>",
+            Token::PlusAssign => "+=",
+            Token::MinusAssign => "-=",
+            Token::StarAssign => "*=",
+            Token::SlashAssign => "/=",
+            Token::PercentAssign => "%=",
+            Token::BitAndAssign => "&=",
+            Token::BitOrAssign => "|=",
+            Token::BitXorAssign => "^=",
+            Token::ShlAssign => "<<=",
+            Token::ShrAssign => ">>=",
+
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Semicolon => ";",
+            Token::Colon => ":",
+            Token::Comma => ",",
+            Token::Dot => ".",
+            Token::Arrow => "->",
+            Token::FatArrow => "=>",
+            Token::PathSep => "::",
+            Token::DotDot => "..",
+            Token::DotDotEq => "..=",
+            Token::Ellipsis => "...",
+
+            Token::SingleLineComment => "SingleLineComment",
+            Token::MultiLineComment => "MultiLineComment",
+            Token::Whitespace => "Whitespace",
+            Token::Attribute => "Attribute",
+            Token::MacroInvoke => "@",
+            Token::Yield => "Yield",
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}:{}",
+            self.message, self.span.line, self.span.column
+        )
+    }
+}
+
+const BOM: &str = "\u{feff}";
+
+// Byte offset where lexing starts: past a leading UTF-8 BOM and a shebang line.
+// The newline ending the shebang is left in place so line numbers stay intact.
+fn source_start(src: &str) -> usize {
+    let mut start = 0;
+    if src.starts_with(BOM) {
+        start += BOM.len();
+    }
+    if src[start..].starts_with("#!") {
+        start += src[start..].find('\n').unwrap_or(src.len() - start);
+    }
+    start
+}
+
+struct LineTracker<'a> {
+    src: &'a str,
+    cursor: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl<'a> LineTracker<'a> {
+    fn new(src: &'a str) -> Self {
+        let line_start = if src.starts_with(BOM) { BOM.len() } else { 0 };
+        Self {
+            src,
+            cursor: line_start,
+            line: 1,
+            line_start,
+        }
+    }
+
+    fn span(&mut self, start: usize, end: usize) -> Span {
+        for (i, c) in self.src[self.cursor..start].char_indices() {
+            if c == '\n' {
+                self.line += 1;
+                self.line_start = self.cursor + i + 1;
+            }
+        }
+        self.cursor = start;
+        let column = self.src[self.line_start..start].chars().count() + 1;
+        Span::new(start, end, self.line, column)
+    }
+}
+
+// Which trivia `tokenize_with_options` keeps. By default neither is kept, so
+// parsers get a stream of significant tokens; formatters opt into trivia.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LexOptions {
+    pub keep_comments: bool,
+    pub keep_whitespace: bool,
+}
+
+impl LexOptions {
+    pub fn trivia() -> Self {
+        Self {
+            keep_comments: true,
+            keep_whitespace: true,
+        }
+    }
+}
+
+pub fn tokenize(src: &str) -> Result<Vec<SpannedToken>, LexError> {
+    tokenize_with_options(src, LexOptions::default())
+}
+
+pub fn tokenize_with_options(
+    src: &str,
+    options: LexOptions,
+) -> Result<Vec<SpannedToken>, LexError> {
+    lex(src, options)
+}
+
+pub fn tokenize_with_keywords(
+    src: &str,
+    keywords: &Keywords,
+) -> Result<Vec<SpannedToken>, LexError> {
+    let mut tokens = tokenize(src)?;
+    keywords.reclassify(&mut tokens, src);
+    Ok(tokens)
+}
+
+// Keyword changes for a dialect, keyed by spelling. The lexer's own keywords
+// are fixed at compile time; these are applied afterwards by turning matching
+// identifiers into keyword tokens, e.g. `yield` into `Token::Yield`, or
+// `function` into `Token::Fn` for a dialect that spells it that way, and by
+// turning demoted built-in keywords such as `loop` back into identifiers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Keywords {
+    words: HashMap<String, Token>,
+    demoted: HashSet<String>,
+}
+
+impl Keywords {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, spelling: impl Into<String>, keyword: Token) -> Self {
+        self.insert(spelling, keyword);
+        self
+    }
+
+    pub fn without(mut self, spelling: impl Into<String>) -> Self {
+        self.demote(spelling);
+        self
+    }
+
+    pub fn insert(&mut self, spelling: impl Into<String>, keyword: Token) {
+        debug_assert!(keyword.is_keyword(), "`{}` is not a keyword", keyword);
+        let spelling = spelling.into();
+        self.demoted.remove(&spelling);
+        self.words.insert(spelling, keyword);
+    }
+
+    // Makes the built-in keyword spelled `spelling` lex as an identifier.
+    pub fn demote(&mut self, spelling: impl Into<String>) {
+        let spelling = spelling.into();
+        self.words.remove(&spelling);
+        self.demoted.insert(spelling);
+    }
+
+    // Undoes `insert` or `demote` for `spelling`, returning the keyword it
+    // was mapped to, if any.
+    pub fn remove(&mut self, spelling: &str) -> Option<Token> {
+        self.demoted.remove(spelling);
+        self.words.remove(spelling)
+    }
+
+    pub fn get(&self, spelling: &str) -> Option<Token> {
+        self.words.get(spelling).copied()
+    }
+
+    pub fn is_demoted(&self, spelling: &str) -> bool {
+        self.demoted.contains(spelling)
+    }
+
+    // Turns every identifier in `tokens` spelled like one of the keywords
+    // into that keyword, and every demoted keyword into an identifier.
+    // `tokens` must have been lexed from `src`.
+    pub fn reclassify(&self, tokens: &mut [SpannedToken], src: &str) {
+        if self.words.is_empty() && self.demoted.is_empty() {
+            return;
+        }
+        for token in tokens.iter_mut() {
+            let spelling = token.span.slice(src);
+            if token.token == Token::Identifier
+                && let Some(keyword) = self.get(spelling)
+            {
+                token.token = keyword;
+            } else if token.token.is_keyword() && self.is_demoted(spelling) {
+                token.token = Token::Identifier;
+            }
+        }
+    }
+}
+
+// Like `tokenize`, but also yields the whitespace between tokens as
+// `Token::Whitespace`, so the tokens cover the source without gaps.
+pub fn tokenize_with_trivia(src: &str) -> Result<Vec<SpannedToken>, LexError> {
+    lex(src, LexOptions::trivia())
+}
+
+// Number of line breaks in a run of trivia, e.g. the trivia between two items.
+// Two or more means the run contains a blank line.
+pub fn newline_count(trivia: &[SpannedToken], src: &str) -> usize {
+    trivia
+        .iter()
+        .filter(|t| t.token == Token::Whitespace)
+        .map(|t| t.span.slice(src).matches('\n').count())
+        .sum()
+}
+
+// `_` or an XID_Start character, followed by XID_Continue characters.
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || unicode_ident::is_xid_start(c))
+        && chars.all(unicode_ident::is_xid_continue)
+}
+
+fn lex(src: &str, options: LexOptions) -> Result<Vec<SpannedToken>, LexError> {
+    SpannedLexer::with_options(src, options).collect()
+}
+
+// Lexes lazily, one token at a time, with the same tokens, spans and errors
+// as `tokenize_with_options`. Line and column are tracked incrementally, so
+// memory use does not grow with the source. Nothing is yielded after an error.
+pub struct SpannedLexer<'a> {
+    src: &'a str,
+    lexer: logos::Lexer<'a, Token>,
+    tracker: LineTracker<'a>,
+    options: LexOptions,
+    offset: usize,
+    previous_end: usize,
+    // A token found after a gap, held back while the gap's whitespace is
+    // yielded.
+    pending: Option<Result<SpannedToken, LexError>>,
+    done: bool,
+}
+
+impl<'a> SpannedLexer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self::with_options(src, LexOptions::default())
+    }
+
+    pub fn with_options(src: &'a str, options: LexOptions) -> Self {
+        let offset = source_start(src);
+        Self {
+            src,
+            lexer: Token::lexer(&src[offset..]),
+            tracker: LineTracker::new(src),
+            options,
+            offset,
+            previous_end: offset,
+            pending: None,
+            done: false,
+        }
+    }
+
+    fn whitespace(&mut self, end: usize) -> Option<SpannedToken> {
+        if !self.options.keep_whitespace || end <= self.previous_end {
+            return None;
+        }
+        Some(SpannedToken {
+            token: Token::Whitespace,
+            span: self.tracker.span(self.previous_end, end),
+        })
+    }
+}
+
+impl Iterator for SpannedLexer<'_> {
+    type Item = Result<SpannedToken, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.take() {
+            return Some(pending);
+        }
+        if self.done {
+            return None;
+        }
+        loop {
+            let Some(result) = self.lexer.next() else {
+                self.done = true;
+                let whitespace = self.whitespace(self.src.len());
+                self.previous_end = self.src.len();
+                return whitespace.map(Ok);
+            };
+            let range = self.lexer.span();
+            let (start, end) = (range.start + self.offset, range.end + self.offset);
+            let whitespace = self.whitespace(start);
+            self.previous_end = end;
+            let span = self.tracker.span(start, end);
+            let item = match result {
+                Ok(Token::CharLiteral) => {
+                    decode_char_literal(self.lexer.slice(), &span).map(|_| SpannedToken {
+                        token: Token::CharLiteral,
+                        span,
+                    })
+                }
+                Ok(token) if token.is_trivia() && !self.options.keep_comments => {
+                    if whitespace.is_some() {
+                        return whitespace.map(Ok);
+                    }
+                    continue;
+                }
+                Ok(token) => Ok(SpannedToken { token, span }),
+                Err(()) => Err(LexError {
+                    message: format!("unexpected character `{}`", self.lexer.slice()),
+                    span,
+                }),
+            };
+            if item.is_err() {
+                self.done = true;
+            }
+            return match whitespace {
+                Some(whitespace) => {
+                    self.pending = Some(item);
+                    Some(Ok(whitespace))
+                }
+                None => Some(item),
+            };
+        }
+    }
+}
+
+// One line per token, comments included: its display form, source text, and
+// start..end position, e.g. `Fn "fn" @ 1:1..1:3`. A lex error ends the dump
+// with an `error:` line.
+pub fn dump_tokens(src: &str) -> String {
+    let options = LexOptions {
+        keep_comments: true,
+        ..LexOptions::default()
+    };
+    let (tokens, error) = match tokenize_with_options(src, options) {
+        Ok(tokens) => (tokens, None),
+        Err(error) => (Vec::new(), Some(error)),
+    };
+
+    let mut output = String::new();
+    for SpannedToken { token, span } in &tokens {
+        let slice = span.slice(src);
+        let (end_line, end_column) = match slice.rfind('\n') {
+            Some(i) => (
+                span.line + slice.matches('\n').count(),
+                slice[i + 1..].chars().count() + 1,
+            ),
+            None => (span.line, span.column + slice.chars().count()),
+        };
+        output.push_str(&format!(
+            "{} {:?} @ {}:{}..{}:{}\n",
+            token, slice, span.line, span.column, end_line, end_column
+        ));
+    }
+    if let Some(error) = error {
+        output.push_str(&format!("error: {}\n", error));
+    }
+    output
+}
+
+pub const LEX_ERROR: &str = "LexError";
+
+// Counts tokens by kind (`Token::name`), e.g. to see what the lexer spends
+// its time on. Unlike `tokenize`, lexing continues past errors; each
+// unrecognized character counts once under `LEX_ERROR`.
+pub fn token_histogram(src: &str) -> HashMap<&'static str, usize> {
+    let mut counts = HashMap::new();
+    for result in Token::lexer(&src[source_start(src)..]) {
+        let name = result.map_or(LEX_ERROR, |token| token.name());
+        *counts.entry(name).or_insert(0) += 1;
+    }
+    counts
+}
+
+// Decodes a char literal slice including its quotes, e.g. `'a'` or `'\u{1F600}'`.
+pub fn decode_char_literal(slice: &str, span: &Span) -> Result<char, LexError> {
+    let error = |message: String| LexError {
+        message,
+        span: span.clone(),
+    };
+    let inner = slice
+        .strip_prefix('\'')
+        .and_then(|s| s.strip_suffix('\''))
+        .ok_or_else(|| error(format!("malformed char literal `{}`", slice)))?;
+
+    let mut chars = inner.chars();
+    let c = match chars.next() {
+        Some('\\') => decode_escape(&mut chars).map_err(error)?,
+        Some(c) => c,
+        None => return Err(error(format!("malformed char literal `{}`", slice))),
+    };
+    if chars.next().is_some() {
+        return Err(error(format!("malformed char literal `{}`", slice)));
+    }
+    Ok(c)
+}
+
+// Decodes a string literal slice including its quotes, processing escapes.
+pub fn decode_string_literal(slice: &str, span: &Span) -> Result<String, LexError> {
+    let error = |message: String| LexError {
+        message,
+        span: span.clone(),
+    };
+    let inner = slice
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or_else(|| error(format!("malformed string literal `{}`", slice)))?;
+
+    let mut decoded = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && skip_line_continuation(&mut chars) {
+            continue;
+        }
+        if c == '\\' {
+            decoded.push(decode_escape(&mut chars).map_err(error)?);
+        } else {
+            decoded.push(c);
         }
     }
+    Ok(decoded)
+}
+
+// Why `parse_integer_literal` rejected a slice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralError {
+    NoDigits,
+    InvalidDigit(char, IntBase),
+    TooLarge,
+}
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LiteralError::NoDigits => write!(f, "has no digits"),
+            LiteralError::InvalidDigit(digit, base) => write!(
+                f,
+                "has invalid digit `{}` for a base {} literal",
+                digit,
+                base.radix()
+            ),
+            LiteralError::TooLarge => write!(f, "is too large"),
+        }
+    }
+}
+
+const INTEGER_SUFFIXES: [(&str, Type); 12] = [
+    ("i8", Type::I8),
+    ("i16", Type::I16),
+    ("i32", Type::I32),
+    ("i64", Type::I64),
+    ("i128", Type::I128),
+    ("isize", Type::Isize),
+    ("u8", Type::U8),
+    ("u16", Type::U16),
+    ("u32", Type::U32),
+    ("u64", Type::U64),
+    ("u128", Type::U128),
+    ("usize", Type::Usize),
+];
+
+// Decodes an integer literal slice such as `0xFF`, `1_000` or `10u8`: the
+// base prefix picks the radix, `_` separators are dropped, and a trailing
+// integer type is returned as the suffix. No hex digit is a letter used in
+// a suffix, so `0x1f32` is all digits, as in Rust.
+pub fn parse_integer_literal(slice: &str) -> Result<(i128, IntBase, Option<Type>), LiteralError> {
+    let (base, rest) = match slice.get(..2) {
+        Some("0x") => (IntBase::Hex, &slice[2..]),
+        Some("0o") => (IntBase::Oct, &slice[2..]),
+        Some("0b") => (IntBase::Bin, &slice[2..]),
+        _ => (IntBase::Dec, slice),
+    };
+    let (digits, suffix) = match INTEGER_SUFFIXES
+        .iter()
+        .find(|(spelling, _)| rest.ends_with(spelling))
+    {
+        Some((spelling, ty)) => (&rest[..rest.len() - spelling.len()], Some(ty.clone())),
+        None => (rest, None),
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() {
+        return Err(LiteralError::NoDigits);
+    }
+    if let Some(digit) = digits.chars().find(|c| !c.is_digit(base.radix())) {
+        return Err(LiteralError::InvalidDigit(digit, base));
+    }
+    let value = i128::from_str_radix(&digits, base.radix()).map_err(|_| LiteralError::TooLarge)?;
+    Ok((value, base, suffix))
+}
+
+// A backslash at the end of a line continues the string on the next one:
+// the line break and the next line's leading whitespace are dropped. Returns
+// whether the backslash was such a continuation.
+fn skip_line_continuation(chars: &mut std::str::Chars<'_>) -> bool {
+    let rest = chars.as_str();
+    let Some(after) = rest
+        .strip_prefix('\n')
+        .or_else(|| rest.strip_prefix("\r\n"))
+    else {
+        return false;
+    };
+    let after = after.trim_start_matches([' ', '\t', '\n', '\r']);
+    *chars = after.chars();
+    true
+}
+
+// Decodes the escape sequence following a backslash.
+fn decode_escape(chars: &mut std::str::Chars<'_>) -> Result<char, String> {
+    match chars.next() {
+        Some('n') => Ok('\n'),
+        Some('r') => Ok('\r'),
+        Some('t') => Ok('\t'),
+        Some('0') => Ok('\0'),
+        Some('\\') => Ok('\\'),
+        Some('\'') => Ok('\''),
+        Some('"') => Ok('"'),
+        Some('u') => {
+            let rest = chars.as_str();
+            let escape = rest
+                .strip_prefix('{')
+                .and_then(|s| s.find('}').map(|end| &s[..end]))
+                .ok_or_else(|| "malformed unicode escape".to_string())?;
+            // Skip `{`, the digits, and `}`.
+            for _ in 0..escape.len() + 2 {
+                chars.next();
+            }
+            let value = u32::from_str_radix(escape, 16)
+                .map_err(|_| format!("unicode escape `\\u{{{}}}` is out of range", escape))?;
+            if (0xD800..=0xDFFF).contains(&value) {
+                return Err(format!(
+                    "unicode escape `\\u{{{}}}` is a surrogate, not a valid char",
+                    escape
+                ));
+            }
+            char::from_u32(value)
+                .ok_or_else(|| format!("unicode escape `\\u{{{}}}` is out of range", escape))
+        }
+        Some(other) => Err(format!("unknown escape `\\{}`", other)),
+        None => Err("unterminated escape".to_string()),
+    }
 }
 
 #[cfg(test)]
@@ -306,6 +1048,58 @@ mod tests {
         assert_eq!(lex.next(), Some(Ok(Token::False)));
     }
 
+    #[test]
+    fn test_labels() {
+        let mut lex = Token::lexer("'outer: loop { break 'outer; } 'a' '\\n'");
+        assert_eq!(lex.next(), Some(Ok(Token::Label)));
+        assert_eq!(lex.slice(), "'outer");
+        assert_eq!(lex.next(), Some(Ok(Token::Colon)));
+        assert_eq!(lex.next(), Some(Ok(Token::Loop)));
+        assert_eq!(lex.next(), Some(Ok(Token::LBrace)));
+        assert_eq!(lex.next(), Some(Ok(Token::Break)));
+        assert_eq!(lex.next(), Some(Ok(Token::Label)));
+        assert_eq!(lex.next(), Some(Ok(Token::Semicolon)));
+        assert_eq!(lex.next(), Some(Ok(Token::RBrace)));
+        assert_eq!(lex.next(), Some(Ok(Token::CharLiteral)));
+        assert_eq!(lex.next(), Some(Ok(Token::CharLiteral)));
+    }
+
+    #[test]
+    fn test_token_kinds() {
+        let options = LexOptions {
+            keep_comments: true,
+            ..LexOptions::default()
+        };
+        let kinds: Vec<TokenKind> =
+            tokenize_with_options("fn x: i32 = 'a: 1 + true; // c", options)
+                .unwrap()
+                .iter()
+                .map(|t| TokenKind::from(&t.token))
+                .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Keyword,
+                TokenKind::Identifier,
+                TokenKind::Punctuation,
+                TokenKind::PrimitiveType,
+                TokenKind::Operator,
+                TokenKind::Label,
+                TokenKind::Punctuation,
+                TokenKind::Literal,
+                TokenKind::Operator,
+                TokenKind::Literal,
+                TokenKind::Punctuation,
+                TokenKind::Comment,
+            ]
+        );
+        assert_eq!(Token::RBracket.kind(), TokenKind::Delimiter);
+        assert_eq!(Token::DotDotEq.kind(), TokenKind::Operator);
+        assert_eq!(Token::Yield.kind(), TokenKind::Keyword);
+        assert!(Token::Attribute.matches_kind(TokenKind::Attribute));
+        assert!(!Token::True.matches_kind(TokenKind::Keyword));
+    }
+
     #[test]
     fn test_identifiers() {
         let mut lex = Token::lexer("variable_name _test test123");
@@ -314,6 +1108,38 @@ mod tests {
         assert_eq!(lex.next(), Some(Ok(Token::Identifier)));
     }
 
+    #[test]
+    fn test_unicode_identifiers() {
+        let tokens = tokenize("var café = αβγ + x\u{0301};").unwrap();
+        let kinds: Vec<Token> = tokens.iter().map(|t| t.token).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Token::Var,
+                Token::Identifier,
+                Token::Assign,
+                Token::Identifier,
+                Token::Plus,
+                Token::Identifier,
+                Token::Semicolon,
+            ]
+        );
+        // Columns count characters, not bytes.
+        assert_eq!(tokens[3].span, Span::new(12, 18, 1, 12));
+        // Keywords stay keywords; a longer word is still an identifier.
+        assert_eq!(tokenize("if").unwrap()[0].token, Token::If);
+        assert_eq!(tokenize("iféé").unwrap()[0].token, Token::Identifier);
+    }
+
+    #[test]
+    fn test_emoji_identifier_is_rejected() {
+        assert!(tokenize("var 😀 = 1;").is_err());
+        let error = tokenize("var x😀 = 1;").unwrap_err();
+        assert_eq!(error.span.column, 5);
+        // A combining mark may continue an identifier but not start one.
+        assert!(tokenize("\u{0301}x").is_err());
+    }
+
     #[test]
     fn test_operators() {
         let mut lex = Token::lexer(
@@ -404,4 +1230,385 @@ fn main() -> i32 {
         assert!(tokens.contains(&Token::I32));
         assert!(tokens.contains(&Token::Return));
     }
+
+    #[test]
+    fn test_tokenize_spans() {
+        let tokens = tokenize("fn main\n  var").unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[0].token, Token::Fn);
+        assert_eq!(tokens[0].span, Span::new(0, 2, 1, 1));
+        assert_eq!(tokens[1].span, Span::new(3, 7, 1, 4));
+        assert_eq!(tokens[2].token, Token::Var);
+        assert_eq!(tokens[2].span, Span::new(10, 13, 2, 3));
+    }
+
+    #[test]
+    fn test_tokenize_produces_valid_spans() {
+        let src = "// café\nfn main() {\n    var s = \"αβ\" + 'x';\n}\n";
+        for token in tokenize_with_trivia(src).unwrap() {
+            assert!(token.span.is_valid(src.len()), "{:?}", token);
+            assert!(src.is_char_boundary(token.span.start));
+            assert!(src.is_char_boundary(token.span.end));
+        }
+    }
+
+    #[test]
+    fn test_tokenize_error() {
+        let err = tokenize("fn $").unwrap_err();
+        assert_eq!(err.span, Span::new(3, 4, 1, 4));
+    }
+
+    #[test]
+    fn test_tokenize_strips_bom() {
+        let src = "\u{feff}fn main";
+        let tokens = tokenize(src).unwrap();
+        assert_eq!(tokens[0].token, Token::Fn);
+        assert_eq!(tokens[0].span, Span::new(3, 5, 1, 1));
+        assert_eq!(&src[tokens[1].span.start..tokens[1].span.end], "main");
+    }
+
+    #[test]
+    fn test_tokenize_skips_shebang() {
+        let src = "#!/usr/bin/env zenith\nfn main";
+        let tokens = tokenize(src).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].token, Token::Fn);
+        assert_eq!(tokens[0].span, Span::new(22, 24, 2, 1));
+        assert_eq!(&src[tokens[1].span.start..tokens[1].span.end], "main");
+    }
+
+    #[test]
+    fn test_tokenize_bom_and_shebang() {
+        let src = "\u{feff}#!/usr/bin/env zenith\nfn main";
+        let tokens = tokenize(src).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].span, Span::new(25, 27, 2, 1));
+        assert_eq!(&src[tokens[1].span.start..tokens[1].span.end], "main");
+
+        // A shebang-only file has no tokens.
+        assert!(tokenize("#!/usr/bin/env zenith").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_char_literal_escapes() {
+        let span = Span::dummy();
+        assert_eq!(decode_char_literal("'a'", &span), Ok('a'));
+        assert_eq!(decode_char_literal("'\\n'", &span), Ok('\n'));
+        assert_eq!(decode_char_literal("'\\''", &span), Ok('\''));
+        assert_eq!(decode_char_literal("'\\u{1F600}'", &span), Ok('\u{1F600}'));
+    }
+
+    #[test]
+    fn test_char_literal_invalid_code_points() {
+        let err = tokenize("'\\u{D800}'").unwrap_err();
+        assert_eq!(
+            err.message,
+            "unicode escape `\\u{D800}` is a surrogate, not a valid char"
+        );
+
+        let err = tokenize("'\\u{110000}'").unwrap_err();
+        assert_eq!(err.message, "unicode escape `\\u{110000}` is out of range");
+        assert_eq!(err.span, Span::new(0, 12, 1, 1));
+
+        let tokens = tokenize("'\\u{1F600}'").unwrap();
+        assert_eq!(tokens[0].token, Token::CharLiteral);
+    }
+
+    #[test]
+    fn test_string_literal_escapes() {
+        let span = Span::dummy();
+        assert_eq!(
+            decode_string_literal(r#""a\tb\"c\u{41}""#, &span),
+            Ok("a\tb\"cA".to_string())
+        );
+        assert!(decode_string_literal(r#""\q""#, &span).is_err());
+
+        let tokens = tokenize(r#""say \"hi\"""#).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token, Token::StringLiteral);
+    }
+
+    #[test]
+    fn test_string_line_continuation() {
+        let span = Span::dummy();
+        assert_eq!(
+            decode_string_literal("\"a\\\n   \t b\"", &span),
+            Ok("ab".to_string())
+        );
+        assert_eq!(
+            decode_string_literal("\"a\\\r\n\n  b\"", &span),
+            Ok("ab".to_string())
+        );
+        // A `\n` escape is a real newline, and whitespace after it is kept.
+        assert_eq!(
+            decode_string_literal(r#""a\n  b""#, &span),
+            Ok("a\n  b".to_string())
+        );
+
+        let source = "\"long \\\n    line\"";
+        let tokens = tokenize(source).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(
+            decode_string_literal(tokens[0].span.slice(source), &span),
+            Ok("long line".to_string())
+        );
+    }
+
+    #[test]
+    fn test_shared_prefix_operators() {
+        use Token::*;
+
+        // Each group shares a prefix; the longest operator must always win.
+        let groups: &[&[(&str, Token)]] = &[
+            &[("=", Assign), ("==", Eq), ("=>", FatArrow)],
+            &[("-", Minus), ("->", Arrow), ("-=", MinusAssign)],
+            &[("<", Lt), ("<=", LtEq), ("<<", Shl), ("<<=", ShlAssign)],
+            &[(">", Gt), (">=", GtEq), (">>", Shr), (">>=", ShrAssign)],
+            &[("!", Not), ("!=", NotEq)],
+            &[("&", BitAnd), ("&&", And), ("&=", BitAndAssign)],
+            &[("|", BitOr), ("||", Or), ("|=", BitOrAssign)],
+            &[("+", Plus), ("+=", PlusAssign)],
+            &[("*", Star), ("*=", StarAssign)],
+            &[("/", Slash), ("/=", SlashAssign)],
+            &[("%", Percent), ("%=", PercentAssign)],
+            &[("^", BitXor), ("^=", BitXorAssign)],
+            &[(":", Colon), ("::", PathSep)],
+            &[
+                (".", Dot),
+                ("..", DotDot),
+                ("..=", DotDotEq),
+                ("...", Ellipsis),
+            ],
+        ];
+
+        for (source, expected) in groups.iter().flat_map(|group| group.iter()) {
+            let tokens: Vec<Token> = tokenize(&format!("a{}b", source))
+                .unwrap()
+                .into_iter()
+                .map(|t| t.token)
+                .collect();
+            assert_eq!(
+                tokens,
+                vec![Identifier, *expected, Identifier],
+                "lexing `a{}b`",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_dump_tokens() {
+        let dump = dump_tokens("fn main() {\n    @print(\"hi\");\n}");
+        assert_eq!(
+            dump,
+            "Fn \"fn\" @ 1:1..1:3\n\
+             Identifier \"main\" @ 1:4..1:8\n\
+             ( \"(\" @ 1:8..1:9\n\
+             ) \")\" @ 1:9..1:10\n\
+             { \"{\" @ 1:11..1:12\n\
+             @ \"@\" @ 2:5..2:6\n\
+             Identifier \"print\" @ 2:6..2:11\n\
+             ( \"(\" @ 2:11..2:12\n\
+             StringLiteral \"\\\"hi\\\"\" @ 2:12..2:16\n\
+             ) \")\" @ 2:16..2:17\n\
+             ; \";\" @ 2:17..2:18\n\
+             } \"}\" @ 3:1..3:2\n"
+        );
+
+        assert_eq!(
+            dump_tokens("a $"),
+            "error: unexpected character `$` at 1:3\n"
+        );
+    }
+
+    #[test]
+    fn test_lex_options_select_trivia() {
+        let src = "a /* b */ c // d";
+        let tokens = |options| -> Vec<Token> {
+            tokenize_with_options(src, options)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.token)
+                .collect()
+        };
+        use Token::*;
+        assert_eq!(tokens(LexOptions::default()), [Identifier, Identifier]);
+        assert_eq!(
+            tokenize(src).unwrap().len(),
+            2,
+            "`tokenize` drops comments by default"
+        );
+        assert_eq!(
+            tokens(LexOptions {
+                keep_comments: true,
+                ..LexOptions::default()
+            }),
+            [Identifier, MultiLineComment, Identifier, SingleLineComment]
+        );
+        assert_eq!(
+            tokens(LexOptions {
+                keep_whitespace: true,
+                ..LexOptions::default()
+            }),
+            [Identifier, Whitespace, Whitespace, Identifier, Whitespace]
+        );
+        assert_eq!(
+            tokens(LexOptions::trivia()),
+            [
+                Identifier,
+                Whitespace,
+                MultiLineComment,
+                Whitespace,
+                Identifier,
+                Whitespace,
+                SingleLineComment
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spanned_lexer_matches_tokenize() {
+        let sources = [
+            "fn main() {\n    var x = 'a'; // c\n  /* d\n e */ x\n}\n",
+            "\u{feff}#!/usr/bin/env zenith\nfn café() -> i32 { 1 }",
+            "",
+            "   ",
+        ];
+        for src in sources {
+            for options in [LexOptions::default(), LexOptions::trivia()] {
+                let streamed: Result<Vec<_>, _> =
+                    SpannedLexer::with_options(src, options).collect();
+                assert_eq!(
+                    streamed,
+                    tokenize_with_options(src, options),
+                    "lexing {:?}",
+                    src
+                );
+            }
+            assert_eq!(
+                SpannedLexer::new(src).collect::<Result<Vec<_>, _>>(),
+                tokenize(src)
+            );
+        }
+
+        let mut lexer = SpannedLexer::new("a\n  $ b");
+        assert_eq!(lexer.next().unwrap().unwrap().token, Token::Identifier);
+        assert_eq!(lexer.next(), Some(Err(tokenize("a\n  $ b").unwrap_err())));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_parse_integer_literal() {
+        assert_eq!(parse_integer_literal("0xFF"), Ok((255, IntBase::Hex, None)));
+        assert_eq!(parse_integer_literal("0o17"), Ok((15, IntBase::Oct, None)));
+        assert_eq!(parse_integer_literal("0b101"), Ok((5, IntBase::Bin, None)));
+        assert_eq!(
+            parse_integer_literal("1_000"),
+            Ok((1000, IntBase::Dec, None))
+        );
+        assert_eq!(
+            parse_integer_literal("10u8"),
+            Ok((10, IntBase::Dec, Some(Type::U8)))
+        );
+        assert_eq!(
+            parse_integer_literal("0x1f_i128"),
+            Ok((31, IntBase::Hex, Some(Type::I128)))
+        );
+        assert_eq!(
+            parse_integer_literal("0x1f32"),
+            Ok((0x1f32, IntBase::Hex, None))
+        );
+
+        let too_large = format!("0x{}", "f".repeat(33));
+        assert_eq!(
+            parse_integer_literal(&too_large),
+            Err(LiteralError::TooLarge)
+        );
+        assert_eq!(parse_integer_literal("0x_"), Err(LiteralError::NoDigits));
+        assert_eq!(
+            parse_integer_literal("0o19"),
+            Err(LiteralError::InvalidDigit('9', IntBase::Oct))
+        );
+        assert_eq!(
+            LiteralError::InvalidDigit('9', IntBase::Oct).to_string(),
+            "has invalid digit `9` for a base 8 literal"
+        );
+    }
+
+    #[test]
+    fn test_tokenize_with_trivia_covers_source() {
+        let src = "fn a() {}\n\n\n\n// b\nfn b() {}\n";
+        let tokens = tokenize_with_trivia(src).unwrap();
+        let text: String = tokens.iter().map(|t| t.span.slice(src)).collect();
+        assert_eq!(text, src);
+
+        let gap: Vec<SpannedToken> = tokens
+            .iter()
+            .skip_while(|t| t.token != Token::RBrace)
+            .skip(1)
+            .take_while(|t| t.token.is_trivia())
+            .cloned()
+            .collect();
+        assert_eq!(gap.len(), 3);
+        assert_eq!(gap[1].span.line, 5);
+        assert_eq!(newline_count(&gap, src), 5);
+        assert_eq!(newline_count(&[], src), 0);
+    }
+
+    #[test]
+    fn test_token_histogram() {
+        let src = "fn main() {\n    // count\n    var mut n = 0;\n    n = n + 1 $ $;\n}";
+        let counts = token_histogram(src);
+
+        assert_eq!(counts["Fn"], 1);
+        assert_eq!(counts["Identifier"], 4);
+        assert_eq!(counts["IntegerLiteral"], 2);
+        assert_eq!(counts["="], 2);
+        assert_eq!(counts["SingleLineComment"], 1);
+        assert_eq!(counts[LEX_ERROR], 2);
+        assert!(!counts.contains_key("Whitespace"));
+        assert_eq!(counts.values().sum::<usize>(), 21);
+    }
+
+    #[test]
+    fn test_custom_keyword() {
+        let src = "yield x";
+        let plain = tokenize(src).unwrap();
+        assert_eq!(plain[0].token, Token::Identifier);
+
+        let keywords = Keywords::new().with("yield", Token::Yield);
+        let tokens = tokenize_with_keywords(src, &keywords).unwrap();
+        assert_eq!(tokens[0].token, Token::Yield);
+        assert_eq!(tokens[1].token, Token::Identifier);
+        assert_eq!(tokens[0].span, plain[0].span);
+    }
+
+    #[test]
+    fn test_keyword_alias_and_removal() {
+        let mut keywords = Keywords::new().with("function", Token::Fn);
+        let tokens = tokenize_with_keywords("function functions", &keywords).unwrap();
+        assert_eq!(tokens[0].token, Token::Fn);
+        assert_eq!(tokens[1].token, Token::Identifier);
+
+        assert_eq!(keywords.remove("function"), Some(Token::Fn));
+        let tokens = tokenize_with_keywords("function", &keywords).unwrap();
+        assert_eq!(tokens[0].token, Token::Identifier);
+    }
+
+    #[test]
+    fn test_demoted_keyword() {
+        let mut keywords = Keywords::new().without("loop");
+        let tokens = tokenize_with_keywords("loop while", &keywords).unwrap();
+        assert_eq!(tokens[0].token, Token::Identifier);
+        assert_eq!(tokens[1].token, Token::While);
+
+        keywords.insert("loop", Token::Loop);
+        let tokens = tokenize_with_keywords("loop", &keywords).unwrap();
+        assert_eq!(tokens[0].token, Token::Loop);
+
+        keywords.demote("loop");
+        assert_eq!(keywords.remove("loop"), None);
+        let tokens = tokenize_with_keywords("loop", &keywords).unwrap();
+        assert_eq!(tokens[0].token, Token::Loop);
+    }
 }
@@ -1,2 +1,17 @@
-mod ast;
-mod lexer;
+pub mod analysis;
+pub mod ast;
+pub mod cfg;
+pub mod checker;
+pub mod comments;
+pub mod desugar;
+pub mod diagnostics;
+pub mod hir;
+pub mod lexer;
+pub mod modules;
+pub mod parser;
+pub mod printer;
+pub mod serialize;
+pub mod unit;
+
+pub use analysis::{AnalysisResult, analyze};
+pub use unit::CompileUnit;
@@ -0,0 +1,84 @@
+use crate::ast::{Declaration, Program};
+
+// Fills in every `mod name;` declaration using `load_module`, which receives
+// the module's path from the crate root (`["a", "b"]` for `mod b;` inside
+// `a`) and returns its parsed items. Loaded modules may declare further
+// file modules; those are loaded in turn. Stops at the first loader error.
+pub fn load_modules<E>(
+    program: &mut Program,
+    mut load_module: impl FnMut(&[String]) -> Result<Vec<Declaration>, E>,
+) -> Result<(), E> {
+    load_items(&mut program.items, &mut Vec::new(), &mut load_module)
+}
+
+fn load_items<E>(
+    items: &mut [Declaration],
+    path: &mut Vec<String>,
+    load_module: &mut impl FnMut(&[String]) -> Result<Vec<Declaration>, E>,
+) -> Result<(), E> {
+    for item in items {
+        let Declaration::Module(module) = item else {
+            continue;
+        };
+        path.push(module.name.name.clone());
+        if module.items.is_none() {
+            module.items = Some(load_module(path)?);
+        }
+        if let Some(items) = &mut module.items {
+            load_items(items, path, load_module)?;
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn module_items(item: &Declaration) -> &[Declaration] {
+        match item {
+            Declaration::Module(module) => module.items.as_deref().unwrap(),
+            _ => panic!("expected module"),
+        }
+    }
+
+    #[test]
+    fn test_inline_module_is_not_loaded() {
+        let mut program = parse("mod math { fn one() -> i32 { 1 } }").unwrap();
+        load_modules(&mut program, |path| -> Result<_, String> {
+            panic!("unexpected load of {:?}", path)
+        })
+        .unwrap();
+
+        assert_eq!(module_items(&program.items[0]).len(), 1);
+    }
+
+    #[test]
+    fn test_file_modules_are_loaded_by_path() {
+        let mut program = parse("mod net;").unwrap();
+        let mut requested = Vec::new();
+        load_modules(&mut program, |path| {
+            requested.push(path.join("::"));
+            match path {
+                [net] if net == "net" => parse("mod http; fn connect() {}").map(|p| p.items),
+                _ => parse("fn get() {}").map(|p| p.items),
+            }
+        })
+        .unwrap();
+
+        assert_eq!(requested, vec!["net", "net::http"]);
+        let net = module_items(&program.items[0]);
+        assert_eq!(net.len(), 2);
+        assert_eq!(module_items(&net[0]).len(), 1);
+    }
+
+    #[test]
+    fn test_loader_error_is_returned() {
+        let mut program = parse("mod missing;").unwrap();
+        let result = load_modules(&mut program, |path| Err(format!("no file for {}", path[0])));
+
+        assert_eq!(result, Err("no file for missing".to_string()));
+    }
+}
@@ -0,0 +1,1061 @@
+use super::{Expected, ParseError, ParseResult, Parser};
+use crate::ast::expressions::{
+    BinaryExpr, BinaryOperator, CallExpr, CastExpr, Expression, ForExpr, IfExpr, IndexExpr,
+    Literal, LoopExpr, MacroInvocation, MatchArm, MatchExpr, MemberExpr, PanicExpr, Pattern,
+    RangeExpr, StructExpr, UnaryExpr, UnaryOperator, WhileExpr,
+};
+use crate::ast::types::{TypePath, TypePathSegment};
+use crate::ast::{Identifier, Span};
+use crate::lexer::{self, Token, TokenKind};
+
+impl Parser<'_> {
+    pub fn parse_expression(&mut self) -> ParseResult<Expression> {
+        self.nested(|p| Ok(p.parse_range()?.0))
+    }
+
+    // The scrutinee of `match` or the head of `if`/`while`/`for`, which is
+    // followed by a block and so cannot end in a struct literal.
+    pub(super) fn parse_expression_before_block(&mut self) -> ParseResult<Expression> {
+        self.with_struct_literals(false, Parser::parse_expression)
+    }
+
+    fn parse_range(&mut self) -> ParseResult<(Expression, Span)> {
+        let start_span = self.current_span();
+        let start = if matches!(self.peek(), Some(Token::DotDot | Token::DotDotEq)) {
+            None
+        } else {
+            let (start, span) = self.parse_binary(1)?;
+            if !matches!(self.peek(), Some(Token::DotDot | Token::DotDotEq)) {
+                return Ok((start, span));
+            }
+            Some(start)
+        };
+        let inclusive = self.advance().token == Token::DotDotEq;
+        let end = if self.starts_expression() {
+            Some(self.parse_binary(1)?.0)
+        } else {
+            None
+        };
+        let span = start_span.join(&self.previous_span());
+        let range = RangeExpr {
+            start,
+            end,
+            inclusive,
+            span: span.clone(),
+        };
+        Ok((Expression::Range(Box::new(range)), span))
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> ParseResult<(Expression, Span)> {
+        // A left-associative chain nests one level deeper with every operator,
+        // however flat it looks, so each one counts toward the depth limit.
+        // Casts and postfix operators below do the same.
+        self.chained(|p| p.parse_binary_chain(min_precedence))
+    }
+
+    fn parse_binary_chain(&mut self, min_precedence: u8) -> ParseResult<(Expression, Span)> {
+        let (mut left, mut span) = self.parse_cast()?;
+        while let Some(operator) = self.peek().and_then(binary_operator) {
+            let precedence = operator.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            self.deepen()?;
+            self.advance();
+            let next = if operator.is_right_associative() {
+                precedence
+            } else {
+                precedence + 1
+            };
+            let (right, right_span) = self.nested(|p| p.parse_binary(next))?;
+            span = span.join(&right_span);
+            left = Expression::Binary(Box::new(BinaryExpr {
+                left,
+                operator,
+                right,
+                span: span.clone(),
+            }));
+        }
+        if self.peek().and_then(binary_operator).is_none() {
+            self.note_expected(Expected::Operator);
+        }
+        Ok((left, span))
+    }
+
+    fn parse_cast(&mut self) -> ParseResult<(Expression, Span)> {
+        self.chained(Self::parse_cast_chain)
+    }
+
+    fn parse_cast_chain(&mut self) -> ParseResult<(Expression, Span)> {
+        let (mut expr, mut span) = self.parse_unary()?;
+        while self.check(Token::As) {
+            self.deepen()?;
+            self.advance();
+            let target_type = self.parse_type()?;
+            span = span.join(&self.previous_span());
+            expr = Expression::Cast(Box::new(CastExpr {
+                expr,
+                target_type,
+                span: span.clone(),
+            }));
+        }
+        Ok((expr, span))
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<(Expression, Span)> {
+        let start = self.current_span();
+        let operator = match self.peek() {
+            Some(Token::Minus) => UnaryOperator::Neg,
+            Some(Token::Not) => UnaryOperator::Not,
+            Some(Token::BitNot) => UnaryOperator::BitNot,
+            Some(Token::Star) => UnaryOperator::Deref,
+            Some(Token::BitAnd) => {
+                self.advance();
+                let operator = if self.eat(Token::Mut) {
+                    UnaryOperator::RefMut
+                } else {
+                    UnaryOperator::Ref
+                };
+                return self.finish_unary(operator, start);
+            }
+            Some(Token::And) => {
+                // `&&x` is lexed as a single token: a reference to a reference.
+                // In `&&mut x` the `mut` belongs to the inner reference.
+                self.advance();
+                let operator = if self.eat(Token::Mut) {
+                    UnaryOperator::RefMut
+                } else {
+                    UnaryOperator::Ref
+                };
+                let (inner, span) = self.nested(Self::parse_unary)?;
+                let inner = unary(operator, inner, start.join(&span));
+                let span = start.join(&span);
+                return Ok((unary(UnaryOperator::Ref, inner, span.clone()), span));
+            }
+            _ => return self.parse_postfix(),
+        };
+        self.advance();
+        self.finish_unary(operator, start)
+    }
+
+    fn finish_unary(
+        &mut self,
+        operator: UnaryOperator,
+        start: Span,
+    ) -> ParseResult<(Expression, Span)> {
+        let (operand, span) = self.nested(Self::parse_unary)?;
+        let span = start.join(&span);
+        Ok((unary(operator, operand, span.clone()), span))
+    }
+
+    fn parse_postfix(&mut self) -> ParseResult<(Expression, Span)> {
+        self.chained(Self::parse_postfix_chain)
+    }
+
+    fn parse_postfix_chain(&mut self) -> ParseResult<(Expression, Span)> {
+        let (mut expr, mut span) = self.parse_primary()?;
+        loop {
+            if matches!(
+                self.peek(),
+                Some(Token::LParen | Token::Dot | Token::LBracket)
+            ) {
+                self.deepen()?;
+            }
+            match self.peek() {
+                Some(Token::LParen) => {
+                    self.advance();
+                    let arguments =
+                        self.parse_comma_separated(Token::RParen, Parser::parse_expression)?;
+                    span = span.join(&self.previous_span());
+                    expr = Expression::Call(Box::new(CallExpr {
+                        callee: expr,
+                        arguments,
+                        span: span.clone(),
+                    }));
+                }
+                Some(Token::Dot) => {
+                    self.advance();
+                    let member = if self.check(Token::IntegerLiteral) {
+                        let span = self.advance().span;
+                        Identifier::new(self.text(&span).to_string(), span)
+                    } else {
+                        self.parse_identifier()?
+                    };
+                    span = span.join(&member.span);
+                    expr = Expression::Member(Box::new(MemberExpr {
+                        object: expr,
+                        member,
+                        span: span.clone(),
+                    }));
+                }
+                Some(Token::LBracket) => {
+                    self.advance();
+                    let index = self.with_struct_literals(true, Parser::parse_expression)?;
+                    span = span.join(&self.expect(Token::RBracket)?);
+                    expr = Expression::Index(Box::new(IndexExpr {
+                        array: expr,
+                        index,
+                        span: span.clone(),
+                    }));
+                }
+                _ => return Ok((expr, span)),
+            }
+        }
+    }
+
+    // `panic(message)`, as an expression or, followed by `;`, a statement.
+    pub(super) fn parse_panic(&mut self) -> ParseResult<PanicExpr> {
+        let start = self.expect(Token::Panic)?;
+        self.expect(Token::LParen)?;
+        let message = self.parse_expression()?;
+        let end = self.expect(Token::RParen)?;
+        Ok(PanicExpr {
+            message,
+            span: start.join(&end),
+        })
+    }
+
+    // Each compound form is parsed by its own function so that this frame,
+    // which every nesting level passes through, stays small.
+    fn parse_primary(&mut self) -> ParseResult<(Expression, Span)> {
+        let Some(token) = self.peek() else {
+            return Err(self.error_expected(Expected::Expression));
+        };
+        let start = self.current_span();
+        let expr = match token {
+            Token::Identifier => {
+                let name = self.parse_identifier()?;
+                if self.check(Token::PathSep) {
+                    self.parse_expression_path(name)?
+                } else if self.struct_literals && self.check(Token::LBrace) {
+                    self.parse_struct_expression(name)?
+                } else {
+                    Expression::Identifier(name)
+                }
+            }
+            Token::LParen => self.parse_parenthesized(&start)?,
+            Token::LBracket => {
+                self.advance();
+                let elements =
+                    self.parse_comma_separated(Token::RBracket, Parser::parse_expression)?;
+                Expression::Literal(Literal::Array(elements))
+            }
+            Token::LBrace => Expression::Block(Box::new(self.parse_block()?)),
+            Token::If => self.parse_if_expression()?,
+            Token::Match => self.parse_match_expression(&start)?,
+            Token::Loop | Token::While | Token::For => self.parse_loop_expression(&start)?,
+            Token::Label => self.parse_labeled_loop(&start)?,
+            Token::Panic => Expression::Panic(Box::new(self.parse_panic()?)),
+            Token::MacroInvoke => self.parse_macro_invocation(&start)?,
+            _ => self.parse_literal_expression()?,
+        };
+        Ok((expr, start.join(&self.previous_span())))
+    }
+
+    // `()`, `(expr)` or a tuple `(a, b)`.
+    fn parse_parenthesized(&mut self, start: &Span) -> ParseResult<Expression> {
+        self.advance();
+        if self.eat(Token::RParen) {
+            return Ok(Expression::Literal(Literal::Unit));
+        }
+        let expr = self.with_struct_literals(true, Parser::parse_expression)?;
+        if self.eat(Token::Comma) {
+            let mut elements = vec![expr];
+            elements.extend(self.parse_comma_separated(Token::RParen, Parser::parse_expression)?);
+            Ok(Expression::Literal(Literal::Tuple(elements)))
+        } else {
+            self.expect(Token::RParen)?;
+            Ok(Expression::Paren(
+                Box::new(expr),
+                start.join(&self.previous_span()),
+            ))
+        }
+    }
+
+    fn parse_match_expression(&mut self, start: &Span) -> ParseResult<Expression> {
+        self.advance();
+        let value = self.parse_expression_before_block()?;
+        let arms = self.parse_match_arms()?;
+        Ok(Expression::Match(Box::new(MatchExpr {
+            value,
+            arms,
+            span: start.join(&self.previous_span()),
+        })))
+    }
+
+    // `loop`, `while` or `for`, without a label.
+    fn parse_loop_expression(&mut self, start: &Span) -> ParseResult<Expression> {
+        let keyword = self.advance().token;
+        let expr = match keyword {
+            Token::Loop => {
+                let body = Expression::Block(Box::new(self.parse_block()?));
+                Expression::Loop(Box::new(LoopExpr {
+                    body,
+                    label: None,
+                    span: start.join(&self.previous_span()),
+                }))
+            }
+            Token::While => {
+                let condition = self.parse_expression_before_block()?;
+                let body = Expression::Block(Box::new(self.parse_block()?));
+                Expression::While(Box::new(WhileExpr {
+                    condition,
+                    body,
+                    label: None,
+                    span: start.join(&self.previous_span()),
+                }))
+            }
+            _ => {
+                let pattern = self.parse_pattern()?;
+                self.expect(Token::In)?;
+                let iterator = self.parse_expression_before_block()?;
+                let body = Expression::Block(Box::new(self.parse_block()?));
+                Expression::For(Box::new(ForExpr {
+                    pattern,
+                    iterator,
+                    body,
+                    label: None,
+                    span: start.join(&self.previous_span()),
+                }))
+            }
+        };
+        Ok(expr)
+    }
+
+    fn parse_labeled_loop(&mut self, start: &Span) -> ParseResult<Expression> {
+        let label = self.parse_loop_label()?;
+        let loop_start = self.current_span();
+        let mut expr = self.parse_loop_expression(&loop_start)?;
+        match &mut expr {
+            Expression::Loop(loop_expr) => {
+                loop_expr.span = start.join(&loop_expr.span);
+                loop_expr.label = Some(label);
+            }
+            Expression::While(while_expr) => {
+                while_expr.span = start.join(&while_expr.span);
+                while_expr.label = Some(label);
+            }
+            Expression::For(for_expr) => {
+                for_expr.span = start.join(&for_expr.span);
+                for_expr.label = Some(label);
+            }
+            _ => unreachable!("`parse_loop_expression` only parses loops"),
+        }
+        Ok(expr)
+    }
+
+    fn parse_macro_invocation(&mut self, start: &Span) -> ParseResult<Expression> {
+        self.advance();
+        let name = self.parse_identifier()?;
+        self.expect(Token::LParen)?;
+        let arguments = self.parse_comma_separated(Token::RParen, Parser::parse_expression)?;
+        Ok(Expression::MacroInvocation(Box::new(MacroInvocation {
+            name,
+            arguments,
+            span: start.join(&self.previous_span()),
+        })))
+    }
+
+    // A literal token; adjacent string literals are joined into one.
+    fn parse_literal_expression(&mut self) -> ParseResult<Expression> {
+        let token = self.advance();
+        match self.literal_from_token(&token)? {
+            Some(Literal::String(mut value)) if self.concat_strings => {
+                while self.check(Token::StringLiteral) {
+                    let next = self.advance();
+                    value.push_str(&lexer::decode_string_literal(
+                        self.text(&next.span),
+                        &next.span,
+                    )?);
+                }
+                Ok(Expression::Literal(Literal::String(value)))
+            }
+            Some(literal) => Ok(Expression::Literal(literal)),
+            None => {
+                self.pos -= 1;
+                Err(self.error_expected(Expected::Expression))
+            }
+        }
+    }
+
+    // `first::second`, where any segment may be followed by `::<Args>`, as in
+    // `Vec::<u8>::new`. The arguments attach to the segment they follow.
+    fn parse_expression_path(&mut self, first: Identifier) -> ParseResult<Expression> {
+        let start = first.span.clone();
+        let mut segments = vec![TypePathSegment {
+            span: first.span.clone(),
+            ident: first,
+            generic_args: None,
+        }];
+        while self.eat(Token::PathSep) {
+            let last = segments.len() - 1;
+            if segments[last].generic_args.is_none() && self.eat(Token::Lt) {
+                let args = self.parse_generic_args()?;
+                let segment = &mut segments[last];
+                segment.generic_args = Some(args);
+                segment.span = segment.ident.span.join(&self.previous_span());
+            } else {
+                let ident = self.parse_identifier()?;
+                segments.push(TypePathSegment {
+                    span: ident.span.clone(),
+                    ident,
+                    generic_args: None,
+                });
+            }
+        }
+        Ok(Expression::Path(TypePath {
+            segments,
+            span: start.join(&self.previous_span()),
+        }))
+    }
+
+    // `{ field: value, shorthand, ..base }` after the struct name.
+    fn parse_struct_expression(&mut self, name: Identifier) -> ParseResult<Expression> {
+        self.expect(Token::LBrace)?;
+        let mut fields = Vec::new();
+        let mut rest = None;
+        while !self.eat(Token::RBrace) {
+            if self.eat(Token::DotDot) {
+                rest = Some(Box::new(
+                    self.with_struct_literals(true, Parser::parse_expression)?,
+                ));
+                self.expect(Token::RBrace)?;
+                break;
+            }
+            let field = self.parse_identifier()?;
+            let value = if self.eat(Token::Colon) {
+                self.with_struct_literals(true, Parser::parse_expression)?
+            } else {
+                Expression::Identifier(field.clone())
+            };
+            fields.push((field, value));
+            if !self.eat(Token::Comma) {
+                self.expect(Token::RBrace)?;
+                break;
+            }
+        }
+        Ok(Expression::Struct(Box::new(StructExpr {
+            span: name.span.join(&self.previous_span()),
+            name,
+            fields,
+            rest,
+        })))
+    }
+
+    fn parse_if_expression(&mut self) -> ParseResult<Expression> {
+        let start = self.expect(Token::If)?;
+        let condition = self.parse_expression_before_block()?;
+        let then_branch = Expression::Block(Box::new(self.parse_block()?));
+        let else_branch = if self.eat(Token::Else) {
+            if self.check(Token::If) {
+                Some(self.parse_if_expression()?)
+            } else {
+                Some(Expression::Block(Box::new(self.parse_block()?)))
+            }
+        } else {
+            None
+        };
+        Ok(Expression::If(Box::new(IfExpr {
+            condition,
+            then_branch,
+            else_branch,
+            span: start.join(&self.previous_span()),
+        })))
+    }
+
+    pub(super) fn parse_match_arms(&mut self) -> ParseResult<Vec<MatchArm>> {
+        self.expect(Token::LBrace)?;
+        let mut arms = Vec::new();
+        while !self.eat(Token::RBrace) {
+            let start = self.current_span();
+            let pattern = self.parse_pattern()?;
+            let guard = if self.eat(Token::If) {
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
+            self.expect(Token::FatArrow)?;
+            let body = self.parse_expression()?;
+            let span = start.join(&self.previous_span());
+            let block_like = is_block_like(&body);
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+                span,
+            });
+            if !self.eat(Token::Comma) && !block_like {
+                self.expect(Token::RBrace)?;
+                break;
+            }
+        }
+        Ok(arms)
+    }
+
+    pub(super) fn parse_pattern(&mut self) -> ParseResult<Pattern> {
+        self.nested(Self::parse_pattern_inner)
+    }
+
+    fn parse_pattern_inner(&mut self) -> ParseResult<Pattern> {
+        let mut alternatives = vec![self.parse_single_pattern()?];
+        while self.eat(Token::BitOr) {
+            alternatives.push(self.parse_single_pattern()?);
+        }
+        if alternatives.len() == 1 {
+            Ok(alternatives.remove(0))
+        } else {
+            Ok(Pattern::Or(alternatives))
+        }
+    }
+
+    fn parse_single_pattern(&mut self) -> ParseResult<Pattern> {
+        match self.peek() {
+            Some(Token::Identifier) => {
+                let ident = self.parse_identifier()?;
+                if ident.name == "_" {
+                    return Ok(Pattern::Wildcard);
+                }
+                if !self.eat(Token::LBrace) {
+                    return Ok(Pattern::Identifier(ident));
+                }
+                let mut fields = Vec::new();
+                let mut rest = false;
+                while !self.eat(Token::RBrace) {
+                    // `..` must be the last entry of the field list.
+                    if self.eat(Token::DotDot) {
+                        rest = true;
+                        self.expect(Token::RBrace)?;
+                        break;
+                    }
+                    let field = self.parse_identifier()?;
+                    let pattern = if self.eat(Token::Colon) {
+                        self.parse_pattern()?
+                    } else {
+                        Pattern::Identifier(field.clone())
+                    };
+                    fields.push((field, pattern));
+                    if !self.eat(Token::Comma) {
+                        self.expect(Token::RBrace)?;
+                        break;
+                    }
+                }
+                Ok(Pattern::Struct(ident, fields, rest))
+            }
+            Some(token) if token.matches_kind(TokenKind::Keyword) => {
+                Err(self.keyword_as_identifier())
+            }
+            Some(Token::LParen) => {
+                self.advance();
+                let mut trailing_comma = false;
+                let mut patterns = Vec::new();
+                while !self.eat(Token::RParen) {
+                    // At most one `..`; a second one fails to parse as a pattern.
+                    if !patterns.contains(&Pattern::Rest) && self.eat(Token::DotDot) {
+                        patterns.push(Pattern::Rest);
+                    } else {
+                        patterns.push(self.parse_pattern()?);
+                    }
+                    trailing_comma = self.eat(Token::Comma);
+                    if !trailing_comma {
+                        self.expect(Token::RParen)?;
+                        break;
+                    }
+                }
+                match patterns.len() {
+                    0 => Ok(Pattern::Literal(Literal::Unit)),
+                    1 if !trailing_comma && patterns[0] != Pattern::Rest => Ok(patterns.remove(0)),
+                    _ => Ok(Pattern::Tuple(patterns)),
+                }
+            }
+            _ => {
+                let start = self.parse_literal_pattern()?;
+                if self.eat(Token::DotDotEq) {
+                    let end = self.parse_literal_pattern()?;
+                    return Ok(Pattern::Range(Box::new(start), Box::new(end)));
+                }
+                Ok(start)
+            }
+        }
+    }
+
+    fn parse_literal_pattern(&mut self) -> ParseResult<Pattern> {
+        let negative = self.eat(Token::Minus);
+        let token = self.advance_or_eof(Expected::Pattern)?;
+        let literal = match self.literal_from_token(&token)? {
+            Some(Literal::Integer(value, suffix, base)) if negative => {
+                Literal::Integer(-value, suffix, base)
+            }
+            Some(Literal::Float(value, suffix)) if negative => Literal::Float(-value, suffix),
+            Some(literal) if !negative => literal,
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: vec![Expected::Pattern],
+                    found: token.token,
+                    span: token.span,
+                });
+            }
+        };
+        Ok(Pattern::Literal(literal))
+    }
+
+    pub(super) fn starts_expression(&self) -> bool {
+        match self.peek() {
+            Some(token) => !matches!(
+                token,
+                Token::Semicolon
+                    | Token::Comma
+                    | Token::RParen
+                    | Token::RBracket
+                    | Token::RBrace
+                    | Token::FatArrow
+                    | Token::LBrace
+            ),
+            None => false,
+        }
+    }
+}
+
+fn unary(operator: UnaryOperator, operand: Expression, span: Span) -> Expression {
+    Expression::Unary(Box::new(UnaryExpr {
+        operator,
+        operand,
+        span,
+    }))
+}
+
+pub(super) fn is_block_like(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::Block(_)
+            | Expression::If(_)
+            | Expression::Match(_)
+            | Expression::Loop(_)
+            | Expression::While(_)
+            | Expression::For(_)
+    )
+}
+
+fn binary_operator(token: Token) -> Option<BinaryOperator> {
+    let operator = match token {
+        Token::Plus => BinaryOperator::Add,
+        Token::Minus => BinaryOperator::Sub,
+        Token::Star => BinaryOperator::Mul,
+        Token::Slash => BinaryOperator::Div,
+        Token::Percent => BinaryOperator::Rem,
+        Token::And => BinaryOperator::And,
+        Token::Or => BinaryOperator::Or,
+        Token::BitAnd => BinaryOperator::BitAnd,
+        Token::BitOr => BinaryOperator::BitOr,
+        Token::BitXor => BinaryOperator::BitXor,
+        Token::Shl => BinaryOperator::Shl,
+        Token::Shr => BinaryOperator::Shr,
+        Token::Eq => BinaryOperator::Eq,
+        Token::NotEq => BinaryOperator::NotEq,
+        Token::Lt => BinaryOperator::Lt,
+        Token::LtEq => BinaryOperator::LtEq,
+        Token::Gt => BinaryOperator::Gt,
+        Token::GtEq => BinaryOperator::GtEq,
+        Token::Assign => BinaryOperator::Assign,
+        Token::PlusAssign => BinaryOperator::AddAssign,
+        Token::MinusAssign => BinaryOperator::SubAssign,
+        Token::StarAssign => BinaryOperator::MulAssign,
+        Token::SlashAssign => BinaryOperator::DivAssign,
+        Token::PercentAssign => BinaryOperator::RemAssign,
+        Token::BitAndAssign => BinaryOperator::BitAndAssign,
+        Token::BitOrAssign => BinaryOperator::BitOrAssign,
+        Token::BitXorAssign => BinaryOperator::BitXorAssign,
+        Token::ShlAssign => BinaryOperator::ShlAssign,
+        Token::ShrAssign => BinaryOperator::ShrAssign,
+        _ => return None,
+    };
+    Some(operator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Type;
+    use crate::ast::expressions::IntBase;
+    use crate::printer::Printer;
+
+    fn parse_expression(source: &str) -> Expression {
+        Parser::new(source).unwrap().parse_expression().unwrap()
+    }
+
+    fn round_trip(source: &str) -> String {
+        let mut printer = Printer::new();
+        printer.print_expression(&parse_expression(source));
+        printer.finish()
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(round_trip("a + b * c"), "a + b * c");
+        assert_eq!(round_trip("(a + b) * c"), "(a + b) * c");
+        assert_eq!(round_trip("a = b = c"), "a = b = c");
+        assert_eq!(round_trip("-x as u8"), "-x as u8");
+        assert_eq!(round_trip("f(x)[0].y"), "f(x)[0].y");
+    }
+
+    fn unary_operator(expr: &Expression) -> UnaryOperator {
+        let Expression::Unary(unary) = expr else {
+            panic!("expected unary expression, found {:?}", expr);
+        };
+        unary.operator.clone()
+    }
+
+    #[test]
+    fn test_parse_panic_expression() {
+        let Expression::Panic(panic) = parse_expression("panic(\"x\")") else {
+            panic!("expected panic expression");
+        };
+        assert_eq!(
+            panic.message,
+            Expression::Literal(Literal::String("x".to_string()))
+        );
+        assert_eq!(panic.span, Span::new(0, 10, 1, 1));
+
+        let Expression::Binary(or) = parse_expression("ok || panic(\"bad\")") else {
+            panic!("expected binary expression");
+        };
+        assert!(matches!(or.right, Expression::Panic(_)));
+        assert_eq!(round_trip("ok || panic(\"bad\")"), "ok || panic(\"bad\")");
+    }
+
+    #[test]
+    fn test_parse_reference_and_deref() {
+        assert_eq!(unary_operator(&parse_expression("&x")), UnaryOperator::Ref);
+        assert_eq!(
+            unary_operator(&parse_expression("&mut x")),
+            UnaryOperator::RefMut
+        );
+        assert_eq!(
+            unary_operator(&parse_expression("*p")),
+            UnaryOperator::Deref
+        );
+        let Expression::Unary(outer) = parse_expression("&&mut x") else {
+            panic!("expected unary expression");
+        };
+        assert_eq!(outer.operator, UnaryOperator::Ref);
+        assert_eq!(unary_operator(&outer.operand), UnaryOperator::RefMut);
+
+        let Expression::Binary(product) = parse_expression("a * *p") else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(product.operator, BinaryOperator::Mul);
+        assert_eq!(unary_operator(&product.right), UnaryOperator::Deref);
+        let Expression::Binary(and) = parse_expression("a & &b") else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(and.operator, BinaryOperator::BitAnd);
+        assert_eq!(unary_operator(&and.right), UnaryOperator::Ref);
+        assert_eq!(round_trip("a * b"), "a * b");
+        assert_eq!(round_trip("&mut *p"), "&mut *p");
+    }
+
+    #[test]
+    fn test_explicit_parentheses_are_kept() {
+        let Expression::Binary(product) = parse_expression("(a + b) * c") else {
+            panic!("expected binary expression");
+        };
+        let Expression::Paren(sum, span) = &product.left else {
+            panic!("expected parenthesized left operand");
+        };
+        assert!(matches!(sum.as_ref(), Expression::Binary(_)));
+        assert_eq!(*span, Span::new(0, 7, 1, 1));
+
+        assert_eq!(round_trip("(a + b) * c"), "(a + b) * c");
+        assert_eq!(round_trip("(a * b) + c"), "(a * b) + c");
+        assert_eq!(round_trip("((x))"), "((x))");
+        assert_eq!(round_trip("-(x as u8)"), "-(x as u8)");
+    }
+
+    #[test]
+    fn test_parse_literals() {
+        assert_eq!(
+            parse_expression("\"a\\nb\""),
+            Expression::Literal(Literal::String("a\nb".to_string()))
+        );
+        assert_eq!(
+            parse_expression("'x'"),
+            Expression::Literal(Literal::Character('x'))
+        );
+        assert_eq!(parse_expression("()"), Expression::Literal(Literal::Unit));
+        let error = Parser::new("999999999999999999999999999999999999999999")
+            .unwrap()
+            .parse_expression()
+            .unwrap_err();
+        assert!(matches!(error, ParseError::InvalidLiteral { .. }));
+    }
+
+    #[test]
+    fn test_adjacent_strings_concatenate() {
+        let parse = |source| {
+            Parser::new(source)
+                .unwrap()
+                .with_string_concatenation(true)
+                .parse_expression()
+                .unwrap()
+        };
+        assert_eq!(
+            parse("\"foo\" \"bar\""),
+            Expression::Literal(Literal::String("foobar".to_string()))
+        );
+        let Expression::Call(call) = parse("f(\"a\"\n    \"b\\n\", \"c\")") else {
+            panic!("expected call");
+        };
+        assert_eq!(
+            call.arguments,
+            vec![
+                Expression::Literal(Literal::String("ab\n".to_string())),
+                Expression::Literal(Literal::String("c".to_string())),
+            ]
+        );
+        assert_eq!(
+            parse("(\"foo\", \"bar\")"),
+            Expression::Literal(Literal::Tuple(vec![
+                Expression::Literal(Literal::String("foo".to_string())),
+                Expression::Literal(Literal::String("bar".to_string())),
+            ]))
+        );
+
+        // Without the flag the second literal is left unparsed.
+        let mut parser = Parser::new("\"foo\" \"bar\"").unwrap();
+        parser.parse_expression().unwrap();
+        assert!(!parser.at_end());
+    }
+
+    #[test]
+    fn test_parse_integer_bases() {
+        assert_eq!(
+            parse_expression("0xFF"),
+            Expression::Literal(Literal::Integer(255, None, IntBase::Hex))
+        );
+        assert_eq!(round_trip("0xFF"), "0xff");
+        assert_eq!(round_trip("0b1010 | 0o17"), "0b1010 | 0o17");
+        assert_eq!(round_trip("1_000_000"), "1000000");
+        assert_eq!(round_trip("0xdead_beef"), "0xdeadbeef");
+    }
+
+    #[test]
+    fn test_parse_turbofish() {
+        let Expression::Call(call) = parse_expression("foo::<i32>()") else {
+            panic!("expected a call");
+        };
+        let Expression::Path(path) = &call.callee else {
+            panic!("expected a path callee");
+        };
+        assert_eq!(path.segments.len(), 1);
+        assert_eq!(path.segments[0].generic_args, Some(vec![Type::I32]));
+        assert_eq!(path.span, Span::new(0, 10, 1, 1));
+
+        let Expression::Call(call) = parse_expression("Vec::<u8>::new()") else {
+            panic!("expected a call");
+        };
+        let Expression::Path(path) = &call.callee else {
+            panic!("expected a path callee");
+        };
+        let segments: Vec<(&str, Option<&[Type]>)> = path
+            .segments
+            .iter()
+            .map(|s| (s.ident.name.as_str(), s.generic_args.as_deref()))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![("Vec", Some(&[Type::U8][..])), ("new", None)]
+        );
+
+        assert_eq!(
+            round_trip("a::b::<Map<u8, i32>>(x) < c"),
+            "a::b::<Map<u8, i32>>(x) < c"
+        );
+        assert!(
+            Parser::new("f::<i32>::<u8>")
+                .unwrap()
+                .parse_expression()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_literal_suffixes() {
+        assert_eq!(
+            parse_expression("4294967296usize"),
+            Expression::Literal(Literal::Integer(
+                4294967296,
+                Some(Type::Usize),
+                IntBase::Dec
+            ))
+        );
+        assert_eq!(round_trip("0xFFu8 + 2.5f32"), "0xffu8 + 2.5f32");
+        assert_eq!(round_trip("1 as u8"), "1 as u8");
+    }
+
+    #[test]
+    fn test_parse_match_expression() {
+        let Expression::Match(expr) =
+            parse_expression("match x { 0 | 1 => a, n if n > 9 => { b } _ => c }")
+        else {
+            panic!("expected match");
+        };
+        assert_eq!(expr.arms.len(), 3);
+        assert!(matches!(expr.arms[0].pattern, Pattern::Or(_)));
+        assert!(expr.arms[1].guard.is_some());
+        assert_eq!(expr.arms[2].pattern, Pattern::Wildcard);
+    }
+
+    #[test]
+    fn test_parse_rest_patterns() {
+        let Expression::Match(expr) =
+            parse_expression("match p { Point { x, .. } => x, Point { .. } => 0 }")
+        else {
+            panic!("expected match");
+        };
+        let Pattern::Struct(name, fields, rest) = &expr.arms[0].pattern else {
+            panic!("expected struct pattern");
+        };
+        assert_eq!(name.name, "Point");
+        assert_eq!(fields.len(), 1);
+        assert!(rest);
+        assert!(
+            matches!(&expr.arms[1].pattern, Pattern::Struct(_, fields, true) if fields.is_empty())
+        );
+
+        let Expression::Match(expr) = parse_expression("match t { (a, .., z) => a, (..) => 0 }")
+        else {
+            panic!("expected match");
+        };
+        let Pattern::Tuple(patterns) = &expr.arms[0].pattern else {
+            panic!("expected tuple pattern");
+        };
+        assert_eq!(patterns.len(), 3);
+        assert_eq!(patterns[1], Pattern::Rest);
+        assert_eq!(expr.arms[1].pattern, Pattern::Tuple(vec![Pattern::Rest]));
+        assert_eq!(
+            round_trip("match t { (a, .., z) => a, Point { x: 1, .. } => 0 }"),
+            "match t {\n    (a, .., z) => a,\n    Point { x: 1, .. } => 0,\n}"
+        );
+    }
+
+    #[test]
+    fn test_parse_rest_pattern_errors() {
+        for source in [
+            "match t { (a, .., ..) => a }",
+            "match p { P { .., x } => x }",
+        ] {
+            assert!(Parser::new(source).unwrap().parse_expression().is_err());
+        }
+    }
+
+    // Renders every binary expression fully parenthesized to expose grouping.
+    fn grouping(source: &str) -> String {
+        fn render(expr: &Expression) -> String {
+            match expr {
+                Expression::Binary(binary) => format!(
+                    "({} {} {})",
+                    render(&binary.left),
+                    binary.operator,
+                    render(&binary.right)
+                ),
+                Expression::Identifier(ident) => ident.name.clone(),
+                Expression::Literal(literal) => literal.to_string(),
+                other => panic!("unexpected expression {:?}", other),
+            }
+        }
+        render(&parse_expression(source))
+    }
+
+    #[test]
+    fn test_left_associative_grouping() {
+        assert_eq!(grouping("1 - 2 - 3"), "((1 - 2) - 3)");
+        assert_eq!(grouping("a / b / c"), "((a / b) / c)");
+        assert_eq!(grouping("a % b % c"), "((a % b) % c)");
+        assert_eq!(grouping("a << b << c"), "((a << b) << c)");
+        assert_eq!(grouping("a >> b >> c"), "((a >> b) >> c)");
+        assert_eq!(grouping("a - b * c - d"), "((a - (b * c)) - d)");
+        assert_eq!(grouping("a * b - c / d"), "((a * b) - (c / d))");
+        assert_eq!(grouping("a || b && c || d"), "((a || (b && c)) || d)");
+    }
+
+    #[test]
+    fn test_assignment_groups_right() {
+        assert_eq!(grouping("a = b = c"), "(a = (b = c))");
+        assert_eq!(grouping("a += b -= c"), "(a += (b -= c))");
+        assert_eq!(grouping("a = b + c - d"), "(a = ((b + c) - d))");
+    }
+
+    #[test]
+    fn test_postfix_chain_spans() {
+        let Expression::Call(call) = parse_expression("a.b[0].c()") else {
+            panic!("expected call");
+        };
+        assert!(call.arguments.is_empty());
+        assert_eq!((call.span.start, call.span.end), (0, 10));
+
+        let Expression::Member(c) = &call.callee else {
+            panic!("expected member");
+        };
+        assert_eq!(c.member.name, "c");
+        assert_eq!((c.span.start, c.span.end), (0, 8));
+
+        let Expression::Index(index) = &c.object else {
+            panic!("expected index");
+        };
+        assert_eq!(
+            index.index,
+            Expression::Literal(Literal::Integer(0, None, IntBase::Dec))
+        );
+        assert_eq!((index.span.start, index.span.end), (0, 6));
+
+        let Expression::Member(b) = &index.array else {
+            panic!("expected member");
+        };
+        assert_eq!(b.member.name, "b");
+        assert_eq!((b.span.start, b.span.end), (0, 3));
+
+        let Expression::Identifier(a) = &b.object else {
+            panic!("expected identifier");
+        };
+        assert_eq!(a.name, "a");
+        assert_eq!((a.span.start, a.span.end), (0, 1));
+    }
+
+    #[test]
+    fn test_float_literals_round_trip() {
+        for value in [0.1, 1e300, 1.0, 2.5e-8, f64::MAX, f64::MIN_POSITIVE] {
+            let text = Literal::Float(value, None).to_string();
+            let Expression::Literal(Literal::Float(parsed, None)) = parse_expression(&text) else {
+                panic!("`{}` did not parse as a float literal", text);
+            };
+            assert_eq!(parsed.to_bits(), value.to_bits(), "{}", text);
+        }
+        assert_eq!(Literal::Float(0.1, None).to_string(), "0.1");
+        assert_eq!(Literal::Float(1e300, None).to_string(), "1e300");
+        assert_eq!(Literal::Float(1.0, Some(Type::F32)).to_string(), "1.0f32");
+        assert_eq!(
+            Literal::Float(f64::INFINITY, None).to_string(),
+            "(1.0 / 0.0)"
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_float_literal() {
+        let error = Parser::new("1e999")
+            .unwrap()
+            .parse_expression()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "float literal `1e999` is out of range for `f64` at 1:1"
+        );
+        let error = Parser::new("1e39f32")
+            .unwrap()
+            .parse_expression()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "float literal `1e39` is out of range for `f32` at 1:1"
+        );
+        assert!(Parser::new("1e39").unwrap().parse_expression().is_ok());
+    }
+}
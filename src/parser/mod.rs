@@ -0,0 +1,1330 @@
+mod expressions;
+mod statements;
+mod types;
+
+use crate::ast::expressions::Literal;
+use crate::ast::{
+    Attribute, AttributeArg, ConstDecl, Declaration, EnumDecl, EnumVariant, FunctionDecl,
+    Identifier, ModuleDecl, Parameter, Program, Span, StaticDecl, StructDecl, StructField, Type,
+    UnionDecl, UnionField, VarDecl,
+};
+use crate::lexer::{self, Keywords, LexError, SpannedToken, Token};
+use std::cell::RefCell;
+use std::fmt;
+use types::primitive_type;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    Lex(LexError),
+    // `expected` lists everything that would have been accepted instead, in
+    // the order the parser tried them and without duplicates.
+    UnexpectedToken {
+        expected: Vec<Expected>,
+        found: Token,
+        span: Span,
+    },
+    UnexpectedEof {
+        expected: Vec<Expected>,
+        span: Span,
+    },
+    InvalidLiteral {
+        message: String,
+        span: Span,
+    },
+    NestingTooDeep {
+        limit: usize,
+        span: Span,
+    },
+    KeywordAsIdentifier {
+        keyword: String,
+        span: Span,
+    },
+}
+
+impl ParseError {
+    pub fn span(&self) -> &Span {
+        match self {
+            ParseError::Lex(error) => &error.span,
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEof { span, .. }
+            | ParseError::InvalidLiteral { span, .. }
+            | ParseError::NestingTooDeep { span, .. }
+            | ParseError::KeywordAsIdentifier { span, .. } => span,
+        }
+    }
+}
+
+impl ParseError {
+    // The error without its location, for callers reporting the span
+    // separately.
+    pub fn message(&self) -> String {
+        match self {
+            ParseError::Lex(error) => error.message.clone(),
+            ParseError::UnexpectedToken {
+                expected, found, ..
+            } => format!(
+                "expected {}, found {}",
+                expected_list(expected),
+                describe(*found)
+            ),
+            ParseError::UnexpectedEof { expected, .. } => {
+                format!("expected {}, found end of input", expected_list(expected))
+            }
+            ParseError::InvalidLiteral { message, .. } => message.clone(),
+            ParseError::NestingTooDeep { limit, .. } => {
+                format!("nesting exceeds the maximum depth of {}", limit)
+            }
+            ParseError::KeywordAsIdentifier { keyword, .. } => {
+                format!("keyword `{}` cannot be used as an identifier", keyword)
+            }
+        }
+    }
+}
+
+// Something the parser would have accepted where it failed: a particular
+// token, or any of a kind of construct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Token(Token),
+    Item,
+    Statement,
+    Expression,
+    Operator,
+    Type,
+    Pattern,
+    Literal,
+    AttributeArgument,
+    CallingConvention,
+}
+
+impl From<Token> for Expected {
+    fn from(token: Token) -> Self {
+        Expected::Token(token)
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Token(token) => f.write_str(&describe(*token)),
+            Expected::Item => f.write_str("item"),
+            Expected::Statement => f.write_str("statement"),
+            Expected::Expression => f.write_str("expression"),
+            Expected::Operator => f.write_str("an operator"),
+            Expected::Type => f.write_str("type"),
+            Expected::Pattern => f.write_str("pattern"),
+            Expected::Literal => f.write_str("literal"),
+            Expected::AttributeArgument => f.write_str("attribute argument"),
+            Expected::CallingConvention => f.write_str("calling convention"),
+        }
+    }
+}
+
+// A token as it would be written, such as `fn`, `i32` or `(`, or by its
+// kind when its text varies, as in "identifier".
+fn describe(token: Token) -> String {
+    let kind = match token {
+        Token::Identifier => "identifier",
+        Token::Label => "label",
+        Token::IntegerLiteral => "integer literal",
+        Token::FloatLiteral => "float literal",
+        Token::StringLiteral => "string literal",
+        Token::CharLiteral => "character literal",
+        Token::SingleLineComment | Token::MultiLineComment => "comment",
+        Token::Whitespace => "whitespace",
+        Token::Attribute => return "`#[...]`".to_string(),
+        _ if token.is_keyword() || types::primitive_type(token).is_some() => {
+            return format!("`{}`", token.name().to_lowercase());
+        }
+        _ => return format!("`{}`", token),
+    };
+    kind.to_string()
+}
+
+// `a`, `one of a or b`, `one of a, b or c`.
+fn expected_list(expected: &[Expected]) -> String {
+    match expected {
+        [] => "more input".to_string(),
+        [only] => only.to_string(),
+        [rest @ .., last] => {
+            let rest: Vec<String> = rest.iter().map(Expected::to_string).collect();
+            format!("one of {} or {}", rest.join(", "), last)
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(f, "{} at {}:{}", self.message(), span.line, span.column)
+    }
+}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> Self {
+        ParseError::Lex(error)
+    }
+}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+pub fn parse(source: &str) -> ParseResult<Program> {
+    Parser::new(source)?.parse_program()
+}
+
+// Parses as much of `source` as possible, collecting every error instead of
+// stopping at the first one.
+pub fn parse_recovering(source: &str) -> (Program, Vec<ParseError>) {
+    match Parser::new(source) {
+        Ok(mut parser) => parser.parse_program_recovering(),
+        Err(error) => (
+            Program {
+                items: Vec::new(),
+                span: Span::new(0, source.len(), 1, 1),
+            },
+            vec![error],
+        ),
+    }
+}
+
+// Low enough that the deepest input is rejected before it overflows a 2 MB
+// thread stack, the default for spawned threads, even in unoptimized builds.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+pub struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<SpannedToken>,
+    pos: usize,
+    depth: usize,
+    max_depth: usize,
+    // In recovery mode, errors inside statements and declarations are
+    // collected here and parsing resumes at the next synchronization point.
+    recovering: bool,
+    errors: Vec<ParseError>,
+    // Cleared while parsing the expression before a block, as in `if x { .. }`,
+    // where `x {` must not start a struct literal.
+    struct_literals: bool,
+    // Folds adjacent string literals, as in `"foo" "bar"`, into one.
+    concat_strings: bool,
+    // Tokens checked for and not found at a position, so an error there can
+    // list every alternative. Reset whenever the position changes.
+    expected: RefCell<(usize, Vec<Expected>)>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(source: &'a str) -> ParseResult<Self> {
+        let tokens = lexer::tokenize(source)?;
+        Ok(Self {
+            source,
+            tokens,
+            pos: 0,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            recovering: false,
+            errors: Vec::new(),
+            struct_literals: true,
+            concat_strings: false,
+            expected: RefCell::default(),
+        })
+    }
+
+    // Limits how deeply expressions, types, patterns and blocks may nest, so
+    // pathological input fails with an error instead of overflowing the stack.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // Treats the identifiers `keywords` lists as keywords, and the keywords it
+    // demotes as identifiers; see `Keywords`.
+    pub fn with_keywords(mut self, keywords: &Keywords) -> Self {
+        keywords.reclassify(&mut self.tokens, self.source);
+        self
+    }
+
+    // Reads `"foo" "bar"` as the single literal `"foobar"`. Off by default,
+    // where adjacent literals are a syntax error.
+    pub fn with_string_concatenation(mut self, enabled: bool) -> Self {
+        self.concat_strings = enabled;
+        self
+    }
+
+    pub fn parse_program(&mut self) -> ParseResult<Program> {
+        let mut items = Vec::new();
+        while !self.at_end() {
+            items.push(self.parse_declaration()?);
+        }
+        Ok(Program {
+            items,
+            span: Span::new(0, self.source.len(), 1, 1),
+        })
+    }
+
+    // Like `parse_program`, but a broken declaration or statement is recorded
+    // and skipped, so the returned program holds everything that did parse.
+    pub fn parse_program_recovering(&mut self) -> (Program, Vec<ParseError>) {
+        self.recovering = true;
+        let mut items = Vec::new();
+        while !self.at_end() {
+            let start = self.pos;
+            match self.parse_declaration() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    self.errors.push(error);
+                    if self.pos == start {
+                        self.advance();
+                    }
+                    self.synchronize_declaration();
+                }
+            }
+        }
+        self.recovering = false;
+        let program = Program {
+            items,
+            span: Span::new(0, self.source.len(), 1, 1),
+        };
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    // Skips to the next token that can start a top-level declaration, past
+    // any braces the broken declaration opened.
+    fn synchronize_declaration(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            if depth == 0 && starts_declaration(token) {
+                return;
+            }
+            match token {
+                Token::LBrace => depth += 1,
+                Token::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    pub fn parse_declaration(&mut self) -> ParseResult<Declaration> {
+        let attributes = self.parse_attributes()?;
+        match self.peek() {
+            Some(Token::Fn) => self.parse_function(attributes).map(Declaration::Function),
+            Some(Token::Struct) => self.parse_struct(attributes).map(Declaration::Struct),
+            Some(Token::Enum) => self.parse_enum(attributes).map(Declaration::Enum),
+            Some(Token::Union) => self.parse_union(attributes).map(Declaration::Union),
+            Some(Token::Var | Token::Let) => self.parse_var_decl().map(Declaration::Variable),
+            Some(Token::Const) => self.parse_const().map(Declaration::Constant),
+            Some(Token::Static) => self.parse_static().map(Declaration::Static),
+            Some(Token::Mod) => self.parse_module().map(Declaration::Module),
+            _ => Err(self.error_expected(Expected::Item)),
+        }
+    }
+
+    fn parse_attributes(&mut self) -> ParseResult<Vec<Attribute>> {
+        let mut attributes = Vec::new();
+        while self.check(Token::Attribute) {
+            let span = self.advance().span;
+            let text = self.text(&span);
+            attributes.push(self.parse_attribute(&text[2..text.len() - 1], span)?);
+        }
+        Ok(attributes)
+    }
+
+    // Attributes are lexed as a single `#[...]` token; the inner text is
+    // tokenized again with spans shifted back into the enclosing source.
+    fn parse_attribute(&self, inner: &str, span: Span) -> ParseResult<Attribute> {
+        let tokens = lexer::tokenize(inner)
+            .map_err(|error| LexError {
+                span: shift_span(&error.span, &span, 2),
+                ..error
+            })?
+            .into_iter()
+            .map(|token| SpannedToken {
+                span: shift_span(&token.span, &span, 2),
+                ..token
+            })
+            .collect();
+        let mut parser = Parser {
+            source: self.source,
+            tokens,
+            pos: 0,
+            depth: self.depth,
+            max_depth: self.max_depth,
+            recovering: false,
+            errors: Vec::new(),
+            struct_literals: true,
+            concat_strings: false,
+            expected: RefCell::default(),
+        };
+        let name = parser.parse_identifier()?;
+        let mut args = Vec::new();
+        if parser.eat(Token::LParen) {
+            args = parser.parse_comma_separated(Token::RParen, Parser::parse_attribute_arg)?;
+        }
+        if !parser.at_end() {
+            return Err(parser.error_expected(Token::RBracket));
+        }
+        Ok(Attribute { name, args, span })
+    }
+
+    // A literal, a name, `name = literal` as in `feature = "x"`, or a nested
+    // list as in `all(unix, not(test))`.
+    fn parse_attribute_arg(&mut self) -> ParseResult<AttributeArg> {
+        let token = self.advance_or_eof(Expected::AttributeArgument)?;
+        // Primitive type names are keywords but read as plain names
+        // here, as in `#[repr(u8)]`.
+        if token.token == Token::Identifier || primitive_type(token.token).is_some() {
+            let ident = Identifier::new(self.text(&token.span).to_string(), token.span);
+            if self.eat(Token::Assign) {
+                let value = self.advance_or_eof(Expected::Literal)?;
+                return match self.literal_from_token(&value)? {
+                    Some(literal) => Ok(AttributeArg::NameValue(ident, literal)),
+                    None => Err(ParseError::UnexpectedToken {
+                        expected: vec![Expected::Literal],
+                        found: value.token,
+                        span: value.span,
+                    }),
+                };
+            }
+            if self.eat(Token::LParen) {
+                let args =
+                    self.parse_comma_separated(Token::RParen, Parser::parse_attribute_arg)?;
+                return Ok(AttributeArg::List(ident, args));
+            }
+            return Ok(AttributeArg::Identifier(ident));
+        }
+        match self.literal_from_token(&token)? {
+            Some(literal) => Ok(AttributeArg::Literal(literal)),
+            None => Err(ParseError::UnexpectedToken {
+                expected: vec![Expected::AttributeArgument],
+                found: token.token,
+                span: token.span,
+            }),
+        }
+    }
+
+    fn parse_function(&mut self, attributes: Vec<Attribute>) -> ParseResult<FunctionDecl> {
+        let start = self.expect(Token::Fn)?;
+        let name = self.parse_identifier()?;
+        let generics = if self.eat(Token::Lt) {
+            self.parse_comma_separated(Token::Gt, Parser::parse_identifier)?
+        } else {
+            Vec::new()
+        };
+        self.expect(Token::LParen)?;
+        let params = self.parse_comma_separated(Token::RParen, |p| {
+            let name = p.parse_identifier()?;
+            p.expect(Token::Colon)?;
+            let ty = p.parse_type()?;
+            let default = if p.eat(Token::Assign) {
+                Some(p.parse_expression()?)
+            } else {
+                None
+            };
+            let span = name.span.join(&p.previous_span());
+            Ok(Parameter {
+                name,
+                ty,
+                default,
+                span,
+            })
+        })?;
+        let return_type = if self.eat(Token::Arrow) {
+            Some(Box::new(self.parse_type()?))
+        } else {
+            None
+        };
+        let body = self.parse_block()?;
+        Ok(FunctionDecl {
+            name,
+            generics,
+            params,
+            return_type,
+            span: start.join(&body.span),
+            body,
+            attributes,
+        })
+    }
+
+    fn parse_struct(&mut self, attributes: Vec<Attribute>) -> ParseResult<StructDecl> {
+        let start = self.expect(Token::Struct)?;
+        let name = self.parse_identifier()?;
+        self.expect(Token::LBrace)?;
+        let fields = self.parse_comma_separated(Token::RBrace, |p| {
+            let attributes = p.parse_attributes()?;
+            let name = p.parse_identifier()?;
+            p.expect(Token::Colon)?;
+            let ty = p.parse_type()?;
+            let default = if p.eat(Token::Assign) {
+                Some(p.parse_expression()?)
+            } else {
+                None
+            };
+            let span = name.span.join(&p.previous_span());
+            Ok(StructField {
+                name,
+                ty,
+                default,
+                attributes,
+                span,
+            })
+        })?;
+        Ok(StructDecl {
+            name,
+            fields,
+            attributes,
+            span: start.join(&self.previous_span()),
+        })
+    }
+
+    fn parse_enum(&mut self, attributes: Vec<Attribute>) -> ParseResult<EnumDecl> {
+        let start = self.expect(Token::Enum)?;
+        let name = self.parse_identifier()?;
+        self.expect(Token::LBrace)?;
+        let variants = self.parse_comma_separated(Token::RBrace, |p| {
+            let name = p.parse_identifier()?;
+            let data = if p.eat(Token::LParen) {
+                let mut types = p.parse_comma_separated(Token::RParen, Parser::parse_type)?;
+                if types.len() == 1 {
+                    types.pop()
+                } else {
+                    Some(Type::Tuple(types))
+                }
+            } else {
+                None
+            };
+            let discriminant = if p.eat(Token::Assign) {
+                Some(p.parse_expression()?)
+            } else {
+                None
+            };
+            let span = name.span.join(&p.previous_span());
+            Ok(EnumVariant {
+                name,
+                data,
+                discriminant,
+                span,
+            })
+        })?;
+        Ok(EnumDecl {
+            name,
+            variants,
+            attributes,
+            span: start.join(&self.previous_span()),
+        })
+    }
+
+    fn parse_union(&mut self, attributes: Vec<Attribute>) -> ParseResult<UnionDecl> {
+        let start = self.expect(Token::Union)?;
+        let name = self.parse_identifier()?;
+        self.expect(Token::LBrace)?;
+        let fields = self.parse_comma_separated(Token::RBrace, |p| {
+            let name = p.parse_identifier()?;
+            p.expect(Token::Colon)?;
+            let ty = p.parse_type()?;
+            let span = name.span.join(&p.previous_span());
+            Ok(UnionField { name, ty, span })
+        })?;
+        Ok(UnionDecl {
+            name,
+            fields,
+            attributes,
+            span: start.join(&self.previous_span()),
+        })
+    }
+
+    fn parse_var_decl(&mut self) -> ParseResult<VarDecl> {
+        let start = self.advance().span;
+        let mutable = self.eat(Token::Mut);
+        let name = self.parse_identifier()?;
+        let ty = if self.eat(Token::Colon) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let initializer = if self.eat(Token::Assign) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        let end = self.expect(Token::Semicolon)?;
+        Ok(VarDecl {
+            name,
+            ty,
+            mutable,
+            initializer,
+            span: start.join(&end),
+        })
+    }
+
+    fn parse_const(&mut self) -> ParseResult<ConstDecl> {
+        let start = self.expect(Token::Const)?;
+        let name = self.parse_identifier()?;
+        self.expect(Token::Colon)?;
+        let ty = self.parse_type()?;
+        self.expect(Token::Assign)?;
+        let value = self.parse_expression()?;
+        let end = self.expect(Token::Semicolon)?;
+        Ok(ConstDecl {
+            name,
+            ty,
+            value,
+            span: start.join(&end),
+        })
+    }
+
+    fn parse_static(&mut self) -> ParseResult<StaticDecl> {
+        let start = self.expect(Token::Static)?;
+        let mutable = self.eat(Token::Mut);
+        let name = self.parse_identifier()?;
+        self.expect(Token::Colon)?;
+        let ty = self.parse_type()?;
+        self.expect(Token::Assign)?;
+        let value = self.parse_expression()?;
+        let end = self.expect(Token::Semicolon)?;
+        Ok(StaticDecl {
+            name,
+            ty,
+            mutable,
+            value,
+            span: start.join(&end),
+        })
+    }
+
+    fn parse_module(&mut self) -> ParseResult<ModuleDecl> {
+        let start = self.expect(Token::Mod)?;
+        let name = self.parse_identifier()?;
+        if self.eat(Token::Semicolon) {
+            return Ok(ModuleDecl {
+                name,
+                items: None,
+                span: start.join(&self.previous_span()),
+            });
+        }
+        self.expect(Token::LBrace)?;
+        let mut items = Vec::new();
+        while !self.check(Token::RBrace) {
+            if self.at_end() {
+                return Err(self.error_expected(Token::RBrace));
+            }
+            items.push(self.parse_declaration()?);
+        }
+        let end = self.expect(Token::RBrace)?;
+        Ok(ModuleDecl {
+            name,
+            items: Some(items),
+            span: start.join(&end),
+        })
+    }
+
+    fn parse_identifier(&mut self) -> ParseResult<Identifier> {
+        if self.peek().is_some_and(|token| token.is_keyword()) {
+            return Err(self.keyword_as_identifier());
+        }
+        let span = self.expect(Token::Identifier)?;
+        Ok(Identifier::new(self.text(&span).to_string(), span))
+    }
+
+    fn parse_comma_separated<T>(
+        &mut self,
+        close: Token,
+        mut parse_item: impl FnMut(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<Vec<T>> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            items.push(self.with_struct_literals(true, &mut parse_item)?);
+            if !self.eat(Token::Comma) {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn literal_from_token(&mut self, token: &SpannedToken) -> ParseResult<Option<Literal>> {
+        let text = self.text(&token.span);
+        let literal = match token.token {
+            Token::IntegerLiteral => {
+                let end = match self.eat_suffix(&token.span, Type::is_integer) {
+                    Some(_) => self.previous_span().end,
+                    None => token.span.end,
+                };
+                let slice = &self.source[token.span.start..end];
+                let (value, base, suffix) =
+                    lexer::parse_integer_literal(slice).map_err(|error| {
+                        ParseError::InvalidLiteral {
+                            message: format!("integer literal `{}` {}", slice, error),
+                            span: token.span.clone(),
+                        }
+                    })?;
+                Literal::Integer(value, suffix, base)
+            }
+            Token::FloatLiteral => {
+                let value = text.parse().map_err(|_| ParseError::InvalidLiteral {
+                    message: format!("invalid float literal `{}`", text),
+                    span: token.span.clone(),
+                })?;
+                Literal::Float(value, None)
+            }
+            Token::StringLiteral => {
+                Literal::String(lexer::decode_string_literal(text, &token.span)?)
+            }
+            Token::CharLiteral => {
+                Literal::Character(lexer::decode_char_literal(text, &token.span)?)
+            }
+            Token::True => Literal::Boolean(true),
+            Token::False => Literal::Boolean(false),
+            _ => return Ok(None),
+        };
+        let literal = match literal {
+            // Too large a literal parses as infinity, which no literal can be.
+            Literal::Float(value, _) => {
+                let suffix = self.eat_suffix(&token.span, Type::is_float);
+                let finite = match suffix {
+                    Some(Type::F32) => (value as f32).is_finite(),
+                    _ => value.is_finite(),
+                };
+                if !finite {
+                    let ty = suffix.as_ref().unwrap_or(&Type::F64);
+                    return Err(ParseError::InvalidLiteral {
+                        message: format!("float literal `{}` is out of range for `{}`", text, ty),
+                        span: token.span.clone(),
+                    });
+                }
+                Literal::Float(value, suffix)
+            }
+            other => other,
+        };
+        Ok(Some(literal))
+    }
+
+    // A numeric type written directly after a literal, as in `1u8` or `2.5f32`.
+    fn eat_suffix(&mut self, literal: &Span, accepts: fn(&Type) -> bool) -> Option<Type> {
+        let next = self.tokens.get(self.pos)?;
+        if next.span.start != literal.end {
+            return None;
+        }
+        let ty = types::primitive_type(next.token).filter(accepts)?;
+        self.advance();
+        Some(ty)
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<Token> {
+        self.tokens.get(self.pos + n).map(|t| t.token)
+    }
+
+    fn check(&self, token: Token) -> bool {
+        let found = self.peek() == Some(token);
+        if !found {
+            self.note_expected(token.into());
+        }
+        found
+    }
+
+    fn note_expected(&self, expected: Expected) {
+        let mut noted = self.expected.borrow_mut();
+        if noted.0 != self.pos {
+            *noted = (self.pos, Vec::new());
+        }
+        if !noted.1.contains(&expected) {
+            noted.1.push(expected);
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    // Callers must have checked that a token is available.
+    fn advance(&mut self) -> SpannedToken {
+        let token = self.tokens[self.pos].clone();
+        self.pos += 1;
+        token
+    }
+
+    fn advance_or_eof(&mut self, expected: Expected) -> ParseResult<SpannedToken> {
+        if self.at_end() {
+            return Err(self.error_expected(expected));
+        }
+        Ok(self.advance())
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        if self.check(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> ParseResult<Span> {
+        self.expect_described(token, token.into())
+    }
+
+    // Unlike `check`, a failure notes `expected` rather than the token itself.
+    fn expect_described(&mut self, token: Token, expected: Expected) -> ParseResult<Span> {
+        if self.peek() == Some(token) {
+            Ok(self.advance().span)
+        } else {
+            Err(self.error_expected(expected))
+        }
+    }
+
+    // Runs `parse` with struct literals allowed or not, restoring the outer
+    // setting afterwards.
+    fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        let outer = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = parse(self);
+        self.struct_literals = outer;
+        result
+    }
+
+    // Runs `parse` one nesting level deeper, failing once `max_depth` is hit.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> ParseResult<T>) -> ParseResult<T> {
+        self.deepen()?;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // Runs `parse`, which may `deepen` any number of times, and restores the
+    // depth afterwards.
+    fn chained<T>(&mut self, parse: impl FnOnce(&mut Self) -> ParseResult<T>) -> ParseResult<T> {
+        let outer = self.depth;
+        let result = parse(self);
+        self.depth = outer;
+        result
+    }
+
+    // Goes one nesting level deeper, failing once `max_depth` is hit. Used
+    // within `chained`, which restores the depth.
+    fn deepen(&mut self) -> ParseResult<()> {
+        if self.depth >= self.max_depth {
+            return Err(ParseError::NestingTooDeep {
+                limit: self.max_depth,
+                span: self.current_span(),
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn keyword_as_identifier(&self) -> ParseError {
+        let span = self.current_span();
+        ParseError::KeywordAsIdentifier {
+            keyword: self.text(&span).to_string(),
+            span,
+        }
+    }
+
+    // `expected`, along with whatever else was checked for here.
+    fn error_expected(&self, expected: impl Into<Expected>) -> ParseError {
+        self.note_expected(expected.into());
+        let expected = self.expected.borrow().1.clone();
+        match self.tokens.get(self.pos) {
+            Some(token) => ParseError::UnexpectedToken {
+                expected,
+                found: token.token,
+                span: token.span.clone(),
+            },
+            None => ParseError::UnexpectedEof {
+                expected,
+                span: self.eof_span(),
+            },
+        }
+    }
+
+    fn current_span(&self) -> Span {
+        match self.tokens.get(self.pos) {
+            Some(token) => token.span.clone(),
+            None => self.eof_span(),
+        }
+    }
+
+    fn previous_span(&self) -> Span {
+        match self.pos.checked_sub(1).and_then(|i| self.tokens.get(i)) {
+            Some(token) => token.span.clone(),
+            None => self.current_span(),
+        }
+    }
+
+    fn eof_span(&self) -> Span {
+        match self.tokens.last() {
+            Some(last) => Span::new(
+                last.span.end,
+                last.span.end,
+                last.span.line,
+                last.span.column + (last.span.end - last.span.start),
+            ),
+            None => Span::new(0, 0, 1, 1),
+        }
+    }
+
+    fn text(&self, span: &Span) -> &'a str {
+        span.slice(self.source)
+    }
+}
+
+// Tokens that can begin a top-level declaration, where recovery resumes.
+fn starts_declaration(token: Token) -> bool {
+    matches!(
+        token,
+        Token::Attribute
+            | Token::Fn
+            | Token::Struct
+            | Token::Enum
+            | Token::Union
+            | Token::Var
+            | Token::Let
+            | Token::Const
+            | Token::Static
+            | Token::Mod
+    )
+}
+
+// Maps a span inside an attribute's inner text back into the source, where the
+// inner text begins `skip` bytes after the start of `outer`.
+fn shift_span(span: &Span, outer: &Span, skip: usize) -> Span {
+    let column = if span.line == 1 {
+        outer.column + skip + span.column - 1
+    } else {
+        span.column
+    };
+    Span::new(
+        outer.start + skip + span.start,
+        outer.start + skip + span.end,
+        outer.line + span.line - 1,
+        column,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::expressions::{Expression, IntBase};
+    use crate::printer::print_program;
+
+    #[test]
+    fn test_parse_static_declarations() {
+        let program = parse("static LIMIT: i32 = 10;\nstatic mut COUNTER: i32 = 0;").unwrap();
+        assert_eq!(program.items.len(), 2);
+
+        let Declaration::Static(limit) = &program.items[0] else {
+            panic!("expected static declaration");
+        };
+        assert_eq!(limit.name.name, "LIMIT");
+        assert!(!limit.mutable);
+        assert_eq!(limit.ty, Type::I32);
+
+        let Declaration::Static(counter) = &program.items[1] else {
+            panic!("expected static declaration");
+        };
+        assert_eq!(counter.name.name, "COUNTER");
+        assert!(counter.mutable);
+        assert_eq!(
+            counter.value,
+            Expression::Literal(Literal::Integer(0, None, IntBase::Dec))
+        );
+        assert_eq!(counter.span.line, 2);
+    }
+
+    #[test]
+    fn test_parse_function_with_attributes() {
+        let program = parse("#[inline]\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}").unwrap();
+        let Declaration::Function(function) = &program.items[0] else {
+            panic!("expected function");
+        };
+        assert_eq!(function.name.name, "add");
+        assert_eq!(function.params.len(), 2);
+        assert_eq!(function.attributes[0].name.name, "inline");
+        assert_eq!(function.return_type.as_deref(), Some(&Type::I32));
+        assert!(function.body.statements.is_empty());
+        assert!(matches!(
+            function.body.tail.as_deref(),
+            Some(Expression::Binary(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_attribute_arguments() {
+        let program = parse("#[derive(Debug, Clone)]\nstruct Point { x: i32, y: i32 }").unwrap();
+        let Declaration::Struct(decl) = &program.items[0] else {
+            panic!("expected struct");
+        };
+        let attribute = &decl.attributes[0];
+        assert_eq!(attribute.name.name, "derive");
+        assert_eq!(attribute.name.span.column, 3);
+        assert_eq!(attribute.args.len(), 2);
+        assert_eq!(decl.fields.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_nested_attribute_arguments() {
+        let program = parse("#[cfg(all(unix, not(feature = \"a\")))]\nfn f() {}").unwrap();
+        let Declaration::Function(function) = &program.items[0] else {
+            panic!("expected function");
+        };
+        let [AttributeArg::List(all, args)] = &function.attributes[0].args[..] else {
+            panic!("expected a nested list");
+        };
+        assert_eq!(all.name, "all");
+        assert!(matches!(&args[0], AttributeArg::Identifier(ident) if ident.name == "unix"));
+        let AttributeArg::List(not, inner) = &args[1] else {
+            panic!("expected `not(..)`");
+        };
+        assert_eq!(not.name, "not");
+        assert!(matches!(
+            &inner[..],
+            [AttributeArg::NameValue(name, Literal::String(value))]
+                if name.name == "feature" && value == "a"
+        ));
+        assert_eq!(
+            print_program(&program),
+            "#[cfg(all(unix, not(feature = \"a\")))]\nfn f() {}\n"
+        );
+        assert!(parse("#[cfg(feature = x)]\nfn f() {}").is_err());
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let pairs = [
+            ("fn f() { g(a, b,); }", "fn f() { g(a, b); }"),
+            ("struct S { x: i32, }", "struct S { x: i32 }"),
+            ("fn f() { var t = (1, 2,); }", "fn f() { var t = (1, 2); }"),
+            ("fn f() { var a = [1, 2,]; }", "fn f() { var a = [1, 2]; }"),
+            ("fn f<T,>(a: T, b: i32,) {}", "fn f<T>(a: T, b: i32) {}"),
+            ("enum E { A, B(i32, u8,), }", "enum E { A, B(i32, u8) }"),
+            ("union U { a: i32, }", "union U { a: i32 }"),
+            (
+                "var t: (i32, Map<u8, i32,>,);",
+                "var t: (i32, Map<u8, i32>);",
+            ),
+            (
+                "fn f() { match p { P { x, .. } => 0, (a, b,) => 1, } }",
+                "fn f() { match p { P { x, .. } => 0, (a, b) => 1 } }",
+            ),
+            (
+                "#[derive(Debug, Clone,)]\nstruct S {}",
+                "#[derive(Debug, Clone)]\nstruct S {}",
+            ),
+        ];
+        for (with, without) in pairs {
+            let with = parse(with).unwrap_or_else(|error| panic!("{}: {}", with, error));
+            assert_eq!(
+                print_program(&with),
+                print_program(&parse(without).unwrap())
+            );
+        }
+
+        // A trailing comma is what makes a one-element tuple.
+        assert_eq!(
+            print_program(&parse("fn f() { var t = (1,); var n = (1); }").unwrap()),
+            "fn f() {\n    var t = (1,);\n    var n = (1);\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_struct_literals_and_field_defaults() {
+        let source = "struct P { x: i32 = 1, y: i32 }\n\
+                      fn f(b: P) { var p = P { y, x: 2, }; var q = P { y: 0, ..b }; P {}; }";
+        assert_eq!(
+            print_program(&parse(source).unwrap()),
+            "struct P {\n    x: i32 = 1,\n    y: i32,\n}\n\nfn f(b: P) {\n    var p = P { y: y, x: 2 };\n    var q = P { y: 0, ..b };\n    P {};\n}\n"
+        );
+
+        // Before a block, `name {` opens the block; parentheses allow a literal.
+        let program = parse("fn f() { if p { g(); } while (P { x: 1 }).ok() {} }").unwrap();
+        let Declaration::Function(function) = &program.items[0] else {
+            panic!("expected a function");
+        };
+        let crate::ast::statements::Statement::If(if_stmt) = &function.body.statements[0] else {
+            panic!("expected an if statement");
+        };
+        assert!(matches!(if_stmt.condition, Expression::Identifier(_)));
+        let crate::ast::statements::Statement::While(while_stmt) = &function.body.statements[1]
+        else {
+            panic!("expected a while loop");
+        };
+        assert!(matches!(while_stmt.condition, Expression::Call(_)));
+    }
+
+    #[test]
+    fn test_lone_comma_is_rejected() {
+        for source in [
+            "fn f() { g(,); }",
+            "fn f() { var t = (,); }",
+            "fn f() { var a = [,]; }",
+            "fn f() { g(a,,); }",
+            "fn f(,) {}",
+            "struct S { , }",
+            "var t: (,);",
+            "fn f() { match p { (,) => 0 } }",
+        ] {
+            assert!(parse(source).is_err(), "{} should not parse", source);
+        }
+    }
+
+    #[test]
+    fn test_parse_enum_discriminants() {
+        let program = parse("#[repr(u8)]\nenum E { A = 1, B, C(i32) = 0x10 }").unwrap();
+        let Declaration::Enum(decl) = &program.items[0] else {
+            panic!("expected enum");
+        };
+        assert!(matches!(
+            &decl.attributes[0].args[..],
+            [AttributeArg::Identifier(ident)] if ident.name == "u8"
+        ));
+        assert!(decl.variants[0].discriminant.is_some());
+        assert!(decl.variants[1].discriminant.is_none());
+        assert_eq!(
+            print_program(&program),
+            "#[repr(u8)]\nenum E {\n    A = 1,\n    B,\n    C(i32) = 0x10,\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_reports_location() {
+        let error = parse("static mut COUNTER: i32 = 0").unwrap_err();
+        assert!(matches!(error, ParseError::UnexpectedEof { .. }));
+        assert_eq!(
+            error.to_string(),
+            "expected one of `as`, an operator or `;`, found end of input at 1:28"
+        );
+
+        let error = parse("fn 1() {}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected identifier, found integer literal at 1:4"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_lists_every_expected_token() {
+        let error = parse("fn f() { g(1; }").unwrap_err();
+        let ParseError::UnexpectedToken {
+            expected, found, ..
+        } = error
+        else {
+            panic!("expected an unexpected token error");
+        };
+        assert_eq!(found, Token::Semicolon);
+        assert!(expected.contains(&Expected::Token(Token::Comma)));
+        assert!(expected.contains(&Expected::Operator));
+        let described: Vec<String> = expected.iter().map(Expected::to_string).collect();
+        assert_eq!(described, vec!["`as`", "an operator", "`,`", "`)`"]);
+
+        let error = parse("struct S { x: i32 y: i32 }").unwrap_err();
+        let ParseError::UnexpectedToken { expected, .. } = error else {
+            panic!("expected an unexpected token error");
+        };
+        assert_eq!(
+            expected,
+            vec![
+                Expected::Token(Token::Assign),
+                Expected::Token(Token::Comma),
+                Expected::Token(Token::RBrace),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_error_describes_tokens_as_written() {
+        let message = |source: &str| parse(source).unwrap_err().message();
+        assert_eq!(
+            message("return 1;"),
+            "expected one of `#[...]` or item, found `return`"
+        );
+        assert_eq!(
+            message("fn f() { var x: u8 = fn; }"),
+            "expected expression, found `fn`"
+        );
+        assert_eq!(
+            message("var 1 = 2;"),
+            "expected one of `mut` or identifier, found integer literal"
+        );
+        assert_eq!(message("fn f(x: i32) -> { }"), "expected type, found `{`");
+        assert_eq!(
+            message("struct S { i32: u8 }"),
+            "expected one of `}`, `#[...]` or identifier, found `i32`"
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting_is_rejected() {
+        let source = format!("{}x{}", "(".repeat(10_000), ")".repeat(10_000));
+        let error = std::thread::spawn(move || Parser::new(&source).unwrap().parse_expression())
+            .join()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ParseError::NestingTooDeep {
+                limit: DEFAULT_MAX_DEPTH,
+                span: Span::new(64, 65, 1, 65),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "nesting exceeds the maximum depth of 64 at 1:65"
+        );
+
+        // Every kind of nesting stops at the limit on a default-sized stack.
+        let n = 10_000;
+        for source in [
+            format!("fn f() {{ {}x{} }}", "if c { ".repeat(n), " }".repeat(n)),
+            format!(
+                "fn f() {{ {}x{} }}",
+                "match c { _ => ".repeat(n),
+                " }".repeat(n)
+            ),
+            format!("fn f() {{ {}x{} }}", "loop { ".repeat(n), " }".repeat(n)),
+            format!("fn f() {{ {}x{}; }}", "[".repeat(n), "]".repeat(n)),
+            format!("fn f() {{ {}x; }}", "-".repeat(n)),
+            format!("fn f(x: {}i32{}) {{}}", "[".repeat(n), "; 1]".repeat(n)),
+            format!("fn f() {{ let {}x{} = 1; }}", "(".repeat(n), ")".repeat(n)),
+            format!("fn f() {{ 1{}; }}", " + 1".repeat(n)),
+            format!("fn f() {{ x{}; }}", " as i32".repeat(n)),
+            format!("fn f() {{ x{}; }}", ".y".repeat(n)),
+            format!("fn f() {{ f{}; }}", "()".repeat(n)),
+        ] {
+            let result = std::thread::spawn(move || parse(&source)).join().unwrap();
+            assert!(matches!(result, Err(ParseError::NestingTooDeep { .. })));
+        }
+
+        let shallow = Parser::new("fn f() -> [[i32; 2]; 2] { -(-1) }")
+            .unwrap()
+            .with_max_depth(3)
+            .parse_program();
+        assert!(matches!(shallow, Err(ParseError::NestingTooDeep { .. })));
+        assert!(
+            Parser::new("fn f() -> [i32; 2] { 1 }")
+                .unwrap()
+                .with_max_depth(3)
+                .parse_program()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_long_left_associative_chain_is_rejected() {
+        let source = format!("fn f() -> i32 {{ 1{} }}", " + 1".repeat(5000));
+        let error = parse(&source).unwrap_err();
+        assert!(matches!(
+            error,
+            ParseError::NestingTooDeep {
+                limit: DEFAULT_MAX_DEPTH,
+                ..
+            }
+        ));
+
+        // A chain within the limit still parses and checks.
+        let source = format!("fn f() -> i32 {{ 1{} }}", " + 1".repeat(50));
+        let program = parse(&source).unwrap();
+        assert!(crate::checker::check_program(&program).is_empty());
+    }
+
+    #[test]
+    fn test_recovery_reports_every_error() {
+        let source = "\
+fn first() {
+    let x = ;
+    let y = 2;
+}
+struct Broken { x: }
+static LIMIT: i32 = 10
+fn last() -> i32 { 1 }
+";
+        let (program, errors) = parse_recovering(source);
+
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "expected expression, found `;` at 2:13",
+                "expected type, found `}` at 5:20",
+                "expected one of `as`, an operator or `;`, found `fn` at 7:1",
+            ]
+        );
+
+        let names: Vec<&str> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Declaration::Function(function) => Some(function.name.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["first", "last"]);
+        let Declaration::Function(first) = &program.items[0] else {
+            panic!("expected function");
+        };
+        assert_eq!(first.body.statements.len(), 1);
+    }
+
+    #[test]
+    fn test_recovery_without_errors_matches_parse() {
+        let source = "fn main() { let a = 1; }\nstruct P { x: i32 }";
+        let (program, errors) = parse_recovering(source);
+        assert!(errors.is_empty());
+        assert_eq!(program, parse(source).unwrap());
+    }
+
+    #[test]
+    fn test_keyword_as_identifier() {
+        let error = parse("fn main() { let if = 1; }").unwrap_err();
+        assert_eq!(
+            error,
+            ParseError::KeywordAsIdentifier {
+                keyword: "if".to_string(),
+                span: Span::new(16, 18, 1, 17),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "keyword `if` cannot be used as an identifier at 1:17"
+        );
+
+        let error = parse("struct match { }").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "keyword `match` cannot be used as an identifier at 1:8"
+        );
+    }
+
+    #[test]
+    fn test_dialect_keyword_is_reserved_only_when_enabled() {
+        let source = "fn f() { let yield = 1; }";
+        assert!(parse(source).is_ok());
+
+        let keywords = Keywords::new().with("yield", Token::Yield);
+        let error = Parser::new(source)
+            .unwrap()
+            .with_keywords(&keywords)
+            .parse_program()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "keyword `yield` cannot be used as an identifier at 1:14"
+        );
+    }
+
+    #[test]
+    fn test_demoted_keyword_is_an_identifier() {
+        let source = "fn f() { let loop = 1; }";
+        assert!(parse(source).is_err());
+
+        let keywords = Keywords::new().without("loop");
+        let program = Parser::new(source)
+            .unwrap()
+            .with_keywords(&keywords)
+            .parse_program();
+        assert!(program.is_ok());
+    }
+}
@@ -0,0 +1,483 @@
+use super::expressions::is_block_like;
+use super::{Expected, ParseResult, Parser};
+use crate::ast::expressions::{Expression, IfExpr, LoopExpr, MatchExpr};
+use crate::ast::statements::{
+    BreakStatement, ContinueStatement, ElseBranch, ForStatement, IfStatement, LetStatement,
+    LoopStatement, MatchStatement, PanicStatement, ReturnStatement, Statement, WhileStatement,
+};
+use crate::ast::{Block, Identifier, Span};
+use crate::lexer::Token;
+
+impl Parser<'_> {
+    pub fn parse_block(&mut self) -> ParseResult<Block> {
+        self.nested(|p| p.with_struct_literals(true, Self::parse_block_inner))
+    }
+
+    fn parse_block_inner(&mut self) -> ParseResult<Block> {
+        let start = self.expect(Token::LBrace)?;
+        let mut statements = Vec::new();
+        let mut tail = None;
+        while !self.check(Token::RBrace) {
+            if self.at_end() {
+                return Err(self.error_expected(Token::RBrace));
+            }
+            if let Err(error) = self.parse_block_item(&mut statements, &mut tail) {
+                if !self.recovering {
+                    return Err(error);
+                }
+                self.errors.push(error);
+                self.synchronize_statement();
+            }
+        }
+        let end = self.expect(Token::RBrace)?;
+
+        // A trailing `if`/`match`/`loop`/block statement is the block's value.
+        if tail.is_none()
+            && let Some(expr) = statements.last().and_then(statement_as_tail)
+        {
+            statements.pop();
+            tail = Some(Box::new(expr));
+        }
+
+        Ok(Block {
+            statements,
+            tail,
+            span: start.join(&end),
+        })
+    }
+
+    fn parse_block_item(
+        &mut self,
+        statements: &mut Vec<Statement>,
+        tail: &mut Option<Box<Expression>>,
+    ) -> ParseResult<()> {
+        if self.starts_statement() {
+            statements.push(self.parse_statement()?);
+            return Ok(());
+        }
+        let expr = self.parse_expression()?;
+        if self.eat(Token::Semicolon) {
+            statements.push(expression_statement(expr, &self.previous_span()));
+        } else if self.check(Token::RBrace) {
+            *tail = Some(Box::new(expr));
+        } else if is_block_like(&expr) {
+            statements.push(Statement::Expression(expr));
+        } else {
+            return Err(self.error_expected(Token::Semicolon));
+        }
+        Ok(())
+    }
+
+    // Skips past the rest of a broken statement: through the next `;`, or up
+    // to the `}` closing the current block. Nested braces are skipped whole.
+    fn synchronize_statement(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                Token::RBrace if depth == 0 => return,
+                Token::RBrace => depth -= 1,
+                Token::LBrace => depth += 1,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    pub fn parse_statement(&mut self) -> ParseResult<Statement> {
+        let start = self.current_span();
+        let Some(token) = self.peek() else {
+            return Err(self.error_expected(Expected::Statement));
+        };
+        let statement = match token {
+            Token::Semicolon => {
+                self.advance();
+                Statement::Empty
+            }
+            Token::Let | Token::Var => {
+                self.advance();
+                let mutable = self.eat(Token::Mut);
+                let pattern = self.parse_pattern()?;
+                let type_annotation = if self.eat(Token::Colon) {
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
+                let initializer = if self.eat(Token::Assign) {
+                    Some(self.parse_expression()?)
+                } else {
+                    None
+                };
+                let end = self.expect(Token::Semicolon)?;
+                Statement::Let(LetStatement {
+                    pattern,
+                    type_annotation,
+                    initializer,
+                    mutable,
+                    span: start.join(&end),
+                })
+            }
+            Token::Return => {
+                self.advance();
+                let expression = self.parse_optional_expression()?;
+                let end = self.expect(Token::Semicolon)?;
+                Statement::Return(ReturnStatement {
+                    expression,
+                    span: start.join(&end),
+                })
+            }
+            Token::Break => {
+                self.advance();
+                let label = self.parse_optional_label()?;
+                let expression = self.parse_optional_expression()?;
+                let end = self.expect(Token::Semicolon)?;
+                Statement::Break(BreakStatement {
+                    label,
+                    expression,
+                    span: start.join(&end),
+                })
+            }
+            Token::Continue => {
+                self.advance();
+                let label = self.parse_optional_label()?;
+                let end = self.expect(Token::Semicolon)?;
+                Statement::Continue(ContinueStatement {
+                    label,
+                    span: start.join(&end),
+                })
+            }
+            Token::While => {
+                self.advance();
+                let condition = self.parse_expression_before_block()?;
+                let body = self.parse_block()?;
+                Statement::While(WhileStatement {
+                    condition,
+                    span: start.join(&body.span),
+                    body,
+                    label: None,
+                })
+            }
+            Token::For => {
+                self.advance();
+                let pattern = self.parse_pattern()?;
+                self.expect(Token::In)?;
+                let iterator = self.parse_expression_before_block()?;
+                let body = self.parse_block()?;
+                Statement::For(ForStatement {
+                    pattern,
+                    iterator,
+                    span: start.join(&body.span),
+                    body,
+                    label: None,
+                })
+            }
+            Token::Loop => {
+                self.advance();
+                let body = self.parse_block()?;
+                Statement::Loop(LoopStatement {
+                    span: start.join(&body.span),
+                    body,
+                    label: None,
+                })
+            }
+            Token::Label => {
+                let label = self.parse_loop_label()?;
+                let mut statement = self.parse_statement()?;
+                match &mut statement {
+                    Statement::While(stmt) => {
+                        stmt.span = start.join(&stmt.span);
+                        stmt.label = Some(label);
+                    }
+                    Statement::For(stmt) => {
+                        stmt.span = start.join(&stmt.span);
+                        stmt.label = Some(label);
+                    }
+                    Statement::Loop(stmt) => {
+                        stmt.span = start.join(&stmt.span);
+                        stmt.label = Some(label);
+                    }
+                    _ => unreachable!("`parse_loop_label` checks for a loop keyword"),
+                }
+                statement
+            }
+            Token::If => Statement::If(self.parse_if_statement()?),
+            Token::Match => {
+                self.advance();
+                let expression = self.parse_expression_before_block()?;
+                let arms = self.parse_match_arms()?;
+                Statement::Match(MatchStatement {
+                    expression,
+                    arms,
+                    span: start.join(&self.previous_span()),
+                })
+            }
+            Token::LBrace => Statement::Block(self.parse_block()?),
+            _ => {
+                let expr = self.parse_expression()?;
+                if is_block_like(&expr) {
+                    Statement::Expression(expr)
+                } else {
+                    let end = self.expect(Token::Semicolon)?;
+                    expression_statement(expr, &end)
+                }
+            }
+        };
+        Ok(statement)
+    }
+
+    fn parse_if_statement(&mut self) -> ParseResult<IfStatement> {
+        let start = self.expect(Token::If)?;
+        let condition = self.parse_expression_before_block()?;
+        let then_branch = self.parse_block()?;
+        let else_branch = if self.eat(Token::Else) {
+            if self.check(Token::If) {
+                Some(ElseBranch::If(Box::new(self.parse_if_statement()?)))
+            } else {
+                Some(ElseBranch::Block(self.parse_block()?))
+            }
+        } else {
+            None
+        };
+        Ok(IfStatement {
+            condition,
+            then_branch,
+            else_branch,
+            span: start.join(&self.previous_span()),
+        })
+    }
+
+    // `'name:` before a loop. The label is stored without its quote.
+    pub(super) fn parse_loop_label(&mut self) -> ParseResult<Identifier> {
+        let label = self.parse_label()?;
+        self.expect(Token::Colon)?;
+        if !matches!(self.peek(), Some(Token::Loop | Token::While | Token::For)) {
+            self.note_expected(Token::Loop.into());
+            self.note_expected(Token::While.into());
+            return Err(self.error_expected(Token::For));
+        }
+        Ok(label)
+    }
+
+    pub(super) fn parse_optional_label(&mut self) -> ParseResult<Option<Identifier>> {
+        if self.check(Token::Label) {
+            Ok(Some(self.parse_label()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_label(&mut self) -> ParseResult<Identifier> {
+        let span = self.expect(Token::Label)?;
+        Ok(Identifier::new(self.text(&span)[1..].to_string(), span))
+    }
+
+    fn parse_optional_expression(&mut self) -> ParseResult<Option<Expression>> {
+        if self.starts_expression() || self.check(Token::LBrace) {
+            Ok(Some(self.parse_expression()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn starts_statement(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(
+                Token::Semicolon
+                    | Token::Let
+                    | Token::Var
+                    | Token::Return
+                    | Token::Break
+                    | Token::Continue
+                    | Token::While
+                    | Token::For
+                    | Token::Loop
+                    | Token::If
+                    | Token::Match
+                    | Token::LBrace
+                    | Token::Label
+            )
+        )
+    }
+}
+
+// `panic(..);` keeps its own statement form; `end` is the semicolon.
+fn expression_statement(expr: Expression, end: &Span) -> Statement {
+    match expr {
+        Expression::Panic(panic) => Statement::Panic(PanicStatement {
+            message: panic.message,
+            span: panic.span.join(end),
+        }),
+        expr => Statement::Expression(expr),
+    }
+}
+
+fn statement_as_tail(statement: &Statement) -> Option<Expression> {
+    match statement {
+        Statement::If(stmt) if stmt.else_branch.is_some() => Some(if_expression(stmt)),
+        Statement::Match(stmt) => Some(Expression::Match(Box::new(MatchExpr {
+            value: stmt.expression.clone(),
+            arms: stmt.arms.clone(),
+            span: stmt.span.clone(),
+        }))),
+        Statement::Block(block) => Some(Expression::Block(Box::new(block.clone()))),
+        Statement::Loop(stmt) => Some(Expression::Loop(Box::new(LoopExpr {
+            body: Expression::Block(Box::new(stmt.body.clone())),
+            label: stmt.label.clone(),
+            span: stmt.span.clone(),
+        }))),
+        _ => None,
+    }
+}
+
+fn if_expression(stmt: &IfStatement) -> Expression {
+    let else_branch = stmt.else_branch.as_ref().map(|branch| match branch {
+        ElseBranch::Block(block) => Expression::Block(Box::new(block.clone())),
+        ElseBranch::If(nested) => if_expression(nested),
+    });
+    Expression::If(Box::new(IfExpr {
+        condition: stmt.condition.clone(),
+        then_branch: Expression::Block(Box::new(stmt.then_branch.clone())),
+        else_branch,
+        span: stmt.span.clone(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::expressions::{IntBase, Literal, Pattern};
+
+    fn parse_block(source: &str) -> Block {
+        Parser::new(source).unwrap().parse_block().unwrap()
+    }
+
+    #[test]
+    fn test_let_and_var_are_synonyms() {
+        let block = parse_block("{ let mut a: i32 = 1; var b = 2; }");
+        assert_eq!(block.statements.len(), 2);
+        let Statement::Let(a) = &block.statements[0] else {
+            panic!("expected let");
+        };
+        assert!(a.mutable);
+        assert_eq!(a.type_annotation, Some(crate::ast::Type::I32));
+        let Statement::Let(b) = &block.statements[1] else {
+            panic!("expected let");
+        };
+        assert!(matches!(&b.pattern, Pattern::Identifier(ident) if ident.name == "b"));
+        assert!(!b.mutable);
+    }
+
+    #[test]
+    fn test_panic_statement_and_tail() {
+        let block = parse_block("{ panic(\"a\"); panic(\"b\") }");
+        let [Statement::Panic(stmt)] = &block.statements[..] else {
+            panic!("expected a panic statement, found {:?}", block.statements);
+        };
+        assert_eq!(stmt.span, Span::new(2, 13, 1, 3));
+        assert!(matches!(block.tail.as_deref(), Some(Expression::Panic(_))));
+    }
+
+    #[test]
+    fn test_trailing_expression_is_tail() {
+        let block = parse_block("{ x = 1; x }");
+        assert_eq!(block.statements.len(), 1);
+        assert!(block.tail.is_some());
+
+        let block = parse_block("{ if c { 1 } else { 2 } }");
+        assert!(block.statements.is_empty());
+        assert!(matches!(block.tail.as_deref(), Some(Expression::If(_))));
+
+        let block = parse_block("{ while c { f(); } }");
+        assert_eq!(block.statements.len(), 1);
+        assert!(block.tail.is_none());
+    }
+
+    #[test]
+    fn test_block_expression_and_block_statement() {
+        let block = parse_block("{ let x = { let y = 1; S { a: y } }; { let z = 2; } x }");
+        let [Statement::Let(stmt), Statement::Block(inner)] = &block.statements[..] else {
+            panic!("expected a let and a block, found {:?}", block.statements);
+        };
+        let Some(Expression::Block(value)) = &stmt.initializer else {
+            panic!("expected a block initializer, found {:?}", stmt.initializer);
+        };
+        assert_eq!(value.statements.len(), 1);
+        assert!(matches!(value.tail.as_deref(), Some(Expression::Struct(_))));
+        assert_eq!(inner.statements.len(), 1);
+        assert!(inner.tail.is_none());
+        assert!(matches!(
+            block.tail.as_deref(),
+            Some(Expression::Identifier(_))
+        ));
+    }
+
+    #[test]
+    fn test_control_flow_statements() {
+        let block = parse_block("{ loop { break 1; } for i in 0..10 { continue; } return; }");
+        assert!(matches!(block.statements[0], Statement::Loop(_)));
+        assert!(matches!(block.statements[1], Statement::For(_)));
+        assert!(matches!(
+            block.statements[2],
+            Statement::Return(ReturnStatement {
+                expression: None,
+                ..
+            })
+        ));
+        let Statement::Loop(stmt) = &block.statements[0] else {
+            unreachable!();
+        };
+        let Statement::Break(brk) = &stmt.body.statements[0] else {
+            panic!("expected break");
+        };
+        assert_eq!(
+            brk.expression,
+            Some(Expression::Literal(Literal::Integer(1, None, IntBase::Dec)))
+        );
+    }
+
+    #[test]
+    fn test_labeled_loops() {
+        let block = parse_block("{ 'outer: for i in xs { 'inner: while c { continue 'outer; } } }");
+        let Statement::For(outer) = &block.statements[0] else {
+            panic!("expected for");
+        };
+        assert_eq!(outer.label.as_ref().unwrap().name, "outer");
+        assert_eq!(outer.span.start, 2);
+        let Statement::While(inner) = &outer.body.statements[0] else {
+            panic!("expected while");
+        };
+        assert_eq!(inner.label.as_ref().unwrap().name, "inner");
+        let Statement::Continue(cont) = &inner.body.statements[0] else {
+            panic!("expected continue");
+        };
+        assert_eq!(cont.label.as_ref().unwrap().name, "outer");
+
+        let block = parse_block("{ 'a: loop { break 'a 1; } }");
+        let Some(Expression::Loop(loop_expr)) = block.tail.as_deref() else {
+            panic!("expected loop tail");
+        };
+        assert_eq!(loop_expr.label.as_ref().unwrap().name, "a");
+
+        let error = Parser::new("{ 'a: if c {} }")
+            .unwrap()
+            .parse_block()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected one of `loop`, `while` or `for`, found `if` at 1:7"
+        );
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let error = Parser::new("{ a b }").unwrap().parse_block().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected one of `::`, `{`, `as`, an operator, `;` or `}`, found identifier at 1:5"
+        );
+    }
+}
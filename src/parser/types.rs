@@ -0,0 +1,337 @@
+use super::{Expected, ParseResult, Parser};
+use crate::ast::types::{FunctionAbi, Lifetime, Mutability, TypePath, TypePathSegment};
+use crate::ast::{Span, Type};
+use crate::lexer::{Token, decode_string_literal};
+
+impl Parser<'_> {
+    pub fn parse_type(&mut self) -> ParseResult<Type> {
+        self.nested(Self::parse_type_inner)
+    }
+
+    // `'a` after `&`. Lifetimes share the label token.
+    fn parse_optional_lifetime(&mut self) -> ParseResult<Option<Lifetime>> {
+        Ok(self.parse_optional_label()?.map(Lifetime::new))
+    }
+
+    // The parameter list and return type after `fn`. Only `extern` function
+    // types may end their parameters with `...`.
+    fn parse_function_type(&mut self, convention: Option<String>) -> ParseResult<Type> {
+        self.expect(Token::LParen)?;
+        let mut params = Vec::new();
+        let mut variadic = false;
+        while !self.eat(Token::RParen) {
+            if convention.is_some() && self.eat(Token::Ellipsis) {
+                variadic = true;
+                self.expect(Token::RParen)?;
+                break;
+            }
+            params.push(self.parse_type()?);
+            if !self.eat(Token::Comma) {
+                self.expect(Token::RParen)?;
+                break;
+            }
+        }
+        let return_type = if self.eat(Token::Arrow) {
+            self.parse_type()?
+        } else {
+            Type::Unit
+        };
+        let abi = FunctionAbi {
+            convention,
+            variadic,
+        };
+        Ok(Type::Function(params, Box::new(return_type), abi))
+    }
+
+    fn parse_type_inner(&mut self) -> ParseResult<Type> {
+        let Some(token) = self.peek() else {
+            return Err(self.error_expected(Expected::Type));
+        };
+        if let Some(ty) = primitive_type(token) {
+            self.advance();
+            return Ok(ty);
+        }
+        match token {
+            Token::Not => {
+                self.advance();
+                Ok(Type::Never)
+            }
+            Token::LParen => {
+                self.advance();
+                let mut types = Vec::new();
+                while !self.eat(Token::RParen) {
+                    types.push(self.parse_type()?);
+                    if !self.eat(Token::Comma) {
+                        self.expect(Token::RParen)?;
+                        break;
+                    }
+                }
+                // There are no 1-tuples: a trailing comma does not make one,
+                // and `(T,)` is `T` just like `(T)`.
+                Ok(Type::Tuple(types).normalized())
+            }
+            Token::LBracket => {
+                self.advance();
+                let element = Box::new(self.parse_type()?);
+                if self.eat(Token::Semicolon) {
+                    let size = self.parse_expression()?;
+                    self.expect(Token::RBracket)?;
+                    Ok(Type::Array(element, Some(Box::new(size))))
+                } else {
+                    self.expect(Token::RBracket)?;
+                    Ok(Type::Slice(element))
+                }
+            }
+            Token::Star => {
+                self.advance();
+                let mutability = if self.eat(Token::Mut) {
+                    Mutability::Mutable
+                } else {
+                    self.expect(Token::Const)?;
+                    Mutability::Immutable
+                };
+                Ok(Type::Pointer(Box::new(self.parse_type()?), mutability))
+            }
+            Token::BitAnd => {
+                self.advance();
+                let lifetime = self.parse_optional_lifetime()?;
+                let mutability = self.parse_mutability();
+                Ok(Type::Reference(
+                    Box::new(self.parse_type()?),
+                    mutability,
+                    lifetime,
+                ))
+            }
+            Token::And => {
+                // `&&T` is lexed as a single token; a lifetime after it
+                // belongs to the inner reference.
+                self.advance();
+                let lifetime = self.parse_optional_lifetime()?;
+                let mutability = self.parse_mutability();
+                let inner = Type::Reference(Box::new(self.parse_type()?), mutability, lifetime);
+                Ok(Type::Reference(
+                    Box::new(inner),
+                    Mutability::Immutable,
+                    None,
+                ))
+            }
+            Token::Fn => {
+                self.advance();
+                self.parse_function_type(None)
+            }
+            Token::Extern => {
+                self.advance();
+                let span =
+                    self.expect_described(Token::StringLiteral, Expected::CallingConvention)?;
+                let convention = decode_string_literal(self.text(&span), &span)?;
+                self.expect(Token::Fn)?;
+                self.parse_function_type(Some(convention))
+            }
+            Token::Identifier if self.text(&self.current_span()) == "_" => {
+                Ok(Type::Infer(self.advance().span))
+            }
+            Token::Identifier => self.parse_type_path().map(Type::Named),
+            _ => Err(self.error_expected(Expected::Type)),
+        }
+    }
+
+    fn parse_type_path(&mut self) -> ParseResult<TypePath> {
+        let start = self.current_span();
+        let mut segments = Vec::new();
+        loop {
+            let ident = self.parse_identifier()?;
+            let generic_args = if self.eat(Token::Lt) {
+                Some(self.parse_generic_args()?)
+            } else {
+                None
+            };
+            let span = ident.span.join(&self.previous_span());
+            segments.push(TypePathSegment {
+                ident,
+                generic_args,
+                span,
+            });
+            if !self.eat(Token::PathSep) {
+                break;
+            }
+        }
+        Ok(TypePath {
+            segments,
+            span: start.join(&self.previous_span()),
+        })
+    }
+
+    // The arguments after an opening `<`, through the closing `>`.
+    pub(super) fn parse_generic_args(&mut self) -> ParseResult<Vec<Type>> {
+        let mut args = Vec::new();
+        while !self.eat_closing_angle() {
+            args.push(self.parse_type()?);
+            if !self.eat(Token::Comma) {
+                if !self.eat_closing_angle() {
+                    return Err(self.error_expected(Token::Gt));
+                }
+                break;
+            }
+        }
+        Ok(args)
+    }
+
+    fn parse_mutability(&mut self) -> Mutability {
+        if self.eat(Token::Mut) {
+            Mutability::Mutable
+        } else {
+            Mutability::Immutable
+        }
+    }
+
+    // Consumes one `>`, splitting a `>>` token so nested generic argument
+    // lists like `Vec<Vec<i32>>` close correctly.
+    fn eat_closing_angle(&mut self) -> bool {
+        match self.peek() {
+            Some(Token::Gt) => {
+                self.advance();
+                true
+            }
+            Some(Token::Shr) => {
+                let token = &mut self.tokens[self.pos];
+                let span = &token.span;
+                token.token = Token::Gt;
+                token.span = Span::new(span.start + 1, span.end, span.line, span.column + 1);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+pub(super) fn primitive_type(token: Token) -> Option<Type> {
+    let ty = match token {
+        Token::I8 => Type::I8,
+        Token::I16 => Type::I16,
+        Token::I32 => Type::I32,
+        Token::I64 => Type::I64,
+        Token::I128 => Type::I128,
+        Token::Isize => Type::Isize,
+        Token::U8 => Type::U8,
+        Token::U16 => Type::U16,
+        Token::U32 => Type::U32,
+        Token::U64 => Type::U64,
+        Token::U128 => Type::U128,
+        Token::Usize => Type::Usize,
+        Token::F32 => Type::F32,
+        Token::F64 => Type::F64,
+        Token::Bool => Type::Bool,
+        Token::Char => Type::Char,
+        Token::Str => Type::Str,
+        _ => return None,
+    };
+    Some(ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_type(source: &str) -> Type {
+        Parser::new(source).unwrap().parse_type().unwrap()
+    }
+
+    #[test]
+    fn test_parse_primitive_and_compound_types() {
+        assert_eq!(parse_type("i32"), Type::I32);
+        assert_eq!(parse_type("()"), Type::Unit);
+        assert_eq!(parse_type("(i32, bool)").to_string(), "(i32, bool)");
+        assert_eq!(parse_type("&mut [u8]").to_string(), "&mut [u8]");
+        assert_eq!(parse_type("*const char").to_string(), "*const char");
+        assert_eq!(parse_type("fn(i32) -> bool").to_string(), "fn(i32) -> bool");
+    }
+
+    #[test]
+    fn test_parse_reference_lifetimes() {
+        let Type::Reference(inner, Mutability::Immutable, Some(lifetime)) = parse_type("&'a i32")
+        else {
+            panic!("expected a reference with a lifetime");
+        };
+        assert_eq!(*inner, Type::I32);
+        assert_eq!(lifetime.ident.name, "a");
+        assert_eq!(lifetime.ident.span.start, 1);
+
+        assert_eq!(
+            parse_type("&mut i32"),
+            Type::Reference(Box::new(Type::I32), Mutability::Mutable, None)
+        );
+        assert_eq!(parse_type("&'a mut [u8]").to_string(), "&'a mut [u8]");
+        assert_eq!(parse_type("&&'b str").to_string(), "&&'b str");
+        assert!(Parser::new("&mut 'a i32").unwrap().parse_type().is_err());
+    }
+
+    #[test]
+    fn test_parse_variadic_extern_function_type() {
+        let ty = parse_type("extern \"C\" fn(*const u8, ...) -> i32");
+        let Type::Function(params, ret, abi) = &ty else {
+            panic!("expected a function type, found {:?}", ty);
+        };
+        assert_eq!(params.len(), 1);
+        assert_eq!(**ret, Type::I32);
+        assert_eq!(abi.convention.as_deref(), Some("C"));
+        assert!(abi.variadic);
+        assert_eq!(ty.to_string(), "extern \"C\" fn(*const u8, ...) -> i32");
+
+        assert_eq!(
+            parse_type("extern \"C\" fn(...)").to_string(),
+            "extern \"C\" fn(...) -> ()"
+        );
+        assert_eq!(
+            parse_type("extern \"system\" fn(i32,)").to_string(),
+            "extern \"system\" fn(i32) -> ()"
+        );
+        assert_ne!(parse_type("extern \"C\" fn(i32)"), parse_type("fn(i32)"));
+        assert!(Parser::new("fn(i32, ...)").unwrap().parse_type().is_err());
+        assert!(
+            Parser::new("extern \"C\" fn(..., i32)")
+                .unwrap()
+                .parse_type()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_tuple_normalization() {
+        assert_eq!(parse_type("(i32)"), Type::I32);
+        assert_eq!(parse_type("(i32,)"), Type::I32);
+        assert_eq!(parse_type("((),)"), Type::Unit);
+        assert_eq!(parse_type("&[(u8,)]"), parse_type("&[u8]"));
+    }
+
+    #[test]
+    fn test_parse_nested_generic_path() {
+        let ty = parse_type("std::Vec<Option<i32>>");
+        assert_eq!(ty.to_string(), "std::Vec<Option<i32>>");
+        assert_eq!(ty.display_short(), "Vec<Option<i32>>");
+    }
+
+    #[test]
+    fn test_array_length_display() {
+        assert_eq!(parse_type("[i32; 4]").to_string(), "[i32; 4]");
+        assert_eq!(parse_type("[i32; (N)]").to_string(), "[i32; (N)]");
+        assert_eq!(
+            parse_type("[[u8; 0x10]; N * 2]").to_string(),
+            "[[u8; 0x10]; N * 2]"
+        );
+    }
+
+    #[test]
+    fn test_parse_infer_placeholder() {
+        let Type::Array(element, Some(_)) = parse_type("[_; 3]") else {
+            panic!("expected array type");
+        };
+        assert_eq!(*element, Type::Infer(Span::new(1, 2, 1, 2)));
+
+        let Type::Named(path) = parse_type("Vec<_>") else {
+            panic!("expected named type");
+        };
+        let args = path.segments[0].generic_args.as_ref().unwrap();
+        assert!(matches!(args[..], [Type::Infer(_)]));
+        assert_eq!(parse_type("(_, Map<u8, _>)").to_string(), "(_, Map<u8, _>)");
+    }
+}
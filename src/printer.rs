@@ -0,0 +1,1054 @@
+use crate::ast::expressions::{Expression, Literal, MatchArm, Pattern};
+use crate::ast::lookup::NodeRef;
+use crate::ast::statements::{ElseBranch, IfStatement, Statement};
+use crate::ast::{
+    Attribute, AttributeArg, Block, Declaration, FunctionDecl, Identifier, MacroToken, Program,
+    Span, StructDecl, StructField,
+};
+use crate::comments::{Comment, CommentPlacement, collect_comments, statement_span};
+use std::collections::HashMap;
+
+use crate::lexer::{newline_count, tokenize_with_trivia};
+use crate::parser::{ParseError, parse};
+
+const INDENT: &str = "    ";
+
+// Binding strength of non-binary expressions, relative to
+// `BinaryOperator::precedence`.
+const CAST_PRECEDENCE: u8 = 11;
+const UNARY_PRECEDENCE: u8 = 12;
+const POSTFIX_PRECEDENCE: u8 = 13;
+const RANGE_PRECEDENCE: u8 = 0;
+
+#[derive(Debug, Default)]
+pub struct Printer {
+    output: String,
+    indent: usize,
+    // Drops source parentheses that precedence makes redundant, instead of
+    // keeping every `Paren` as written.
+    minimal_parens: bool,
+    // Comments still to be printed, keyed by the start of the node they are
+    // attached to; see `comments::collect_comments`.
+    comments: HashMap<usize, Vec<Comment>>,
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_minimal_parens(mut self, minimal_parens: bool) -> Self {
+        self.minimal_parens = minimal_parens;
+        self
+    }
+
+    pub fn with_comments(mut self, comments: Vec<Comment>) -> Self {
+        for comment in comments {
+            self.comments
+                .entry(comment.node.start)
+                .or_default()
+                .push(comment);
+        }
+        self
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    pub fn print_program(&mut self, program: &Program) {
+        self.print_items(&program.items, &[]);
+        self.print_remaining_comments();
+    }
+
+    // `newlines[i]` is how many line breaks preceded item `i` in the source;
+    // items without an entry are separated by a blank line.
+    fn print_items(&mut self, items: &[Declaration], newlines: &[usize]) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 && newlines.get(i).is_none_or(|&count| count > 1) {
+                self.output.push('\n');
+            }
+            self.with_node_comments(Some(item.span()), |p| p.print_declaration(item));
+        }
+    }
+
+    // Prints the comments attached to the node at `span` around `print`,
+    // which must print the node through to its final newline.
+    fn with_node_comments(&mut self, span: Option<&Span>, print: impl FnOnce(&mut Self)) {
+        let comments = span
+            .and_then(|span| self.comments.remove(&span.start))
+            .unwrap_or_default();
+        let placed = |placement| {
+            comments
+                .iter()
+                .filter(move |comment| comment.placement == placement)
+        };
+        for comment in placed(CommentPlacement::Leading) {
+            self.write_indent();
+            self.output.push_str(&comment.text);
+            self.output.push('\n');
+        }
+        print(self);
+        for comment in placed(CommentPlacement::Trailing) {
+            if self.output.ends_with('\n') {
+                self.output.pop();
+            }
+            self.output.push(' ');
+            self.output.push_str(&comment.text);
+            self.output.push('\n');
+        }
+        for comment in placed(CommentPlacement::After) {
+            self.write_indent();
+            self.output.push_str(&comment.text);
+            self.output.push('\n');
+        }
+    }
+
+    // Comments whose node was never printed, such as those following an empty
+    // program, each on its own line in source order.
+    fn print_remaining_comments(&mut self) {
+        let mut remaining: Vec<Comment> = self.comments.drain().flat_map(|(_, c)| c).collect();
+        remaining.sort_by_key(|comment| comment.span.start);
+        for comment in remaining {
+            self.output.push_str(&comment.text);
+            self.output.push('\n');
+        }
+    }
+
+    pub fn print_declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::Function(function) => self.print_function(function),
+            Declaration::Struct(decl) => self.print_struct(decl),
+            Declaration::Enum(decl) => {
+                self.print_attributes(&decl.attributes);
+                self.write_indent();
+                self.output.push_str(&format!("enum {} {{\n", decl.name));
+                self.indent += 1;
+                for variant in &decl.variants {
+                    self.with_node_comments(Some(&variant.span), |p| {
+                        p.write_indent();
+                        p.output.push_str(&variant.name.name);
+                        if let Some(data) = &variant.data {
+                            p.output.push_str(&format!("({})", data));
+                        }
+                        if let Some(discriminant) = &variant.discriminant {
+                            p.output.push_str(" = ");
+                            p.print_expression(discriminant);
+                        }
+                        p.output.push_str(",\n");
+                    });
+                }
+                self.indent -= 1;
+                self.write_indent();
+                self.output.push_str("}\n");
+            }
+            Declaration::Union(decl) => {
+                self.print_attributes(&decl.attributes);
+                self.write_indent();
+                self.output.push_str(&format!("union {} {{\n", decl.name));
+                self.indent += 1;
+                for field in &decl.fields {
+                    self.with_node_comments(Some(&field.span), |p| {
+                        p.write_indent();
+                        p.output
+                            .push_str(&format!("{}: {},\n", field.name, field.ty));
+                    });
+                }
+                self.indent -= 1;
+                self.write_indent();
+                self.output.push_str("}\n");
+            }
+            Declaration::Variable(var) => {
+                self.write_indent();
+                self.output.push_str("var ");
+                if var.mutable {
+                    self.output.push_str("mut ");
+                }
+                self.output.push_str(&var.name.name);
+                if let Some(ty) = &var.ty {
+                    self.output.push_str(&format!(": {}", ty));
+                }
+                if let Some(initializer) = &var.initializer {
+                    self.output.push_str(" = ");
+                    self.print_expression(initializer);
+                }
+                self.output.push_str(";\n");
+            }
+            Declaration::Constant(constant) => {
+                self.write_indent();
+                self.output
+                    .push_str(&format!("const {}: {} = ", constant.name, constant.ty));
+                self.print_expression(&constant.value);
+                self.output.push_str(";\n");
+            }
+            Declaration::Static(decl) => {
+                self.write_indent();
+                self.output.push_str("static ");
+                if decl.mutable {
+                    self.output.push_str("mut ");
+                }
+                self.output
+                    .push_str(&format!("{}: {} = ", decl.name, decl.ty));
+                self.print_expression(&decl.value);
+                self.output.push_str(";\n");
+            }
+            Declaration::Module(module) => {
+                self.write_indent();
+                let Some(items) = &module.items else {
+                    self.output.push_str(&format!("mod {};\n", module.name));
+                    return;
+                };
+                self.output.push_str(&format!("mod {} {{\n", module.name));
+                self.indent += 1;
+                self.print_items(items, &[]);
+                self.indent -= 1;
+                self.write_indent();
+                self.output.push_str("}\n");
+            }
+            Declaration::Macro(decl) => {
+                self.write_indent();
+                self.output.push_str(&format!("macro {}(", decl.name));
+                for (i, param) in decl.params.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.output
+                        .push_str(&format!("{}: {}", param.name, param.ty));
+                }
+                self.output.push_str(") { ");
+                print_macro_tokens(&mut self.output, &decl.body.tokens);
+                self.output.push_str(" }\n");
+            }
+        }
+    }
+
+    fn print_function(&mut self, function: &FunctionDecl) {
+        self.print_attributes(&function.attributes);
+        self.write_indent();
+        self.output.push_str(&format!("fn {}", function.name));
+        if !function.generics.is_empty() {
+            let generics: Vec<&str> = function.generics.iter().map(|g| g.name.as_str()).collect();
+            self.output.push_str(&format!("<{}>", generics.join(", ")));
+        }
+        self.output.push('(');
+        for (i, param) in function.params.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.output
+                .push_str(&format!("{}: {}", param.name, param.ty));
+            if let Some(default) = &param.default {
+                self.output.push_str(" = ");
+                self.print_expression(default);
+            }
+        }
+        self.output.push(')');
+        if let Some(return_type) = &function.return_type {
+            self.output.push_str(&format!(" -> {}", return_type));
+        }
+        self.output.push(' ');
+        self.print_block(&function.body);
+        self.output.push('\n');
+    }
+
+    fn print_struct(&mut self, decl: &StructDecl) {
+        self.print_attributes(&decl.attributes);
+        self.write_indent();
+        self.output.push_str(&format!("struct {} {{\n", decl.name));
+        self.indent += 1;
+        for field in &decl.fields {
+            self.with_node_comments(Some(&field.span), |p| p.print_struct_field(field));
+        }
+        self.indent -= 1;
+        self.write_indent();
+        self.output.push_str("}\n");
+    }
+
+    fn print_struct_field(&mut self, field: &StructField) {
+        self.print_attributes(&field.attributes);
+        self.write_indent();
+        self.output
+            .push_str(&format!("{}: {}", field.name, field.ty));
+        if let Some(default) = &field.default {
+            self.output.push_str(" = ");
+            self.print_expression(default);
+        }
+        self.output.push_str(",\n");
+    }
+
+    fn print_attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.write_indent();
+            self.output.push_str(&format!("#[{}", attribute.name));
+            if !attribute.args.is_empty() {
+                self.print_attribute_args(&attribute.args);
+            }
+            self.output.push_str("]\n");
+        }
+    }
+
+    fn print_attribute_args(&mut self, args: &[AttributeArg]) {
+        self.output.push('(');
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            match arg {
+                AttributeArg::Literal(literal) => self.output.push_str(&literal.to_string()),
+                AttributeArg::Identifier(ident) => self.output.push_str(&ident.name),
+                AttributeArg::NameValue(ident, literal) => self
+                    .output
+                    .push_str(&format!("{} = {}", ident.name, literal)),
+                AttributeArg::List(ident, args) => {
+                    self.output.push_str(&ident.name);
+                    self.print_attribute_args(args);
+                }
+            }
+        }
+        self.output.push(')');
+    }
+
+    pub fn print_block(&mut self, block: &Block) {
+        if block.statements.is_empty() && block.tail.is_none() {
+            self.output.push_str("{}");
+            return;
+        }
+        self.output.push_str("{\n");
+        self.indent += 1;
+        for statement in &block.statements {
+            self.with_node_comments(statement_span(statement), |p| p.print_statement(statement));
+        }
+        if let Some(tail) = &block.tail {
+            self.with_node_comments(NodeRef::Expression(tail).span(), |p| {
+                p.write_indent();
+                p.print_expression(tail);
+                p.output.push('\n');
+            });
+        }
+        self.indent -= 1;
+        self.write_indent();
+        self.output.push('}');
+    }
+
+    pub fn print_statement(&mut self, statement: &Statement) {
+        self.write_indent();
+        match statement {
+            Statement::Empty => self.output.push(';'),
+            Statement::Expression(expr) => {
+                self.print_expression(expr);
+                if !is_block_like(expr) {
+                    self.output.push(';');
+                }
+            }
+            Statement::Let(let_stmt) => {
+                self.output.push_str("var ");
+                if let_stmt.mutable {
+                    self.output.push_str("mut ");
+                }
+                self.print_pattern(&let_stmt.pattern);
+                if let Some(ty) = &let_stmt.type_annotation {
+                    self.output.push_str(&format!(": {}", ty));
+                }
+                if let Some(initializer) = &let_stmt.initializer {
+                    self.output.push_str(" = ");
+                    self.print_expression(initializer);
+                }
+                self.output.push(';');
+            }
+            Statement::Return(ret) => {
+                self.output.push_str("return");
+                if let Some(expr) = &ret.expression {
+                    self.output.push(' ');
+                    self.print_expression(expr);
+                }
+                self.output.push(';');
+            }
+            Statement::Break(brk) => {
+                self.output.push_str("break");
+                if let Some(label) = &brk.label {
+                    self.output.push_str(&format!(" '{}", label));
+                }
+                if let Some(expr) = &brk.expression {
+                    self.output.push(' ');
+                    self.print_expression(expr);
+                }
+                self.output.push(';');
+            }
+            Statement::Continue(cont) => {
+                self.output.push_str("continue");
+                if let Some(label) = &cont.label {
+                    self.output.push_str(&format!(" '{}", label));
+                }
+                self.output.push(';');
+            }
+            Statement::While(while_stmt) => {
+                self.print_label(&while_stmt.label);
+                self.output.push_str("while ");
+                self.print_expression(&while_stmt.condition);
+                self.output.push(' ');
+                self.print_block(&while_stmt.body);
+            }
+            Statement::For(for_stmt) => {
+                self.print_label(&for_stmt.label);
+                self.output.push_str("for ");
+                self.print_pattern(&for_stmt.pattern);
+                self.output.push_str(" in ");
+                self.print_expression(&for_stmt.iterator);
+                self.output.push(' ');
+                self.print_block(&for_stmt.body);
+            }
+            Statement::Loop(loop_stmt) => {
+                self.print_label(&loop_stmt.label);
+                self.output.push_str("loop ");
+                self.print_block(&loop_stmt.body);
+            }
+            Statement::Block(block) => self.print_block(block),
+            Statement::If(if_stmt) => self.print_if_statement(if_stmt),
+            Statement::Match(match_stmt) => {
+                self.output.push_str("match ");
+                self.print_expression(&match_stmt.expression);
+                self.print_match_arms(&match_stmt.arms);
+            }
+            Statement::Panic(panic_stmt) => {
+                self.output.push_str("panic(");
+                self.print_expression(&panic_stmt.message);
+                self.output.push_str(");");
+            }
+        }
+        self.output.push('\n');
+    }
+
+    fn print_if_statement(&mut self, if_stmt: &IfStatement) {
+        self.output.push_str("if ");
+        self.print_expression(&if_stmt.condition);
+        self.output.push(' ');
+        self.print_block(&if_stmt.then_branch);
+        match &if_stmt.else_branch {
+            Some(ElseBranch::Block(block)) => {
+                self.output.push_str(" else ");
+                self.print_block(block);
+            }
+            Some(ElseBranch::If(nested)) => {
+                self.output.push_str(" else ");
+                self.print_if_statement(nested);
+            }
+            None => {}
+        }
+    }
+
+    fn print_match_arms(&mut self, arms: &[MatchArm]) {
+        self.output.push_str(" {\n");
+        self.indent += 1;
+        for arm in arms {
+            self.write_indent();
+            self.print_pattern(&arm.pattern);
+            if let Some(guard) = &arm.guard {
+                self.output.push_str(" if ");
+                self.print_expression(guard);
+            }
+            self.output.push_str(" => ");
+            self.print_expression(&arm.body);
+            self.output.push_str(",\n");
+        }
+        self.indent -= 1;
+        self.write_indent();
+        self.output.push('}');
+    }
+
+    fn print_label(&mut self, label: &Option<Identifier>) {
+        if let Some(label) = label {
+            self.output.push_str(&format!("'{}: ", label));
+        }
+    }
+
+    pub fn print_expression(&mut self, expr: &Expression) {
+        self.print_expression_prec(expr, 0);
+    }
+
+    // Prints `expr`, wrapping it in parentheses if it binds more loosely than
+    // `min_precedence` requires.
+    fn print_expression_prec(&mut self, expr: &Expression, min_precedence: u8) {
+        let expr = if self.minimal_parens {
+            strip_redundant_parens(expr)
+        } else {
+            expr
+        };
+        let needs_parens = expression_precedence(expr) < min_precedence;
+        if needs_parens {
+            self.output.push('(');
+        }
+        match expr {
+            Expression::Literal(literal) => self.print_literal(literal),
+            Expression::Identifier(ident) => self.output.push_str(&ident.name),
+            Expression::Binary(binary) => {
+                let precedence = binary.operator.precedence();
+                let (left, right) = if binary.operator.is_right_associative() {
+                    (precedence + 1, precedence)
+                } else {
+                    (precedence, precedence + 1)
+                };
+                self.print_expression_prec(&binary.left, left);
+                self.output.push_str(&format!(" {} ", binary.operator));
+                self.print_expression_prec(&binary.right, right);
+            }
+            Expression::Unary(unary) => {
+                self.output.push_str(&unary.operator.to_string());
+                self.print_expression_prec(&unary.operand, UNARY_PRECEDENCE);
+            }
+            Expression::Call(call) => {
+                self.print_expression_prec(&call.callee, POSTFIX_PRECEDENCE);
+                self.print_arguments(&call.arguments);
+            }
+            Expression::Member(member) => {
+                self.print_expression_prec(&member.object, POSTFIX_PRECEDENCE);
+                self.output.push_str(&format!(".{}", member.member));
+            }
+            Expression::Index(index) => {
+                self.print_expression_prec(&index.array, POSTFIX_PRECEDENCE);
+                self.output.push('[');
+                self.print_expression(&index.index);
+                self.output.push(']');
+            }
+            Expression::Cast(cast) => {
+                self.print_expression_prec(&cast.expr, CAST_PRECEDENCE);
+                self.output.push_str(&format!(" as {}", cast.target_type));
+            }
+            Expression::Block(block) => self.print_block(block),
+            Expression::If(if_expr) => {
+                self.output.push_str("if ");
+                self.print_expression(&if_expr.condition);
+                self.output.push(' ');
+                self.print_expression(&if_expr.then_branch);
+                if let Some(else_branch) = &if_expr.else_branch {
+                    self.output.push_str(" else ");
+                    self.print_expression(else_branch);
+                }
+            }
+            Expression::Match(match_expr) => {
+                self.output.push_str("match ");
+                self.print_expression(&match_expr.value);
+                self.print_match_arms(&match_expr.arms);
+            }
+            Expression::Loop(loop_expr) => {
+                self.print_label(&loop_expr.label);
+                self.output.push_str("loop ");
+                self.print_expression(&loop_expr.body);
+            }
+            Expression::While(while_expr) => {
+                self.print_label(&while_expr.label);
+                self.output.push_str("while ");
+                self.print_expression(&while_expr.condition);
+                self.output.push(' ');
+                self.print_expression(&while_expr.body);
+            }
+            Expression::For(for_expr) => {
+                self.print_label(&for_expr.label);
+                self.output.push_str("for ");
+                self.print_pattern(&for_expr.pattern);
+                self.output.push_str(" in ");
+                self.print_expression(&for_expr.iterator);
+                self.output.push(' ');
+                self.print_expression(&for_expr.body);
+            }
+            Expression::Range(range) => {
+                if let Some(start) = &range.start {
+                    self.print_expression_prec(start, RANGE_PRECEDENCE + 1);
+                }
+                self.output
+                    .push_str(if range.inclusive { "..=" } else { ".." });
+                if let Some(end) = &range.end {
+                    self.print_expression_prec(end, RANGE_PRECEDENCE + 1);
+                }
+            }
+            Expression::MacroInvocation(invocation) => {
+                self.output.push_str(&format!("@{}", invocation.name));
+                self.print_arguments(&invocation.arguments);
+            }
+            Expression::Panic(panic) => {
+                self.output.push_str("panic(");
+                self.print_expression(&panic.message);
+                self.output.push(')');
+            }
+            // Generic arguments in expressions need the turbofish: `f::<T>`.
+            Expression::Path(path) => {
+                for (i, segment) in path.segments.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str("::");
+                    }
+                    self.output.push_str(&segment.ident.name);
+                    if let Some(args) = &segment.generic_args {
+                        let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+                        self.output.push_str(&format!("::<{}>", args.join(", ")));
+                    }
+                }
+            }
+            Expression::Struct(literal) => {
+                self.output.push_str(&format!("{} {{", literal.name));
+                for (i, (field, value)) in literal.fields.iter().enumerate() {
+                    self.output.push_str(if i > 0 { ", " } else { " " });
+                    self.output.push_str(&format!("{}: ", field));
+                    self.print_expression(value);
+                }
+                if let Some(rest) = &literal.rest {
+                    self.output.push_str(if literal.fields.is_empty() {
+                        " .."
+                    } else {
+                        ", .."
+                    });
+                    self.print_expression(rest);
+                }
+                if literal.fields.is_empty() && literal.rest.is_none() {
+                    self.output.push('}');
+                } else {
+                    self.output.push_str(" }");
+                }
+            }
+            Expression::Paren(inner, _) => {
+                self.output.push('(');
+                self.print_expression(inner);
+                self.output.push(')');
+            }
+        }
+        if needs_parens {
+            self.output.push(')');
+        }
+    }
+
+    fn print_arguments(&mut self, arguments: &[Expression]) {
+        self.output.push('(');
+        for (i, argument) in arguments.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.print_expression(argument);
+        }
+        self.output.push(')');
+    }
+
+    fn print_literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Array(elements) => {
+                self.output.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.print_expression(element);
+                }
+                self.output.push(']');
+            }
+            Literal::Tuple(elements) => {
+                self.output.push('(');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.print_expression(element);
+                }
+                if elements.len() == 1 {
+                    self.output.push(',');
+                }
+                self.output.push(')');
+            }
+            _ => self.output.push_str(&literal.to_string()),
+        }
+    }
+
+    pub fn print_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Literal(literal) => self.print_literal(literal),
+            Pattern::Identifier(ident) => self.output.push_str(&ident.name),
+            Pattern::Tuple(patterns) => {
+                self.output.push('(');
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.print_pattern(pattern);
+                }
+                self.output.push(')');
+            }
+            Pattern::Struct(name, fields, rest) => {
+                self.output.push_str(&format!("{} {{ ", name));
+                for (i, (field, pattern)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.output.push_str(&format!("{}: ", field));
+                    self.print_pattern(pattern);
+                }
+                if *rest {
+                    self.output
+                        .push_str(if fields.is_empty() { ".." } else { ", .." });
+                }
+                self.output.push_str(" }");
+            }
+            Pattern::Or(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(" | ");
+                    }
+                    self.print_pattern(alternative);
+                }
+            }
+            Pattern::Range(start, end) => {
+                self.print_pattern(start);
+                self.output.push_str("..=");
+                self.print_pattern(end);
+            }
+            Pattern::Wildcard => self.output.push('_'),
+            Pattern::Rest => self.output.push_str(".."),
+        }
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+    }
+}
+
+pub fn print_program(program: &Program) -> String {
+    let mut printer = Printer::new();
+    printer.print_program(program);
+    printer.finish()
+}
+
+// Reformats `source`. Top-level items keep at most one blank line between
+// them: several blank lines collapse to one, and adjacent items stay adjacent.
+pub fn format_source(source: &str) -> Result<String, ParseError> {
+    let program = parse(source)?;
+    let tokens = tokenize_with_trivia(source)?;
+    let mut newlines = vec![0];
+    for pair in program.items.windows(2) {
+        // The trivia run right after the previous item, up to the next token.
+        let first = tokens.partition_point(|t| t.span.start < pair[0].span().end);
+        let length = tokens[first..]
+            .iter()
+            .take_while(|t| t.token.is_trivia())
+            .count();
+        newlines.push(newline_count(&tokens[first..first + length], source));
+    }
+
+    let mut printer = Printer::new().with_comments(collect_comments(source, &program)?);
+    printer.print_items(&program.items, &newlines);
+    printer.print_remaining_comments();
+    Ok(printer.finish())
+}
+
+fn expression_precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Binary(binary) => binary.operator.precedence(),
+        Expression::Unary(_) => UNARY_PRECEDENCE,
+        Expression::Cast(_) => CAST_PRECEDENCE,
+        Expression::Range(_) => RANGE_PRECEDENCE,
+        _ => POSTFIX_PRECEDENCE,
+    }
+}
+
+// `expr` without its outer parentheses, which `print_expression_prec` puts
+// back where precedence needs them. Parentheses around a block-like
+// expression or a struct literal also keep it from being read as a
+// statement or a block, so those stay.
+fn strip_redundant_parens(mut expr: &Expression) -> &Expression {
+    while let Expression::Paren(inner, _) = expr {
+        if is_block_like(inner) || contains_bare_struct(inner) {
+            break;
+        }
+        expr = inner;
+    }
+    expr
+}
+
+// Whether a struct literal appears among the operands of `expr` without
+// brackets or parentheses of its own around it.
+fn contains_bare_struct(expr: &Expression) -> bool {
+    match expr {
+        Expression::Struct(_) => true,
+        Expression::Binary(binary) => {
+            contains_bare_struct(&binary.left) || contains_bare_struct(&binary.right)
+        }
+        Expression::Unary(unary) => contains_bare_struct(&unary.operand),
+        Expression::Cast(cast) => contains_bare_struct(&cast.expr),
+        Expression::Call(call) => contains_bare_struct(&call.callee),
+        Expression::Member(member) => contains_bare_struct(&member.object),
+        Expression::Index(index) => contains_bare_struct(&index.array),
+        Expression::Range(range) => {
+            range.start.as_ref().is_some_and(contains_bare_struct)
+                || range.end.as_ref().is_some_and(contains_bare_struct)
+        }
+        _ => false,
+    }
+}
+
+fn is_block_like(expr: &Expression) -> bool {
+    matches!(
+        expr,
+        Expression::Block(_)
+            | Expression::If(_)
+            | Expression::Match(_)
+            | Expression::Loop(_)
+            | Expression::While(_)
+            | Expression::For(_)
+    )
+}
+
+fn print_macro_tokens(output: &mut String, tokens: &[MacroToken]) {
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            output.push(' ');
+        }
+        match token {
+            MacroToken::Literal(text) => output.push_str(text),
+            MacroToken::Variable(ident) => output.push_str(&format!("${}", ident)),
+            MacroToken::Group(group) => {
+                output.push('(');
+                print_macro_tokens(output, group);
+                output.push(')');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::expressions::{BinaryExpr, BinaryOperator};
+    use crate::ast::statements::LetStatement;
+    use crate::ast::{Span, Type};
+
+    fn ident(name: &str) -> Identifier {
+        Identifier::new(name.to_string(), Span::dummy())
+    }
+
+    fn attribute(name: &str, args: Vec<AttributeArg>) -> Attribute {
+        Attribute {
+            name: ident(name),
+            args,
+            span: Span::dummy(),
+        }
+    }
+
+    #[test]
+    fn test_struct_field_attributes() {
+        let decl = StructDecl {
+            name: ident("Point"),
+            fields: vec![
+                StructField {
+                    name: ident("x"),
+                    ty: Type::I32,
+                    default: None,
+                    attributes: vec![
+                        attribute("deprecated", vec![]),
+                        attribute(
+                            "rename",
+                            vec![AttributeArg::Literal(Literal::String("px".to_string()))],
+                        ),
+                    ],
+                    span: Span::dummy(),
+                },
+                StructField {
+                    name: ident("y"),
+                    ty: Type::I32,
+                    default: None,
+                    attributes: vec![],
+                    span: Span::dummy(),
+                },
+            ],
+            attributes: vec![attribute(
+                "derive",
+                vec![
+                    AttributeArg::Identifier(ident("Debug")),
+                    AttributeArg::Identifier(ident("Clone")),
+                ],
+            )],
+            span: Span::dummy(),
+        };
+        let program = Program {
+            items: vec![Declaration::Struct(decl)],
+            span: Span::dummy(),
+        };
+
+        assert_eq!(
+            print_program(&program),
+            "#[derive(Debug, Clone)]\n\
+             struct Point {\n    \
+                 #[deprecated]\n    \
+                 #[rename(\"px\")]\n    \
+                 x: i32,\n    \
+                 y: i32,\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_function_attributes() {
+        let function = FunctionDecl::builder("square")
+            .param("x", Type::I32)
+            .returns(Type::I32)
+            .body(Block {
+                tail: Some(Box::new(Expression::Binary(Box::new(BinaryExpr {
+                    left: Expression::Identifier(ident("x")),
+                    operator: BinaryOperator::Mul,
+                    right: Expression::Identifier(ident("x")),
+                    span: Span::dummy(),
+                })))),
+                ..Block::default()
+            })
+            .attribute(attribute("inline", vec![]))
+            .attribute(attribute("test", vec![]))
+            .build();
+        let program = Program {
+            items: vec![Declaration::Function(function)],
+            span: Span::dummy(),
+        };
+
+        assert_eq!(
+            print_program(&program),
+            "#[inline]\n#[test]\nfn square(x: i32) -> i32 {\n    x * x\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        let minimize = |source: &str| {
+            let program = parse(&format!("fn f() {{ {}; }}", source)).unwrap();
+            let Declaration::Function(function) = &program.items[0] else {
+                unreachable!();
+            };
+            let Statement::Expression(expr) = &function.body.statements[0] else {
+                panic!("expected expression statement");
+            };
+            let mut printer = Printer::new().with_minimal_parens(true);
+            printer.print_expression(expr);
+            printer.finish()
+        };
+
+        assert_eq!(minimize("(a * b) + c"), "a * b + c");
+        assert_eq!(minimize("a * (b + c)"), "a * (b + c)");
+        assert_eq!(minimize("((a))"), "a");
+        assert_eq!(minimize("(a - b) - c"), "a - b - c");
+        assert_eq!(minimize("a - (b - c)"), "a - (b - c)");
+        assert_eq!(minimize("a = (b = c)"), "a = b = c");
+        assert_eq!(minimize("(a = b) = c"), "(a = b) = c");
+        assert_eq!(minimize("(a == b) && (c < d)"), "a == b && c < d");
+        assert_eq!(minimize("-(x as u8)"), "-(x as u8)");
+        assert_eq!(minimize("(-x) as u8"), "-x as u8");
+        assert_eq!(minimize("(f(x)).y"), "f(x).y");
+        assert_eq!(minimize("(a + b).y"), "(a + b).y");
+
+        // A struct literal in a condition must stay parenthesized.
+        let program = parse("fn f() { if (P { x: 1 } == p) { } }").unwrap();
+        let mut printer = Printer::new().with_minimal_parens(true);
+        printer.print_program(&program);
+        assert!(printer.finish().contains("if (P { x: 1 } == p) {"));
+
+        // Kept by default.
+        let program = parse("fn f() { (a * b) + c; }").unwrap();
+        assert!(print_program(&program).contains("(a * b) + c;"));
+    }
+
+    #[test]
+    fn test_precedence_parentheses() {
+        let sum = Expression::Binary(Box::new(BinaryExpr {
+            left: Expression::Identifier(ident("a")),
+            operator: BinaryOperator::Add,
+            right: Expression::Identifier(ident("b")),
+            span: Span::dummy(),
+        }));
+        let product = Expression::Binary(Box::new(BinaryExpr {
+            left: sum,
+            operator: BinaryOperator::Mul,
+            right: Expression::Identifier(ident("c")),
+            span: Span::dummy(),
+        }));
+
+        let mut printer = Printer::new();
+        printer.print_expression(&product);
+        assert_eq!(printer.finish(), "(a + b) * c");
+    }
+
+    #[test]
+    fn test_format_source_collapses_blank_lines() {
+        assert_eq!(
+            format_source("fn a() {}\n\n\n\nfn b() {}").unwrap(),
+            "fn a() {}\n\nfn b() {}\n"
+        );
+        assert_eq!(
+            format_source("fn a() {}\nfn b() {}\n\nstruct C {}").unwrap(),
+            "fn a() {}\nfn b() {}\n\nstruct C {\n}\n"
+        );
+        assert_eq!(
+            format_source("fn a() {} fn b() {}").unwrap(),
+            "fn a() {}\nfn b() {}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_source_keeps_type_paths() {
+        assert_eq!(
+            format_source("fn f(x: a::Foo) -> std::Vec<b::Bar> {}").unwrap(),
+            "fn f(x: a::Foo) -> std::Vec<b::Bar> {}\n"
+        );
+        assert_eq!(
+            format_source("var grid: [i32; 4];").unwrap(),
+            "var grid: [i32; 4];\n"
+        );
+    }
+
+    #[test]
+    fn test_format_source_keeps_escaped_literals() {
+        let source = "fn f() {\n    var a = \"a\\\\n\";\n    var b = \"say \\\"hi\\\"\\t\\u{7}\";\n    var c = '\\'';\n    var d = '\\\\';\n    var e = '\"';\n}\n";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, source);
+
+        let program = parse(&formatted).unwrap();
+        let Declaration::Function(function) = &program.items[0] else {
+            panic!("expected function");
+        };
+        let values: Vec<Literal> = function
+            .body
+            .statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Let(LetStatement {
+                    initializer: Some(Expression::Literal(literal)),
+                    ..
+                }) => literal.clone(),
+                _ => panic!("expected let with a literal"),
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                Literal::String("a\\n".to_string()),
+                Literal::String("say \"hi\"\t\u{7}".to_string()),
+                Literal::Character('\''),
+                Literal::Character('\\'),
+                Literal::Character('"'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_source_keeps_comments() {
+        let source = "// Adds one.\nfn inc(x: i32) -> i32 {\n    // Widen first.\n    var y = x; // copy\n    y + 1\n    // done\n}\n";
+        assert_eq!(format_source(source).unwrap(), source);
+        assert_eq!(
+            format_source("fn f() {   x =  1;   /* why */ }").unwrap(),
+            "fn f() {\n    x = 1; /* why */\n}\n"
+        );
+        assert_eq!(
+            format_source("fn f() {\n    g(1, /* inline */ 2);\n}").unwrap(),
+            "fn f() {\n    /* inline */\n    g(1, 2);\n}\n"
+        );
+        assert_eq!(
+            format_source("fn f(a: i32, /* why */ b: i32) {}").unwrap(),
+            "/* why */\nfn f(a: i32, b: i32) {}\n"
+        );
+        let source =
+            "struct S {\n    // field doc\n    xs: i32, // xs\n}\nenum E {\n    A, // first\n}\n";
+        assert_eq!(format_source(source).unwrap(), source);
+        assert_eq!(format_source("// only\n").unwrap(), "// only\n");
+    }
+}
@@ -0,0 +1,1662 @@
+use crate::ast::expressions::{
+    BinaryExpr, BinaryOperator, CallExpr, CastExpr, Expression, ForExpr, IfExpr, IndexExpr,
+    IntBase, Literal, LoopExpr, MacroInvocation, MatchArm, MatchExpr, MemberExpr, PanicExpr,
+    Pattern, RangeExpr, StructExpr, UnaryExpr, UnaryOperator, WhileExpr,
+};
+use crate::ast::statements::{
+    BreakStatement, ContinueStatement, ElseBranch, ForStatement, IfStatement, LetStatement,
+    LoopStatement, MatchStatement, PanicStatement, ReturnStatement, Statement, WhileStatement,
+};
+use crate::ast::types::{FunctionAbi, Lifetime, Mutability, TypePath, TypePathSegment};
+use crate::ast::{
+    Attribute, AttributeArg, Block, ConstDecl, Declaration, EnumDecl, EnumVariant, FunctionDecl,
+    Identifier, MacroBody, MacroDecl, MacroParam, MacroToken, ModuleDecl, Parameter, Program, Span,
+    StaticDecl, StructDecl, StructField, Type, UnionDecl, UnionField, VarDecl,
+};
+use std::fmt;
+
+// Version of the wire format below. The shape is independent of the AST's Rust
+// layout: every node is an object tagged with a `kind` string and fields are
+// named explicitly, so internal refactors need not change persisted data.
+pub const FORMAT_VERSION: i128 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeserializeError {
+    pub message: String,
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid serialized program: {}", self.message)
+    }
+}
+
+type Result<T> = std::result::Result<T, DeserializeError>;
+
+fn error<T>(message: impl Into<String>) -> Result<T> {
+    Err(DeserializeError {
+        message: message.into(),
+    })
+}
+
+pub fn serialize_program(program: &Program) -> Value {
+    object(vec![
+        ("version", Value::Integer(FORMAT_VERSION)),
+        (
+            "program",
+            object(vec![
+                ("items", list(&program.items, write_declaration)),
+                ("span", write_span(&program.span)),
+            ]),
+        ),
+    ])
+}
+
+pub fn deserialize_program(value: Value) -> Result<Program> {
+    match value.get("version") {
+        Some(Value::Integer(FORMAT_VERSION)) => {}
+        Some(Value::Integer(version)) => {
+            return error(format!(
+                "unsupported version {}; expected {}",
+                version, FORMAT_VERSION
+            ));
+        }
+        Some(_) => return error("`version` must be an integer"),
+        None => return error("missing `version` field"),
+    }
+    let program = field(&value, "program")?;
+    Ok(Program {
+        items: read_list(program, "items", read_declaration)?,
+        span: read_span(field(program, "span")?)?,
+    })
+}
+
+// A hash of `function`'s structure for build caches. Spans are skipped, so
+// reformatting keeps the hash while any other edit changes it. FNV-1a over the
+// wire format is used instead of `DefaultHasher`, whose output may change
+// between Rust releases.
+pub fn stable_hash(function: &FunctionDecl) -> u64 {
+    let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+    hash_value(&write_function(function), &mut hasher);
+    hasher.0
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+}
+
+fn hash_value(value: &Value, hasher: &mut Fnv1a) {
+    match value {
+        Value::Null => hasher.write(&[0]),
+        Value::Bool(b) => hasher.write(&[1, *b as u8]),
+        Value::Integer(n) => {
+            hasher.write(&[2]);
+            hasher.write(&n.to_le_bytes());
+        }
+        Value::Float(x) => {
+            hasher.write(&[3]);
+            hasher.write(&x.to_bits().to_le_bytes());
+        }
+        Value::String(s) => {
+            hasher.write(&[4]);
+            hasher.write_len(s.len());
+            hasher.write(s.as_bytes());
+        }
+        Value::Array(items) => {
+            hasher.write(&[5]);
+            hasher.write_len(items.len());
+            for item in items {
+                hash_value(item, hasher);
+            }
+        }
+        Value::Object(entries) => {
+            let entries: Vec<_> = entries.iter().filter(|(key, _)| key != "span").collect();
+            hasher.write(&[6]);
+            hasher.write_len(entries.len());
+            for (key, value) in entries {
+                hasher.write_len(key.len());
+                hasher.write(key.as_bytes());
+                hash_value(value, hasher);
+            }
+        }
+    }
+}
+
+// Writing
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn node(kind: &str, fields: Vec<(&str, Value)>) -> Value {
+    let mut entries = vec![("kind", Value::String(kind.to_string()))];
+    entries.extend(fields);
+    object(entries)
+}
+
+fn list<T>(items: &[T], write: impl Fn(&T) -> Value) -> Value {
+    Value::Array(items.iter().map(write).collect())
+}
+
+fn optional<T>(item: Option<&T>, write: impl Fn(&T) -> Value) -> Value {
+    item.map(write).unwrap_or(Value::Null)
+}
+
+fn string(s: &str) -> Value {
+    Value::String(s.to_string())
+}
+
+fn write_span(span: &Span) -> Value {
+    Value::Array(
+        [span.start, span.end, span.line, span.column]
+            .iter()
+            .map(|&n| Value::Integer(n as i128))
+            .collect(),
+    )
+}
+
+fn write_identifier(ident: &Identifier) -> Value {
+    object(vec![
+        ("name", string(&ident.name)),
+        ("span", write_span(&ident.span)),
+    ])
+}
+
+fn write_function(function: &FunctionDecl) -> Value {
+    node(
+        "function",
+        vec![
+            ("name", write_identifier(&function.name)),
+            ("generics", list(&function.generics, write_identifier)),
+            ("params", list(&function.params, write_parameter)),
+            (
+                "return_type",
+                optional(function.return_type.as_deref(), write_type),
+            ),
+            ("body", write_block(&function.body)),
+            ("attributes", list(&function.attributes, write_attribute)),
+            ("span", write_span(&function.span)),
+        ],
+    )
+}
+
+fn write_declaration(decl: &Declaration) -> Value {
+    match decl {
+        Declaration::Function(function) => write_function(function),
+        Declaration::Struct(decl) => node(
+            "struct",
+            vec![
+                ("name", write_identifier(&decl.name)),
+                (
+                    "fields",
+                    list(&decl.fields, |field| {
+                        object(vec![
+                            ("name", write_identifier(&field.name)),
+                            ("ty", write_type(&field.ty)),
+                            (
+                                "default",
+                                optional(field.default.as_ref(), write_expression),
+                            ),
+                            ("attributes", list(&field.attributes, write_attribute)),
+                            ("span", write_span(&field.span)),
+                        ])
+                    }),
+                ),
+                ("attributes", list(&decl.attributes, write_attribute)),
+                ("span", write_span(&decl.span)),
+            ],
+        ),
+        Declaration::Enum(decl) => node(
+            "enum",
+            vec![
+                ("name", write_identifier(&decl.name)),
+                (
+                    "variants",
+                    list(&decl.variants, |variant| {
+                        object(vec![
+                            ("name", write_identifier(&variant.name)),
+                            ("data", optional(variant.data.as_ref(), write_type)),
+                            (
+                                "discriminant",
+                                optional(variant.discriminant.as_ref(), write_expression),
+                            ),
+                            ("span", write_span(&variant.span)),
+                        ])
+                    }),
+                ),
+                ("attributes", list(&decl.attributes, write_attribute)),
+                ("span", write_span(&decl.span)),
+            ],
+        ),
+        Declaration::Union(decl) => node(
+            "union",
+            vec![
+                ("name", write_identifier(&decl.name)),
+                (
+                    "fields",
+                    list(&decl.fields, |field| {
+                        object(vec![
+                            ("name", write_identifier(&field.name)),
+                            ("ty", write_type(&field.ty)),
+                            ("span", write_span(&field.span)),
+                        ])
+                    }),
+                ),
+                ("attributes", list(&decl.attributes, write_attribute)),
+                ("span", write_span(&decl.span)),
+            ],
+        ),
+        Declaration::Variable(decl) => node(
+            "variable",
+            vec![
+                ("name", write_identifier(&decl.name)),
+                ("ty", optional(decl.ty.as_ref(), write_type)),
+                ("mutable", Value::Bool(decl.mutable)),
+                (
+                    "initializer",
+                    optional(decl.initializer.as_ref(), write_expression),
+                ),
+                ("span", write_span(&decl.span)),
+            ],
+        ),
+        Declaration::Constant(decl) => node(
+            "constant",
+            vec![
+                ("name", write_identifier(&decl.name)),
+                ("ty", write_type(&decl.ty)),
+                ("value", write_expression(&decl.value)),
+                ("span", write_span(&decl.span)),
+            ],
+        ),
+        Declaration::Static(decl) => node(
+            "static",
+            vec![
+                ("name", write_identifier(&decl.name)),
+                ("ty", write_type(&decl.ty)),
+                ("mutable", Value::Bool(decl.mutable)),
+                ("value", write_expression(&decl.value)),
+                ("span", write_span(&decl.span)),
+            ],
+        ),
+        Declaration::Module(module) => node(
+            "module",
+            vec![
+                ("name", write_identifier(&module.name)),
+                (
+                    "items",
+                    optional(module.items.as_ref(), |items| {
+                        list(items, write_declaration)
+                    }),
+                ),
+                ("span", write_span(&module.span)),
+            ],
+        ),
+        Declaration::Macro(decl) => node(
+            "macro",
+            vec![
+                ("name", write_identifier(&decl.name)),
+                (
+                    "params",
+                    list(&decl.params, |param| {
+                        object(vec![
+                            ("name", write_identifier(&param.name)),
+                            ("ty", write_type(&param.ty)),
+                            ("span", write_span(&param.span)),
+                        ])
+                    }),
+                ),
+                ("tokens", list(&decl.body.tokens, write_macro_token)),
+                ("body_span", write_span(&decl.body.span)),
+                ("span", write_span(&decl.span)),
+            ],
+        ),
+    }
+}
+
+fn write_parameter(param: &Parameter) -> Value {
+    object(vec![
+        ("name", write_identifier(&param.name)),
+        ("ty", write_type(&param.ty)),
+        (
+            "default",
+            optional(param.default.as_ref(), write_expression),
+        ),
+        ("span", write_span(&param.span)),
+    ])
+}
+
+fn write_attribute(attribute: &Attribute) -> Value {
+    object(vec![
+        ("name", write_identifier(&attribute.name)),
+        ("args", list(&attribute.args, write_attribute_arg)),
+        ("span", write_span(&attribute.span)),
+    ])
+}
+
+fn write_attribute_arg(arg: &AttributeArg) -> Value {
+    match arg {
+        AttributeArg::Literal(literal) => {
+            node("literal", vec![("literal", write_literal(literal))])
+        }
+        AttributeArg::Identifier(ident) => {
+            node("identifier", vec![("ident", write_identifier(ident))])
+        }
+        AttributeArg::NameValue(ident, literal) => node(
+            "name_value",
+            vec![
+                ("ident", write_identifier(ident)),
+                ("literal", write_literal(literal)),
+            ],
+        ),
+        AttributeArg::List(ident, args) => node(
+            "list",
+            vec![
+                ("ident", write_identifier(ident)),
+                ("args", list(args, write_attribute_arg)),
+            ],
+        ),
+    }
+}
+
+fn write_macro_token(token: &MacroToken) -> Value {
+    match token {
+        MacroToken::Literal(text) => node("literal", vec![("text", string(text))]),
+        MacroToken::Variable(ident) => node("variable", vec![("ident", write_identifier(ident))]),
+        MacroToken::Group(tokens) => {
+            node("group", vec![("tokens", list(tokens, write_macro_token))])
+        }
+    }
+}
+
+fn write_block(block: &Block) -> Value {
+    object(vec![
+        ("statements", list(&block.statements, write_statement)),
+        ("tail", optional(block.tail.as_deref(), write_expression)),
+        ("span", write_span(&block.span)),
+    ])
+}
+
+fn write_label(label: &Option<Identifier>) -> Value {
+    optional(label.as_ref(), write_identifier)
+}
+
+fn write_statement(statement: &Statement) -> Value {
+    match statement {
+        Statement::Empty => node("empty", vec![]),
+        Statement::Expression(expr) => node("expression", vec![("expr", write_expression(expr))]),
+        Statement::Let(stmt) => node(
+            "let",
+            vec![
+                ("pattern", write_pattern(&stmt.pattern)),
+                (
+                    "type_annotation",
+                    optional(stmt.type_annotation.as_ref(), write_type),
+                ),
+                (
+                    "initializer",
+                    optional(stmt.initializer.as_ref(), write_expression),
+                ),
+                ("mutable", Value::Bool(stmt.mutable)),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+        Statement::Return(stmt) => node(
+            "return",
+            vec![
+                (
+                    "expression",
+                    optional(stmt.expression.as_ref(), write_expression),
+                ),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+        Statement::Break(stmt) => node(
+            "break",
+            vec![
+                ("label", write_label(&stmt.label)),
+                (
+                    "expression",
+                    optional(stmt.expression.as_ref(), write_expression),
+                ),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+        Statement::Continue(stmt) => node(
+            "continue",
+            vec![
+                ("label", write_label(&stmt.label)),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+        Statement::While(stmt) => node(
+            "while",
+            vec![
+                ("condition", write_expression(&stmt.condition)),
+                ("body", write_block(&stmt.body)),
+                ("label", write_label(&stmt.label)),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+        Statement::For(stmt) => node(
+            "for",
+            vec![
+                ("pattern", write_pattern(&stmt.pattern)),
+                ("iterator", write_expression(&stmt.iterator)),
+                ("body", write_block(&stmt.body)),
+                ("label", write_label(&stmt.label)),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+        Statement::Loop(stmt) => node(
+            "loop",
+            vec![
+                ("body", write_block(&stmt.body)),
+                ("label", write_label(&stmt.label)),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+        Statement::Block(block) => node("block", vec![("block", write_block(block))]),
+        Statement::If(stmt) => write_if_statement(stmt),
+        Statement::Match(stmt) => node(
+            "match",
+            vec![
+                ("expression", write_expression(&stmt.expression)),
+                ("arms", list(&stmt.arms, write_match_arm)),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+        Statement::Panic(stmt) => node(
+            "panic",
+            vec![
+                ("message", write_expression(&stmt.message)),
+                ("span", write_span(&stmt.span)),
+            ],
+        ),
+    }
+}
+
+fn write_if_statement(stmt: &IfStatement) -> Value {
+    node(
+        "if",
+        vec![
+            ("condition", write_expression(&stmt.condition)),
+            ("then_branch", write_block(&stmt.then_branch)),
+            (
+                "else_branch",
+                optional(stmt.else_branch.as_ref(), |branch| match branch {
+                    ElseBranch::Block(block) => write_block(block),
+                    ElseBranch::If(nested) => write_if_statement(nested),
+                }),
+            ),
+            ("span", write_span(&stmt.span)),
+        ],
+    )
+}
+
+fn write_match_arm(arm: &MatchArm) -> Value {
+    object(vec![
+        ("pattern", write_pattern(&arm.pattern)),
+        ("guard", optional(arm.guard.as_ref(), write_expression)),
+        ("body", write_expression(&arm.body)),
+        ("span", write_span(&arm.span)),
+    ])
+}
+
+// Shared by named types and expression paths.
+fn write_path_fields(path: &TypePath) -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "segments",
+            list(&path.segments, |segment| {
+                object(vec![
+                    ("ident", write_identifier(&segment.ident)),
+                    (
+                        "generic_args",
+                        optional(segment.generic_args.as_ref(), |args| list(args, write_type)),
+                    ),
+                    ("span", write_span(&segment.span)),
+                ])
+            }),
+        ),
+        ("span", write_span(&path.span)),
+    ]
+}
+
+fn write_expression(expr: &Expression) -> Value {
+    match expr {
+        Expression::Literal(literal) => node("literal", vec![("literal", write_literal(literal))]),
+        Expression::Identifier(ident) => {
+            node("identifier", vec![("ident", write_identifier(ident))])
+        }
+        Expression::Binary(binary) => node(
+            "binary",
+            vec![
+                ("left", write_expression(&binary.left)),
+                ("operator", string(binary.operator.symbol())),
+                ("right", write_expression(&binary.right)),
+                ("span", write_span(&binary.span)),
+            ],
+        ),
+        Expression::Unary(unary) => node(
+            "unary",
+            vec![
+                ("operator", string(unary_operator_name(&unary.operator))),
+                ("operand", write_expression(&unary.operand)),
+                ("span", write_span(&unary.span)),
+            ],
+        ),
+        Expression::Call(call) => node(
+            "call",
+            vec![
+                ("callee", write_expression(&call.callee)),
+                ("arguments", list(&call.arguments, write_expression)),
+                ("span", write_span(&call.span)),
+            ],
+        ),
+        Expression::Member(member) => node(
+            "member",
+            vec![
+                ("object", write_expression(&member.object)),
+                ("member", write_identifier(&member.member)),
+                ("span", write_span(&member.span)),
+            ],
+        ),
+        Expression::Index(index) => node(
+            "index",
+            vec![
+                ("array", write_expression(&index.array)),
+                ("index", write_expression(&index.index)),
+                ("span", write_span(&index.span)),
+            ],
+        ),
+        Expression::Cast(cast) => node(
+            "cast",
+            vec![
+                ("expr", write_expression(&cast.expr)),
+                ("target_type", write_type(&cast.target_type)),
+                ("span", write_span(&cast.span)),
+            ],
+        ),
+        Expression::Block(block) => node("block", vec![("block", write_block(block))]),
+        Expression::If(if_expr) => node(
+            "if",
+            vec![
+                ("condition", write_expression(&if_expr.condition)),
+                ("then_branch", write_expression(&if_expr.then_branch)),
+                (
+                    "else_branch",
+                    optional(if_expr.else_branch.as_ref(), write_expression),
+                ),
+                ("span", write_span(&if_expr.span)),
+            ],
+        ),
+        Expression::Match(match_expr) => node(
+            "match",
+            vec![
+                ("value", write_expression(&match_expr.value)),
+                ("arms", list(&match_expr.arms, write_match_arm)),
+                ("span", write_span(&match_expr.span)),
+            ],
+        ),
+        Expression::Loop(loop_expr) => node(
+            "loop",
+            vec![
+                ("body", write_expression(&loop_expr.body)),
+                ("label", write_label(&loop_expr.label)),
+                ("span", write_span(&loop_expr.span)),
+            ],
+        ),
+        Expression::While(while_expr) => node(
+            "while",
+            vec![
+                ("condition", write_expression(&while_expr.condition)),
+                ("body", write_expression(&while_expr.body)),
+                ("label", write_label(&while_expr.label)),
+                ("span", write_span(&while_expr.span)),
+            ],
+        ),
+        Expression::For(for_expr) => node(
+            "for",
+            vec![
+                ("pattern", write_pattern(&for_expr.pattern)),
+                ("iterator", write_expression(&for_expr.iterator)),
+                ("body", write_expression(&for_expr.body)),
+                ("label", write_label(&for_expr.label)),
+                ("span", write_span(&for_expr.span)),
+            ],
+        ),
+        Expression::Range(range) => node(
+            "range",
+            vec![
+                ("start", optional(range.start.as_ref(), write_expression)),
+                ("end", optional(range.end.as_ref(), write_expression)),
+                ("inclusive", Value::Bool(range.inclusive)),
+                ("span", write_span(&range.span)),
+            ],
+        ),
+        Expression::MacroInvocation(invocation) => node(
+            "macro_invocation",
+            vec![
+                ("name", write_identifier(&invocation.name)),
+                ("arguments", list(&invocation.arguments, write_expression)),
+                ("span", write_span(&invocation.span)),
+            ],
+        ),
+        Expression::Panic(panic) => node(
+            "panic",
+            vec![
+                ("message", write_expression(&panic.message)),
+                ("span", write_span(&panic.span)),
+            ],
+        ),
+        Expression::Path(path) => node("path", write_path_fields(path)),
+        Expression::Struct(literal) => node(
+            "struct",
+            vec![
+                ("name", write_identifier(&literal.name)),
+                (
+                    "fields",
+                    list(&literal.fields, |(field, value)| {
+                        object(vec![
+                            ("name", write_identifier(field)),
+                            ("value", write_expression(value)),
+                        ])
+                    }),
+                ),
+                ("rest", optional(literal.rest.as_deref(), write_expression)),
+                ("span", write_span(&literal.span)),
+            ],
+        ),
+        Expression::Paren(inner, span) => node(
+            "paren",
+            vec![
+                ("expression", write_expression(inner)),
+                ("span", write_span(span)),
+            ],
+        ),
+    }
+}
+
+fn write_literal(literal: &Literal) -> Value {
+    match literal {
+        Literal::Integer(value, suffix, base) => node(
+            "integer",
+            vec![
+                ("value", Value::Integer(*value)),
+                ("base", string(int_base_name(base))),
+                ("suffix", optional(suffix.as_ref(), write_type)),
+            ],
+        ),
+        Literal::Float(value, suffix) => node(
+            "float",
+            vec![
+                ("value", Value::Float(*value)),
+                ("suffix", optional(suffix.as_ref(), write_type)),
+            ],
+        ),
+        Literal::String(value) => node("string", vec![("value", string(value))]),
+        Literal::Character(value) => node("char", vec![("value", string(&value.to_string()))]),
+        Literal::Boolean(value) => node("bool", vec![("value", Value::Bool(*value))]),
+        Literal::Unit => node("unit", vec![]),
+        Literal::Array(elements) => node(
+            "array",
+            vec![("elements", list(elements, write_expression))],
+        ),
+        Literal::Tuple(elements) => node(
+            "tuple",
+            vec![("elements", list(elements, write_expression))],
+        ),
+    }
+}
+
+fn write_pattern(pattern: &Pattern) -> Value {
+    match pattern {
+        Pattern::Literal(literal) => node("literal", vec![("literal", write_literal(literal))]),
+        Pattern::Identifier(ident) => node("identifier", vec![("ident", write_identifier(ident))]),
+        Pattern::Tuple(patterns) => {
+            node("tuple", vec![("patterns", list(patterns, write_pattern))])
+        }
+        Pattern::Struct(name, fields, rest) => node(
+            "struct",
+            vec![
+                ("name", write_identifier(name)),
+                ("rest", Value::Bool(*rest)),
+                (
+                    "fields",
+                    list(fields, |(field, pattern)| {
+                        object(vec![
+                            ("name", write_identifier(field)),
+                            ("pattern", write_pattern(pattern)),
+                        ])
+                    }),
+                ),
+            ],
+        ),
+        Pattern::Or(patterns) => node("or", vec![("patterns", list(patterns, write_pattern))]),
+        Pattern::Range(start, end) => node(
+            "range",
+            vec![("start", write_pattern(start)), ("end", write_pattern(end))],
+        ),
+        Pattern::Wildcard => node("wildcard", vec![]),
+        Pattern::Rest => node("rest", vec![]),
+    }
+}
+
+fn write_type(ty: &Type) -> Value {
+    if let Some(name) = primitive_name(ty) {
+        return node(name, vec![]);
+    }
+    match ty {
+        Type::Array(element, size) => node(
+            "array",
+            vec![
+                ("element", write_type(element)),
+                ("size", optional(size.as_deref(), write_expression)),
+            ],
+        ),
+        Type::Slice(element) => node("slice", vec![("element", write_type(element))]),
+        Type::Pointer(target, mutability) => node(
+            "pointer",
+            vec![
+                ("target", write_type(target)),
+                ("mutable", Value::Bool(*mutability == Mutability::Mutable)),
+            ],
+        ),
+        Type::Reference(target, mutability, lifetime) => node(
+            "reference",
+            vec![
+                ("target", write_type(target)),
+                ("mutable", Value::Bool(*mutability == Mutability::Mutable)),
+                (
+                    "lifetime",
+                    optional(lifetime.as_ref(), |lifetime| {
+                        write_identifier(&lifetime.ident)
+                    }),
+                ),
+            ],
+        ),
+        Type::Tuple(types) => node("tuple", vec![("types", list(types, write_type))]),
+        Type::Function(params, ret, abi) => node(
+            "function",
+            vec![
+                ("params", list(params, write_type)),
+                ("return_type", write_type(ret)),
+                (
+                    "convention",
+                    optional(abi.convention.as_ref(), |convention| string(convention)),
+                ),
+                ("variadic", Value::Bool(abi.variadic)),
+            ],
+        ),
+        Type::Named(path) => node("named", write_path_fields(path)),
+        Type::Infer(span) => node("infer", vec![("span", write_span(span))]),
+        Type::Generic(base, args) => node(
+            "generic",
+            vec![("base", write_type(base)), ("args", list(args, write_type))],
+        ),
+        _ => unreachable!("primitive types are handled above"),
+    }
+}
+
+const PRIMITIVES: [(&str, Type); 19] = [
+    ("i8", Type::I8),
+    ("i16", Type::I16),
+    ("i32", Type::I32),
+    ("i64", Type::I64),
+    ("i128", Type::I128),
+    ("isize", Type::Isize),
+    ("u8", Type::U8),
+    ("u16", Type::U16),
+    ("u32", Type::U32),
+    ("u64", Type::U64),
+    ("u128", Type::U128),
+    ("usize", Type::Usize),
+    ("f32", Type::F32),
+    ("f64", Type::F64),
+    ("bool", Type::Bool),
+    ("char", Type::Char),
+    ("str", Type::Str),
+    ("unit", Type::Unit),
+    ("never", Type::Never),
+];
+
+fn primitive_name(ty: &Type) -> Option<&'static str> {
+    PRIMITIVES
+        .iter()
+        .find(|(_, primitive)| primitive == ty)
+        .map(|(name, _)| *name)
+}
+
+const INT_BASES: [(&str, IntBase); 4] = [
+    ("dec", IntBase::Dec),
+    ("hex", IntBase::Hex),
+    ("oct", IntBase::Oct),
+    ("bin", IntBase::Bin),
+];
+
+fn int_base_name(base: &IntBase) -> &'static str {
+    INT_BASES
+        .iter()
+        .find(|(_, b)| b == base)
+        .map(|(name, _)| *name)
+        .unwrap_or_default()
+}
+
+const UNARY_OPERATORS: [(&str, UnaryOperator); 6] = [
+    ("neg", UnaryOperator::Neg),
+    ("not", UnaryOperator::Not),
+    ("bit_not", UnaryOperator::BitNot),
+    ("deref", UnaryOperator::Deref),
+    ("ref", UnaryOperator::Ref),
+    ("ref_mut", UnaryOperator::RefMut),
+];
+
+fn unary_operator_name(operator: &UnaryOperator) -> &'static str {
+    UNARY_OPERATORS
+        .iter()
+        .find(|(_, op)| op == operator)
+        .map(|(name, _)| *name)
+        .unwrap_or_default()
+}
+
+const BINARY_OPERATORS: [BinaryOperator; 29] = [
+    BinaryOperator::Add,
+    BinaryOperator::Sub,
+    BinaryOperator::Mul,
+    BinaryOperator::Div,
+    BinaryOperator::Rem,
+    BinaryOperator::And,
+    BinaryOperator::Or,
+    BinaryOperator::BitAnd,
+    BinaryOperator::BitOr,
+    BinaryOperator::BitXor,
+    BinaryOperator::Shl,
+    BinaryOperator::Shr,
+    BinaryOperator::Eq,
+    BinaryOperator::NotEq,
+    BinaryOperator::Lt,
+    BinaryOperator::LtEq,
+    BinaryOperator::Gt,
+    BinaryOperator::GtEq,
+    BinaryOperator::Assign,
+    BinaryOperator::AddAssign,
+    BinaryOperator::SubAssign,
+    BinaryOperator::MulAssign,
+    BinaryOperator::DivAssign,
+    BinaryOperator::RemAssign,
+    BinaryOperator::BitAndAssign,
+    BinaryOperator::BitOrAssign,
+    BinaryOperator::BitXorAssign,
+    BinaryOperator::ShlAssign,
+    BinaryOperator::ShrAssign,
+];
+
+// Reading
+
+fn field<'a>(value: &'a Value, key: &str) -> Result<&'a Value> {
+    match value.get(key) {
+        Some(field) => Ok(field),
+        None => error(format!("missing `{}` field", key)),
+    }
+}
+
+fn read_kind(value: &Value) -> Result<&str> {
+    match field(value, "kind")? {
+        Value::String(kind) => Ok(kind),
+        _ => error("`kind` must be a string"),
+    }
+}
+
+fn read_string(value: &Value, key: &str) -> Result<String> {
+    match field(value, key)? {
+        Value::String(s) => Ok(s.clone()),
+        _ => error(format!("`{}` must be a string", key)),
+    }
+}
+
+fn read_integer(value: &Value, key: &str) -> Result<i128> {
+    match field(value, key)? {
+        Value::Integer(n) => Ok(*n),
+        _ => error(format!("`{}` must be an integer", key)),
+    }
+}
+
+fn read_bool(value: &Value, key: &str) -> Result<bool> {
+    match field(value, key)? {
+        Value::Bool(b) => Ok(*b),
+        _ => error(format!("`{}` must be a boolean", key)),
+    }
+}
+
+fn read_list<T>(value: &Value, key: &str, read: impl Fn(&Value) -> Result<T>) -> Result<Vec<T>> {
+    match field(value, key)? {
+        Value::Array(items) => items.iter().map(read).collect(),
+        _ => error(format!("`{}` must be an array", key)),
+    }
+}
+
+fn read_optional<T>(
+    value: &Value,
+    key: &str,
+    read: impl Fn(&Value) -> Result<T>,
+) -> Result<Option<T>> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(field) => read(field).map(Some),
+    }
+}
+
+fn read_span(value: &Value) -> Result<Span> {
+    let parts: Option<Vec<usize>> = match value {
+        Value::Array(parts) if parts.len() == 4 => parts
+            .iter()
+            .map(|part| match part {
+                Value::Integer(n) => usize::try_from(*n).ok(),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    };
+    match parts.as_deref() {
+        Some(&[start, end, line, column]) => match Span::checked(start, end, line, column) {
+            Some(span) => Ok(span),
+            None => error(format!("span start {} is after its end {}", start, end)),
+        },
+        _ => error("a span must be an array of four non-negative integers"),
+    }
+}
+
+fn read_field_span(value: &Value) -> Result<Span> {
+    read_span(field(value, "span")?)
+}
+
+fn read_identifier(value: &Value) -> Result<Identifier> {
+    Ok(Identifier::new(
+        read_string(value, "name")?,
+        read_field_span(value)?,
+    ))
+}
+
+fn read_ident_field(value: &Value, key: &str) -> Result<Identifier> {
+    read_identifier(field(value, key)?)
+}
+
+fn read_declaration(value: &Value) -> Result<Declaration> {
+    let name = || read_ident_field(value, "name");
+    let span = || read_field_span(value);
+    let attributes = || read_list(value, "attributes", read_attribute);
+    let decl = match read_kind(value)? {
+        "function" => Declaration::Function(FunctionDecl {
+            name: name()?,
+            generics: read_list(value, "generics", read_identifier)?,
+            params: read_list(value, "params", read_parameter)?,
+            return_type: read_optional(value, "return_type", read_type)?.map(Box::new),
+            body: read_block(field(value, "body")?)?,
+            attributes: attributes()?,
+            span: span()?,
+        }),
+        "struct" => Declaration::Struct(StructDecl {
+            name: name()?,
+            fields: read_list(value, "fields", |field| {
+                Ok(StructField {
+                    name: read_ident_field(field, "name")?,
+                    ty: read_type_field(field, "ty")?,
+                    default: read_optional(field, "default", read_expression)?,
+                    attributes: read_list(field, "attributes", read_attribute)?,
+                    span: read_field_span(field)?,
+                })
+            })?,
+            attributes: attributes()?,
+            span: span()?,
+        }),
+        "enum" => Declaration::Enum(EnumDecl {
+            name: name()?,
+            variants: read_list(value, "variants", |variant| {
+                Ok(EnumVariant {
+                    name: read_ident_field(variant, "name")?,
+                    data: read_optional(variant, "data", read_type)?,
+                    discriminant: read_optional(variant, "discriminant", read_expression)?,
+                    span: read_field_span(variant)?,
+                })
+            })?,
+            attributes: attributes()?,
+            span: span()?,
+        }),
+        "union" => Declaration::Union(UnionDecl {
+            name: name()?,
+            fields: read_list(value, "fields", |field| {
+                Ok(UnionField {
+                    name: read_ident_field(field, "name")?,
+                    ty: read_type_field(field, "ty")?,
+                    span: read_field_span(field)?,
+                })
+            })?,
+            attributes: attributes()?,
+            span: span()?,
+        }),
+        "variable" => Declaration::Variable(VarDecl {
+            name: name()?,
+            ty: read_optional(value, "ty", read_type)?,
+            mutable: read_bool(value, "mutable")?,
+            initializer: read_optional(value, "initializer", read_expression)?,
+            span: span()?,
+        }),
+        "constant" => Declaration::Constant(ConstDecl {
+            name: name()?,
+            ty: read_type_field(value, "ty")?,
+            value: read_expression_field(value, "value")?,
+            span: span()?,
+        }),
+        "static" => Declaration::Static(StaticDecl {
+            name: name()?,
+            ty: read_type_field(value, "ty")?,
+            mutable: read_bool(value, "mutable")?,
+            value: read_expression_field(value, "value")?,
+            span: span()?,
+        }),
+        "module" => Declaration::Module(ModuleDecl {
+            name: name()?,
+            items: read_optional(value, "items", |items| match items {
+                Value::Array(items) => items.iter().map(read_declaration).collect(),
+                _ => error("`items` must be an array"),
+            })?,
+            span: span()?,
+        }),
+        "macro" => Declaration::Macro(MacroDecl {
+            name: name()?,
+            params: read_list(value, "params", |param| {
+                Ok(MacroParam {
+                    name: read_ident_field(param, "name")?,
+                    ty: read_type_field(param, "ty")?,
+                    span: read_field_span(param)?,
+                })
+            })?,
+            body: MacroBody {
+                tokens: read_list(value, "tokens", read_macro_token)?,
+                span: read_span(field(value, "body_span")?)?,
+            },
+            span: span()?,
+        }),
+        other => return error(format!("unknown declaration kind `{}`", other)),
+    };
+    Ok(decl)
+}
+
+fn read_parameter(value: &Value) -> Result<Parameter> {
+    Ok(Parameter {
+        name: read_ident_field(value, "name")?,
+        ty: read_type_field(value, "ty")?,
+        default: read_optional(value, "default", read_expression)?,
+        span: read_field_span(value)?,
+    })
+}
+
+fn read_attribute(value: &Value) -> Result<Attribute> {
+    Ok(Attribute {
+        name: read_ident_field(value, "name")?,
+        args: read_list(value, "args", read_attribute_arg)?,
+        span: read_field_span(value)?,
+    })
+}
+
+fn read_attribute_arg(arg: &Value) -> Result<AttributeArg> {
+    match read_kind(arg)? {
+        "literal" => Ok(AttributeArg::Literal(read_literal(field(arg, "literal")?)?)),
+        "identifier" => Ok(AttributeArg::Identifier(read_ident_field(arg, "ident")?)),
+        "name_value" => Ok(AttributeArg::NameValue(
+            read_ident_field(arg, "ident")?,
+            read_literal(field(arg, "literal")?)?,
+        )),
+        "list" => Ok(AttributeArg::List(
+            read_ident_field(arg, "ident")?,
+            read_list(arg, "args", read_attribute_arg)?,
+        )),
+        other => error(format!("unknown attribute argument kind `{}`", other)),
+    }
+}
+
+fn read_macro_token(value: &Value) -> Result<MacroToken> {
+    match read_kind(value)? {
+        "literal" => Ok(MacroToken::Literal(read_string(value, "text")?)),
+        "variable" => Ok(MacroToken::Variable(read_ident_field(value, "ident")?)),
+        "group" => Ok(MacroToken::Group(read_list(
+            value,
+            "tokens",
+            read_macro_token,
+        )?)),
+        other => error(format!("unknown macro token kind `{}`", other)),
+    }
+}
+
+fn read_block(value: &Value) -> Result<Block> {
+    Ok(Block {
+        statements: read_list(value, "statements", read_statement)?,
+        tail: read_optional(value, "tail", read_expression)?.map(Box::new),
+        span: read_field_span(value)?,
+    })
+}
+
+fn read_block_field(value: &Value, key: &str) -> Result<Block> {
+    read_block(field(value, key)?)
+}
+
+fn read_label(value: &Value) -> Result<Option<Identifier>> {
+    read_optional(value, "label", read_identifier)
+}
+
+fn read_statement(value: &Value) -> Result<Statement> {
+    let span = || read_field_span(value);
+    let statement = match read_kind(value)? {
+        "empty" => Statement::Empty,
+        "expression" => Statement::Expression(read_expression_field(value, "expr")?),
+        "let" => Statement::Let(LetStatement {
+            pattern: read_pattern(field(value, "pattern")?)?,
+            type_annotation: read_optional(value, "type_annotation", read_type)?,
+            initializer: read_optional(value, "initializer", read_expression)?,
+            mutable: read_bool(value, "mutable")?,
+            span: span()?,
+        }),
+        "return" => Statement::Return(ReturnStatement {
+            expression: read_optional(value, "expression", read_expression)?,
+            span: span()?,
+        }),
+        "break" => Statement::Break(BreakStatement {
+            label: read_label(value)?,
+            expression: read_optional(value, "expression", read_expression)?,
+            span: span()?,
+        }),
+        "continue" => Statement::Continue(ContinueStatement {
+            label: read_label(value)?,
+            span: span()?,
+        }),
+        "while" => Statement::While(WhileStatement {
+            condition: read_expression_field(value, "condition")?,
+            body: read_block_field(value, "body")?,
+            label: read_label(value)?,
+            span: span()?,
+        }),
+        "for" => Statement::For(ForStatement {
+            pattern: read_pattern(field(value, "pattern")?)?,
+            iterator: read_expression_field(value, "iterator")?,
+            body: read_block_field(value, "body")?,
+            label: read_label(value)?,
+            span: span()?,
+        }),
+        "loop" => Statement::Loop(LoopStatement {
+            body: read_block_field(value, "body")?,
+            label: read_label(value)?,
+            span: span()?,
+        }),
+        "block" => Statement::Block(read_block_field(value, "block")?),
+        "if" => Statement::If(read_if_statement(value)?),
+        "match" => Statement::Match(MatchStatement {
+            expression: read_expression_field(value, "expression")?,
+            arms: read_list(value, "arms", read_match_arm)?,
+            span: span()?,
+        }),
+        "panic" => Statement::Panic(PanicStatement {
+            message: read_expression_field(value, "message")?,
+            span: span()?,
+        }),
+        other => return error(format!("unknown statement kind `{}`", other)),
+    };
+    Ok(statement)
+}
+
+fn read_if_statement(value: &Value) -> Result<IfStatement> {
+    Ok(IfStatement {
+        condition: read_expression_field(value, "condition")?,
+        then_branch: read_block_field(value, "then_branch")?,
+        else_branch: read_optional(value, "else_branch", |branch| {
+            // A nested `if` is tagged; a plain block has no `kind`.
+            if branch.get("kind").is_some() {
+                Ok(ElseBranch::If(Box::new(read_if_statement(branch)?)))
+            } else {
+                Ok(ElseBranch::Block(read_block(branch)?))
+            }
+        })?,
+        span: read_field_span(value)?,
+    })
+}
+
+fn read_match_arm(value: &Value) -> Result<MatchArm> {
+    Ok(MatchArm {
+        pattern: read_pattern(field(value, "pattern")?)?,
+        guard: read_optional(value, "guard", read_expression)?,
+        body: read_expression_field(value, "body")?,
+        span: read_field_span(value)?,
+    })
+}
+
+fn read_expression_field(value: &Value, key: &str) -> Result<Expression> {
+    read_expression(field(value, key)?)
+}
+
+fn read_expression(value: &Value) -> Result<Expression> {
+    let expr = |key| read_expression_field(value, key);
+    let span = || read_field_span(value);
+    let expression = match read_kind(value)? {
+        "literal" => Expression::Literal(read_literal(field(value, "literal")?)?),
+        "identifier" => Expression::Identifier(read_ident_field(value, "ident")?),
+        "binary" => {
+            let symbol = read_string(value, "operator")?;
+            let Some(operator) = BINARY_OPERATORS
+                .iter()
+                .find(|op| op.symbol() == symbol)
+                .cloned()
+            else {
+                return error(format!("unknown binary operator `{}`", symbol));
+            };
+            Expression::Binary(Box::new(BinaryExpr {
+                left: expr("left")?,
+                operator,
+                right: expr("right")?,
+                span: span()?,
+            }))
+        }
+        "unary" => {
+            let name = read_string(value, "operator")?;
+            let Some((_, operator)) = UNARY_OPERATORS.iter().find(|(n, _)| *n == name) else {
+                return error(format!("unknown unary operator `{}`", name));
+            };
+            Expression::Unary(Box::new(UnaryExpr {
+                operator: operator.clone(),
+                operand: expr("operand")?,
+                span: span()?,
+            }))
+        }
+        "call" => Expression::Call(Box::new(CallExpr {
+            callee: expr("callee")?,
+            arguments: read_list(value, "arguments", read_expression)?,
+            span: span()?,
+        })),
+        "panic" => Expression::Panic(Box::new(PanicExpr {
+            message: read_expression_field(value, "message")?,
+            span: span()?,
+        })),
+        "member" => Expression::Member(Box::new(MemberExpr {
+            object: expr("object")?,
+            member: read_ident_field(value, "member")?,
+            span: span()?,
+        })),
+        "index" => Expression::Index(Box::new(IndexExpr {
+            array: expr("array")?,
+            index: expr("index")?,
+            span: span()?,
+        })),
+        "cast" => Expression::Cast(Box::new(CastExpr {
+            expr: expr("expr")?,
+            target_type: read_type_field(value, "target_type")?,
+            span: span()?,
+        })),
+        "block" => Expression::Block(Box::new(read_block_field(value, "block")?)),
+        "if" => Expression::If(Box::new(IfExpr {
+            condition: expr("condition")?,
+            then_branch: expr("then_branch")?,
+            else_branch: read_optional(value, "else_branch", read_expression)?,
+            span: span()?,
+        })),
+        "match" => Expression::Match(Box::new(MatchExpr {
+            value: expr("value")?,
+            arms: read_list(value, "arms", read_match_arm)?,
+            span: span()?,
+        })),
+        "loop" => Expression::Loop(Box::new(LoopExpr {
+            body: expr("body")?,
+            label: read_label(value)?,
+            span: span()?,
+        })),
+        "while" => Expression::While(Box::new(WhileExpr {
+            condition: expr("condition")?,
+            body: expr("body")?,
+            label: read_label(value)?,
+            span: span()?,
+        })),
+        "for" => Expression::For(Box::new(ForExpr {
+            pattern: read_pattern(field(value, "pattern")?)?,
+            iterator: expr("iterator")?,
+            body: expr("body")?,
+            label: read_label(value)?,
+            span: span()?,
+        })),
+        "range" => Expression::Range(Box::new(RangeExpr {
+            start: read_optional(value, "start", read_expression)?,
+            end: read_optional(value, "end", read_expression)?,
+            inclusive: read_bool(value, "inclusive")?,
+            span: span()?,
+        })),
+        "macro_invocation" => Expression::MacroInvocation(Box::new(MacroInvocation {
+            name: read_ident_field(value, "name")?,
+            arguments: read_list(value, "arguments", read_expression)?,
+            span: span()?,
+        })),
+        "path" => Expression::Path(read_path(value)?),
+        "struct" => Expression::Struct(Box::new(StructExpr {
+            name: read_ident_field(value, "name")?,
+            fields: read_list(value, "fields", |field| {
+                Ok((
+                    read_ident_field(field, "name")?,
+                    read_expression(self::field(field, "value")?)?,
+                ))
+            })?,
+            rest: read_optional(value, "rest", read_expression)?.map(Box::new),
+            span: span()?,
+        })),
+        "paren" => Expression::Paren(Box::new(expr("expression")?), span()?),
+        other => return error(format!("unknown expression kind `{}`", other)),
+    };
+    Ok(expression)
+}
+
+fn read_path(value: &Value) -> Result<TypePath> {
+    Ok(TypePath {
+        segments: read_list(value, "segments", |segment| {
+            Ok(TypePathSegment {
+                ident: read_ident_field(segment, "ident")?,
+                generic_args: read_optional(segment, "generic_args", |args| match args {
+                    Value::Array(args) => args.iter().map(read_type).collect(),
+                    _ => error("`generic_args` must be an array"),
+                })?,
+                span: read_field_span(segment)?,
+            })
+        })?,
+        span: read_field_span(value)?,
+    })
+}
+
+fn read_literal(value: &Value) -> Result<Literal> {
+    let literal = match read_kind(value)? {
+        "integer" => {
+            let base = match value.get("base") {
+                None => IntBase::Dec,
+                Some(Value::String(name)) => match INT_BASES.iter().find(|(n, _)| n == name) {
+                    Some((_, base)) => *base,
+                    None => return error(format!("unknown integer base `{}`", name)),
+                },
+                Some(_) => return error("`base` must be a string"),
+            };
+            Literal::Integer(
+                read_integer(value, "value")?,
+                read_optional(value, "suffix", read_type)?,
+                base,
+            )
+        }
+        "float" => {
+            let number = match field(value, "value")? {
+                Value::Float(n) => *n,
+                Value::Integer(n) => *n as f64,
+                _ => return error("`value` must be a number"),
+            };
+            Literal::Float(number, read_optional(value, "suffix", read_type)?)
+        }
+        "string" => Literal::String(read_string(value, "value")?),
+        "char" => {
+            let text = read_string(value, "value")?;
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Literal::Character(c),
+                _ => return error("a char literal must hold exactly one character"),
+            }
+        }
+        "bool" => Literal::Boolean(read_bool(value, "value")?),
+        "unit" => Literal::Unit,
+        "array" => Literal::Array(read_list(value, "elements", read_expression)?),
+        "tuple" => Literal::Tuple(read_list(value, "elements", read_expression)?),
+        other => return error(format!("unknown literal kind `{}`", other)),
+    };
+    Ok(literal)
+}
+
+fn read_pattern(value: &Value) -> Result<Pattern> {
+    let pattern = match read_kind(value)? {
+        "literal" => Pattern::Literal(read_literal(field(value, "literal")?)?),
+        "identifier" => Pattern::Identifier(read_ident_field(value, "ident")?),
+        "tuple" => Pattern::Tuple(read_list(value, "patterns", read_pattern)?),
+        "struct" => Pattern::Struct(
+            read_ident_field(value, "name")?,
+            read_list(value, "fields", |field| {
+                Ok((
+                    read_ident_field(field, "name")?,
+                    read_pattern(self::field(field, "pattern")?)?,
+                ))
+            })?,
+            // Older documents predate `..` in struct patterns.
+            match value.get("rest") {
+                None => false,
+                Some(_) => read_bool(value, "rest")?,
+            },
+        ),
+        "or" => Pattern::Or(read_list(value, "patterns", read_pattern)?),
+        "range" => Pattern::Range(
+            Box::new(read_pattern(field(value, "start")?)?),
+            Box::new(read_pattern(field(value, "end")?)?),
+        ),
+        "wildcard" => Pattern::Wildcard,
+        "rest" => Pattern::Rest,
+        other => return error(format!("unknown pattern kind `{}`", other)),
+    };
+    Ok(pattern)
+}
+
+fn read_type_field(value: &Value, key: &str) -> Result<Type> {
+    read_type(field(value, key)?)
+}
+
+fn read_mutability(value: &Value) -> Result<Mutability> {
+    Ok(if read_bool(value, "mutable")? {
+        Mutability::Mutable
+    } else {
+        Mutability::Immutable
+    })
+}
+
+fn read_type(value: &Value) -> Result<Type> {
+    let kind = read_kind(value)?;
+    if let Some((_, ty)) = PRIMITIVES.iter().find(|(name, _)| *name == kind) {
+        return Ok(ty.clone());
+    }
+    let ty = match kind {
+        "array" => Type::Array(
+            Box::new(read_type_field(value, "element")?),
+            read_optional(value, "size", read_expression)?.map(Box::new),
+        ),
+        "slice" => Type::Slice(Box::new(read_type_field(value, "element")?)),
+        "pointer" => Type::Pointer(
+            Box::new(read_type_field(value, "target")?),
+            read_mutability(value)?,
+        ),
+        "reference" => Type::Reference(
+            Box::new(read_type_field(value, "target")?),
+            read_mutability(value)?,
+            read_optional(value, "lifetime", read_identifier)?.map(Lifetime::new),
+        ),
+        "tuple" => Type::Tuple(read_list(value, "types", read_type)?),
+        "function" => Type::Function(
+            read_list(value, "params", read_type)?,
+            Box::new(read_type_field(value, "return_type")?),
+            FunctionAbi {
+                convention: read_optional(value, "convention", |convention| match convention {
+                    Value::String(s) => Ok(s.clone()),
+                    _ => error("`convention` must be a string"),
+                })?,
+                variadic: read_bool(value, "variadic")?,
+            },
+        ),
+        "named" => Type::Named(read_path(value)?),
+        "infer" => Type::Infer(read_field_span(value)?),
+        "generic" => Type::Generic(
+            Box::new(read_type_field(value, "base")?),
+            read_list(value, "args", read_type)?,
+        ),
+        other => return error(format!("unknown type kind `{}`", other)),
+    };
+    Ok(ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    const SOURCE: &str = r#"
+#[derive(Debug)]
+struct Point { x: i32, #[rename("why")] y: *const u8 }
+enum Shape { Empty, Circle(f64) }
+#[repr(u8)]
+enum Flag { Off = 0, On = 1 << 7 }
+static mut COUNT: usize = 0;
+static PRINTF: extern "C" fn(*const u8, ...) -> i32 = 0;
+mod util;
+mod math {
+    fn id<T>(value: T, scale: i32 = 1) -> T { value }
+}
+#[cfg(any(test, not(feature = "std")))]
+fn main() -> i32 {
+    let (a, _) = (1, 2,);
+    var mut total: [u8; 4] = [1, 2, 3, 4];
+    for i in 0..=3 { total[i] += 1; }
+    if a > 0 { return -a; } else if a < 0 { @println("{}", a); } else { panic("zero"); }
+    let s = match a { 0 | 1 => 'x', n if n > 9 => { "big" } _ => "\n" };
+    loop { break; }
+    while !true { continue; }
+    (a as i64 << 0b10) & ~0xff == 0;
+    *&mut total.len
+}
+"#;
+
+    #[test]
+    fn test_round_trip() {
+        let program = parse(SOURCE).unwrap();
+        let value = serialize_program(&program);
+
+        assert_eq!(value.get("version"), Some(&Value::Integer(1)));
+        assert_eq!(deserialize_program(value), Ok(program));
+    }
+
+    fn function_hash(source: &str, index: usize) -> u64 {
+        let program = parse(source).unwrap();
+        let Declaration::Function(function) = &program.items[index] else {
+            panic!("expected function");
+        };
+        stable_hash(function)
+    }
+
+    #[test]
+    fn test_stable_hash_ignores_formatting() {
+        let original = function_hash("fn add(a: i32, b: i32) -> i32 { a + b * 2 }", 0);
+        let reformatted = function_hash(
+            "fn other() {}\n\nfn add(\n    a: i32,\n    b: i32,\n) -> i32 {\n    a + b * 2\n}\n",
+            1,
+        );
+        assert_eq!(original, reformatted);
+        assert_eq!(
+            original,
+            function_hash("fn add(a:i32,b:i32)->i32{a+b*2}", 0)
+        );
+    }
+
+    #[test]
+    fn test_stable_hash_changes_with_edits() {
+        let original = function_hash("fn add(a: i32, b: i32) -> i32 { a + b * 2 }", 0);
+        for edited in [
+            "fn add(a: i32, b: i32) -> i32 { a - b * 2 }",
+            "fn add(a: i32, b: i32) -> i32 { a + b * 3 }",
+            "fn add(a: i32, b: i64) -> i32 { a + b * 2 }",
+            "fn sum(a: i32, b: i32) -> i32 { a + b * 2 }",
+            "#[inline]\nfn add(a: i32, b: i32) -> i32 { a + b * 2 }",
+        ] {
+            assert_ne!(original, function_hash(edited, 0), "{}", edited);
+        }
+    }
+
+    #[test]
+    fn test_missing_or_old_version_is_rejected() {
+        let program = parse("fn main() {}").unwrap();
+        let Value::Object(mut entries) = serialize_program(&program) else {
+            panic!("expected an object");
+        };
+
+        entries[0].1 = Value::Integer(0);
+        let error = deserialize_program(Value::Object(entries.clone())).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid serialized program: unsupported version 0; expected 1"
+        );
+
+        entries.remove(0);
+        let error = deserialize_program(Value::Object(entries)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid serialized program: missing `version` field"
+        );
+    }
+
+    #[test]
+    fn test_unknown_kind_is_rejected() {
+        let value = object(vec![
+            ("version", Value::Integer(FORMAT_VERSION)),
+            (
+                "program",
+                object(vec![
+                    ("items", Value::Array(vec![node("trait", vec![])])),
+                    ("span", write_span(&Span::dummy())),
+                ]),
+            ),
+        ]);
+        assert_eq!(
+            deserialize_program(value).unwrap_err().message,
+            "unknown declaration kind `trait`"
+        );
+    }
+
+    #[test]
+    fn test_reversed_span_is_rejected() {
+        let span = Value::Array([7, 3, 1, 8].map(Value::Integer).to_vec());
+        let value = object(vec![
+            ("version", Value::Integer(FORMAT_VERSION)),
+            (
+                "program",
+                object(vec![("items", Value::Array(vec![])), ("span", span)]),
+            ),
+        ]);
+        assert_eq!(
+            deserialize_program(value).unwrap_err().message,
+            "span start 7 is after its end 3"
+        );
+    }
+}
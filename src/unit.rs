@@ -0,0 +1,164 @@
+use crate::analysis::analyze;
+use crate::ast::{Program, Span};
+use crate::diagnostics::Diagnostic;
+use std::fmt::Write;
+
+// Source text together with the program parsed from it and the diagnostics
+// found so far, so spans can always be resolved against the right text.
+#[derive(Debug, Clone)]
+pub struct CompileUnit {
+    pub source: String,
+    pub program: Program,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CompileUnit {
+    pub fn new(source: impl Into<String>, program: Program) -> Self {
+        Self {
+            source: source.into(),
+            program,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    // Runs `analyze`; a source that cannot be tokenized gets an empty program.
+    pub fn from_source(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let result = analyze(&source);
+        let program = result.program.unwrap_or_else(|| Program {
+            items: Vec::new(),
+            span: Span::new(0, source.len(), 1, 1),
+        });
+        Self {
+            source,
+            program,
+            diagnostics: result.diagnostics,
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    // The source text under `span`, or `None` if it does not lie in `source`.
+    pub fn text(&self, span: &Span) -> Option<&str> {
+        self.source.get(span.start..span.end)
+    }
+
+    pub fn render_diagnostics(&self) -> String {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| self.render_diagnostic(diagnostic))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // A header, then the offending line with the span underlined by `^` and
+    // each label's span by `-`. Spans outside the source get no snippet.
+    pub fn render_diagnostic(&self, diagnostic: &Diagnostic) -> String {
+        let mut output = format!("{}: {}\n", diagnostic.severity, diagnostic.message);
+        if !diagnostic.span.is_valid(self.source.len()) || diagnostic.span.line == 0 {
+            return output;
+        }
+        let spans = std::iter::once((&diagnostic.span, '^', ""))
+            .chain(
+                diagnostic
+                    .labels
+                    .iter()
+                    .map(|label| (&label.span, '-', label.message.as_str())),
+            )
+            .filter(|(span, _, _)| span.is_valid(self.source.len()) && span.line > 0);
+        let width = spans
+            .clone()
+            .map(|(span, _, _)| span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+        let _ = writeln!(
+            output,
+            "{}--> {}:{}",
+            gutter, diagnostic.span.line, diagnostic.span.column
+        );
+        for (span, marker, message) in spans {
+            let (line, column, length) = self.locate(span);
+            let underline = marker.to_string().repeat(length);
+            let _ = writeln!(output, "{} |", gutter);
+            let _ = writeln!(output, "{:>width$} | {}", span.line, line, width = width);
+            let padding = " ".repeat(column);
+            let _ = writeln!(output, "{} | {}{} {}", gutter, padding, underline, message);
+        }
+        output
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n"
+    }
+
+    // The line containing the start of `span`, the span's column within it
+    // (in characters, from zero) and how many characters of it to underline.
+    fn locate(&self, span: &Span) -> (&str, usize, usize) {
+        let line_start = self.source[..span.start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let line_end = self.source[span.start..]
+            .find('\n')
+            .map_or(self.source.len(), |index| span.start + index);
+        let line = self.source[line_start..line_end].trim_end_matches('\r');
+        let column = self.source[line_start..span.start].chars().count();
+        let length = self.source[span.start..span.end.min(line_end)]
+            .chars()
+            .count();
+        (line, column, length.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_render_diagnostic_against_source() {
+        let source = "fn f() {\n    let x: i32 = true;\n}\n";
+        let mut unit = CompileUnit::new(source, parse(source).unwrap());
+        let start = source.find("true").unwrap();
+        unit.diagnostics.push(
+            Diagnostic::error("mismatched types", Span::new(start, start + 4, 2, 18))
+                .with_label("expected due to this", Span::new(20, 23, 2, 12)),
+        );
+        assert_eq!(unit.text(&unit.diagnostics[0].span), Some("true"));
+        assert_eq!(
+            unit.render_diagnostics(),
+            "error: mismatched types\n \
+             --> 2:18\n  \
+             |\n\
+             2 |     let x: i32 = true;\n  \
+             |                  ^^^^\n  \
+             |\n\
+             2 |     let x: i32 = true;\n  \
+             |            --- expected due to this\n"
+        );
+    }
+
+    #[test]
+    fn test_from_source_collects_diagnostics() {
+        let unit = CompileUnit::from_source("fn f() -> i32 { true }");
+        assert!(unit.has_errors());
+        let rendered = unit.render_diagnostics();
+        assert!(rendered.starts_with("error: "), "{}", rendered);
+        assert!(
+            rendered.contains("1 | fn f() -> i32 { true }"),
+            "{}",
+            rendered
+        );
+
+        let unit = CompileUnit::from_source("fn f() {}");
+        assert!(!unit.has_errors());
+        assert_eq!(unit.render_diagnostics(), "");
+        assert!(
+            unit.render_diagnostic(&Diagnostic::note("detached", Span::dummy()))
+                .starts_with("note: detached\n")
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use zenith::analyze;
+
+#[test]
+fn test_analyze_reports_type_error_alongside_valid_function() {
+    let source = "\
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+fn broken() -> i32 {
+    let flag: bool = 1;
+    add(2, 3)
+}
+";
+    let result = analyze(source);
+
+    assert!(!result.tokens.is_empty());
+    let program = result.program.expect("source should parse");
+    assert_eq!(program.items.len(), 2);
+
+    let errors: Vec<_> = result.diagnostics.iter().filter(|d| d.is_error()).collect();
+    assert_eq!(errors.len(), 1, "{:?}", result.diagnostics);
+    assert_eq!(errors[0].span.line, 6);
+    assert!(
+        errors[0].message.contains("mismatched types"),
+        "{}",
+        errors[0]
+    );
+}